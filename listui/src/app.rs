@@ -1,40 +1,145 @@
 use anyhow::Result;
-use listui_lib::db::{Database, DbError};
-use listui_lib::models::{Playlist, Track};
+use listui_lib::db::{Database, DbError, TrackDiff};
+use listui_lib::export::ExportFormat;
+use listui_lib::artwork::{self, Artwork, Protocol};
+use listui_lib::api::{MediaSource, MusicBrainzClient, Release, RemotePlaylist, RSS_PREFIX};
+use listui_lib::models::{Drawable, NewPlaylist, NewVideo, Playlist, Track};
+use listui_lib::downloader::{DownloadResult, DownloadState, DownloadStates, Downloader};
+use listui_lib::player::Player;
+use listui_lib::tools::YtdlpStatus;
 
 use ratatui::backend::CrosstermBackend;
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::text::Span;
+use ratatui::widgets::Clear;
 use ratatui::Frame;
 use ratatui::Terminal;
 use tokio::runtime;
 use tokio::sync::mpsc;
 
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::error::Error;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crossterm::event;
-use crossterm::event::{Event, KeyCode};
-use crossterm::execute;
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, KeyCode, MouseButton, MouseEventKind,
+};
+use crossterm::cursor::{MoveTo, RestorePosition, SavePosition};
+use crossterm::{execute, queue};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
 
-use crate::utils;
-use crate::utils::Message;
-use crate::widgets;
-use crate::widgets::list::ListWidget;
+use crate::config::ConfigError;
+use crate::events::{AfterLoad, AppEvent};
+use crate::exclusions::{Exclusions, EXCLUSIONS_KEY};
+use crate::import::{self, ImportSummary};
+use crate::input;
+use crate::remote::Request;
+use crate::jobs::{ItemStatus, JobId, Scheduler};
+use crate::layout::AppLayout;
+use crate::notifications::{Dispatcher, Kind, Notification};
+use crate::utils::{self, AutoSync};
+use crate::widgets::{self, MinSize};
+use crate::widgets::diagnostics::{DiagnosticsWidget, OutputOption};
+use crate::widgets::batch::BatchWidget;
+use crate::widgets::input::InputWidget;
+use crate::widgets::jobs::JobsWidget;
+use crate::widgets::list::{ListWidget, SortKey};
 use crate::widgets::loading::LoadingWidget;
+use crate::widgets::now_playing::{NowPlaying, NowPlayingWidget};
+use crate::widgets::osd::Osd;
 use crate::widgets::player::PlayerWidget;
+use crate::widgets::status_bar::StatusBar;
+
+// Key of the last version that opened the database, in its app state.
+const VERSION_KEY: &str = "version";
+
+// Keys of the last release that was found, and of when it was checked.
+const RELEASE_VERSION_KEY: &str = "release_version";
+const RELEASE_NOTES_KEY: &str = "release_notes";
+const RELEASE_URL_KEY: &str = "release_url";
+const RELEASE_CHECKED_KEY: &str = "release_checked_at";
+
+// How long the latest release is remembered before checking again.
+const RELEASE_CHECK_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+// The Invidious instances, one per line from the fastest one, and when they were probed.
+const INSTANCE_RANKING_KEY: &str = "instance_ranking";
+const INSTANCE_RANKED_KEY: &str = "instance_ranked_at";
+
+// The instances are probed again when the saved ranking is older than this.
+const INSTANCE_RANKING_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+// How the playlists menu is sorted. The tracks of every playlist are sorted by this key with ":<playlist id>" after it.
+const SORT_KEY: &str = "sort";
+
+// How far back the digest looks for new uploads.
+const DIGEST_PERIOD: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// How often a device to play on is looked for, while there's none.
+pub const OUTPUT_RETRY: Duration = Duration::from_secs(2);
+
+// Two clicks on the same item within this time play or open it, like Enter.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+// Smallest terminal where any screen is drawn.
+const MIN_WIDTH: u16 = 25;
+const MIN_HEIGHT: u16 = 10;
 
 #[derive(Clone, PartialEq)]
 pub enum CurrentScreen {
     Playlists,
     Songs,
     Controls(Box<CurrentScreen>),
+    WhatsNew(Box<CurrentScreen>),
     LoadingScreen,
     ErrorScreen(String, Box<CurrentScreen>),
+    MessageScreen(String, Box<CurrentScreen>),
+    Prompt(Prompt),
+    PlaylistPicker,
+    Diagnostics(Box<CurrentScreen>),
+    Jobs(Box<CurrentScreen>),
+    // The items of a batch job, opened from the jobs screen.
+    Batch(JobId, Box<CurrentScreen>),
+    Library,
+    // Asks whether to search for another upload of `unavailable_track`.
+    Substitute(Box<CurrentScreen>),
+    // The track being played, in big, opened from the songs screen.
+    NowPlaying,
+    // Offers to install yt-dlp, when it's missing or `ytdlp_status` says a newer one is out.
+    InstallYtdlp(Box<CurrentScreen>),
+}
+
+// What to do with the text entered in the input prompt.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Prompt {
+    ImportPlaylistFile,
+    ImportUrlFile,
+    CreatePlaylist,
+    AddUrl,
+    PlaylistStyle,
+    RenamePlaylist,
+    SubscribePodcast,
+    AddLocalFile,
+    TrimTrack,
+    ExclusionRules,
+}
+
+impl Prompt {
+    // The screen the prompt is opened from, and goes back to.
+    fn screen(&self) -> CurrentScreen {
+        match self {
+            Prompt::AddLocalFile | Prompt::TrimTrack => CurrentScreen::Songs,
+            _ => CurrentScreen::Playlists,
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -47,54 +152,105 @@ pub struct ListuiApp {
     runtime: Arc<runtime::Runtime>,
     current_screen: CurrentScreen,
     playlists_widget: ListWidget<Playlist>,
+    picker_widget: ListWidget<Playlist>,
+    library_widget: ListWidget<RemotePlaylist>,
     songs_widget: ListWidget<Track>,
     player_widget: PlayerWidget,
     loading_widget: Option<LoadingWidget>,
-    sender: mpsc::Sender<utils::Message>,
-    recv: mpsc::Receiver<utils::Message>,
+    input_widget: Option<InputWidget>,
+    diagnostics_widget: Option<DiagnosticsWidget>,
+    jobs_widget: JobsWidget,
+    batch_widget: BatchWidget,
+    // Transient messages, shown at the bottom of every screen.
+    status_bar: StatusBar,
+    // The volume or position, drawn big for a moment after they are changed.
+    osd: Osd,
+    now_playing_widget: NowPlayingWidget,
+    layout: AppLayout,
+    sender: mpsc::Sender<AppEvent>,
+    recv: mpsc::Receiver<AppEvent>,
+    media_source: Arc<dyn MediaSource>,
     database: Option<Database>,
+    download_dir: PathBuf,
     current_playlist: Option<String>,
+    current_playlist_id: Option<i32>,
     current_song_ind: Option<usize>,
+    // Ids of the tracks played since the list was opened, for going back with 'b'.
+    played_ids: Vec<i32>,
+    // Where the current shuffle cycle starts in `played_ids`.
+    cycle_start: usize,
     songs_selmode: SelectionMode,
     search_query: String,
+    // The last track that failed to download, while the user decides what to do with it.
+    unavailable_track: Option<Track>,
+    auto_substitute: bool,
+    // Whether the track is paused when the output device disappears. While there's no other
+    // device, when it was last looked for, and whether the track goes on once there is one.
+    pause_on_disconnect: bool,
+    output_lost: Option<Instant>,
+    resume_on_output: bool,
+    downloader: Arc<dyn Downloader>,
+    // Whether each track is downloaded, or how its download is going, shared with the downloader.
+    download_states: DownloadStates,
+    // Shown next to the title of the playlists menu while a playlist is being downloaded.
+    download_status: Option<String>,
+    // A newer version of lisTUI, if there is one.
+    latest_release: Option<Release>,
+    ytdlp_status: Option<YtdlpStatus>,
+    notifications: Dispatcher,
+    // Runs the background jobs, that can be paused with z.
+    jobs: Scheduler,
+    // The playlist of every download job, that leaves the download queue if the job is cancelled.
+    download_jobs: HashMap<JobId, i32>,
+    // The job syncing every playlist, so they aren't synced twice at once.
+    sync_job: Option<JobId>,
+    // How many `spawn_db` tasks haven't sent their result yet.
+    db_work: Arc<AtomicUsize>,
+    // The playlist whose tracks were asked for last, so a slower load of another one is dropped.
+    loading_songs: Option<i32>,
+    // When and where the mouse was last clicked, to tell double clicks apart.
+    last_click: Option<(Instant, u16, u16)>,
+    // How the thumbnail of the track being played is drawn, if ALBUM_ART isn't off.
+    album_art: Option<Protocol>,
+    // The thumbnail of the track being played, and the id of the track.
+    artwork: Option<(i32, Artwork)>,
+    // Where the thumbnail was drawn in the last frame, and the track it was. With graphics
+    // protocols, it's only written to the terminal again if any of them changes.
+    art_area: Option<(i32, Rect)>,
+    shown_art: Option<(i32, Rect)>,
+    // Looks up the artist, album and year of the tracks as they are played, if MUSICBRAINZ is set.
+    musicbrainz: Option<MusicBrainzClient>,
+    safe_mode: bool,
 }
 
 impl ListuiApp {
     pub fn new(playlist_dir: PathBuf, dao: Database) -> Result<Self> {
-        let (sender, recv) = mpsc::channel::<utils::Message>(5);
-        let runtime = Arc::new(
-            runtime::Builder::new_multi_thread()
-                .enable_all()
-                .worker_threads(2)
-                .build()
-                .expect("Failed to create runtime"),
-        );
-
-        Ok(Self {
-            current_screen: CurrentScreen::Playlists,
-            playlists_widget: ListWidget::with_items(
-                "Playlists (press h for help)",
-                dao.get_playlists()?,
-            ),
-            songs_widget: ListWidget::empty("..."),
-            player_widget: PlayerWidget::try_new(
-                &playlist_dir,
-                Arc::clone(&runtime),
-                sender.clone(),
-                3,
-            )?,
-            loading_widget: None,
-            sender,
-            recv,
-
-            database: Some(dao),
+        Self::with_parts(
+            playlist_dir,
+            dao,
+            utils::get_player().map_err(|e| anyhow::anyhow!("{e}"))?,
+            Arc::new(utils::get_downloader()),
+            Arc::new(utils::YoutubeSource::default()),
+        )
+    }
 
-            current_playlist: None,
-            current_song_ind: None,
-            songs_selmode: SelectionMode::Follow,
-            search_query: String::new(),
-            runtime,
-        })
+    /// Creates an app that plays tracks with `player`, downloads them with `downloader`
+    /// and fetches new playlists from `media_source`.
+    pub fn with_parts(
+        playlist_dir: PathBuf,
+        dao: Database,
+        player: Arc<dyn Player>,
+        downloader: Arc<dyn Downloader>,
+        media_source: Arc<dyn MediaSource>,
+    ) -> Result<Self> {
+        let playlists = dao.get_playlists()?;
+        let upgraded = record_version(&dao, !playlists.is_empty())?;
+        let mut app = Self::build(playlist_dir, Some(dao), player, downloader, media_source);
+        app.set_playlists(playlists);
+        if upgraded {
+            app.current_screen = CurrentScreen::WhatsNew(Box::new(CurrentScreen::Playlists));
+        }
+        Ok(app)
     }
 
     pub fn new_open_playlist(
@@ -107,13 +263,66 @@ impl ListuiApp {
         Ok(app)
     }
 
+    pub fn new_open_video(
+        playlist_dir: PathBuf,
+        dao: Database,
+        yt_id: String,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut app = ListuiApp::new(playlist_dir, dao)?;
+        app.fetch_new_video(yt_id);
+        Ok(app)
+    }
+
+    pub fn new_open_channel(
+        playlist_dir: PathBuf,
+        dao: Database,
+        channel: String,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut app = ListuiApp::new(playlist_dir, dao)?;
+        app.fetch_new_channel(channel);
+        Ok(app)
+    }
+
+    /// Opens the playlist of a directory, saving it first if it's new.
+    pub fn new_open_folder(
+        playlist_dir: PathBuf,
+        dao: Database,
+        path: PathBuf,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let (playlist, _) = utils::sync_music_dir(&dao, &path)?;
+        let songs = dao.get_tracks(playlist.id)?;
+        let mut app = ListuiApp::new(playlist_dir, dao)?;
+        app.show_songs(playlist, songs);
+        app.current_screen = CurrentScreen::Songs;
+        Ok(app)
+    }
+
     pub fn with_tracks(
         playlist_dir: PathBuf,
+        playlist_name: String,
         tracks: Vec<Track>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let playlist_name = (|| Some(playlist_dir.file_name()?.to_string_lossy().to_string()))()
-            .unwrap_or(String::from("Unknown playlist."));
+        let mut app = Self::build(
+            playlist_dir,
+            None,
+            utils::get_player()?,
+            Arc::new(utils::get_downloader()),
+            Arc::new(utils::YoutubeSource::default()),
+        );
+        app.current_screen = CurrentScreen::Songs;
+        app.songs_widget = ListWidget::with_items(&playlist_name, tracks);
+        app.current_playlist = Some(playlist_name);
+        Ok(app)
+    }
 
+    fn build(
+        playlist_dir: PathBuf,
+        database: Option<Database>,
+        player: Arc<dyn Player>,
+        downloader: Arc<dyn Downloader>,
+        media_source: Arc<dyn MediaSource>,
+    ) -> Self {
+        let (sender, recv) = mpsc::channel::<AppEvent>(64);
         let runtime = Arc::new(
             runtime::Builder::new_multi_thread()
                 .enable_all()
@@ -121,313 +330,2007 @@ impl ListuiApp {
                 .build()
                 .expect("Failed to create runtime"),
         );
-        let (sender, recv) = mpsc::channel::<utils::Message>(5);
 
-        Ok(Self {
-            current_screen: CurrentScreen::Songs,
+        let jobs = Scheduler::new(runtime.handle().clone());
+        let player_widget = PlayerWidget::new(
+            &playlist_dir,
+            Arc::clone(&runtime),
+            sender.clone(),
+            player,
+            Arc::clone(&downloader),
+        );
+
+        Self {
+            current_screen: CurrentScreen::Playlists,
             playlists_widget: ListWidget::empty("Playlists (press h for help)"),
-            songs_widget: ListWidget::with_items(
-                playlist_dir.file_name().unwrap().to_str().unwrap(),
-                tracks,
-            ),
-            player_widget: PlayerWidget::try_new(
-                &playlist_dir,
-                Arc::clone(&runtime),
-                sender.clone(),
-                3,
-            )?,
+            picker_widget: ListWidget::empty("Add to playlist"),
+            library_widget: ListWidget::empty("Library"),
+            songs_widget: ListWidget::empty("..."),
+            layout: AppLayout::new(Rect::default(), player_widget.min_size().1),
+            player_widget,
             loading_widget: None,
+            input_widget: None,
+            diagnostics_widget: None,
+            jobs_widget: JobsWidget::default(),
+            batch_widget: BatchWidget::default(),
+            status_bar: StatusBar::default(),
+            osd: Osd::default(),
+            now_playing_widget: NowPlayingWidget::default(),
             sender,
             recv,
+            media_source,
 
-            database: None,
+            database,
+            download_dir: playlist_dir,
 
-            current_playlist: Some(playlist_name),
+            current_playlist: None,
+            current_playlist_id: None,
             current_song_ind: None,
+            played_ids: Vec::new(),
+            cycle_start: 0,
             songs_selmode: SelectionMode::Follow,
             search_query: String::new(),
+            unavailable_track: None,
+            auto_substitute: utils::auto_substitute(),
+            pause_on_disconnect: utils::pause_on_disconnect(),
+            output_lost: None,
+            resume_on_output: false,
+            download_states: downloader.states(),
+            downloader,
+            download_status: None,
+            latest_release: None,
+            ytdlp_status: None,
+            notifications: Dispatcher::from_env(runtime.handle().clone()).with_gate(jobs.gate()),
+            jobs,
+            download_jobs: HashMap::new(),
+            sync_job: None,
+            db_work: Arc::new(AtomicUsize::new(0)),
+            loading_songs: None,
+            last_click: None,
+            album_art: utils::album_art(),
+            artwork: None,
+            art_area: None,
+            shown_art: None,
+            musicbrainz: utils::musicbrainz().then(MusicBrainzClient::default),
+            safe_mode: false,
             runtime,
-        })
+        }
+    }
+
+    /// Disables the background jobs and shows a banner, to debug the app with the default settings.
+    pub fn set_safe_mode(&mut self) {
+        self.safe_mode = true;
+    }
+
+    /// Looks for a newer version of lisTUI in the background, unless it was checked in the last day.
+    pub fn check_for_updates(&mut self) {
+        let Some(dao) = self.database.as_ref().filter(|_| !self.safe_mode) else { return };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let checked_at = dao
+            .get_state(RELEASE_CHECKED_KEY)
+            .ok()
+            .flatten()
+            .and_then(|checked_at| checked_at.parse::<u64>().ok());
+
+        if checked_at.is_some_and(|checked_at| now.saturating_sub(checked_at) < RELEASE_CHECK_PERIOD.as_secs()) {
+            let saved = |key| dao.get_state(key).ok().flatten();
+            if let (Some(version), Some(notes), Some(url)) =
+                (saved(RELEASE_VERSION_KEY), saved(RELEASE_NOTES_KEY), saved(RELEASE_URL_KEY))
+            {
+                self.latest_release = Some(Release { version, notes, url })
+                    .filter(|release| utils::is_newer_version(&release.version, env!("CARGO_PKG_VERSION")));
+            }
+            return;
+        }
+
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        self.jobs.spawn("Check for a new version", move |_| async move {
+            let result = media_source.fetch_latest_release().await;
+            let _ = sender.send(AppEvent::LatestRelease(result)).await;
+        });
+    }
+
+    /// Checks in the background that yt-dlp can be run and, with CHECK_UPDATES, that it's the
+    /// latest version, offering to install it otherwise.
+    pub fn check_ytdlp(&mut self) {
+        if self.safe_mode { return; }
+        let online = utils::check_updates();
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        self.jobs.spawn("Check yt-dlp", move |_| async move {
+            let result = media_source.check_ytdlp(online).await;
+            let _ = sender.send(AppEvent::Ytdlp(result)).await;
+        });
+    }
+
+    // Lifts the DOWNLOAD_RATE_LIMIT of the downloads that start from now on, or sets it back.
+    fn toggle_rate_limit(&mut self) {
+        match self.downloader.toggle_rate_limit() {
+            Some(true) => {
+                let rate = utils::download_options().rate_limit.unwrap_or_default();
+                self.status_bar.info(format!("Downloads are limited to {rate}/s again."));
+            }
+            Some(false) => self.status_bar.info("The download speed is no longer limited."),
+            None => self.status_bar.error("Set DOWNLOAD_RATE_LIMIT, like 500K, to limit the download speed."),
+        }
+    }
+
+    // Downloads the latest yt-dlp into the data directory, which is used from then on.
+    fn install_ytdlp(&mut self) {
+        self.status_bar.info("Downloading yt-dlp...");
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        self.jobs.spawn("Install yt-dlp", move |_| async move {
+            let result = media_source.install_ytdlp().await;
+            let _ = sender.send(AppEvent::YtdlpInstalled(result)).await;
+        });
+    }
+
+    /// Tries the Invidious instances from the fastest one, probing them again in the background
+    /// if they weren't in the last day. Only Invidious is ranked, so nothing is done with YT_API_KEY.
+    pub fn rank_instances(&mut self) {
+        if env::var("YT_API_KEY").is_ok() { return; }
+        let Some(dao) = self.database.as_ref().filter(|_| !self.safe_mode) else { return };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let ranked_at = dao
+            .get_state(INSTANCE_RANKED_KEY)
+            .ok()
+            .flatten()
+            .and_then(|ranked_at| ranked_at.parse::<u64>().ok());
+
+        // A stale ranking is still better than the default order, until the new one arrives.
+        if let Some(ranking) = dao.get_state(INSTANCE_RANKING_KEY).ok().flatten() {
+            utils::set_instance_ranking(ranking.lines().map(String::from).collect());
+        }
+        if ranked_at.is_some_and(|ranked_at| now.saturating_sub(ranked_at) < INSTANCE_RANKING_PERIOD.as_secs()) {
+            return;
+        }
+
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        self.jobs.spawn("Rank the Invidious instances", move |_| async move {
+            let ranking = media_source.rank_instances().await;
+            let _ = sender.send(AppEvent::InstanceRanking(ranking)).await;
+        });
+    }
+
+    /// Lists the lines of the config file that were skipped, before showing the current screen.
+    pub fn show_config_errors(&mut self, errors: &[ConfigError]) {
+        if errors.is_empty() { return; }
+        let lines: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        let message = format!("Some settings of the config file were skipped, the defaults are used instead:\n{}", lines.join("\n"));
+        self.current_screen = CurrentScreen::ErrorScreen(message, Box::new(self.current_screen.clone()));
     }
 
     pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
-        let tick_rate = Duration::from_millis(500); // TODO: add config for this.
-        let mut last_tick = Instant::now();
+        // The visualizer needs to be drawn often to follow the music.
+        let tick_rate = Duration::from_millis(if utils::visualizer() { 50 } else { 500 }); // TODO: add config for this.
 
         enable_raw_mode()?;
         let mut stdout = std::io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
+        execute!(stdout, EnterAlternateScreen, EnableBracketedPaste, EnableMouseCapture)?;
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
 
-        loop {
+        input::spawn_input_thread(self.sender.clone());
+        self.spawn_ticker(tick_rate);
+
+        'main: loop {
             terminal.draw(|f| self.draw(f))?;
+            self.show_artwork(terminal.backend_mut())?;
 
-            if let Err(err) = self.check_message_received() {
-                self.set_error(err)
+            let Some(event) = self.recv.blocking_recv() else { break };
+            if self.handle_event(event) {
+                break;
             }
 
-            let timeout = tick_rate
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or_else(|| Duration::from_secs(0));
-
-            if crossterm::event::poll(timeout)? {
-                if let Event::Key(key) = event::read()? {
-                    match self.process_input(key.code) {
-                        Ok(false) => {}
-                        Ok(true) => break,
-                        Err(err) => self.set_error(err),
-                    }
+            // Handle everything that is already queued before drawing again.
+            while let Some(event) = self.try_recv_event() {
+                if self.handle_event(event) {
+                    break 'main;
                 }
             }
-
-            if last_tick.elapsed() >= tick_rate {
-                last_tick = Instant::now();
-            }
         }
 
         disable_raw_mode()?;
         terminal.backend_mut();
 
-        execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
+        execute!(terminal.backend_mut(), DisableMouseCapture, DisableBracketedPaste, LeaveAlternateScreen,)?;
 
         terminal.show_cursor()?;
 
         Ok(())
     }
 
-    fn check_message_received(&mut self) -> Result<(), Box<dyn Error>> {
-        if let Ok(msg) = self.recv.try_recv() {
-            return match msg {
-                Message::SongFinished => {
-                    self.play_next();
-                    Ok(())
-                }
-
-                Message::PlaylistUpdate(Ok((playlist_id, tracks))) => {
-                    self.database
-                        .as_ref()
-                        .expect("No connection to database.")
-                        .replace_tracks(playlist_id, tracks)?;
-                    self.current_screen = CurrentScreen::Playlists;
-                    Ok(())
-                }
+    // Writes the thumbnail of the track into the cells left for it, when it's drawn with a
+    // graphics protocol instead of blocks.
+    fn show_artwork(&mut self, out: &mut impl Write) -> std::io::Result<()> {
+        let shown = self.art_area.take();
+        if shown == self.shown_art {
+            return Ok(());
+        }
+        // Kitty keeps its images over the text until they are deleted.
+        if self.shown_art.take().is_some() && self.album_art == Some(Protocol::Kitty) {
+            write!(out, "\x1b_Ga=d,q=2\x1b\\")?;
+        }
+        let (Some((track_id, area)), Some(protocol), Some((_, artwork))) = (shown, self.album_art, self.artwork.as_ref()) else {
+            return out.flush();
+        };
+        // Terminals that don't report their size in pixels get the usual cells of 8x16.
+        let cell_size = crossterm::terminal::window_size()
+            .ok()
+            .filter(|size| size.width > 0 && size.columns > 0 && size.rows > 0)
+            .map_or((8, 16), |size| (size.width / size.columns, size.height / size.rows));
+        if let Some(sequence) = widgets::artwork::escape_sequence(artwork, protocol, area, cell_size) {
+            queue!(out, SavePosition, MoveTo(area.x, area.y))?;
+            write!(out, "{sequence}")?;
+            queue!(out, RestorePosition)?;
+        }
+        self.shown_art = Some((track_id, area));
+        out.flush()
+    }
 
-                Message::NewPlaylist(Ok((new_playlist, tracks))) => {
-                    let dao = self.database.as_ref().expect("No connection to database.");
-                    let playlist = dao.save_playlist(new_playlist)?;
-                    dao.save_tracks(tracks, playlist.id)?;
-                    self.current_screen = CurrentScreen::Playlists;
-                    self.playlists_widget = ListWidget::with_items(
-                        "Playlists (press h for help)",
-                        dao.get_playlists()?,
-                    );
-                    self.playlists_widget
-                        .select_ind(self.playlists_widget.total_len() - 1);
-                    Ok(())
-                }
+    /// Takes the requests of other instances from `listener`, see `remote`.
+    #[cfg(unix)]
+    pub fn serve(&self, listener: std::os::unix::net::UnixListener) -> std::io::Result<()> {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
-                Message::DownloadProgress(message) => {
-                    if let Some(widget) = &mut self.loading_widget {
-                        widget.change_label(message);
+        listener.set_nonblocking(true)?;
+        let _runtime = self.runtime.enter();
+        let listener = tokio::net::UnixListener::from_std(listener)?;
+        let sender = self.sender.clone();
+        self.runtime.spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        log::error!("Stopped listening to other instances: {e}");
+                        break;
                     }
-                    Ok(())
-                }
-                Message::PlaylistUpdate(error) => error.map(|(_, _)| Ok(()))?,
-                Message::NewPlaylist(error) => error.map(|(_, _)| Ok(()))?,
-            };
-        }
-
+                };
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let reply = match Request::parse(&line) {
+                            Some(request) => {
+                                log::info!("Another instance asked to {line}.");
+                                let _ = sender.send(AppEvent::Remote(request)).await;
+                                "ok\n"
+                            }
+                            None => "unknown request\n",
+                        };
+                        if write.write_all(reply.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
         Ok(())
     }
 
-    fn load_songs(&mut self, playlist_id: i32) -> Result<(), DbError> {
-        // Loads from the DB all tracks of the playlist with the given id.
-        if let Some(ref dao) = self.database {
-            let playlist = dao.get_playlist(playlist_id)?;
-            let songs = dao.get_tracks(playlist_id)?;
-            self.songs_widget = ListWidget::with_items(&playlist.title, songs);
-            self.current_playlist = Some(playlist.title);
+    /// Sends an `AppEvent::Tick` once per `tick_rate`, until the app is closed.
+    fn spawn_ticker(&self, tick_rate: Duration) {
+        let sender = self.sender.clone();
+        self.runtime.spawn(async move {
+            let mut interval = tokio::time::interval(tick_rate);
+            loop {
+                interval.tick().await;
+                if sender.send(AppEvent::Tick).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
 
-            Ok(())
-        } else {
-            Err(DbError::ConnectionError)
-        }
+    /// Returns the next queued event, if there is any.
+    pub fn try_recv_event(&mut self) -> Option<AppEvent> {
+        self.recv.try_recv().ok()
     }
 
-    fn draw(&mut self, frame: &mut Frame) {
-        if frame.size().width < 25 {
-            widgets::draw_error_msg(frame, "-->(x_x)<--");
-        } else if frame.size().height < 10 {
-            widgets::draw_error_msg(frame, "Please make the terminal a bit taller :(")
-        } else {
-            match &self.current_screen {
-                CurrentScreen::Playlists => self.draw_playlists(frame, frame.size()),
-                CurrentScreen::Songs => self.draw_songs(frame, frame.size()),
-                CurrentScreen::Controls(_) => widgets::draw_controls_screen(frame, frame.size()),
-                CurrentScreen::LoadingScreen => self.draw_loading_screen(frame, frame.size()),
-                CurrentScreen::ErrorScreen(msg, _) => widgets::draw_error_msg(frame, msg),
-            }
-        };
+    /// Whether something is still being read from or saved to the database in the background,
+    /// whose result will arrive as an event.
+    #[cfg(test)]
+    pub fn has_db_work(&self) -> bool {
+        self.db_work.load(Ordering::SeqCst) > 0
     }
 
-    fn draw_loading_screen(&mut self, frame: &mut Frame, area: Rect) {
-        if let Some(widget) = self.loading_widget.as_mut() {
-            widget.draw(frame, area);
+    /// Applies an event, showing the error screen if it fails.
+    ///
+    /// Returns true when the app needs to terminate.
+    pub fn handle_event(&mut self, event: AppEvent) -> bool {
+        match self.update(event) {
+            Ok(quit) => quit,
+            Err(err) => {
+                self.set_error(err);
+                false
+            }
         }
     }
 
-    fn draw_playlists(&mut self, frame: &mut Frame, area: Rect) {
-        if area.height < 20 || area.width < 50 {
-            self.playlists_widget.draw(frame, area);
-        } else {
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints(
-                    [Constraint::Length(10), Constraint::Length(area.height - 10)].as_ref(),
-                )
-                .split(area);
+    /// Applies an event to the app state.
+    ///
+    /// Returns true when the app needs to terminate.
+    pub fn update(&mut self, event: AppEvent) -> Result<bool, Box<dyn Error>> {
+        match event {
+            AppEvent::Key(key) => return self.process_input(key.code),
 
-            widgets::draw_logo(frame, chunks[0]);
-            self.playlists_widget.draw(frame, chunks[1]);
-        }
-    }
+            AppEvent::Tick => self.check_output(),
+            AppEvent::SongStarted => {}
 
-    fn draw_songs(&mut self, frame: &mut Frame, area: Rect) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Length(area.height - 5), Constraint::Length(5)].as_ref())
-            .split(area);
+            AppEvent::Resize(width, height) => {
+                log::debug!("Terminal resized to {width}x{height}.");
+                self.relayout(Rect::new(0, 0, width, height));
+            }
 
-        self.songs_widget.draw(frame, chunks[0]);
-        self.player_widget.draw(frame, chunks[1]);
-    }
+            AppEvent::Mouse(mouse) => match mouse.kind {
+                MouseEventKind::ScrollDown => return self.process_input(KeyCode::Down),
+                MouseEventKind::ScrollUp => return self.process_input(KeyCode::Up),
+                MouseEventKind::Down(MouseButton::Left) => return self.click(mouse.column, mouse.row),
+                _ => {}
+            },
 
-    fn process_input(&mut self, key: KeyCode) -> Result<bool, Box<dyn Error>> {
-        // The function returns true when the app needs to terminate.
-        match &self.current_screen {
-            CurrentScreen::Playlists => match key {
-                KeyCode::Down => self.playlists_widget.next(),
-                KeyCode::Up => self.playlists_widget.previous(),
-                KeyCode::Enter => {
-                    if let Some(ind) = self.playlists_widget.get_selected() {
-                        self.open_playlist(ind)?;
+            AppEvent::Paste(text) => self.paste(&text),
+
+            AppEvent::SongFinished => {
+                if let Some(ind) = self.current_song_ind {
+                    // Tracks of temporary playlists aren't in the database.
+                    if self.database.is_some() && self.current_playlist_id.is_some() {
+                        let duration = self.player_widget.duration().map(|duration| duration as i32);
+                        let track = self.songs_widget.get_ind_mut(ind);
+                        let track_id = track.id;
+                        track.play_count += 1;
+                        track.duration = duration.or(track.duration);
+                        self.spawn_db(move |dao| dao.record_play(track_id, duration), AppEvent::DbWrite);
                     }
                 }
-                KeyCode::Char('d') => {
-                    if let Some(ind) = self.playlists_widget.get_selected() {
-                        self.delete_playlist(ind)?;
-                    }
+                if let Some(track) = self.current_song_ind.map(|ind| self.songs_widget.get_ind(ind).clone()) {
+                    let ended = Notification::new(Kind::Ended, format!("{} has ended", track.title))
+                        .with_track(track, self.current_playlist.clone());
+                    self.notifications.dispatch(ended);
                 }
-                KeyCode::Char('u') => {
-                    if let Some(ind) = self.playlists_widget.get_selected() {
-                        self.update_playlist(ind);
-                    }
+                self.save_episode_progress(true)?;
+                self.play_next();
+            }
+
+            AppEvent::TrackUnavailable(track) => {
+                // Tracks of temporary playlists aren't in the database.
+                let substitutable = self.current_playlist_id.is_some()
+                    && track.source_url.is_none()
+                    && track.source_id.as_ref().is_some_and(|id| !id.contains(':'));
+                if substitutable && self.auto_substitute {
+                    // Keep the track selected, it is played again if an alternate is found.
+                    self.find_alternate(track);
+                    return Ok(false);
                 }
-                KeyCode::Char('q') => return Ok(true),
-                KeyCode::Char('h') => {
+                let failure = Notification::new(Kind::Failure, format!("{} could not be downloaded", track.title))
+                    .with_track(track.clone(), self.current_playlist.clone());
+                self.notifications.dispatch(failure);
+                if substitutable && !matches!(self.current_screen, CurrentScreen::Substitute(_)) {
+                    self.unavailable_track = Some(track);
                     self.current_screen =
-                        CurrentScreen::Controls(Box::new(self.current_screen.clone()));
+                        CurrentScreen::Substitute(Box::new(self.current_screen.clone()));
                 }
-                _ => {}
-            },
-            CurrentScreen::Songs => {
-                match key {
-                    KeyCode::Down => {
-                        self.songs_selmode = SelectionMode::Manual;
-                        self.songs_widget.next();
-                    }
-                    KeyCode::Up => {
-                        self.songs_selmode = SelectionMode::Manual;
-                        self.songs_widget.previous();
+                self.save_episode_progress(true)?;
+                self.play_next();
+            }
+
+            AppEvent::Alternate(track, result) => {
+                let alternate = match result {
+                    Ok(alternate) => alternate,
+                    Err(e) => {
+                        log::warn!("Failed to search for another upload of {}: {e}", track.title);
+                        None
                     }
-                    KeyCode::Enter => {
-                        if let Some(ind) = self.songs_widget.get_selected() {
-                            self.play_ind(ind);
-                            self.songs_widget.clear_filter();
-                            self.activate_follow();
+                };
+                let is_current = self
+                    .current_song_ind
+                    .is_some_and(|ind| self.songs_widget.get_ind(ind).id == track.id);
+
+                let Some(video) = alternate else {
+                    if self.auto_substitute {
+                        if is_current {
+                            self.play_next();
                         }
+                    } else {
+                        self.current_screen = CurrentScreen::MessageScreen(
+                            format!("Couldn't find another upload of {}.", track.title),
+                            Box::new(self.current_screen.clone()),
+                        );
                     }
-                    KeyCode::Left => {
-                        self.player_widget.rewind(15);
-                    }
-                    KeyCode::Right => {
-                        self.player_widget.forward(15);
+                    return Ok(false);
+                };
+
+                // The track keeps its title and playlists, only the video changes.
+                let dao = self.database.as_ref().expect("No connection to database.");
+                dao.replace_source(track.id, &video)?;
+                log::info!("Replaced {} with {} ({}).", track.title, video.title, video.source());
+                let ind = (0..self.songs_widget.total_len())
+                    .find(|ind| self.songs_widget.get_ind(*ind).id == track.id);
+                if let Some(ind) = ind {
+                    let song = self.songs_widget.get_ind_mut(ind);
+                    song.source_id = video.source_id.clone();
+                    song.source_url = video.source_url.clone();
+                    song.restriction = None;
+                }
+
+                if self.auto_substitute {
+                    if let (true, Some(ind)) = (is_current, ind) {
+                        // The player still holds the unavailable track, so it has to be stopped first.
+                        self.player_widget.stop();
+                        self.play_ind(ind);
                     }
-                    KeyCode::Char(c) => {
-                        if self.songs_widget.is_filtered() {
-                            self.search_query.push(c);
-                            self.songs_widget.filter(&self.search_query);
+                } else {
+                    self.current_screen = CurrentScreen::MessageScreen(
+                        format!("{} will now be played from {}.", track.title, video.title),
+                        Box::new(self.current_screen.clone()),
+                    );
+                }
+            }
+
+            AppEvent::PlaylistUpdate(result) => {
+                let (playlist_id, details, tracks) = result?;
+                self.loading_widget = Some(LoadingWidget::new("Saving playlist..."));
+                self.spawn_db(
+                    move |dao| {
+                        let playlist = dao.get_playlist(playlist_id)?;
+                        let tracks = kept_tracks(dao, &playlist, tracks);
+                        let yt_ids = if is_podcast(&playlist) {
+                            Vec::new()
                         } else {
-                            match c {
-                                'p' => self.player_widget.toggle_pause(),
-                                'f' => self.activate_follow(),
-                                's' => {
-                                    self.search_query = String::new();
-                                    self.songs_widget.filter("");
-                                }
-                                'n' => self.play_next(),
-                                'b' => self.play_previous(),
-                                'r' => {
-                                    self.stop_playing();
-                                    self.songs_widget.toggle_shuffle();
-                                }
-                                'q' => {
-                                    self.close_playlist();
-                                    // Terminate the app if it was playing a local playlist.
-                                    if self.database.is_none() {
-                                        return Ok(true);
-                                    }
-                                }
-                                'h' => {
-                                    self.current_screen = CurrentScreen::Controls(Box::new(
-                                        self.current_screen.clone(),
-                                    ));
-                                }
-                                '+' => self.player_widget.increase_volume(10),
-                                '-' => self.player_widget.decrease_volume(10),
-                                c => {
-                                    if let Some(digit) = c.to_digit(10) {
-                                        let pcent = digit as u64 * 10;
-                                        self.player_widget.seek_percentage(pcent);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    KeyCode::Backspace => {
-                        if self.songs_widget.is_filtered() {
-                            self.search_query.pop();
-                            self.songs_widget.filter(&self.search_query);
-                        }
+                            tracks.iter().filter_map(|t| t.source_id.clone()).collect()
+                        };
+                        let (playlist, diff) = save_update(dao, &playlist, &details, tracks)?;
+                        Ok((playlist, diff, yt_ids))
+                    },
+                    AppEvent::PlaylistSynced,
+                );
+            }
+
+            AppEvent::PlaylistSynced(result) => {
+                let (playlist, diff, yt_ids) = result?;
+                if !yt_ids.is_empty() {
+                    self.check_restrictions(yt_ids);
+                }
+                self.load_playlists(Some(playlist.id));
+                self.current_screen = CurrentScreen::Playlists;
+                if let Some(diff) = diff {
+                    for (track, title) in &diff.renamed {
+                        utils::rename_track_file(&self.download_dir, track, title);
                     }
-                    KeyCode::Esc => self.songs_widget.clear_filter(),
-                    _ => {}
+                    self.show_update(&playlist, &diff);
                 }
             }
-            CurrentScreen::Controls(previous_screen) => {
-                self.current_screen = *previous_screen.clone()
-            }
-            CurrentScreen::LoadingScreen => {}
-            CurrentScreen::ErrorScreen(_, previous_screen) => {
-                self.current_screen = *previous_screen.clone();
+
+            AppEvent::SyncDue => {
+                self.sync_playlists()?;
             }
-        }
+
+            AppEvent::PlaylistsSynced(synced, yt_ids) => {
+                self.load_playlists(None);
+                if !yt_ids.is_empty() {
+                    self.check_restrictions(yt_ids);
+                }
+                self.notify(Kind::Finished, sync_summary(&synced));
+            }
+
+            AppEvent::NewVideo(result) => {
+                let video = result?;
+                self.check_restrictions(video.source_id.iter().cloned().collect());
+                self.spawn_db(move |dao| dao.add_single(video), AppEvent::SingleSaved);
+            }
+
+            AppEvent::SingleSaved(result) => {
+                let (singles, track) = result?;
+                self.load_playlists(None);
+                self.load_songs(singles.id, AfterLoad::PlayTrack(track.id));
+            }
+
+            AppEvent::PlaylistsLoaded(result, select) => {
+                // Whatever was selected stays selected, unless another playlist should be.
+                let selected = self.playlists_widget.get_selected().map(|ind| self.playlists_widget.get_ind(ind).id);
+                self.set_playlists(result?);
+                if let Some(id) = select.or(selected) {
+                    self.select_playlist(id);
+                }
+            }
+
+            AppEvent::SongsLoaded(result, then) => {
+                let (playlist, songs) = result?;
+                // It's stale if another playlist was opened in the meantime.
+                if self.loading_songs != Some(playlist.id) {
+                    return Ok(false);
+                }
+                self.show_songs(playlist, songs);
+                match then {
+                    AfterLoad::Reload => {}
+                    AfterLoad::Select(ind) => self.songs_widget.select_ind(ind),
+                    AfterLoad::Open => self.current_screen = CurrentScreen::Songs,
+                    AfterLoad::Play(shuffle) => {
+                        self.current_screen = CurrentScreen::Songs;
+                        if shuffle {
+                            self.songs_widget.toggle_shuffle();
+                        }
+                        if self.songs_widget.total_len() > 0 {
+                            self.play_ind(0);
+                        }
+                    }
+                    AfterLoad::PlayTrack(id) => {
+                        self.current_screen = CurrentScreen::Songs;
+                        let ind = (0..self.songs_widget.total_len()).find(|ind| self.songs_widget.get_ind(*ind).id == id);
+                        if let Some(ind) = ind {
+                            self.play_ind(ind);
+                        }
+                    }
+                }
+            }
+
+            AppEvent::SearchResults(playlist_id, query, result) => {
+                // Only the results for what's still in the search bar are shown.
+                if self.current_playlist_id != Some(playlist_id) || !self.songs_widget.is_filtered() || query != self.search_query {
+                    return Ok(false);
+                }
+                match result {
+                    Ok(ids) => {
+                        let ids: HashSet<i32> = ids.into_iter().collect();
+                        self.songs_widget.filter_with(&query, |track| ids.contains(&track.id));
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to search for {query}: {e}");
+                        self.songs_widget.filter(&query);
+                    }
+                }
+            }
+
+            AppEvent::NewPlaylist(result) => {
+                let (new_playlist, tracks) = result?;
+                self.loading_widget = Some(LoadingWidget::new("Saving playlist..."));
+                self.spawn_db(
+                    move |dao| {
+                        let playlist = dao.save_playlist(new_playlist)?;
+                        if is_podcast(&playlist) {
+                            dao.save_episodes(playlist.id, tracks)?;
+                            return Ok((playlist, Vec::new()));
+                        }
+                        let yt_ids = tracks.iter().filter_map(|t| t.source_id.clone()).collect();
+                        dao.save_tracks(tracks, playlist.id)?;
+                        Ok((playlist, yt_ids))
+                    },
+                    AppEvent::PlaylistSaved,
+                );
+            }
+
+            AppEvent::PlaylistSaved(result) => {
+                let (playlist, yt_ids) = result?;
+                if !yt_ids.is_empty() {
+                    self.check_restrictions(yt_ids);
+                }
+                self.current_screen = CurrentScreen::Playlists;
+                self.load_playlists(Some(playlist.id));
+            }
+
+            AppEvent::BulkImport(results) => {
+                self.loading_widget = Some(LoadingWidget::new("Saving playlists..."));
+                self.spawn_db(
+                    move |dao| {
+                        let mut summary = ImportSummary::default();
+                        let mut yt_ids = Vec::new();
+                        for (url, result) in results {
+                            let saved = result
+                                .map_err(|e| e.to_string())
+                                .and_then(|imported| import::save(dao, imported).map_err(|e| e.to_string()));
+                            match saved {
+                                Ok((title, ids)) => {
+                                    summary.imported.push(title);
+                                    yt_ids.extend(ids);
+                                }
+                                Err(e) => summary.failed.push((url, e)),
+                            }
+                        }
+                        Ok((summary, yt_ids))
+                    },
+                    AppEvent::ImportSaved,
+                );
+            }
+
+            AppEvent::ImportSaved(result) => {
+                let (summary, yt_ids) = result?;
+                self.load_playlists(None);
+                self.check_restrictions(yt_ids);
+                self.notify(Kind::Finished, summary.totals());
+                self.current_screen = CurrentScreen::MessageScreen(
+                    summary.report(),
+                    Box::new(CurrentScreen::Playlists),
+                );
+            }
+
+            AppEvent::DbWrite(result) => result?,
+
+            AppEvent::DatabaseBackup(result) => match result {
+                Ok((path, problems)) if problems.is_empty() => {
+                    self.status_bar.info(format!("Database backed up to {}", path.display()));
+                }
+                Ok((path, problems)) => {
+                    self.status_bar.error(format!(
+                        "Backed up to {}, but the database is damaged: {}",
+                        path.display(),
+                        problems.join("; ")
+                    ));
+                }
+                Err(e) => self.status_bar.error(format!("Failed to back up the database: {e}")),
+            },
+
+            AppEvent::Digest(result) => {
+                let videos = result?;
+                if videos.is_empty() {
+                    self.current_screen = CurrentScreen::MessageScreen(
+                        String::from("Your channels haven't uploaded anything in the last week."),
+                        Box::new(CurrentScreen::Playlists),
+                    );
+                } else {
+                    // A temporary playlist, fetched again every time it is opened.
+                    let tracks = videos
+                        .into_iter()
+                        .enumerate()
+                        .map(|(ind, video)| Track {
+                            id: ind as i32,
+                            title: video.title,
+                            source_id: video.source_id,
+                            playlist_id: None,
+                            played: None,
+                            position: None,
+                            restriction: None,
+                            removed: false,
+                            source_url: video.source_url,
+                            artist: video.artist,
+                            album: video.album,
+                            track_number: video.track_number,
+                            duration: None,
+                            play_count: 0,
+                            trim_start: None,
+                            trim_end: None,
+                            year: None,
+                            metadata_checked: false,
+                            uploaded_at: None,
+                        })
+                        .collect();
+                    let title = "New this week";
+                    self.songs_widget = ListWidget::with_items(title, tracks);
+                    self.forget_played();
+                    self.set_theme(None);
+                    self.current_playlist = Some(String::from(title));
+                    self.current_playlist_id = None;
+                    self.current_screen = CurrentScreen::Songs;
+                }
+            }
+
+            AppEvent::Library(result) => {
+                let playlists = result?;
+                if playlists.is_empty() {
+                    self.current_screen = CurrentScreen::MessageScreen(
+                        String::from("There are no playlists in your library."),
+                        Box::new(CurrentScreen::Playlists),
+                    );
+                } else {
+                    self.library_widget =
+                        ListWidget::with_items("Library (press Enter to import)", playlists);
+                    self.current_screen = CurrentScreen::Library;
+                }
+            }
+
+            AppEvent::Restrictions(result) => {
+                // Not being able to check them isn't worth an error screen.
+                let restrictions = match result {
+                    Ok(restrictions) => restrictions,
+                    Err(e) => {
+                        log::warn!("Could not check the restrictions of the videos: {e}");
+                        return Ok(false);
+                    }
+                };
+
+                for ind in 0..self.songs_widget.total_len() {
+                    let track = self.songs_widget.get_ind_mut(ind);
+                    let restriction = restrictions
+                        .iter()
+                        .find(|(yt_id, _)| track.source_id.as_ref() == Some(yt_id))
+                        .map(|(_, restriction)| *restriction);
+                    if restriction.is_some() {
+                        track.restriction = restriction;
+                    }
+                }
+                self.spawn_db(move |dao| dao.set_restrictions(&restrictions), AppEvent::DbWrite);
+            }
+
+            AppEvent::LatestRelease(result) => {
+                let release = match result {
+                    Ok(release) => release,
+                    Err(e) => {
+                        log::warn!("Could not check the latest release of lisTUI: {e}");
+                        return Ok(false);
+                    }
+                };
+
+                if let Some(dao) = self.database.as_ref() {
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                    dao.set_state(RELEASE_CHECKED_KEY, &now.to_string())?;
+                    if let Some(release) = &release {
+                        dao.set_state(RELEASE_VERSION_KEY, &release.version)?;
+                        dao.set_state(RELEASE_NOTES_KEY, &release.notes)?;
+                        dao.set_state(RELEASE_URL_KEY, &release.url)?;
+                    }
+                }
+                self.latest_release = release
+                    .filter(|release| utils::is_newer_version(&release.version, env!("CARGO_PKG_VERSION")));
+                if let Some(release) = &self.latest_release {
+                    self.notify(Kind::Update, format!("lisTUI {} is out", release.version));
+                }
+            }
+
+            AppEvent::Ytdlp(result) => {
+                let status = match result {
+                    Ok(status) => status,
+                    Err(e) => {
+                        log::warn!("Could not check the latest release of yt-dlp: {e}");
+                        return Ok(false);
+                    }
+                };
+                if status == YtdlpStatus::UpToDate {
+                    return Ok(false);
+                }
+                // Only offered from the playlists menu, so it doesn't take any key meant for something else.
+                if let CurrentScreen::Playlists = self.current_screen {
+                    self.current_screen = CurrentScreen::InstallYtdlp(Box::new(CurrentScreen::Playlists));
+                } else {
+                    self.status_bar.error("yt-dlp is missing or outdated, press Y in the playlists menu to download it.");
+                }
+                self.ytdlp_status = Some(status);
+            }
+
+            AppEvent::YtdlpInstalled(result) => match result {
+                Ok(version) => {
+                    self.ytdlp_status = None;
+                    self.status_bar.info(format!("yt-dlp {version} installed."));
+                }
+                Err(e) => {
+                    log::error!("Failed to install yt-dlp: {e}");
+                    self.status_bar.error(format!("Could not install yt-dlp: {e}"));
+                }
+            },
+
+            AppEvent::InstanceRanking(ranking) => {
+                if ranking.is_empty() { return Ok(false); }
+                for instance in &ranking {
+                    match instance.latency {
+                        Some(latency) => log::info!("{} answered in {}ms.", instance.url, latency.as_millis()),
+                        None => log::warn!("{} is down.", instance.url),
+                    }
+                }
+                let ranking: Vec<String> = ranking.into_iter().map(|instance| instance.url).collect();
+                if let Some(dao) = self.database.as_ref() {
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                    dao.set_state(INSTANCE_RANKING_KEY, &ranking.join("\n"))?;
+                    dao.set_state(INSTANCE_RANKED_KEY, &now.to_string())?;
+                }
+                utils::set_instance_ranking(ranking);
+            }
+
+            AppEvent::PlaylistDownload(status) => self.download_status = Some(status),
+
+            AppEvent::Artwork(track_id, artwork) => {
+                // Another track may have started while it was fetched.
+                let playing = self.current_song_ind.map(|ind| self.songs_widget.get_ind(ind).id);
+                if playing == Some(track_id) {
+                    self.artwork = Some((track_id, artwork));
+                }
+            }
+
+            AppEvent::Metadata(track_id, recording) => {
+                if let Some(dao) = &self.database {
+                    utils::save_metadata(dao, track_id, recording.as_ref())?;
+                    // The list is updated too, if the track is still in it.
+                    if let (Some(recording), Some(_)) = (recording, self.current_playlist_id) {
+                        if let Some(ind) = (0..self.songs_widget.total_len()).find(|ind| self.songs_widget.get_ind(*ind).id == track_id) {
+                            let track = self.songs_widget.get_ind_mut(ind);
+                            track.artist = Some(recording.artist);
+                            track.album = track.album.take().or(recording.album);
+                            track.year = recording.year;
+                        }
+                    }
+                }
+            }
+
+            AppEvent::PlaylistDownloaded(playlist_id) => {
+                self.download_jobs.retain(|_, id| *id != playlist_id);
+                if let Some(dao) = &self.database {
+                    utils::dequeue_download(dao, playlist_id)?;
+                }
+            }
+
+            AppEvent::FilesVerified(playlist_id, result) => {
+                let dao = self.database.as_ref().expect("No connection to database.");
+                let playlist = dao.get_playlist(playlist_id)?;
+                match result {
+                    Ok(0) => self.status_bar.info(format!("The downloads of {} are fine.", playlist.title)),
+                    Ok(damaged) => {
+                        self.status_bar.info(format!(
+                            "{damaged} damaged downloads of {} were deleted, downloading them again.",
+                            playlist.title
+                        ));
+                        self.start_download(playlist)?;
+                    }
+                    Err(e) => self.status_bar.error(format!("Failed to verify {}: {e}", playlist.title)),
+                }
+            }
+
+            AppEvent::Notify(notification) => self.notifications.dispatch(notification),
+
+            AppEvent::FetchProgress(progress) => {
+                if let Some(widget) = &mut self.loading_widget {
+                    widget.show_progress(progress);
+                }
+            }
+
+            AppEvent::LoadingLabel(label) => {
+                if let Some(widget) = &mut self.loading_widget {
+                    widget.change_label(label);
+                }
+            }
+
+            AppEvent::Remote(request) => self.open_request(request)?,
+        }
+
+        Ok(false)
+    }
+
+    // Loads from the DB all tracks of the playlist with the given id, in the background, and
+    // shows them once they're read doing what `then` says.
+    fn load_songs(&mut self, playlist_id: i32, then: AfterLoad) {
+        self.loading_songs = Some(playlist_id);
+        self.spawn_db(
+            move |dao| Ok((dao.get_playlist(playlist_id)?, dao.get_tracks(playlist_id)?)),
+            move |result| AppEvent::SongsLoaded(result, then),
+        );
+    }
+
+    // Shows the tracks of `playlist` in the songs screen, sorted the way the user left them.
+    fn show_songs(&mut self, playlist: Playlist, songs: Vec<Track>) {
+        let title = match &playlist.emoji {
+            Some(emoji) => format!("{emoji} {}", playlist.title),
+            None => playlist.title.clone(),
+        };
+        let sort = self.saved_sort(&format!("{SORT_KEY}:{}", playlist.id));
+        self.songs_widget = ListWidget::with_items(&title, songs).sorted_by(sort);
+        self.forget_played();
+        self.scan_downloads();
+        self.set_theme(playlist.color.as_deref());
+        self.current_playlist = Some(playlist.title);
+        self.current_playlist_id = Some(playlist.id);
+    }
+
+    // Reads the saved playlists again in the background, to show them in the playlists menu.
+    fn load_playlists(&self, select: Option<i32>) {
+        self.spawn_db(|dao| dao.get_playlists(), move |result| AppEvent::PlaylistsLoaded(result, select));
+    }
+
+    // Shows `playlists` in the playlists menu, sorted the way the user left it.
+    fn set_playlists(&mut self, playlists: Vec<Playlist>) {
+        let sort = self.saved_sort(SORT_KEY);
+        self.playlists_widget = ListWidget::with_items("Playlists (press h for help)", playlists).sorted_by(sort);
+    }
+
+    fn select_playlist(&mut self, id: i32) {
+        let ind = (0..self.playlists_widget.total_len()).find(|ind| self.playlists_widget.get_ind(*ind).id == id);
+        if let Some(ind) = ind {
+            self.playlists_widget.select_ind(ind);
+        }
+    }
+
+    fn saved_sort(&self, key: &str) -> SortKey {
+        self.database
+            .as_ref()
+            .and_then(|dao| dao.get_state(key).ok().flatten())
+            .and_then(|name| name.parse().ok())
+            .unwrap_or_default()
+    }
+
+    // The exclusion rules of a playlist. Saved rules that can't be read are ignored.
+    fn exclusions(&self, playlist_id: i32) -> Exclusions {
+        self.database
+            .as_ref()
+            .map(|dao| Exclusions::saved(dao, playlist_id))
+            .unwrap_or_default()
+    }
+
+    // Opens the prompt to change the exclusion rules of a playlist, with the current ones.
+    fn edit_exclusions(&mut self, ind: usize) {
+        let playlist = self.playlists_widget.get_ind(ind);
+        if playlist.yt_id.is_none() || is_folder(playlist) {
+            self.status_bar.error("Only the playlists fetched from YouTube or other sites can exclude videos.");
+            return;
+        }
+        let exclusions = self.exclusions(playlist.id);
+        self.open_prompt(Prompt::ExclusionRules, "Videos to leave out, like title=live|remix; max=15:00; channel=Some Channel");
+        if !exclusions.is_empty() {
+            let widget = self.input_widget.as_mut().expect("No input widget.");
+            exclusions.to_string().chars().for_each(|c| widget.push(c));
+        }
+    }
+
+    // Saves the exclusion rules of the selected playlist, and updates it so they apply right away.
+    fn save_exclusions(&mut self, value: &str) -> Result<(), Box<dyn Error>> {
+        let (Some(dao), Some(ind)) = (self.database.as_ref(), self.playlists_widget.get_selected()) else {
+            return Ok(());
+        };
+        let exclusions = Exclusions::parse(value)?;
+        let playlist_id = self.playlists_widget.get_ind(ind).id;
+        dao.set_state(&format!("{EXCLUSIONS_KEY}:{playlist_id}"), &exclusions.to_string())?;
+        self.update_playlist(ind, false);
+        Ok(())
+    }
+
+    fn save_sort(&self, key: String, sort: SortKey) -> Result<(), DbError> {
+        match self.database.as_ref() {
+            Some(dao) => dao.set_state(&key, sort.name()),
+            None => Ok(()),
+        }
+    }
+
+    // Sorts the tracks by the next key, and remembers it for the playlist. The same track keeps playing.
+    fn cycle_songs_sort(&mut self) -> Result<(), DbError> {
+        let playing = self.current_song_ind.map(|ind| self.songs_widget.get_ind(ind).id);
+        let sort = self.songs_widget.cycle_sort();
+        self.current_song_ind = playing
+            .and_then(|id| (0..self.songs_widget.total_len()).find(|ind| self.songs_widget.get_ind(*ind).id == id));
+        match self.current_playlist_id {
+            Some(playlist_id) => self.save_sort(format!("{SORT_KEY}:{playlist_id}"), sort),
+            None => Ok(()),
+        }
+    }
+
+    fn relayout(&mut self, area: Rect) {
+        self.layout = AppLayout::new(area, self.player_widget.min_size().1);
+    }
+
+    // Area the current screen needs to be drawn.
+    fn min_size(&self) -> (u16, u16) {
+        let (width, height) = match &self.current_screen {
+            CurrentScreen::Playlists => self.playlists_widget.min_size(),
+            CurrentScreen::Prompt(prompt) if !matches!(prompt.screen(), CurrentScreen::Songs) => self.playlists_widget.min_size(),
+            CurrentScreen::Library => self.library_widget.min_size(),
+            CurrentScreen::Songs | CurrentScreen::PlaylistPicker | CurrentScreen::Prompt(_) => {
+                let (songs, player) = (self.songs_widget.min_size(), self.player_widget.min_size());
+                (songs.0.max(player.0), songs.1 + player.1)
+            }
+            CurrentScreen::Diagnostics(_) => self
+                .diagnostics_widget
+                .as_ref()
+                .map(|widget| widget.min_size())
+                .unwrap_or_default(),
+            CurrentScreen::Jobs(_) | CurrentScreen::Batch(_, _) => self.jobs_widget.min_size(),
+            CurrentScreen::NowPlaying => self.now_playing_widget.min_size(),
+            CurrentScreen::Controls(_)
+            | CurrentScreen::WhatsNew(_)
+            | CurrentScreen::LoadingScreen
+            | CurrentScreen::ErrorScreen(_, _)
+            | CurrentScreen::MessageScreen(_, _)
+            | CurrentScreen::Substitute(_)
+            | CurrentScreen::InstallYtdlp(_) => (0, 0),
+        };
+
+        (width.max(MIN_WIDTH), height.max(MIN_HEIGHT))
+    }
+
+    // Uses the color of the playlist on the songs screen.
+    fn set_theme(&mut self, color: Option<&str>) {
+        let accent = color.and_then(widgets::parse_color).unwrap_or(widgets::ACC_COLOR);
+        self.songs_widget.set_accent(accent);
+        self.player_widget.set_accent(accent);
+        self.now_playing_widget.set_accent(accent);
+        self.osd.set_accent(accent);
+        self.batch_widget.set_accent(accent);
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        // Resizes are handled as events too, but the first draw and some
+        // terminals can still get here with a different size.
+        if frame.size() != self.layout.area {
+            self.relayout(frame.size());
+        }
+
+        let min_size = self.min_size();
+        if !widgets::fits(frame.size(), min_size) {
+            widgets::draw_size_hint(frame, min_size);
+        } else {
+            match &self.current_screen {
+                CurrentScreen::Playlists => self.draw_playlists(frame),
+                CurrentScreen::Songs => self.draw_songs(frame),
+                CurrentScreen::Controls(_) => widgets::draw_controls_screen(frame, frame.size()),
+                CurrentScreen::WhatsNew(_) => widgets::draw_whats_new(frame, frame.size()),
+                CurrentScreen::LoadingScreen => self.draw_loading_screen(frame, frame.size()),
+                CurrentScreen::ErrorScreen(msg, _) => widgets::draw_error_msg(frame, msg),
+                CurrentScreen::MessageScreen(msg, _) => widgets::draw_message(frame, msg),
+                CurrentScreen::Prompt(prompt) => {
+                    match prompt.screen() {
+                        CurrentScreen::Songs => self.draw_songs(frame),
+                        _ => self.draw_playlists(frame),
+                    }
+                    if let Some(widget) = self.input_widget.as_mut() {
+                        widget.draw(frame, frame.size());
+                    }
+                }
+                CurrentScreen::PlaylistPicker => {
+                    self.draw_songs(frame);
+                    self.draw_picker(frame, frame.size());
+                }
+                CurrentScreen::Diagnostics(_) => {
+                    let info = self.player_widget.player_info();
+                    if let Some(widget) = self.diagnostics_widget.as_mut() {
+                        widget.draw(frame, frame.size(), &info);
+                    }
+                }
+                CurrentScreen::Jobs(_) => {
+                    self.jobs_widget.update(self.jobs.jobs(), self.jobs.is_paused());
+                    self.jobs_widget.draw(frame, frame.size());
+                }
+                CurrentScreen::Batch(id, _) => {
+                    self.jobs_widget.update(self.jobs.jobs(), self.jobs.is_paused());
+                    self.jobs_widget.draw(frame, frame.size());
+                    if let Some(job) = self.jobs.job(*id) {
+                        self.batch_widget.update(&job);
+                    }
+                    self.batch_widget.draw(frame, frame.size());
+                }
+                CurrentScreen::Library => self.library_widget.draw(frame, frame.size()),
+                CurrentScreen::NowPlaying => self.draw_now_playing(frame),
+                CurrentScreen::Substitute(_) => {
+                    let title = self.unavailable_track.as_ref().map(|t| t.title.as_str()).unwrap_or_default();
+                    widgets::draw_message(frame, &format!(
+                        "{title} is no longer available.\nPress y to search for another upload, or any other key to skip it."
+                    ));
+                }
+                CurrentScreen::InstallYtdlp(_) => {
+                    let problem = match &self.ytdlp_status {
+                        Some(YtdlpStatus::Outdated { version, latest }) => {
+                            format!("yt-dlp {latest} is out, you have {version}.\nOld versions often fail to download.")
+                        }
+                        _ => String::from("yt-dlp was not found, and it's needed to download the tracks."),
+                    };
+                    widgets::draw_message(frame, &format!(
+                        "{problem}\nPress y to download the latest one into lisTUI's data directory,\nor any other key to skip it."
+                    ));
+                }
+            }
+        };
+
+        if self.safe_mode {
+            widgets::draw_banner(frame, "SAFE MODE");
+        }
+
+        self.status_bar.draw(frame);
+        self.osd.draw(frame);
+
+        if self.notifications.is_flashing() {
+            widgets::draw_flash(frame);
+        }
+    }
+
+    fn draw_loading_screen(&mut self, frame: &mut Frame, area: Rect) {
+        if let Some(widget) = self.loading_widget.as_mut() {
+            widget.draw(frame, area);
+        }
+    }
+
+    fn draw_playlists(&mut self, frame: &mut Frame) {
+        if let Some(logo) = self.layout.logo {
+            widgets::draw_logo(frame, logo);
+        }
+        let mut title = String::from("Playlists (press h for help)");
+        // Recent notifications are shown instead of the progress of the download.
+        if let Some(status) = self.notifications.status().or(self.download_status.as_deref()) {
+            title.push_str(&format!(" - {status}"));
+        }
+        if let Some(release) = &self.latest_release {
+            title.push_str(&format!(" - lisTUI {} is out (press v)", release.version));
+        }
+        if self.jobs.is_paused() {
+            title.push_str(" - background jobs paused (press z)");
+        }
+        self.playlists_widget.set_title(&title);
+        let selected = self.playlists_widget.get_selected().map(|ind| self.playlists_widget.get_ind(ind))
+            .filter(|playlist| has_details(playlist))
+            .cloned();
+        match selected.zip(self.layout.playlists_with_details) {
+            Some((playlist, (playlists, details))) => {
+                self.playlists_widget.draw(frame, playlists);
+                widgets::draw_playlist_details(frame, details, &playlist);
+            }
+            None => self.playlists_widget.draw(frame, self.layout.playlists),
+        }
+    }
+
+    fn draw_picker(&mut self, frame: &mut Frame, area: Rect) {
+        let width = area.width.saturating_sub(4).min(60);
+        let height = area.height.saturating_sub(4).min(self.picker_widget.total_len() as u16 + 2);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+
+        frame.render_widget(Clear, popup);
+        self.picker_widget.draw(frame, popup);
+    }
+
+    // Marks the tracks whose file is already in the download directory, so that the list doesn't
+    // look for their files every time it's drawn.
+    fn scan_downloads(&self) {
+        for track in self.songs_widget.items().iter().filter(|track| !track.is_local()) {
+            let Some(id) = track.source() else { continue };
+            let exists = utils::get_track_path(&self.download_dir, track).exists();
+            match self.download_states.get(id) {
+                Some(DownloadState::Queued | DownloadState::Downloading) => {}
+                _ if exists => self.download_states.set(id, DownloadState::Downloaded),
+                // Removed since it was downloaded.
+                Some(DownloadState::Downloaded) => self.download_states.remove(id),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw_songs(&mut self, frame: &mut Frame) {
+        // The thumbnail is hidden under prompts and pickers, that would be drawn over it.
+        let playing = self.current_song_ind.map(|ind| self.songs_widget.get_ind(ind).id);
+        let art = self.artwork.as_ref()
+            .filter(|(track_id, _)| Some(*track_id) == playing && self.current_screen == CurrentScreen::Songs)
+            .zip(self.album_art)
+            .zip(self.layout.songs_with_art);
+        let states = self.download_states.clone();
+        let badge = |track: &Track| download_badge(&states, track);
+        match art {
+            Some((((track_id, artwork), protocol), (songs, area))) => {
+                self.songs_widget.draw_with_badges(frame, songs, badge);
+                let block = widgets::BLOCK.clone().title("Now playing");
+                self.art_area = Some((*track_id, block.inner(area)));
+                widgets::artwork::draw_artwork(frame, area, block, artwork, protocol);
+            }
+            None => self.songs_widget.draw_with_badges(frame, self.layout.songs, badge),
+        }
+        self.player_widget.draw(frame, self.layout.player);
+    }
+
+    fn draw_now_playing(&mut self, frame: &mut Frame) {
+        let ind = self.current_song_ind;
+        let len = self.songs_widget.total_len();
+        // As many as could fit, in the order `play_next` goes through them.
+        let upcoming = ind
+            .map(|ind| (1..len.min(frame.size().height as usize)).map(|offset| self.songs_widget.get_ind((ind + offset) % len)).collect())
+            .unwrap_or_default();
+        let now = NowPlaying {
+            track: ind.map(|ind| self.songs_widget.get_ind(ind)),
+            progress: self.player_widget.progress().zip(self.player_widget.duration()),
+            paused: self.player_widget.is_paused(),
+            volume: self.player_widget.volume(),
+            shuffled: self.songs_widget.is_shuffled(),
+            upcoming,
+        };
+        self.now_playing_widget.draw(frame, frame.size(), &now);
+    }
+
+    // Selects the item clicked, and plays or opens it on a double click. Clicking the progress bar
+    // seeks there.
+    fn click(&mut self, column: u16, row: u16) -> Result<bool, Box<dyn Error>> {
+        let now = Instant::now();
+        let double = self.last_click
+            .is_some_and(|(at, last_column, last_row)| (last_column, last_row) == (column, row) && now - at < DOUBLE_CLICK);
+        // A third click starts another double click.
+        self.last_click = (!double).then_some((now, column, row));
+
+        let selected = match self.current_screen {
+            CurrentScreen::Playlists => self.playlists_widget.select_at(column, row),
+            CurrentScreen::Songs => {
+                if self.player_widget.seek_at(column, row) {
+                    self.show_position();
+                    return Ok(false);
+                }
+                let selected = self.songs_widget.select_at(column, row);
+                if selected {
+                    self.songs_selmode = SelectionMode::Manual;
+                }
+                selected
+            }
+            CurrentScreen::PlaylistPicker => self.picker_widget.select_at(column, row),
+            CurrentScreen::Library => self.library_widget.select_at(column, row),
+            _ => false,
+        };
+        if selected && double {
+            return self.process_input(KeyCode::Enter);
+        }
+        Ok(false)
+    }
+
+    fn process_input(&mut self, key: KeyCode) -> Result<bool, Box<dyn Error>> {
+        // The function returns true when the app needs to terminate.
+        // Esc also dismisses the message in the status bar, like it did the message screens.
+        if key == KeyCode::Esc {
+            self.status_bar.clear();
+        }
+        match &self.current_screen {
+            CurrentScreen::Playlists => match key {
+                KeyCode::Down => self.playlists_widget.next(),
+                KeyCode::Up => self.playlists_widget.previous(),
+                KeyCode::Enter => {
+                    if let Some(ind) = self.playlists_widget.get_selected() {
+                        self.open_playlist(ind);
+                    }
+                }
+                KeyCode::Char('d') => {
+                    if let Some(ind) = self.playlists_widget.get_selected() {
+                        self.delete_playlist(ind)?;
+                    }
+                }
+                KeyCode::Char('u') => {
+                    if let Some(ind) = self.playlists_widget.get_selected() {
+                        self.update_playlist(ind, false);
+                    }
+                }
+                KeyCode::Char('U') => {
+                    if let Some(ind) = self.playlists_widget.get_selected() {
+                        self.update_playlist(ind, true);
+                    }
+                }
+                KeyCode::Char('A') => self.update_all_playlists()?,
+                KeyCode::Char('g') => {
+                    if let Some(ind) = self.playlists_widget.get_selected() {
+                        self.download_playlist(ind)?;
+                    }
+                }
+                KeyCode::Char('e') => {
+                    if let Some(ind) = self.playlists_widget.get_selected() {
+                        self.export_playlist(ind, ExportFormat::M3u)?;
+                    }
+                }
+                KeyCode::Char('E') => {
+                    if let Some(ind) = self.playlists_widget.get_selected() {
+                        self.export_playlist(ind, ExportFormat::Json)?;
+                    }
+                }
+                KeyCode::Char('i') => {
+                    self.open_prompt(Prompt::ImportPlaylistFile, "Path to a .m3u/.pls file");
+                }
+                KeyCode::Char('I') => {
+                    self.open_prompt(Prompt::ImportUrlFile, "Path to a text file with one URL per line");
+                }
+                KeyCode::Char('a') => {
+                    self.open_prompt(Prompt::AddUrl, "YouTube playlist, video, channel or Bandcamp album URL");
+                }
+                KeyCode::Char('c') => {
+                    self.open_prompt(Prompt::CreatePlaylist, "Name of the new playlist");
+                }
+                KeyCode::Char('p') => {
+                    self.open_prompt(Prompt::SubscribePodcast, "URL of the podcast's RSS feed");
+                }
+                KeyCode::Char('t') if self.playlists_widget.get_selected().is_some() => {
+                    self.open_prompt(
+                        Prompt::PlaylistStyle,
+                        "Color and emoji, like \"red 🎸\" (\"none\" to remove them)",
+                    );
+                }
+                KeyCode::Char('r') => {
+                    if let Some(ind) = self.playlists_widget.get_selected() {
+                        let title = self.playlists_widget.get_ind(ind).title.clone();
+                        self.open_prompt(Prompt::RenamePlaylist, "New name of the playlist");
+                        let widget = self.input_widget.as_mut().expect("No input widget.");
+                        title.chars().for_each(|c| widget.push(c));
+                    }
+                }
+                KeyCode::Char('x') => {
+                    if let Some(ind) = self.playlists_widget.get_selected() {
+                        self.edit_exclusions(ind);
+                    }
+                }
+                KeyCode::Char('l') => self.fetch_library(),
+                KeyCode::Char('w') => self.fetch_digest()?,
+                KeyCode::Char('v') => {
+                    if let Some(release) = &self.latest_release {
+                        self.current_screen = CurrentScreen::MessageScreen(
+                            format!("lisTUI {}\n\n{}\n\n{}", release.version, release.notes.trim(), release.url),
+                            Box::new(CurrentScreen::Playlists),
+                        );
+                    }
+                }
+                KeyCode::Char('o') => self.open_diagnostics(),
+                KeyCode::Char('O') => {
+                    let sort = self.playlists_widget.cycle_sort();
+                    self.save_sort(String::from(SORT_KEY), sort)?;
+                }
+                KeyCode::Char('z') => self.toggle_jobs(),
+                KeyCode::Char('Y') => self.install_ytdlp(),
+                KeyCode::Char('B') => self.backup_database(),
+                KeyCode::Char('L') => self.toggle_rate_limit(),
+                KeyCode::Char('V') => {
+                    if let Some(ind) = self.playlists_widget.get_selected() {
+                        self.verify_playlist(ind)?;
+                    }
+                }
+                KeyCode::Char('j') => {
+                    self.current_screen = CurrentScreen::Jobs(Box::new(self.current_screen.clone()));
+                }
+                KeyCode::Char('q') => return Ok(true),
+                KeyCode::Char('h') => {
+                    self.current_screen =
+                        CurrentScreen::Controls(Box::new(self.current_screen.clone()));
+                }
+                key => {
+                    navigate(&mut self.playlists_widget, key);
+                }
+            },
+            CurrentScreen::Songs => {
+                match key {
+                    KeyCode::Down => {
+                        self.songs_selmode = SelectionMode::Manual;
+                        self.songs_widget.next();
+                    }
+                    KeyCode::Up => {
+                        self.songs_selmode = SelectionMode::Manual;
+                        self.songs_widget.previous();
+                    }
+                    KeyCode::Enter => {
+                        if let Some(ind) = self.songs_widget.get_selected() {
+                            self.play_ind(ind);
+                            self.songs_widget.clear_filter();
+                            self.activate_follow();
+                        }
+                    }
+                    KeyCode::Left => {
+                        self.player_widget.rewind(15);
+                        self.show_position();
+                    }
+                    KeyCode::Right => {
+                        self.player_widget.forward(15);
+                        self.show_position();
+                    }
+                    KeyCode::Char(c) => {
+                        if self.songs_widget.is_filtered() {
+                            self.search_query.push(c);
+                            self.search_songs();
+                        } else {
+                            match c {
+                                'p' => self.player_widget.toggle_pause(),
+                                'f' => self.activate_follow(),
+                                's' => {
+                                    self.search_query = String::new();
+                                    self.songs_widget.filter("");
+                                }
+                                ' ' => {
+                                    self.songs_selmode = SelectionMode::Manual;
+                                    self.songs_widget.toggle_mark();
+                                    self.songs_widget.next();
+                                }
+                                'a' => self.open_picker()?,
+                                'o' => self.open_diagnostics(),
+                                'O' => self.cycle_songs_sort()?,
+                                'v' => self.current_screen = CurrentScreen::NowPlaying,
+                                'x' => self.remove_from_playlist()?,
+                                'd' => self.download_tracks()?,
+                                'D' => self.delete_downloads(),
+                                'L' => self.toggle_rate_limit(),
+                                'i' if self.current_playlist_id.is_some() => {
+                                    self.open_prompt(Prompt::AddLocalFile, "Path of the file to add after the selected track");
+                                }
+                                't' => {
+                                    if let Some(ind) = self.songs_widget.get_selected().filter(|_| self.database.is_some()) {
+                                        let track = self.songs_widget.get_ind(ind);
+                                        let trimmed = track.trim_start.is_some() || track.trim_end.is_some();
+                                        let trim = if trimmed { utils::trim_str(track.trim_start, track.trim_end) } else { String::new() };
+                                        self.open_prompt(Prompt::TrimTrack, "Where to start and stop playing, like 0:30-3:45 (\"none\" for the whole track)");
+                                        let widget = self.input_widget.as_mut().expect("No input widget.");
+                                        trim.chars().for_each(|c| widget.push(c));
+                                    }
+                                }
+                                'g' | 'G' => {
+                                    self.songs_selmode = SelectionMode::Manual;
+                                    navigate(&mut self.songs_widget, key);
+                                }
+                                'K' => self.move_track(true)?,
+                                'J' => self.move_track(false)?,
+                                'n' => self.play_next(),
+                                'b' => self.play_previous(),
+                                'r' => {
+                                    self.stop_playing();
+                                    self.songs_widget.toggle_shuffle();
+                                }
+                                'q' => {
+                                    self.close_playlist();
+                                    // Terminate the app if it was playing a local playlist.
+                                    if self.database.is_none() {
+                                        return Ok(true);
+                                    }
+                                }
+                                'h' => {
+                                    self.current_screen = CurrentScreen::Controls(Box::new(
+                                        self.current_screen.clone(),
+                                    ));
+                                }
+                                '+' => self.change_volume(10),
+                                '-' => self.change_volume(-10),
+                                c => {
+                                    if let Some(digit) = c.to_digit(10) {
+                                        let pcent = digit as u64 * 10;
+                                        self.player_widget.seek_percentage(pcent);
+                                        self.show_position();
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    KeyCode::Backspace if self.songs_widget.is_filtered() => {
+                        self.search_query.pop();
+                        self.search_songs();
+                    }
+                    KeyCode::Esc if self.songs_widget.is_filtered() => self.songs_widget.clear_filter(),
+                    KeyCode::Esc => self.songs_widget.clear_marks(),
+                    key => {
+                        if navigate(&mut self.songs_widget, key) {
+                            self.songs_selmode = SelectionMode::Manual;
+                        }
+                    }
+                }
+            }
+            CurrentScreen::Controls(previous_screen) if key == KeyCode::Char('n') => {
+                self.current_screen = CurrentScreen::WhatsNew(previous_screen.clone())
+            }
+            CurrentScreen::Controls(previous_screen) | CurrentScreen::WhatsNew(previous_screen) => {
+                self.current_screen = *previous_screen.clone()
+            }
+            CurrentScreen::LoadingScreen if key == KeyCode::Char('n') => self.media_source.skip_source(),
+            CurrentScreen::LoadingScreen => {}
+            CurrentScreen::ErrorScreen(_, previous_screen)
+            | CurrentScreen::MessageScreen(_, previous_screen) => {
+                self.current_screen = *previous_screen.clone();
+            }
+            CurrentScreen::Diagnostics(previous_screen) => {
+                let previous_screen = previous_screen.clone();
+                let widget = self.diagnostics_widget.as_mut().expect("No diagnostics widget.");
+                match key {
+                    KeyCode::Down => widget.next(),
+                    KeyCode::Up => widget.previous(),
+                    KeyCode::Enter => self.switch_output()?,
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.diagnostics_widget = None;
+                        self.current_screen = *previous_screen;
+                    }
+                    _ => {}
+                }
+            }
+            CurrentScreen::Jobs(previous_screen) => match key {
+                KeyCode::Down => self.jobs_widget.next(),
+                KeyCode::Up => self.jobs_widget.previous(),
+                KeyCode::Char('c') => {
+                    if let Some(id) = self.jobs_widget.get_selected() {
+                        self.cancel_job(id)?;
+                    }
+                }
+                KeyCode::Enter => {
+                    let batch = self.jobs_widget.get_selected().filter(|id| self.jobs.job(*id).is_some_and(|job| !job.items.is_empty()));
+                    if let Some(id) = batch {
+                        self.current_screen = CurrentScreen::Batch(id, Box::new(self.current_screen.clone()));
+                    }
+                }
+                KeyCode::Char('z') => self.toggle_jobs(),
+                KeyCode::Esc | KeyCode::Char('q') => self.current_screen = *previous_screen.clone(),
+                _ => {}
+            },
+            // Closing it leaves the job running.
+            CurrentScreen::Batch(id, previous_screen) => match key {
+                KeyCode::Down => self.batch_widget.next(),
+                KeyCode::Up => self.batch_widget.previous(),
+                KeyCode::Char('z') => { self.jobs.toggle_job(*id); },
+                KeyCode::Char('c') => {
+                    let id = *id;
+                    self.cancel_job(id)?;
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.current_screen = *previous_screen.clone(),
+                _ => {}
+            },
+            // Only the keys that control the player, the rest of the songs screen is hidden.
+            CurrentScreen::NowPlaying => match key {
+                KeyCode::Left => {
+                    self.player_widget.rewind(15);
+                    self.show_position();
+                }
+                KeyCode::Right => {
+                    self.player_widget.forward(15);
+                    self.show_position();
+                }
+                KeyCode::Char('p') => self.player_widget.toggle_pause(),
+                KeyCode::Char('n') => self.play_next(),
+                KeyCode::Char('b') => self.play_previous(),
+                KeyCode::Char('+') => self.change_volume(10),
+                KeyCode::Char('-') => self.change_volume(-10),
+                KeyCode::Char(c) if c.is_ascii_digit() => {
+                    self.player_widget.seek_percentage(c.to_digit(10).unwrap_or(0) as u64 * 10);
+                    self.show_position();
+                }
+                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('v') => self.current_screen = CurrentScreen::Songs,
+                _ => {}
+            },
+            CurrentScreen::PlaylistPicker => match key {
+                KeyCode::Down => self.picker_widget.next(),
+                KeyCode::Up => self.picker_widget.previous(),
+                KeyCode::Enter => {
+                    if let Some(ind) = self.picker_widget.get_selected() {
+                        self.add_to_playlist(ind)?;
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.current_screen = CurrentScreen::Songs,
+                key => {
+                    navigate(&mut self.picker_widget, key);
+                }
+            },
+            CurrentScreen::Library => match key {
+                KeyCode::Down => self.library_widget.next(),
+                KeyCode::Up => self.library_widget.previous(),
+                KeyCode::Enter => {
+                    if let Some(ind) = self.library_widget.get_selected() {
+                        let yt_id = self.library_widget.get_ind(ind).yt_id.clone();
+                        self.fetch_new_playlist(yt_id);
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.current_screen = CurrentScreen::Playlists,
+                key => {
+                    navigate(&mut self.library_widget, key);
+                }
+            },
+            CurrentScreen::Substitute(previous_screen) => {
+                self.current_screen = *previous_screen.clone();
+                if let Some(track) = self.unavailable_track.take() {
+                    if key == KeyCode::Char('y') {
+                        self.find_alternate(track);
+                    }
+                }
+            }
+            CurrentScreen::InstallYtdlp(previous_screen) => {
+                self.current_screen = *previous_screen.clone();
+                if key == KeyCode::Char('y') {
+                    self.install_ytdlp();
+                }
+            }
+            CurrentScreen::Prompt(prompt) => {
+                let prompt = *prompt;
+                let widget = self.input_widget.as_mut().expect("No input widget.");
+                match key {
+                    KeyCode::Char(c) => widget.push(c),
+                    KeyCode::Backspace => widget.pop(),
+                    KeyCode::Esc => {
+                        self.input_widget = None;
+                        self.current_screen = prompt.screen();
+                    }
+                    KeyCode::Enter => {
+                        let value = widget.value().trim().to_string();
+                        self.input_widget = None;
+                        self.current_screen = prompt.screen();
+                        self.submit_prompt(prompt, value)?;
+                    }
+                    _ => {}
+                }
+            }
+        }
 
         Ok(false)
     }
 
-    fn open_playlist(&mut self, ind: usize) -> Result<(), DbError> {
-        if utils::probe_ytdlp() && utils::probe_ffmpeg() {
-            let playlist = self.playlists_widget.get_ind(ind);
-            self.load_songs(playlist.id)?;
-            self.current_screen = CurrentScreen::Songs;
+    fn paste(&mut self, text: &str) {
+        // Only the first line is used, the prompts and the search bar are single line.
+        let text = text.lines().next().unwrap_or_default();
+        match self.current_screen {
+            CurrentScreen::Prompt(_) => {
+                if let Some(widget) = self.input_widget.as_mut() {
+                    text.chars().for_each(|c| widget.push(c));
+                }
+            }
+            CurrentScreen::Songs if self.songs_widget.is_filtered() => {
+                self.search_query.push_str(text);
+                self.search_songs();
+            }
+            _ => {}
+        }
+    }
+
+    // A cancelled download leaves the download queue, so it isn't resumed either.
+    fn cancel_job(&mut self, id: JobId) -> Result<(), DbError> {
+        self.jobs.cancel(id);
+        if let (Some(dao), Some(playlist_id)) = (&self.database, self.download_jobs.remove(&id)) {
+            utils::dequeue_download(dao, playlist_id)?;
+        }
+        Ok(())
+    }
+
+    fn toggle_jobs(&mut self) {
+        let paused = self.jobs.toggle();
+        log::info!("Background jobs {}.", if paused { "paused" } else { "resumed" });
+    }
+
+    fn open_diagnostics(&mut self) {
+        let options = utils::available_backends()
+            .into_iter()
+            .map(OutputOption::backend)
+            .chain(listui_lib::player::list_output_devices().into_iter().map(OutputOption::device))
+            .collect();
+
+        self.diagnostics_widget = Some(DiagnosticsWidget::new(options));
+        self.current_screen = CurrentScreen::Diagnostics(Box::new(self.current_screen.clone()));
+    }
+
+    fn switch_output(&mut self) -> Result<(), Box<dyn Error>> {
+        let widget = self.diagnostics_widget.as_ref().expect("No diagnostics widget.");
+        let player: Arc<dyn Player> = match widget.get_selected() {
+            Some(OutputOption::Backend(backend, _)) => utils::create_player(backend)?,
+            Some(OutputOption::Device(device, _)) => Arc::new(utils::create_rodio_player(Some(device))?),
+            None => return Ok(()),
+        };
+
+        self.stop_playing();
+        self.player_widget.set_player(player);
+        Ok(())
+    }
+
+    // Shows the tracks that match the search bar. The ones of a saved playlist are searched in
+    // the database, by the start of the words of their title, artist or album, in the background.
+    // Those results are shown when they arrive, if the query hasn't changed by then.
+    fn search_songs(&mut self) {
+        match (self.current_playlist_id, self.database.is_some()) {
+            (Some(playlist_id), true) => {
+                let (query, searched) = (self.search_query.clone(), self.search_query.clone());
+                self.spawn_db(
+                    move |dao| dao.search_tracks(playlist_id, &searched),
+                    move |result| AppEvent::SearchResults(playlist_id, query, result),
+                );
+            }
+            _ => self.songs_widget.filter(&self.search_query),
+        }
+    }
+
+    fn open_prompt(&mut self, prompt: Prompt, title: &str) {
+        self.input_widget = Some(InputWidget::new(title));
+        self.current_screen = CurrentScreen::Prompt(prompt);
+    }
+
+    fn submit_prompt(&mut self, prompt: Prompt, value: String) -> Result<(), Box<dyn Error>> {
+        if value.is_empty() {
+            return Ok(());
+        }
+
+        match prompt {
+            Prompt::ImportPlaylistFile => self.open_playlist_file(&value),
+            Prompt::ImportUrlFile => self.fetch_url_file(&value),
+            Prompt::CreatePlaylist => self.create_playlist(&value)?,
+            Prompt::PlaylistStyle => self.style_playlist(&value)?,
+            Prompt::RenamePlaylist => self.rename_playlist(&value)?,
+            Prompt::SubscribePodcast => self.fetch_new_playlist(format!("{RSS_PREFIX}{value}")),
+            Prompt::AddLocalFile => self.add_local_file(&value)?,
+            Prompt::TrimTrack => self.trim_track(&value)?,
+            Prompt::ExclusionRules => self.save_exclusions(&value)?,
+            Prompt::AddUrl => {
+                if let Some(yt_id) = utils::parse_playlist_url(&value) {
+                    self.fetch_new_playlist(yt_id);
+                } else if let Some(yt_id) = utils::parse_video_url(&value) {
+                    self.fetch_new_video(yt_id);
+                } else if let Some(channel) = utils::parse_channel_url(&value) {
+                    self.fetch_new_channel(channel);
+                } else if let Some(url) = utils::parse_bandcamp_url(&value) {
+                    self.fetch_new_playlist(url);
+                } else {
+                    self.status_bar.error("Not a YouTube playlist, video, channel or Bandcamp album URL.");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_playlist(&mut self, title: &str) -> Result<(), DbError> {
+        let dao = self.database.as_ref().expect("No connection to database.");
+        let playlist = dao.create_playlist(title)?;
+        let playlists = dao.get_playlists()?;
+        self.set_playlists(playlists);
+        self.select_playlist(playlist.id);
+
+        Ok(())
+    }
+
+    fn style_playlist(&mut self, value: &str) -> Result<(), Box<dyn Error>> {
+        let Some(ind) = self.playlists_widget.get_selected() else { return Ok(()) };
+        let (mut color, mut emoji) = (None, None);
+        if !value.eq_ignore_ascii_case("none") {
+            for word in value.split_whitespace() {
+                if widgets::parse_color(word).is_some() {
+                    color = Some(word);
+                } else if word.is_ascii() {
+                    return Err(format!("Unknown color {word}.").into());
+                } else {
+                    emoji = Some(word);
+                }
+            }
+        }
+
+        let dao = self.database.as_ref().expect("No connection to database.");
+        let id = self.playlists_widget.get_ind(ind).id;
+        dao.set_playlist_style(id, color, emoji)?;
+        let playlists = dao.get_playlists()?;
+        self.set_playlists(playlists);
+        self.select_playlist(id);
+        Ok(())
+    }
+
+    fn rename_playlist(&mut self, title: &str) -> Result<(), DbError> {
+        let Some(ind) = self.playlists_widget.get_selected() else { return Ok(()) };
+        let dao = self.database.as_ref().expect("No connection to database.");
+        let id = self.playlists_widget.get_ind(ind).id;
+        dao.rename_playlist(id, title)?;
+        let playlists = dao.get_playlists()?;
+        self.set_playlists(playlists);
+        self.select_playlist(id);
+        Ok(())
+    }
+
+    fn open_picker(&mut self) -> Result<(), DbError> {
+        // Only playlists created by the user can be modified, and only with tracks from the database.
+        let (Some(dao), Some(_)) = (self.database.as_ref(), self.current_playlist_id) else {
+            return Ok(());
+        };
+        let playlists: Vec<Playlist> = dao
+            .get_playlists()?
+            .into_iter()
+            .filter(|p| p.yt_id.is_none() && Some(p.id) != self.current_playlist_id)
+            .collect();
+
+        if playlists.is_empty() {
+            self.status_bar.error("Create a playlist first (press c in the playlists menu).");
+        } else if !self.chosen_tracks().is_empty() {
+            self.picker_widget = ListWidget::with_items("Add to playlist", playlists);
+            self.current_screen = CurrentScreen::PlaylistPicker;
+        }
+
+        Ok(())
+    }
+
+    // The marked tracks, or the selected one if none is.
+    fn chosen_tracks(&self) -> Vec<usize> {
+        let marked = self.songs_widget.marked();
+        if marked.is_empty() {
+            self.songs_widget.get_selected().into_iter().collect()
+        } else {
+            marked
+        }
+    }
+
+    fn add_to_playlist(&mut self, ind: usize) -> Result<(), DbError> {
+        let dao = self.database.as_ref().expect("No connection to database.");
+        let playlist = self.picker_widget.get_ind(ind);
+        let chosen = self.chosen_tracks();
+        for track_ind in &chosen {
+            dao.add_track(playlist.id, self.songs_widget.get_ind(*track_ind).id)?;
+        }
+
+        let added = match chosen.as_slice() {
+            [] => return Ok(()),
+            [track_ind] => self.songs_widget.get_ind(*track_ind).title.clone(),
+            _ => format!("{} tracks", chosen.len()),
+        };
+        self.status_bar.info(format!("Added {added} to {}", playlist.title));
+        self.songs_widget.clear_marks();
+        Ok(())
+    }
+
+    fn remove_from_playlist(&mut self) -> Result<(), DbError> {
+        let (Some(dao), Some(playlist_id)) = (self.database.as_ref(), self.current_playlist_id) else {
+            return Ok(());
+        };
+        let tracks: Vec<&Track> = self.chosen_tracks().into_iter().map(|ind| self.songs_widget.get_ind(ind)).collect();
+        if tracks.is_empty() {
+            return Ok(());
+        }
+
+        // Local files can be removed from any playlist, since they were added by the user.
+        if dao.get_playlist(playlist_id)?.yt_id.is_some() && tracks.iter().any(|track| !track.is_local()) {
+            self.status_bar.error("Only tracks from your own playlists and local files can be removed.");
+            return Ok(());
+        }
+
+        for track in tracks {
+            dao.remove_track(playlist_id, track.id)?;
+        }
+        self.stop_playing();
+        self.load_songs(playlist_id, AfterLoad::Reload);
+        Ok(())
+    }
+
+    // Deletes the downloaded files of the marked tracks (or the selected one), so they are downloaded
+    // again the next time they're played. The user's own files are left alone.
+    fn delete_downloads(&mut self) {
+        let paths: Vec<PathBuf> = self
+            .chosen_tracks()
+            .into_iter()
+            .map(|ind| self.songs_widget.get_ind(ind))
+            .filter(|track| !track.is_local() && track.source().is_some())
+            .map(|track| utils::get_track_path(&self.download_dir, track))
+            .filter(|path| path.exists())
+            .collect();
+        if paths.is_empty() {
+            self.status_bar.info("Those tracks aren't downloaded.");
+            return;
+        }
+
+        for path in &paths {
+            if let Err(e) = std::fs::remove_file(path) {
+                self.status_bar.error(format!("Failed to delete {}: {e}", path.display()));
+                self.scan_downloads();
+                return;
+            }
+        }
+        self.scan_downloads();
+        self.songs_widget.clear_marks();
+        self.status_bar.info(match paths.len() {
+            1 => String::from("Deleted the download, it will be downloaded again when played."),
+            deleted => format!("Deleted {deleted} downloads, they will be downloaded again when played."),
+        });
+    }
+
+    // Adds a local file to the current playlist, after the selected track (or at the top, if none is).
+    fn add_local_file(&mut self, path: &str) -> Result<(), DbError> {
+        let (Some(dao), Some(playlist_id)) = (self.database.as_ref(), self.current_playlist_id) else {
+            return Ok(());
+        };
+        let path = PathBuf::from(path);
+        if !path.is_file() {
+            self.status_bar.error(format!("{} is not a file.", path.display()));
+            return Ok(());
+        }
+
+        let selected = self.songs_widget.get_selected();
+        let after = selected.map(|ind| self.songs_widget.get_ind(ind).id);
+        let track = dao.insert_track(playlist_id, utils::local_video(&path))?;
+        dao.move_track(playlist_id, track.id, after)?;
+        self.stop_playing();
+        self.load_songs(playlist_id, AfterLoad::Select(selected.map_or(0, |ind| ind + 1)));
+        Ok(())
+    }
+
+    // Saves where the selected track starts and stops playing, from the next time it's played.
+    fn trim_track(&mut self, value: &str) -> Result<(), Box<dyn Error>> {
+        let (Some(dao), Some(ind)) = (self.database.as_ref(), self.songs_widget.get_selected()) else {
+            return Ok(());
+        };
+        let (start, end) = utils::parse_trim(value)?;
+        let track = self.songs_widget.get_ind_mut(ind);
+        dao.set_trim(track.id, start, end)?;
+        track.trim_start = start;
+        track.trim_end = end;
+        Ok(())
+    }
+
+    // Moves the marked tracks (or the selected one) one place up or down, and saves the new order.
+    fn move_track(&mut self, up: bool) -> Result<(), DbError> {
+        if self.database.is_none() || self.current_playlist_id.is_none() {
+            return Ok(());
+        }
+
+        if self.songs_widget.is_shuffled() {
+            self.status_bar.error("Turn off shuffle (press r) to move tracks.");
+            return Ok(());
+        }
+        if self.songs_widget.sort_key() != SortKey::Original {
+            self.status_bar.error("Go back to the playlist's order (press Shift+O) to move tracks.");
+            return Ok(());
+        }
+
+        // The first ones in the direction they move go first, so the tracks behind them follow. Tracks
+        // that reach the top or the bottom stop there, and so do the ones right behind them.
+        let mut chosen = self.chosen_tracks();
+        if !up {
+            chosen.reverse();
+        }
+        let mut stopped = Vec::new();
+        for ind in chosen {
+            let other = if up { ind.checked_sub(1) } else { Some(ind + 1) };
+            match other.filter(|other| *other < self.songs_widget.total_len() && !stopped.contains(other)) {
+                Some(other) => self.swap_tracks(ind, other)?,
+                None => stopped.push(ind),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn swap_tracks(&mut self, ind: usize, other: usize) -> Result<(), DbError> {
+        let (Some(dao), Some(playlist_id)) = (self.database.as_ref(), self.current_playlist_id) else {
+            return Ok(());
+        };
+        dao.swap_tracks(playlist_id, self.songs_widget.get_ind(ind).id, self.songs_widget.get_ind(other).id)?;
+        self.songs_widget.swap(ind, other);
+        // The current track is still the one playing.
+        if self.current_song_ind == Some(ind) {
+            self.current_song_ind = Some(other);
+        } else if self.current_song_ind == Some(other) {
+            self.current_song_ind = Some(ind);
+        }
+
+        Ok(())
+    }
+
+    fn open_playlist_file(&mut self, path: &str) {
+        let path = PathBuf::from(path);
+        match utils::get_playlist_file(&path) {
+            Some(tracks) => {
+                let title = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+                self.songs_widget = ListWidget::with_items(&title, tracks);
+                self.forget_played();
+                self.set_theme(None);
+                self.current_playlist = Some(title);
+                self.current_playlist_id = None;
+                self.current_screen = CurrentScreen::Songs;
+            }
+            None => {
+                self.status_bar.error("Could not read the playlist file.");
+            }
+        }
+    }
+
+    fn open_playlist(&mut self, ind: usize) {
+        let playlist = self.playlists_widget.get_ind(ind);
+        // The files of a directory are played without downloading anything.
+        if is_folder(playlist) || self.media_source.is_available() {
+            self.load_songs(playlist.id, AfterLoad::Open);
         } else {
             self.current_screen = CurrentScreen::ErrorScreen(
-                String::from("Please install yt-dlp and ffmpeg first."),
+                String::from("Please install yt-dlp and ffmpeg first (or set YTDLP_PATH and FFMPEG_PATH). Press Y in the playlists menu to download yt-dlp."),
                 Box::new(self.current_screen.clone()),
             );
         }
+    }
+
+    /// Opens a saved playlist and plays it from the first track, in a random order if `shuffle`
+    /// is set, like `listui play` does.
+    pub fn play_playlist(&mut self, playlist_id: i32, shuffle: bool) {
+        self.load_songs(playlist_id, AfterLoad::Play(shuffle));
+    }
+
+    // What was asked of another `listui`, opened like that one would have, instead of what's playing.
+    fn open_request(&mut self, request: Request) -> Result<(), Box<dyn Error>> {
+        self.stop_playing();
+        let target = match request {
+            Request::Play(name, shuffle) => {
+                let dao = self.database.as_ref().ok_or("There are no saved playlists in this player.")?;
+                match utils::find_playlist(dao, &name)? {
+                    Some(playlist) => self.play_playlist(playlist.id, shuffle),
+                    None => self.status_bar.error(format!("Playlist {name} not found.")),
+                }
+                return Ok(());
+            }
+            Request::Open(target) => target,
+        };
+
+        let playlist = utils::parse_playlist_url(&target).or_else(|| utils::parse_bandcamp_url(&target));
+        let path = Path::new(&target);
+        if let Some(yt_id) = playlist {
+            self.fetch_new_playlist(yt_id);
+        } else if let Some(yt_id) = utils::parse_video_url(&target) {
+            self.fetch_new_video(yt_id);
+        } else if let Some(channel) = utils::parse_channel_url(&target) {
+            self.fetch_new_channel(channel);
+        } else if utils::is_playlist_file(path) {
+            self.open_playlist_file(&target);
+        } else if let (true, Some(dao)) = (path.is_dir(), self.database.as_ref()) {
+            let (playlist, _) = utils::sync_music_dir(dao, path)?;
+            self.load_playlists(None);
+            self.load_songs(playlist.id, AfterLoad::Open);
+        } else {
+            self.status_bar.error(format!("Couldn't open {target}."));
+        }
         Ok(())
     }
 
@@ -435,52 +2338,512 @@ impl ListuiApp {
         let dao = self.database.as_ref().expect("No connection to database.");
         dao.delete_playlist(self.playlists_widget.get_ind(ind).id)?;
         let playlists = dao.get_playlists()?;
-        self.playlists_widget = ListWidget::with_items("Playlists (press h for help)", playlists);
+        self.set_playlists(playlists);
+
+        Ok(())
+    }
+
+    fn export_playlist(&mut self, ind: usize, format: ExportFormat) -> Result<(), Box<dyn Error>> {
+        let dao = self.database.as_ref().expect("No connection to database.");
+        let playlist = self.playlists_widget.get_ind(ind);
+        let path = utils::export_playlist(
+            dao,
+            playlist,
+            &self.download_dir,
+            &self.download_dir,
+            format,
+        )?;
+
+        self.status_bar.info(format!("Playlist exported to {}", path.display()));
+        Ok(())
+    }
+
+    // Backs up the database next to the exported playlists, one file a day, and checks it.
+    fn backup_database(&mut self) {
+        let date = &listui_lib::stats::utc_time(
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64,
+        )[..10];
+        let path = self.download_dir.join(format!("listui-backup-{date}.sqlite"));
+        self.status_bar.info(String::from("Backing up the database..."));
+        self.spawn_db(
+            move |dao| {
+                let problems = dao.check_integrity()?;
+                dao.backup(&path)?;
+                Ok((path, problems))
+            },
+            AppEvent::DatabaseBackup,
+        );
+    }
+
+    pub fn fetch_new_playlist(&mut self, yt_id: String) {
+        let sender = self.sender.clone();
+
+        // Callback that will be called.
+        let runtime = self.runtime.clone();
+        let progress_callback = Box::new(move |progress| {
+            let sender = sender.clone();
+            runtime.spawn(async move {
+                let _ = sender.send(AppEvent::FetchProgress(progress)).await;
+            });
+        });
 
-        Ok(())
+        // Show download screen.
+        self.loading_widget = Some(LoadingWidget::new("Updating playlist..."));
+        self.current_screen = CurrentScreen::LoadingScreen;
+
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        self.runtime.spawn(async move {
+            let result = media_source
+                .fetch_playlist(&yt_id, Some(progress_callback))
+                .await;
+            match result {
+                Ok((playlist, videos)) => {
+                    sender
+                        .send(AppEvent::NewPlaylist(Ok((playlist, videos))))
+                        .await
+                }
+                Err(e) => sender.send(AppEvent::PlaylistUpdate(Err(e))).await,
+            }
+            .expect("Failed to send message.");
+        });
     }
 
-    fn fetch_new_playlist(&mut self, yt_id: String) {
+    /// Fetches the playlist with all the uploads of a channel, and saves it like any other playlist.
+    pub fn fetch_new_channel(&mut self, channel: String) {
         let sender = self.sender.clone();
 
         // Callback that will be called.
         let runtime = self.runtime.clone();
-        let progress_callback = Box::new(move |message| {
+        let progress_callback = Box::new(move |progress| {
             let sender = sender.clone();
             runtime.spawn(async move {
-                let _ = sender.send(Message::DownloadProgress(message)).await;
+                let _ = sender.send(AppEvent::FetchProgress(progress)).await;
             });
         });
 
         // Show download screen.
-        self.loading_widget = Some(LoadingWidget::new("Updating playlist..."));
+        self.loading_widget = Some(LoadingWidget::new("Fetching channel..."));
         self.current_screen = CurrentScreen::LoadingScreen;
 
         let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
         self.runtime.spawn(async move {
-            let result = utils::get_youtube_playlist(&yt_id, Some(progress_callback)).await;
+            let result = match media_source.fetch_channel_uploads(&channel).await {
+                Ok(yt_id) => media_source.fetch_playlist(&yt_id, Some(progress_callback)).await,
+                Err(e) => Err(e),
+            };
             match result {
                 Ok((playlist, videos)) => {
                     sender
-                        .send(utils::Message::NewPlaylist(Ok((playlist, videos))))
+                        .send(AppEvent::NewPlaylist(Ok((playlist, videos))))
                         .await
                 }
-                Err(e) => sender.send(utils::Message::PlaylistUpdate(Err(e))).await,
+                Err(e) => sender.send(AppEvent::PlaylistUpdate(Err(e))).await,
             }
             .expect("Failed to send message.");
         });
     }
 
-    fn update_playlist(&mut self, ind: usize) {
+    pub fn fetch_new_video(&mut self, yt_id: String) {
+        self.loading_widget = Some(LoadingWidget::new("Fetching video..."));
+        self.current_screen = CurrentScreen::LoadingScreen;
+
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        self.runtime.spawn(async move {
+            let result = media_source.fetch_video(&yt_id).await;
+            sender
+                .send(AppEvent::NewVideo(result))
+                .await
+                .expect("Failed to send message.");
+        });
+    }
+
+    /// Imports, one after the other, every URL in a text file.
+    fn fetch_url_file(&mut self, path: &str) {
+        let urls = match import::read_url_file(&PathBuf::from(path)) {
+            Ok(urls) => urls,
+            Err(e) => {
+                self.status_bar.error(format!("Could not read {path}: {e}"));
+                return;
+            }
+        };
+
+        self.loading_widget = Some(LoadingWidget::new(&format!("Importing {} URLs...", urls.len())));
+        self.current_screen = CurrentScreen::LoadingScreen;
+
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        self.runtime.spawn(async move {
+            let total = urls.len();
+            let mut results = Vec::new();
+            for (ind, url) in urls.into_iter().enumerate() {
+                let progress = format!("[{}/{total}] Importing {url}", ind + 1);
+                let _ = sender.send(AppEvent::LoadingLabel(progress)).await;
+                let result = import::fetch_url(media_source.as_ref(), &url, None).await;
+                results.push((url, result));
+            }
+            let _ = sender.send(AppEvent::BulkImport(results)).await;
+        });
+    }
+
+    /// Lists the playlists of the user's media server, so they can be imported.
+    pub fn fetch_library(&mut self) {
+        self.loading_widget = Some(LoadingWidget::new("Fetching library..."));
+        self.current_screen = CurrentScreen::LoadingScreen;
+
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        self.runtime.spawn(async move {
+            let result = media_source.fetch_library().await;
+            sender
+                .send(AppEvent::Library(result))
+                .await
+                .expect("Failed to send message.");
+        });
+    }
+
+    /// Gathers the videos uploaded in the last week by every channel the user follows.
+    pub fn fetch_digest(&mut self) -> Result<(), DbError> {
+        let dao = self.database.as_ref().expect("No connection to database.");
+        let channels: Vec<String> = dao
+            .get_playlists()?
+            .into_iter()
+            .filter(is_channel)
+            .filter_map(|playlist| playlist.yt_id)
+            .collect();
+
+        if channels.is_empty() {
+            self.current_screen = CurrentScreen::MessageScreen(
+                String::from("Add a YouTube channel first (press a in the playlists menu)."),
+                Box::new(CurrentScreen::Playlists),
+            );
+            return Ok(());
+        }
+
+        self.loading_widget = Some(LoadingWidget::new("Fetching new music..."));
+        self.current_screen = CurrentScreen::LoadingScreen;
+
+        let since = SystemTime::now()
+            .checked_sub(DIGEST_PERIOD)
+            .and_then(|since| since.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_secs())
+            .unwrap_or(0);
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        self.runtime.spawn(async move {
+            // A channel that can't be fetched shouldn't hide the others.
+            let mut videos = Vec::new();
+            let mut error = None;
+            for uploads_id in &channels {
+                match media_source.fetch_recent_uploads(uploads_id, since).await {
+                    Ok(uploads) => videos.extend(uploads),
+                    Err(e) => {
+                        log::warn!("Failed to fetch the uploads of {uploads_id}: {e}");
+                        error = Some(e);
+                    }
+                }
+            }
+
+            let result = match error {
+                Some(e) if videos.is_empty() => Err(e),
+                _ => Ok(videos),
+            };
+            let _ = sender.send(AppEvent::Digest(result)).await;
+        });
+
+        Ok(())
+    }
+
+    // Runs `work` on the database away from the UI, since saving a big playlist takes a while, and
+    // handles what it returns as the event `done` makes of it.
+    fn spawn_db<T: Send + 'static>(
+        &self,
+        work: impl FnOnce(&Database) -> Result<T, DbError> + Send + 'static,
+        done: impl FnOnce(Result<T, DbError>) -> AppEvent + Send + 'static,
+    ) {
+        let Some(dao) = self.database.clone() else { return };
+        let sender = self.sender.clone();
+        let pending = Arc::clone(&self.db_work);
+        pending.fetch_add(1, Ordering::SeqCst);
+        self.runtime.spawn(async move {
+            let result = tokio::task::spawn_blocking(move || work(&dao))
+                .await
+                .unwrap_or(Err(DbError::UnknownError));
+            let _ = sender.send(done(result)).await;
+            pending.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+
+    // Checks in the background which of the videos will fail to download.
+    fn check_restrictions(&self, yt_ids: Vec<String>) {
+        if self.safe_mode {
+            return;
+        }
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        let name = format!("Check {} videos for restrictions", yt_ids.len());
+        self.jobs.spawn(name, move |_| async move {
+            let result = media_source.fetch_restrictions(&yt_ids).await;
+            let _ = sender.send(AppEvent::Restrictions(result)).await;
+        });
+    }
+
+    // Downloads and decodes the thumbnail of a video in the background, to show it while it plays.
+    fn fetch_artwork(&self, track: &Track) {
+        let Some(path) = utils::get_thumbnail_path(track) else { return };
+        let (track_id, yt_id) = (track.id, track.source_id.clone().unwrap_or_default());
+        let sender = self.sender.clone();
+        self.runtime.spawn(async move {
+            let result = async {
+                artwork::fetch(&artwork::thumbnail_url(&yt_id), &path).await?;
+                Artwork::decode(&utils::get_tools(), &path, 320, 180).await
+            };
+            match result.await {
+                Ok(artwork) => {
+                    let _ = sender.send(AppEvent::Artwork(track_id, artwork)).await;
+                }
+                Err(e) => log::warn!("Failed to get the thumbnail of {yt_id}: {e}"),
+            }
+        });
+    }
+
+    // Looks up the artist, album and year of a track in MusicBrainz in the background.
+    fn lookup_metadata(&self, track: &Track) {
+        let Some(client) = self.musicbrainz.clone() else { return };
+        let track = track.clone();
+        let sender = self.sender.clone();
+        self.runtime.spawn(async move {
+            match client.lookup(&track.title, track.artist.as_deref(), track.duration).await {
+                Ok(recording) => {
+                    let _ = sender.send(AppEvent::Metadata(track.id, recording)).await;
+                }
+                Err(e) => log::warn!("Failed to look up {} in MusicBrainz: {e}", track.title),
+            }
+        });
+    }
+
+    // Searches in the background for another upload of a track that can't be downloaded.
+    fn find_alternate(&self, track: Track) {
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        self.runtime.spawn(async move {
+            let yt_id = track.source_id.clone().unwrap_or_default();
+            let result = media_source.find_alternate(&track.title, &yt_id).await;
+            let _ = sender.send(AppEvent::Alternate(track, result)).await;
+        });
+    }
+
+    // Downloads, one by one, every track of a playlist that isn't on disk yet.
+    fn download_playlist(&mut self, ind: usize) -> Result<(), DbError> {
+        let playlist = self.playlists_widget.get_ind(ind).clone();
+        if !self.start_download(playlist.clone())? {
+            self.status_bar.info(format!("Every track of {} is already downloaded.", playlist.title));
+        }
+        Ok(())
+    }
+
+    /// Resumes the downloads of playlists that were interrupted by closing lisTUI.
+    pub fn resume_downloads(&mut self) -> Result<(), DbError> {
+        let Some(dao) = self.database.as_ref().filter(|_| !self.safe_mode) else { return Ok(()) };
+        let mut playlists = dao.get_playlists()?;
+        let queue = utils::download_queue(dao)?;
+        for id in queue {
+            match playlists.iter().position(|playlist| playlist.id == id) {
+                Some(ind) => { self.start_download(playlists.swap_remove(ind))?; },
+                // Deleted while it was being downloaded.
+                None => utils::dequeue_download(self.database.as_ref().expect("No connection to database."), id)?,
+            }
+        }
+        Ok(())
+    }
+
+    // Returns false if there was nothing left to download. The playlist stays in the download
+    // queue until every track has been tried, so it's resumed if lisTUI is closed before that.
+    fn start_download(&mut self, playlist: Playlist) -> Result<bool, DbError> {
+        let dao = self.database.as_ref().expect("No connection to database.");
+        let tracks = dao.get_tracks(playlist.id)?;
+        let total = tracks.len();
+        let missing = utils::missing_tracks(&self.download_dir, tracks);
+        if missing.is_empty() {
+            utils::dequeue_download(dao, playlist.id)?;
+            return Ok(false);
+        }
+        utils::queue_download(dao, playlist.id)?;
+        let job = self.spawn_download(playlist.title, Some(playlist.id), total, missing);
+        self.download_jobs.insert(job, playlist.id);
+        Ok(true)
+    }
+
+    // Downloads the marked tracks (or the selected one) of the current playlist in the background.
+    fn download_tracks(&mut self) -> Result<(), DbError> {
+        let tracks: Vec<Track> = self
+            .chosen_tracks()
+            .into_iter()
+            .map(|ind| self.songs_widget.get_ind(ind).clone())
+            .collect();
+        let total = tracks.len();
+        let missing = utils::missing_tracks(&self.download_dir, tracks);
+        if total == 0 {
+            return Ok(());
+        }
+        if missing.is_empty() {
+            self.status_bar.info("Those tracks are already downloaded.");
+            return Ok(());
+        }
+
+        let title = format!("{} tracks of {}", total, self.current_playlist.as_deref().unwrap_or_default());
+        self.spawn_download(title, None, total, missing);
+        self.songs_widget.clear_marks();
+        Ok(())
+    }
+
+    // Decodes the downloaded tracks of a playlist in the background. The damaged ones are deleted,
+    // and downloaded again once every file has been checked.
+    fn verify_playlist(&mut self, ind: usize) -> Result<(), DbError> {
+        let playlist = self.playlists_widget.get_ind(ind).clone();
+        let dao = self.database.as_ref().expect("No connection to database.");
+        let files: Vec<(String, PathBuf)> = dao
+            .get_tracks(playlist.id)?
+            .into_iter()
+            .filter(|track| !track.is_local() && track.source().is_some())
+            .map(|track| (track.title.clone(), utils::get_track_path(&self.download_dir, &track)))
+            .filter(|(_, path)| path.exists())
+            .collect();
+        if files.is_empty() {
+            self.status_bar.info(format!("No track of {} is downloaded.", playlist.title));
+            return Ok(());
+        }
+
+        let sender = self.sender.clone();
+        let tools = utils::get_tools();
+        let name = format!("Verify {}", playlist.title);
+        self.jobs.spawn(name, move |mut job| async move {
+            job.items(files.iter().map(|(title, _)| title.clone()));
+            let mut damaged = 0;
+            for (ind, (title, path)) in files.iter().enumerate() {
+                if !job.proceed().await {
+                    break;
+                }
+                job.item(ind, ItemStatus::Running);
+                let status = match tools.check_audio(path).await {
+                    Ok(None) => ItemStatus::Done,
+                    Ok(Some(problem)) => {
+                        log::warn!("{title} is damaged, deleting it: {problem}");
+                        if let Err(e) = std::fs::remove_file(path) {
+                            log::error!("Failed to delete {}: {e}", path.display());
+                        }
+                        damaged += 1;
+                        ItemStatus::Failed(problem)
+                    }
+                    Err(e) => {
+                        let reason = format!("Failed to run ffmpeg: {e}");
+                        job.item(ind, ItemStatus::Failed(reason.clone()));
+                        let _ = sender.send(AppEvent::FilesVerified(playlist.id, Err(reason))).await;
+                        return;
+                    }
+                };
+                job.item(ind, status);
+                job.progress(format!("{}/{} verified", ind + 1, files.len()));
+            }
+            let _ = sender.send(AppEvent::FilesVerified(playlist.id, Ok(damaged))).await;
+        });
+        Ok(())
+    }
+
+    // Shows how many of the `total` tracks of `title` are downloaded next to the title of the playlists menu,
+    // until every one of `missing` has been tried. Then `playlist_id` leaves the download queue.
+    fn spawn_download(&self, title: String, playlist_id: Option<i32>, total: usize, missing: Vec<Track>) -> JobId {
+        let sender = self.sender.clone();
+        let downloader = Arc::clone(&self.downloader);
+        let states = self.download_states.clone();
+        let dir = self.download_dir.clone();
+        let name = format!("Download {title}");
+        self.jobs.spawn(name, move |mut job| async move {
+            let (mut downloaded, mut failed) = (total - missing.len(), 0);
+            let status = |downloaded: usize, failed: usize| match failed {
+                0 => format!("{title}: {downloaded}/{total} downloaded"),
+                _ => format!("{title}: {downloaded}/{total} downloaded, {failed} failed"),
+            };
+            let _ = sender.send(AppEvent::PlaylistDownload(status(downloaded, failed))).await;
+            job.items(missing.iter().map(|track| track.title.clone()));
+
+            // A cancelled download leaves the queue too, with the tracks it got.
+            for (ind, track) in missing.into_iter().enumerate() {
+                if !job.proceed().await {
+                    break;
+                }
+                job.item(ind, ItemStatus::Running);
+                let path = utils::get_track_path(&dir, &track);
+                let yt_id = track.source().unwrap_or_default();
+                let result = match downloader.download_id(yt_id, &path, None).await {
+                    Some(result) => result,
+                    // Already being downloaded, like when the user is playing it. If that download
+                    // doesn't get the file, it's tried once more here.
+                    None => {
+                        if wait_for_download(&states, yt_id).await {
+                            DownloadResult::Completed(path.clone())
+                        } else {
+                            downloader.download_id(yt_id, &path, None).await
+                                .unwrap_or_else(|| DownloadResult::Failed(String::from("it's still being downloaded somewhere else")))
+                        }
+                    }
+                };
+                let result = match result {
+                    DownloadResult::Completed(_) => {
+                        downloaded += 1;
+                        ItemStatus::Done
+                    }
+                    DownloadResult::Failed(reason) => {
+                        log::warn!("{} could not be downloaded: {reason}", track.title);
+                        failed += 1;
+                        ItemStatus::Failed(reason)
+                    },
+                    DownloadResult::Cancelled => {
+                        failed += 1;
+                        ItemStatus::Failed(String::from("cancelled"))
+                    }
+                };
+                job.item(ind, result);
+                job.progress(format!("{downloaded}/{total} downloaded"));
+                let _ = sender.send(AppEvent::PlaylistDownload(status(downloaded, failed))).await;
+            }
+            let finished = Notification::new(Kind::Finished, status(downloaded, failed));
+            let _ = sender.send(AppEvent::Notify(finished)).await;
+            if let Some(playlist_id) = playlist_id {
+                let _ = sender.send(AppEvent::PlaylistDownloaded(playlist_id)).await;
+            }
+        })
+    }
+
+    // Unless `refresh` is set, the pages fetched recently are taken from the cache.
+    fn update_playlist(&mut self, ind: usize, refresh: bool) {
         let playlist = self.playlists_widget.get_ind(ind).clone();
+        let Some(yt_id) = playlist.yt_id else {
+            self.status_bar.error("This playlist was created by you, it can't be updated.");
+            return;
+        };
+        // Directories are read again right away, there's nothing to fetch.
+        if let Some(dir) = yt_id.strip_prefix(utils::FOLDER_PREFIX) {
+            let dao = self.database.as_ref().expect("No connection to database.");
+            match utils::sync_music_dir(dao, Path::new(dir)) {
+                Ok((playlist, diff)) => self.show_update(&playlist, &diff),
+                Err(e) => self.set_error(e),
+            }
+            return;
+        }
+        if refresh {
+            self.media_source.invalidate_cache(&yt_id);
+        }
         let sender = self.sender.clone();
 
         // Callback that will be called.
         let runtime = self.runtime.clone();
-        let progress_callback = Box::new(move |message| {
+        let progress_callback = Box::new(move |progress| {
             let sender = sender.clone();
             runtime.spawn(async move {
-                let _ = sender.send(Message::DownloadProgress(message)).await;
+                let _ = sender.send(AppEvent::FetchProgress(progress)).await;
             });
         });
 
@@ -489,21 +2852,124 @@ impl ListuiApp {
         self.current_screen = CurrentScreen::LoadingScreen;
 
         let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
         self.runtime.spawn(async move {
-            let result =
-                utils::get_youtube_playlist(&playlist.yt_id, Some(progress_callback)).await;
+            let result = media_source
+                .fetch_playlist(&yt_id, Some(progress_callback))
+                .await;
             match result {
-                Ok((_, videos)) => {
+                Ok((details, videos)) => {
                     sender
-                        .send(utils::Message::PlaylistUpdate(Ok((playlist.id, videos))))
+                        .send(AppEvent::PlaylistUpdate(Ok((playlist.id, details, videos))))
                         .await
                 }
-                Err(e) => sender.send(utils::Message::PlaylistUpdate(Err(e))).await,
+                Err(e) => sender.send(AppEvent::PlaylistUpdate(Err(e))).await,
             }
             .expect("Failed to send message.");
         });
     }
 
+    /// Syncs every playlist in the background as AUTO_SYNC says: right away, and again every
+    /// time its period passes if it has one.
+    pub fn start_auto_sync(&mut self, auto_sync: AutoSync) -> Result<(), DbError> {
+        if self.safe_mode {
+            return Ok(());
+        }
+        match auto_sync {
+            AutoSync::Off => {}
+            AutoSync::Startup => {
+                self.sync_playlists()?;
+            }
+            AutoSync::Every(period) => {
+                let sender = self.sender.clone();
+                self.runtime.spawn(async move {
+                    // The first tick is right away.
+                    let mut interval = tokio::time::interval(period);
+                    loop {
+                        interval.tick().await;
+                        if sender.send(AppEvent::SyncDue).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Fetches every playlist again in a job, one at a time, and saves what changed in them. The
+    // ones made by the user and the directories have nothing to fetch. Returns the job, or the
+    // one of the last sync if it's still going, or `None` if there's nothing to sync.
+    fn sync_playlists(&mut self) -> Result<Option<JobId>, DbError> {
+        if let Some(id) = self.sync_job.filter(|id| self.jobs.job(*id).is_some_and(|job| !job.status.is_done())) {
+            return Ok(Some(id));
+        }
+        let Some(dao) = self.database.clone() else { return Ok(None) };
+        let playlists: Vec<Playlist> = dao
+            .get_playlists()?
+            .into_iter()
+            .filter(|playlist| playlist.yt_id.is_some() && !is_folder(playlist))
+            .collect();
+        if playlists.is_empty() {
+            return Ok(None);
+        }
+
+        let sender = self.sender.clone();
+        let media_source = Arc::clone(&self.media_source);
+        let dir = self.download_dir.clone();
+        let id = self.jobs.spawn("Sync playlists", move |mut job| async move {
+            job.items(playlists.iter().map(|playlist| playlist.title.clone()));
+            let total = playlists.len();
+            let (mut synced, mut yt_ids) = (Vec::new(), Vec::new());
+            for (ind, playlist) in playlists.into_iter().enumerate() {
+                if !job.proceed().await {
+                    break;
+                }
+                job.item(ind, ItemStatus::Running);
+                let title = playlist.title.clone();
+                match sync_playlist(&*media_source, &dao, &dir, playlist).await {
+                    Ok((diff, ids)) => {
+                        job.item(ind, ItemStatus::Done);
+                        yt_ids.extend(ids);
+                        synced.push((title, Ok(diff)));
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to sync {title}: {e}");
+                        job.item(ind, ItemStatus::Failed(e.clone()));
+                        synced.push((title, Err(e)));
+                    }
+                }
+                job.progress(format!("{}/{total} synced", ind + 1));
+            }
+            let _ = sender.send(AppEvent::PlaylistsSynced(synced, yt_ids)).await;
+        });
+        self.sync_job = Some(id);
+        Ok(Some(id))
+    }
+
+    // Updates every playlist at once, showing how each one goes in the batch screen. If they are
+    // already being synced in the background, that job is shown instead of starting another.
+    fn update_all_playlists(&mut self) -> Result<(), DbError> {
+        match self.sync_playlists()? {
+            Some(id) => self.current_screen = CurrentScreen::Batch(id, Box::new(self.current_screen.clone())),
+            None => self.status_bar.error("There's nothing to update, only the playlists from YouTube or other sites can be."),
+        }
+        Ok(())
+    }
+
+    // Tells what changed in an updated playlist, or that nothing did.
+    fn show_update(&mut self, playlist: &Playlist, diff: &TrackDiff) {
+        if diff.is_empty() {
+            self.notify(Kind::Finished, format!("{} is up to date", playlist.title));
+        } else {
+            self.notify(Kind::Finished, format!("{}: {diff}", playlist.title));
+            self.current_screen = CurrentScreen::MessageScreen(
+                update_report(&playlist.title, diff),
+                Box::new(CurrentScreen::Playlists),
+            );
+        }
+    }
+
     fn close_playlist(&mut self) {
         self.stop_playing();
         self.current_screen = CurrentScreen::Playlists;
@@ -517,27 +2983,88 @@ impl ListuiApp {
         }
     }
 
+    // Forgets the tracks that were played, when another list is shown or playing stops.
+    fn forget_played(&mut self) {
+        self.played_ids.clear();
+        self.cycle_start = 0;
+    }
+
+    // Goes back through the tracks that were played, and up the list once there are no more.
     fn play_previous(&mut self) {
+        if self.played_ids.len() > 1 {
+            self.played_ids.pop();
+            let id = self.played_ids.pop().unwrap();
+            // Going back into the last cycle plays its tracks again before the next one.
+            self.cycle_start = self.cycle_start.min(self.played_ids.len());
+            if let Some(ind) = self.songs_widget.position(|track| track.id == id) {
+                self.play_ind(ind);
+                return;
+            }
+        }
+
+        let len = self.songs_widget.total_len();
         let ind = match self.current_song_ind {
-            Some(ind) => (ind - 1) % self.songs_widget.total_len(),
+            Some(ind) => (ind + len - 1) % len,
             None => 0,
         };
 
         self.play_ind(ind);
     }
 
+    // When shuffling, every track is played once before the list is shuffled again.
     fn play_next(&mut self) {
-        let ind = match self.current_song_ind {
-            Some(ind) => (ind + 1) % self.songs_widget.total_len(),
-            None => 0,
+        let len = self.songs_widget.total_len();
+        let next = self.current_song_ind.map_or(0, |ind| (ind + 1) % len);
+        if !self.songs_widget.is_shuffled() {
+            self.play_ind(next);
+            return;
+        }
+
+        // The next track down the list not played in this cycle, wherever it was started from.
+        let played: HashSet<i32> = self.played_ids[self.cycle_start..].iter().copied().collect();
+        let unplayed = (0..len)
+            .map(|step| (next + step) % len)
+            .find(|ind| !played.contains(&self.songs_widget.get_ind(*ind).id));
+        let ind = match unplayed {
+            Some(ind) => ind,
+            None => {
+                let playing = self.current_song_ind.map(|ind| self.songs_widget.get_ind(ind).id);
+                self.songs_widget.reshuffle();
+                self.cycle_start = self.played_ids.len();
+                self.current_song_ind = self.songs_widget.position(|track| Some(track.id) == playing);
+                // The track that just ended isn't played again right away.
+                usize::from(len > 1 && self.current_song_ind == Some(0))
+            }
         };
 
         self.play_ind(ind);
     }
 
+    // Saves where the current podcast episode was left, or marks it as played if it has `finished`.
+    fn save_episode_progress(&mut self, finished: bool) -> Result<(), DbError> {
+        let (Some(dao), Some(ind)) = (self.database.as_ref(), self.current_song_ind) else {
+            return Ok(());
+        };
+        // Only the progress of unplayed episodes is kept.
+        let track = self.songs_widget.get_ind_mut(ind);
+        if track.played != Some(false) {
+            return Ok(());
+        }
+
+        let position = if finished { 0 } else { self.player_widget.progress().unwrap_or(0) as i32 };
+        dao.set_episode_progress(track.id, position, finished)?;
+        track.position = Some(position);
+        track.played = Some(finished);
+        Ok(())
+    }
+
     fn stop_playing(&mut self) {
+        if let Err(e) = self.save_episode_progress(false) {
+            log::error!("Failed to save the progress of the episode: {e}");
+        }
         self.player_widget.stop();
         self.current_song_ind = None;
+        self.forget_played();
     }
 
     fn play_ind(&mut self, ind: usize) {
@@ -546,9 +3073,92 @@ impl ListuiApp {
             self.songs_widget.select_ind(ind);
         }
 
-        let song = self.songs_widget.get_ind(ind);
+        let song = self.songs_widget.get_ind(ind).clone();
+        if self.current_song_ind != Some(ind) {
+            if let Err(e) = self.save_episode_progress(false) {
+                log::error!("Failed to save the progress of the episode: {e}");
+            }
+            let started = Notification::new(Kind::Track, format!("Playing {}", song.title))
+                .with_track(song.clone(), self.current_playlist.clone());
+            self.notifications.dispatch(started);
+            self.played_ids.push(song.id);
+        }
         self.current_song_ind = Some(ind);
-        self.player_widget.play(song.clone());
+        if self.album_art.is_some() && self.artwork.as_ref().map(|(track_id, _)| *track_id) != Some(song.id) {
+            self.artwork = None;
+            self.fetch_artwork(&song);
+        }
+        if !song.metadata_checked && !song.is_local() && self.current_playlist_id.is_some() {
+            self.lookup_metadata(&song);
+        }
+        self.player_widget.play(song);
+    }
+
+    fn change_volume(&mut self, change: i32) {
+        if change > 0 {
+            self.player_widget.increase_volume(change);
+        } else {
+            self.player_widget.decrease_volume(-change);
+        }
+        self.osd.volume(self.player_widget.volume());
+    }
+
+    // Pauses the track when the output device disappears, like unplugged headphones, instead
+    // of letting it go on where nobody hears it (or through the speakers). The output is opened
+    // again on the device there is now, or on the first one that shows up, like the headphones
+    // once they reconnect, and the track goes on from where it was unless PAUSE_ON_DISCONNECT.
+    fn check_output(&mut self) {
+        if !self.player_widget.output_lost() {
+            return;
+        }
+        let waiting = self.output_lost.is_some();
+        if self.output_lost.is_some_and(|looked_at| looked_at.elapsed() < OUTPUT_RETRY) {
+            return;
+        }
+
+        // Nothing is heard until the output is opened again.
+        let playing = self.player_widget.progress().is_some() && !self.player_widget.is_paused();
+        if playing {
+            self.player_widget.toggle_pause();
+        }
+        if !waiting {
+            self.resume_on_output = playing && !self.pause_on_disconnect;
+        }
+
+        match self.player_widget.reopen_output() {
+            Ok(()) => {
+                self.output_lost = None;
+                let device = self.player_widget.player_info().device.unwrap_or_else(|| String::from("the default device"));
+                if self.resume_on_output {
+                    self.player_widget.toggle_pause();
+                    self.status_bar.info(format!("The audio device was disconnected, playing on {device}."));
+                } else if waiting {
+                    self.status_bar.info(format!("Found {device}, press p to go on."));
+                } else if playing {
+                    self.status_bar.error("The audio device was disconnected, the track is paused.");
+                } else {
+                    self.status_bar.error("The audio device was disconnected.");
+                }
+            }
+            Err(e) => {
+                if !waiting {
+                    log::warn!("No audio device to play on: {e}");
+                    self.status_bar.error("The audio device was disconnected, and there's no other one.");
+                }
+                self.output_lost = Some(Instant::now());
+            }
+        }
+    }
+
+    // Shows where the track is after seeking, unless nothing is playing.
+    fn show_position(&mut self) {
+        if let (Some(progress), Some(duration)) = (self.player_widget.progress(), self.player_widget.duration()) {
+            self.osd.seek(progress, duration);
+        }
+    }
+
+    fn notify(&mut self, kind: Kind, message: String) {
+        self.notifications.dispatch(Notification::new(kind, message));
     }
 
     fn set_error(&mut self, error: Box<dyn Error>) {
@@ -560,3 +3170,152 @@ impl ListuiApp {
         self.current_screen = CurrentScreen::ErrorScreen(error.to_string(), following_screen);
     }
 }
+
+// Moves the selection of a list with the keys they all share besides the arrows: a page at a
+// time, or to the first or last item. Returns whether the key was one of them.
+fn navigate<T: Drawable>(list: &mut ListWidget<T>, key: KeyCode) -> bool {
+    match key {
+        KeyCode::PageDown => list.page_down(),
+        KeyCode::PageUp => list.page_up(),
+        KeyCode::Home | KeyCode::Char('g') => list.first(),
+        KeyCode::End | KeyCode::Char('G') => list.last(),
+        _ => return false,
+    }
+    true
+}
+
+// Shown before the title of a track: ↓ once it's downloaded, and ·, … or ! while its download is
+// queued, running or after it failed. Tracks without a download get blanks, to keep the titles aligned.
+fn download_badge(states: &DownloadStates, track: &Track) -> Option<Span<'static>> {
+    let state = track.source().filter(|_| !track.is_local()).and_then(|id| states.get(id));
+    Some(match state {
+        Some(DownloadState::Downloaded) => Span::styled("↓ ", Style::default().fg(Color::Green)),
+        Some(DownloadState::Queued) => Span::styled("· ", Style::default().fg(Color::DarkGray)),
+        Some(DownloadState::Downloading) => Span::styled("… ", Style::default().fg(Color::Yellow)),
+        Some(DownloadState::Failed) => Span::styled("! ", Style::default().fg(Color::Red)),
+        None => Span::raw("  "),
+    })
+}
+
+// Waits for a download another task started to end, returning whether it got the file.
+async fn wait_for_download(states: &DownloadStates, yt_id: &str) -> bool {
+    loop {
+        match states.get(yt_id) {
+            Some(DownloadState::Queued | DownloadState::Downloading) => tokio::time::sleep(Duration::from_millis(200)).await,
+            state => return state == Some(DownloadState::Downloaded),
+        }
+    }
+}
+
+// Channels are saved as the playlist with their uploads, whose id starts with UU.
+fn is_channel(playlist: &Playlist) -> bool {
+    playlist.yt_id.as_ref().is_some_and(|yt_id| yt_id.starts_with("UU"))
+}
+
+fn is_podcast(playlist: &Playlist) -> bool {
+    playlist.yt_id.as_ref().is_some_and(|yt_id| yt_id.starts_with(RSS_PREFIX))
+}
+
+// Playlists saved by older versions have nothing to show under the menu until they are updated.
+fn has_details(playlist: &Playlist) -> bool {
+    playlist.author.is_some() || playlist.description.is_some() || playlist.thumbnail_url.is_some()
+        || playlist.created_at.is_some() || playlist.last_synced_at.is_some()
+}
+
+fn is_folder(playlist: &Playlist) -> bool {
+    playlist.yt_id.as_ref().is_some_and(|yt_id| yt_id.starts_with(utils::FOLDER_PREFIX))
+}
+
+// The tracks of an update that are saved: every episode of a podcast, or the videos the exclusion
+// rules of the playlist don't leave out. Excluded videos that were already saved count as removed.
+fn kept_tracks(dao: &Database, playlist: &Playlist, tracks: Vec<NewVideo>) -> Vec<NewVideo> {
+    if is_podcast(playlist) {
+        return tracks;
+    }
+    let exclusions = Exclusions::saved(dao, playlist.id);
+    tracks.into_iter().filter(|video| !exclusions.excludes(video)).collect()
+}
+
+// Saves the details and tracks of an update. Podcasts only get their new episodes, without a diff.
+fn save_update(dao: &Database, playlist: &Playlist, details: &NewPlaylist, tracks: Vec<NewVideo>) -> Result<(Playlist, Option<TrackDiff>), DbError> {
+    let synced = dao.sync_playlist(playlist.id, details)?;
+    if is_podcast(playlist) {
+        dao.save_episodes(playlist.id, tracks)?;
+        return Ok((synced, None));
+    }
+    let diff = dao.update_tracks(playlist.id, tracks)?;
+    Ok((synced, Some(diff)))
+}
+
+// Fetches a playlist again and saves what changed, renaming the downloads of the renamed tracks.
+// Returns the diff, with the ids of the tracks to check for restrictions if there are new ones.
+async fn sync_playlist(media_source: &dyn MediaSource, dao: &Database, dir: &Path, playlist: Playlist) -> Result<(TrackDiff, Vec<String>), String> {
+    let yt_id = playlist.yt_id.clone().unwrap_or_default();
+    let (details, tracks) = media_source.fetch_playlist(&yt_id, None).await.map_err(|e| e.to_string())?;
+    let dao = dao.clone();
+    let saved = tokio::task::spawn_blocking(move || {
+        let tracks = kept_tracks(&dao, &playlist, tracks);
+        let yt_ids: Vec<String> = tracks.iter().filter_map(|t| t.source_id.clone()).collect();
+        let (_, diff) = save_update(&dao, &playlist, &details, tracks)?;
+        Ok::<_, DbError>(match diff {
+            Some(diff) if !diff.added.is_empty() => (diff, yt_ids),
+            diff => (diff.unwrap_or_default(), Vec::new()),
+        })
+    });
+    let (diff, yt_ids) = saved.await.map_err(|e| e.to_string())?.map_err(|e| e.to_string())?;
+    for (track, title) in &diff.renamed {
+        utils::rename_track_file(dir, track, title);
+    }
+    Ok((diff, yt_ids))
+}
+
+// Like "Synced 12 playlists: +5 new, −2 removed, 1 renamed, 1 failed".
+fn sync_summary(synced: &[(String, Result<TrackDiff, String>)]) -> String {
+    let diffs: Vec<&TrackDiff> = synced.iter().filter_map(|(_, result)| result.as_ref().ok()).collect();
+    let count = |len: fn(&TrackDiff) -> usize| diffs.iter().map(|diff| len(diff)).sum::<usize>();
+    let playlists = if synced.len() == 1 { "playlist" } else { "playlists" };
+    let mut summary = if diffs.iter().all(|diff| diff.is_empty()) {
+        format!("Synced {} {playlists}, nothing changed", synced.len())
+    } else {
+        format!(
+            "Synced {} {playlists}: +{} new, −{} removed, {} renamed",
+            synced.len(),
+            count(|diff| diff.added.len()),
+            count(|diff| diff.removed.len()),
+            count(|diff| diff.renamed.len()),
+        )
+    };
+    let failed = synced.len() - diffs.len();
+    if failed > 0 {
+        summary.push_str(&format!(", {failed} failed"));
+    }
+    summary
+}
+
+// The totals of an update, followed by every track that changed.
+fn update_report(title: &str, diff: &TrackDiff) -> String {
+    let mut report = format!("{title} has been updated: {diff}.\n");
+    for added in &diff.added {
+        report.push_str(&format!("\nNew: {added}"));
+    }
+    for removed in &diff.removed {
+        report.push_str(&format!("\nRemoved: {removed}"));
+    }
+    for (track, title) in &diff.renamed {
+        report.push_str(&format!("\nRenamed: {} -> {title}", track.title));
+    }
+    report
+}
+
+// Saves the version that opened the database, returning whether it was an older one. Databases
+// without a version and without playlists are new, and their users have nothing to catch up on.
+fn record_version(dao: &Database, has_playlists: bool) -> Result<bool> {
+    let version = env!("CARGO_PKG_VERSION");
+    let last = dao.get_state(VERSION_KEY)?;
+    if last.as_deref() == Some(version) {
+        return Ok(false);
+    }
+
+    dao.set_state(VERSION_KEY, version)?;
+    Ok(last.is_some() || has_playlists)
+}
@@ -0,0 +1,314 @@
+//! Reading the config file (~/.config/listui/listui.config).
+//!
+//! Every line is a `KEY=value` pair. Lines that can't be parsed and values that aren't valid
+//! are reported and skipped, so the rest of the file still applies and the defaults are used
+//! for them.
+//!
+//! Settings can also be set as environment variables, or with `--set KEY=value`. The command
+//! line has the highest priority, then the environment, the config file and the defaults.
+//! The rest of the app reads the resolved values from the environment.
+
+use std::env;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use listui_lib::artwork::Protocol;
+use listui_lib::downloader::AudioFormat;
+
+use crate::notifications::{self, Channel};
+use crate::utils;
+
+/// Every setting that can be set in the config file (or as an environment variable).
+pub const SETTINGS: [&str; 42] = [
+    "DATABASE_PATH", "DOWNLOAD_DIR", "MUSIC_DIRS", "YT_API_KEY", "REGION", "LOG_PATH", "PLAYER_BACKEND", "PRELOAD_MB", "AUTO_SUBSTITUTE",
+    "DOWNLOAD_FORMAT", "DOWNLOAD_QUALITY", "EMBED_THUMBNAIL", "YTDLP_ARGS", "YTDLP_PATH", "FFMPEG_PATH", "FPCALC_PATH", "DOWNLOAD_TIMEOUT_MINUTES",
+    "DOWNLOAD_STALL_MINUTES", "DOWNLOAD_RATE_LIMIT", "AUTO_SYNC", "CHECK_UPDATES", "ALBUM_ART", "VISUALIZER", "MUSICBRAINZ", "PAUSE_ON_DISCONNECT",
+    "API_CONNECT_TIMEOUT", "API_TIMEOUT", "API_RETRIES", "API_CACHE_MINUTES", "NOTIFY_STATUS", "NOTIFY_BELL", "NOTIFY_FLASH", "NOTIFY_DESKTOP", "NOTIFY_WEBHOOK", "WEBHOOK_URL",
+    "SUBSONIC_URL", "SUBSONIC_USER", "SUBSONIC_PASSWORD", "JELLYFIN_URL", "JELLYFIN_API_KEY", "LISTENBRAINZ_TOKEN", "LISTENBRAINZ_URL"
+];
+
+/// Settings whose values are hidden by `listui config show`.
+const SECRETS: [&str; 4] = ["YT_API_KEY", "SUBSONIC_PASSWORD", "JELLYFIN_API_KEY", "LISTENBRAINZ_TOKEN"];
+
+/// Where the value of a setting comes from, from the highest priority to the lowest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Cli,
+    Env,
+    File,
+    Default
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Cli => write!(f, "command line"),
+            Source::Env => write!(f, "environment"),
+            Source::File => write!(f, "config file"),
+            Source::Default => write!(f, "default")
+        }
+    }
+}
+
+/// The effective value of a setting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved {
+    pub key: &'static str,
+    /// `None` if the setting isn't set anywhere, and has no default.
+    pub value: Option<String>,
+    pub source: Source
+}
+
+impl std::fmt::Display for Resolved {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}={} ({})", self.key, shown_value(self.key, value), self.source),
+            None => write!(f, "{} is not set", self.key)
+        }
+    }
+}
+
+/// Every setting, resolved from the command line, the environment and the config file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings(Vec<Resolved>);
+
+impl Settings {
+
+    /// Takes each setting from the first of `cli`, the environment and `file` that sets it.
+    pub fn resolve(cli: &[(String, String)], file: &[(String, String)]) -> Self {
+
+        let find = |settings: &[(String, String)], key: &str| settings.iter()
+            .rfind(|(setting, _)| setting == key)
+            .map(|(_, value)| value.clone());
+
+        Self(SETTINGS.into_iter()
+            .map(|key| {
+                let (value, source) = if let Some(value) = find(cli, key) { (Some(value), Source::Cli) }
+                    else if let Ok(value) = env::var(key) { (Some(value), Source::Env) }
+                    else if let Some(value) = find(file, key) { (Some(value), Source::File) }
+                    else { (None, Source::Default) };
+                Resolved { key, value, source }
+            })
+            .collect())
+    }
+
+    /// Every setting with its default value, ignoring everything else.
+    pub fn defaults() -> Self {
+        Self(SETTINGS.into_iter().map(|key| Resolved { key, value: None, source: Source::Default }).collect())
+    }
+
+    /// Sets the resolved values as environment variables, and removes the rest.
+    pub fn apply(&self) {
+        for setting in &self.0 {
+            match &setting.value {
+                Some(value) => env::set_var(setting.key, value),
+                None => env::remove_var(setting.key)
+            }
+        }
+    }
+
+    /// Fills the settings that aren't set with their default values, once applied.
+    pub fn with_defaults(mut self) -> Self {
+        for setting in self.0.iter_mut().filter(|setting| setting.value.is_none()) {
+            setting.value = default_value(setting.key);
+        }
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Resolved> {
+        self.0.iter()
+    }
+}
+
+/// The value of a setting as it's printed, with the API keys and passwords hidden.
+pub fn shown_value<'a>(key: &str, value: &'a str) -> &'a str {
+    if SECRETS.contains(&key) { "********" } else { value }
+}
+
+/// Parses a `--set KEY=value` argument.
+pub fn parse_cli_setting(arg: &str) -> Result<(String, String), String> {
+
+    let (key, value) = arg.split_once('=').ok_or(format!("expected KEY=value, got \"{arg}\""))?;
+    if !SETTINGS.contains(&key) {
+        return Err(format!("unknown setting {key}"));
+    }
+    validate(key, value).map_err(|message| format!("{key}: {message}"))?;
+    Ok((String::from(key), String::from(value)))
+}
+
+/// A line of the config file that was skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    /// Starting from 1.
+    pub line: usize,
+    pub key: Option<String>,
+    pub message: String
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.key {
+            Some(key) => write!(f, "line {} ({key}): {}", self.line, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+/// Reads the settings of the config file at `path`, and the lines that were skipped.
+///
+/// A missing file is not an error, all the settings are optional.
+pub fn read_config(path: &Path) -> (Vec<(String, String)>, Vec<ConfigError>) {
+
+    match read_to_string(path) {
+        Ok(content) => parse_config(&content),
+        Err(_) => (Vec::new(), Vec::new())
+    }
+}
+
+/// Parses the content of a config file into its valid settings, and the lines that aren't.
+pub fn parse_config(content: &str) -> (Vec<(String, String)>, Vec<ConfigError>) {
+
+    let mut settings = Vec::new();
+    let mut errors = Vec::new();
+    for (ind, line) in content.lines().enumerate() {
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |key: Option<&str>, message: String| ConfigError { line: ind + 1, key: key.map(String::from), message };
+        let Some((key, value)) = line.strip_prefix("export ").unwrap_or(line).split_once('=') else {
+            errors.push(error(None, format!("expected KEY=value, got \"{line}\"")));
+            continue;
+        };
+
+        let key = key.trim();
+        if !SETTINGS.contains(&key) {
+            errors.push(error(Some(key), String::from("unknown setting")));
+            continue;
+        }
+
+        let value = unquote(value.trim());
+        match value.and_then(|value| validate(key, &value).map(|_| value)) {
+            Ok(value) => settings.push((String::from(key), value)),
+            Err(message) => errors.push(error(Some(key), message))
+        }
+    }
+
+    (settings, errors)
+}
+
+// What is used when a setting isn't set. It must be called after applying the settings.
+fn default_value(key: &str) -> Option<String> {
+
+    let path = |path: Option<std::path::PathBuf>| path.map(|path| path.display().to_string());
+    match key {
+        "DATABASE_PATH" => path(utils::get_db_path()),
+        "DOWNLOAD_DIR" => path(utils::get_download_dir()),
+        "LOG_PATH" => path(utils::get_log_path()),
+        "PLAYER_BACKEND" => Some(String::from("rodio")),
+        "PRELOAD_MB" => Some(String::from("0")),
+        "AUTO_SUBSTITUTE" => Some(String::from("false")),
+        "DOWNLOAD_FORMAT" => Some(String::from(utils::download_format().extension())),
+        "EMBED_THUMBNAIL" => Some(String::from("true")),
+        "AUTO_SYNC" => Some(String::from("off")),
+        "CHECK_UPDATES" => Some(String::from("false")),
+        "ALBUM_ART" => Some(String::from("off")),
+        "VISUALIZER" => Some(String::from("false")),
+        "MUSICBRAINZ" => Some(String::from("false")),
+        "PAUSE_ON_DISCONNECT" => Some(String::from("true")),
+        "YTDLP_PATH" => Some(String::from("yt-dlp")),
+        "FFMPEG_PATH" => Some(String::from("ffmpeg")),
+        "FPCALC_PATH" => Some(String::from("fpcalc")),
+        "DOWNLOAD_TIMEOUT_MINUTES" => Some(minutes(utils::download_watchdog().timeout)),
+        "DOWNLOAD_STALL_MINUTES" => Some(minutes(utils::download_watchdog().stall_timeout)),
+        "API_CONNECT_TIMEOUT" => Some(utils::api_timeouts().0.as_secs().to_string()),
+        "API_TIMEOUT" => Some(utils::api_timeouts().1.as_secs().to_string()),
+        "API_RETRIES" => Some(utils::retry_policy().retries.to_string()),
+        "API_CACHE_MINUTES" => Some(String::from("30")),
+        "LISTENBRAINZ_URL" => Some(String::from(listui_lib::api::LISTENBRAINZ_URL)),
+        _ => Channel::ALL
+            .into_iter()
+            .find(|channel| channel.setting() == key)
+            .map(|channel| notifications::format_kinds(&channel.default_kinds()))
+    }
+}
+
+// Limits that are disabled are set to 0.
+fn minutes(limit: Option<std::time::Duration>) -> String {
+    limit.map_or(0, |limit| limit.as_secs() / 60).to_string()
+}
+
+// Removes the quotes around a value, if any.
+fn unquote(value: &str) -> Result<String, String> {
+
+    for quote in ['"', '\''] {
+        if let Some(rest) = value.strip_prefix(quote) {
+            return rest.strip_suffix(quote)
+                .map(String::from)
+                .ok_or(format!("missing closing {quote}"));
+        }
+    }
+
+    // Unquoted values can be followed by a comment.
+    Ok(String::from(value.split(" #").next().unwrap_or_default().trim_end()))
+}
+
+// Checks that a value makes sense for its setting, explaining what was expected if it doesn't.
+fn validate(key: &str, value: &str) -> Result<(), String> {
+
+    match key {
+        "PRELOAD_MB" if value.parse::<usize>().is_err() => Err(format!("expected a number of megabytes, got \"{value}\"")),
+        "API_CONNECT_TIMEOUT" | "API_TIMEOUT" if !value.parse::<u64>().is_ok_and(|seconds| seconds > 0) => {
+            Err(format!("expected a number of seconds, got \"{value}\""))
+        },
+        "API_RETRIES" if value.parse::<u32>().is_err() => Err(format!("expected a number of retries, got \"{value}\"")),
+        "API_CACHE_MINUTES" | "DOWNLOAD_TIMEOUT_MINUTES" | "DOWNLOAD_STALL_MINUTES" if value.parse::<u64>().is_err() => Err(format!("expected a number of minutes, got \"{value}\"")),
+        "AUTO_SUBSTITUTE" | "EMBED_THUMBNAIL" | "CHECK_UPDATES" | "VISUALIZER" | "MUSICBRAINZ" | "PAUSE_ON_DISCONNECT" if value != "true" && value != "false" => Err(format!("expected true or false, got \"{value}\"")),
+        "ALBUM_ART" if value != "off" && value.parse::<Protocol>().is_err() => {
+            Err(format!("expected off, auto, blocks, kitty, sixel or iterm, got \"{value}\""))
+        },
+        "DOWNLOAD_FORMAT" if value.parse::<AudioFormat>().is_err() => Err(format!("expected mp3, opus or m4a, got \"{value}\"")),
+        "DOWNLOAD_QUALITY" if !is_audio_quality(value) => {
+            Err(format!("expected a number from 0 (best) to 10, or a bitrate like 128K, got \"{value}\""))
+        },
+        "AUTO_SYNC" if value != "off" && value != "startup" && value.parse::<u64>().is_err() => {
+            Err(format!("expected off, startup or a number of minutes, got \"{value}\""))
+        },
+        "DOWNLOAD_RATE_LIMIT" if !is_rate(value) => {
+            Err(format!("expected bytes per second, like 500K or 1.5M, got \"{value}\""))
+        },
+        "REGION" if value.len() != 2 || !value.chars().all(|c| c.is_ascii_alphabetic()) => {
+            Err(format!("expected a two letter country code, like ES, got \"{value}\""))
+        },
+        "PLAYER_BACKEND" if !utils::available_backends().contains(&value.to_lowercase().as_str()) => {
+            Err(format!("expected one of {}, got \"{value}\"", utils::available_backends().join(", ")))
+        },
+        "SUBSONIC_URL" | "JELLYFIN_URL" | "WEBHOOK_URL" | "LISTENBRAINZ_URL" if !value.starts_with("http://") && !value.starts_with("https://") => {
+            Err(format!("expected an http:// or https:// URL, got \"{value}\""))
+        },
+        // Names without a directory are searched in the PATH.
+        "YTDLP_PATH" | "FFMPEG_PATH" | "FPCALC_PATH" if value.contains(std::path::MAIN_SEPARATOR) && !Path::new(value).is_file() => {
+            Err(format!("{value} doesn't exist"))
+        },
+        "NOTIFY_STATUS" | "NOTIFY_BELL" | "NOTIFY_FLASH" | "NOTIFY_DESKTOP" | "NOTIFY_WEBHOOK" => notifications::parse_kinds(value).map(|_| ()).map_err(|e| {
+            format!("{e}, expected none or some of {}", notifications::format_kinds(&notifications::Kind::ALL))
+        }),
+        _ => Ok(())
+    }
+}
+
+// The values yt-dlp's --audio-quality accepts.
+fn is_audio_quality(value: &str) -> bool {
+    match value.strip_suffix(['K', 'k']) {
+        Some(bitrate) => bitrate.parse::<u32>().is_ok(),
+        None => value.parse::<u8>().is_ok_and(|quality| quality <= 10)
+    }
+}
+
+// The values yt-dlp's --limit-rate accepts: bytes per second, with an optional K, M or G.
+fn is_rate(value: &str) -> bool {
+    let number = value.strip_suffix(['K', 'k', 'M', 'm', 'G', 'g']).unwrap_or(value);
+    number.parse::<f64>().is_ok_and(|rate| rate.is_finite() && rate > 0.0)
+}
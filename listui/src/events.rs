@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use crossterm::event::{KeyEvent, MouseEvent};
+use listui_lib::artwork::Artwork;
+use listui_lib::api::{ApiError, FetchProgress, InstanceHealth, Recording, Release, RemotePlaylist};
+use listui_lib::db::{DbError, TrackDiff};
+use listui_lib::models::{NewPlaylist, NewVideo, Playlist, Restriction, Track};
+use listui_lib::tools::YtdlpStatus;
+
+use crate::import::{ImportSummary, Imported};
+use crate::notifications::Notification;
+use crate::remote::Request;
+
+/// Everything the app can react to.
+///
+/// Terminal input, ticks and the results of background tasks are all turned
+/// into an `AppEvent` and handled by `ListuiApp::update`, one at a time.
+#[derive(Debug)]
+pub enum AppEvent {
+    /// A key was pressed.
+    Key(KeyEvent),
+    /// Text was pasted into the terminal.
+    Paste(String),
+    /// The mouse was moved, clicked or scrolled.
+    Mouse(MouseEvent),
+    /// The terminal was resized to the given width and height.
+    Resize(u16, u16),
+    /// Emitted periodically, even if nothing else happened.
+    Tick,
+    /// The track that was going to be played has started, once it was downloaded.
+    SongStarted,
+    /// The track that was being played has ended.
+    SongFinished,
+    /// The track that was going to be played could not be downloaded.
+    TrackUnavailable(Track),
+    /// The search for another upload of an unavailable track has finished.
+    Alternate(Track, Result<Option<NewVideo>, ApiError>),
+    /// A new playlist has been fetched from the API.
+    NewPlaylist(Result<(NewPlaylist, Vec<NewVideo>), ApiError>),
+    /// The new tracks of an already saved playlist have been fetched.
+    PlaylistUpdate(Result<(i32, NewPlaylist, Vec<NewVideo>), ApiError>),
+    /// It's time to sync every playlist in the background again, as AUTO_SYNC says.
+    SyncDue,
+    /// Every playlist has been fetched again and saved, with what changed in each one (or why
+    /// it failed), and the ids of the tracks to check for restrictions.
+    PlaylistsSynced(Vec<(String, Result<TrackDiff, String>)>, Vec<String>),
+    /// A new playlist has been saved with its tracks, whose restrictions are checked next.
+    PlaylistSaved(Result<(Playlist, Vec<String>), DbError>),
+    /// An updated playlist has been saved, with what changed in it (podcasts don't tell), and
+    /// the ids of the tracks to check for restrictions.
+    PlaylistSynced(Result<(Playlist, Option<TrackDiff>, Vec<String>), DbError>),
+    /// The saved playlists have been read again, with the one to select afterwards.
+    PlaylistsLoaded(Result<Vec<Playlist>, DbError>, Option<i32>),
+    /// The tracks of a playlist have been read, with what to do once they are shown.
+    SongsLoaded(Result<(Playlist, Vec<Track>), DbError>, AfterLoad),
+    /// The tracks of the playlist with the given id that the database found for a search.
+    SearchResults(i32, String, Result<Vec<i32>, DbError>),
+    /// A single video has been fetched from the API.
+    NewVideo(Result<NewVideo, ApiError>),
+    /// A single video has been saved to the singles playlist, as that track.
+    SingleSaved(Result<(Playlist, Track), DbError>),
+    /// Every URL of a file has been fetched, in the same order as in the file.
+    BulkImport(Vec<(String, Result<Imported, ApiError>)>),
+    /// The playlists and videos of a file have been saved, with the ids of every imported track.
+    ImportSaved(Result<(ImportSummary, Vec<String>), DbError>),
+    /// Something that was saved without waiting for it, like a play, if it failed.
+    DbWrite(Result<(), DbError>),
+    /// The database has been backed up to a file, with the problems its integrity check found.
+    DatabaseBackup(Result<(PathBuf, Vec<String>), DbError>),
+    /// The videos uploaded last week by the followed channels have been fetched.
+    Digest(Result<Vec<NewVideo>, ApiError>),
+    /// The playlists of the user's media server have been listed.
+    Library(Result<Vec<RemotePlaylist>, ApiError>),
+    /// The videos that will fail to download, because of their age or region restrictions.
+    Restrictions(Result<Vec<(String, Restriction)>, ApiError>),
+    /// The latest release of lisTUI has been checked.
+    LatestRelease(Result<Option<Release>, ApiError>),
+    /// Whether yt-dlp can be run, and is the latest version.
+    Ytdlp(Result<YtdlpStatus, ApiError>),
+    /// The latest yt-dlp has been installed, with its version.
+    YtdlpInstalled(Result<String, ApiError>),
+    /// The Invidious instances have been probed, and sorted from the fastest one.
+    InstanceRanking(Vec<InstanceHealth>),
+    /// Progress of the download of a whole playlist, like "37/120 downloaded".
+    PlaylistDownload(String),
+    /// Every missing track of a playlist has been tried, so it leaves the download queue.
+    PlaylistDownloaded(i32),
+    /// The downloads of a playlist have been decoded, with how many were damaged and deleted.
+    FilesVerified(i32, Result<usize, String>),
+    /// The thumbnail of a track has been fetched, and decoded to be shown while it plays.
+    Artwork(i32, Artwork),
+    /// A track has been looked up in MusicBrainz, which may not know it.
+    Metadata(i32, Option<Recording>),
+    /// Something the user should be told about, wherever the notification settings route it.
+    Notify(Notification),
+    /// Progress information while fetching a playlist.
+    FetchProgress(FetchProgress),
+    /// Another step of a longer job shown in the loading screen, like "[2/5] Importing ...".
+    LoadingLabel(String),
+    /// Something `listui` was asked to open while this instance was running, forwarded to it.
+    Remote(Request),
+}
+
+/// What to do once the tracks of a playlist have been loaded.
+#[derive(Debug, Clone, Copy)]
+pub enum AfterLoad {
+    /// Show them again where they already were, like after removing some.
+    Reload,
+    /// Like `Reload`, but selecting the track at that position.
+    Select(usize),
+    /// Open the songs screen.
+    Open,
+    /// Open the songs screen and play from the first track, shuffled if set.
+    Play(bool),
+    /// Open the songs screen and play the track with that id.
+    PlayTrack(i32),
+}
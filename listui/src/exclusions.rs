@@ -0,0 +1,98 @@
+//! Rules that keep some videos out of a playlist every time it's updated, like the live versions
+//! or the hour long compilations of a mixed playlist.
+//!
+//! They are written like `title=live|remix; max=15:00; channel=Some Channel`, and saved in the app
+//! state for each playlist (press x in the playlists menu).
+
+use std::fmt;
+
+use listui_lib::db::Database;
+use listui_lib::models::NewVideo;
+use regex::{Regex, RegexBuilder};
+
+use crate::utils;
+
+/// The rules of each playlist are saved with this key, and ":<playlist id>" after it.
+pub const EXCLUSIONS_KEY: &str = "exclude";
+
+#[derive(Debug, Default)]
+pub struct Exclusions {
+    /// Leaves out the videos whose title matches, ignoring case.
+    title: Option<Regex>,
+    /// Leaves out the videos longer than this, in seconds.
+    max_duration: Option<i32>,
+    /// Leaves out the videos uploaded by these channels, in lowercase.
+    channels: Vec<String>,
+}
+
+impl Exclusions {
+
+    /// Reads the rules, separated by semicolons. "none" or nothing at all excludes nothing.
+    pub fn parse(rules: &str) -> Result<Self, String> {
+
+        let mut exclusions = Exclusions::default();
+        if rules.trim().eq_ignore_ascii_case("none") {
+            return Ok(exclusions);
+        }
+        for rule in rules.split(';').map(str::trim).filter(|rule| !rule.is_empty()) {
+            let (key, value) = rule.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("\"{rule}\" is not like title=..., max=... or channel=..."))?;
+            match key {
+                "title" => {
+                    let regex = RegexBuilder::new(value).case_insensitive(true).build()
+                        .map_err(|e| format!("Invalid title pattern \"{value}\": {e}"))?;
+                    exclusions.title = Some(regex);
+                }
+                "max" => exclusions.max_duration = utils::parse_seconds(value)?,
+                "channel" if !value.is_empty() => {
+                    // Videos get the name of the artist of "Artist - Topic" channels.
+                    let channel = value.to_lowercase();
+                    exclusions.channels.push(String::from(channel.strip_suffix(" - topic").unwrap_or(&channel)));
+                }
+                _ => return Err(format!("Unknown rule \"{rule}\".")),
+            }
+        }
+        Ok(exclusions)
+    }
+
+    /// The rules saved for a playlist. Rules that can't be read anymore exclude nothing.
+    pub fn saved(dao: &Database, playlist_id: i32) -> Self {
+
+        dao.get_state(&format!("{EXCLUSIONS_KEY}:{playlist_id}")).ok().flatten()
+            .and_then(|rules| Self::parse(&rules).map_err(|e| log::warn!("Ignoring the exclusion rules of playlist {playlist_id}: {e}")).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.max_duration.is_none() && self.channels.is_empty()
+    }
+
+    /// Whether any rule matches the video. Videos whose duration or channel isn't known are
+    /// only checked against the rest of the rules.
+    pub fn excludes(&self, video: &NewVideo) -> bool {
+
+        self.title.as_ref().is_some_and(|title| title.is_match(&video.title))
+            || self.max_duration.zip(video.duration).is_some_and(|(max, duration)| duration > max)
+            || video.artist.as_ref().is_some_and(|artist| self.channels.contains(&artist.to_lowercase()))
+    }
+}
+
+impl fmt::Display for Exclusions {
+
+    // Like it's parsed, so it can be edited again.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        let mut rules = Vec::new();
+        if let Some(title) = &self.title {
+            rules.push(format!("title={title}"));
+        }
+        if let Some(max) = self.max_duration {
+            rules.push(format!("max={}:{:02}", max / 60, max % 60));
+        }
+        rules.extend(self.channels.iter().map(|channel| format!("channel={channel}")));
+
+        if rules.is_empty() { write!(f, "none") }
+        else { write!(f, "{}", rules.join("; ")) }
+    }
+}
@@ -0,0 +1,100 @@
+//! Importing many playlists at once, from a text file with one URL per line.
+//!
+//! Fetching and saving are split, because the database can only be used from the thread
+//! that opened it: the URLs are fetched in the background and saved once they arrive.
+
+use std::fs::read_to_string;
+use std::io;
+use std::path::Path;
+
+use listui_lib::api::{ApiError, ApiProgressCallback, MediaSource};
+use listui_lib::db::{Database, DbError};
+use listui_lib::models::{NewPlaylist, NewVideo};
+
+use crate::utils;
+
+/// What a URL points to, once fetched.
+#[derive(Debug)]
+pub enum Imported {
+    Playlist(NewPlaylist, Vec<NewVideo>),
+    Video(NewVideo),
+}
+
+/// How the import of every URL of the file went.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// Titles of the saved playlists and videos.
+    pub imported: Vec<String>,
+    /// URLs that couldn't be imported, and why.
+    pub failed: Vec<(String, String)>,
+}
+
+impl ImportSummary {
+    /// Like "Imported 3 of 4 URLs."
+    pub fn totals(&self) -> String {
+        format!(
+            "Imported {} of {} URLs.",
+            self.imported.len(),
+            self.imported.len() + self.failed.len()
+        )
+    }
+
+    /// One line with the totals, followed by the failed URLs.
+    pub fn report(&self) -> String {
+        let mut report = self.totals();
+        for (url, error) in &self.failed {
+            report.push_str(&format!("\n{url}: {error}"));
+        }
+        report
+    }
+}
+
+/// Reads the URLs of a file, skipping empty lines and comments (lines starting with #).
+pub fn read_url_file(path: &Path) -> io::Result<Vec<String>> {
+    Ok(read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Fetches a YouTube playlist, video or channel, or a Bandcamp album.
+pub async fn fetch_url(
+    source: &dyn MediaSource,
+    url: &str,
+    callback: Option<ApiProgressCallback>,
+) -> Result<Imported, ApiError> {
+    let playlist_id = utils::parse_playlist_url(url).or_else(|| utils::parse_bandcamp_url(url));
+    if let Some(yt_id) = playlist_id {
+        let (playlist, videos) = source.fetch_playlist(&yt_id, callback).await?;
+        Ok(Imported::Playlist(playlist, videos))
+    } else if let Some(yt_id) = utils::parse_video_url(url) {
+        Ok(Imported::Video(source.fetch_video(&yt_id).await?))
+    } else if let Some(channel) = utils::parse_channel_url(url) {
+        let yt_id = source.fetch_channel_uploads(&channel).await?;
+        let (playlist, videos) = source.fetch_playlist(&yt_id, callback).await?;
+        Ok(Imported::Playlist(playlist, videos))
+    } else {
+        Err(ApiError::RequestError(String::from(
+            "Not a YouTube playlist, video, channel or Bandcamp album URL.",
+        )))
+    }
+}
+
+/// Saves what was fetched, returning its title and the ids of the new tracks.
+pub fn save(dao: &Database, imported: Imported) -> Result<(String, Vec<String>), DbError> {
+    match imported {
+        Imported::Playlist(playlist, videos) => {
+            let yt_ids = videos.iter().map(|video| String::from(video.source())).collect();
+            let playlist = dao.save_playlist(playlist)?;
+            dao.save_tracks(videos, playlist.id)?;
+            Ok((playlist.title, yt_ids))
+        }
+        Imported::Video(video) => {
+            let (_, track) = dao.add_single(video)?;
+            let ids = track.source().map(String::from).into_iter().collect();
+            Ok((track.title, ids))
+        }
+    }
+}
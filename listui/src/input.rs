@@ -0,0 +1,47 @@
+use std::thread;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyEventKind};
+use tokio::sync::mpsc;
+
+use crate::events::AppEvent;
+
+// How often the thread checks if the app has been closed.
+const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Reads terminal events on a dedicated thread, and sends them to the app.
+///
+/// This way no input is lost while the app is drawing or waiting for a lock.
+/// The thread stops once the receiving end of `sender` is dropped.
+pub fn spawn_input_thread(sender: mpsc::Sender<AppEvent>) -> thread::JoinHandle<()> {
+
+    thread::spawn(move || {
+        while !sender.is_closed() {
+            match event::poll(POLL_TIMEOUT) {
+                Ok(false) => continue,
+                Ok(true) => {}
+                Err(e) => {
+                    log::error!("Failed to poll terminal events: {e}");
+                    break;
+                }
+            }
+
+            let event = match event::read() {
+                // Some terminals also report key releases.
+                Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => AppEvent::Key(key),
+                Ok(Event::Paste(text)) => AppEvent::Paste(text),
+                Ok(Event::Mouse(mouse)) => AppEvent::Mouse(mouse),
+                Ok(Event::Resize(width, height)) => AppEvent::Resize(width, height),
+                Ok(_) => continue,
+                Err(e) => {
+                    log::error!("Failed to read terminal event: {e}");
+                    break;
+                }
+            };
+
+            if sender.blocking_send(event).is_err() {
+                break;
+            }
+        }
+    })
+}
@@ -0,0 +1,313 @@
+//! What lisTUI does in the background without being asked right then: downloading whole
+//! playlists, checking for updates and restrictions, ranking instances and posting to the webhook.
+//!
+//! These jobs are spawned through the `Scheduler`, that runs a few of them at a time and lists
+//! them in the jobs screen (press j in the playlists menu), where they can be cancelled. They can
+//! also be paused all at once (press z) when the bandwidth or CPU is needed for something else.
+//! Jobs wait to start until they are resumed, and the long ones wait between their steps too, so
+//! they go on where they were left.
+//!
+//! Jobs that go through many items, like the tracks of a playlist, say how each one went, so the
+//! batch screen (press Enter on a job) can show them, pause or cancel just that job, and sum up
+//! what happened once it's over.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::runtime::Handle;
+use tokio::sync::{watch, Semaphore};
+
+/// How many jobs run at the same time. The rest wait in the queue.
+const MAX_RUNNING: usize = 2;
+
+/// How many finished jobs are still listed.
+const MAX_FINISHED: usize = 20;
+
+pub type JobId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Finished => "finished",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        matches!(self, JobStatus::Finished | JobStatus::Cancelled)
+    }
+}
+
+/// How an item of a batch job went.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemStatus {
+    Pending,
+    Running,
+    Done,
+    Failed(String),
+}
+
+/// An item of a batch job, like one of the tracks being downloaded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchItem {
+    pub name: String,
+    pub status: ItemStatus,
+}
+
+/// A job, as shown in the jobs screen.
+#[derive(Debug, Clone)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub name: String,
+    pub status: JobStatus,
+    /// Paused on its own, while the rest of the jobs go on.
+    pub paused: bool,
+    /// What the job said last, like "37/120 downloaded".
+    pub progress: Option<String>,
+    /// The items of a batch job, empty for the rest.
+    pub items: Vec<BatchItem>,
+}
+
+impl JobInfo {
+    /// How many items are done, have failed and were skipped because the job was cancelled.
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let count = |done: fn(&ItemStatus) -> bool| self.items.iter().filter(|item| done(&item.status)).count();
+        let done = count(|status| *status == ItemStatus::Done);
+        let failed = count(|status| matches!(status, ItemStatus::Failed(_)));
+        let skipped = match self.status {
+            JobStatus::Cancelled => self.items.len() - done - failed,
+            _ => 0,
+        };
+        (done, failed, skipped)
+    }
+
+    /// What happened to the items, like "37 done, 2 failed", once the job is over.
+    pub fn summary(&self) -> Option<String> {
+        if !self.status.is_done() || self.items.is_empty() {
+            return None;
+        }
+        let (done, failed, skipped) = self.counts();
+        let mut summary = format!("{done} done");
+        if failed > 0 {
+            summary.push_str(&format!(", {failed} failed"));
+        }
+        if skipped > 0 {
+            summary.push_str(&format!(", {skipped} skipped"));
+        }
+        Some(summary)
+    }
+}
+
+struct Entry {
+    info: JobInfo,
+    cancel: watch::Sender<bool>,
+    pause: watch::Sender<bool>,
+}
+
+pub struct Scheduler {
+    runtime: Handle,
+    paused: watch::Sender<bool>,
+    permits: Arc<Semaphore>,
+    // Every job, from the oldest one.
+    jobs: Arc<Mutex<Vec<Entry>>>,
+    next_id: AtomicUsize,
+}
+
+impl Scheduler {
+    pub fn new(runtime: Handle) -> Self {
+        let (paused, _) = watch::channel(false);
+        Self {
+            runtime,
+            paused,
+            permits: Arc::new(Semaphore::new(MAX_RUNNING)),
+            jobs: Arc::new(Mutex::new(Vec::new())),
+            next_id: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Pauses the jobs, or resumes them if they were paused. Returns whether they are paused now.
+    pub fn toggle(&self) -> bool {
+        let paused = !self.is_paused();
+        self.paused.send_replace(paused);
+        paused
+    }
+
+    /// A gate for jobs spawned somewhere else, like the webhook requests.
+    pub fn gate(&self) -> Gate {
+        Gate(self.paused.subscribe())
+    }
+
+    /// Queues a job, that runs once there's room for it and the jobs aren't paused. Long jobs
+    /// should call `Job::proceed` between their steps.
+    pub fn spawn<F>(&self, name: impl Into<String>, run: impl FnOnce(Job) -> F + Send + 'static) -> JobId
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (cancel, cancelled) = watch::channel(false);
+        let (pause, paused) = watch::channel(false);
+        let info = JobInfo { id, name: name.into(), status: JobStatus::Queued, paused: false, progress: None, items: Vec::new() };
+        log::info!("Queued job {}.", info.name);
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.push(Entry { info, cancel, pause });
+            // Only the last finished jobs are kept.
+            let finished = jobs.iter().filter(|entry| entry.info.status.is_done()).count();
+            let mut extra = finished.saturating_sub(MAX_FINISHED);
+            jobs.retain(|entry| {
+                let remove = extra > 0 && entry.info.status.is_done();
+                extra -= remove as usize;
+                !remove
+            });
+        }
+
+        let mut job = Job { id, gate: self.gate(), paused, cancelled, jobs: Arc::clone(&self.jobs) };
+        let permits = Arc::clone(&self.permits);
+        self.runtime.spawn(async move {
+            if !job.proceed().await {
+                return;
+            }
+            let Ok(_permit) = permits.acquire_owned().await else { return };
+            if !job.proceed().await {
+                return;
+            }
+            let jobs = Arc::clone(&job.jobs);
+            set_status(&jobs, id, JobStatus::Running);
+            run(job).await;
+            set_status(&jobs, id, JobStatus::Finished);
+        });
+        id
+    }
+
+    /// Every job, from the newest one.
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        self.jobs.lock().unwrap().iter().rev().map(|entry| entry.info.clone()).collect()
+    }
+
+    /// A single job, if it's still listed.
+    pub fn job(&self, id: JobId) -> Option<JobInfo> {
+        self.jobs.lock().unwrap().iter().find(|entry| entry.info.id == id).map(|entry| entry.info.clone())
+    }
+
+    /// Pauses a job on its own, or resumes it, the next time it calls `Job::proceed`. Returns
+    /// whether it's paused now.
+    pub fn toggle_job(&self, id: JobId) -> bool {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(entry) = jobs.iter_mut().find(|entry| entry.info.id == id && !entry.info.status.is_done()) else { return false };
+        entry.info.paused = !entry.info.paused;
+        entry.pause.send_replace(entry.info.paused);
+        log::info!("Job {} {}.", entry.info.name, if entry.info.paused { "paused" } else { "resumed" });
+        entry.info.paused
+    }
+
+    /// Stops a job the next time it calls `Job::proceed`, or before it starts if it's queued.
+    pub fn cancel(&self, id: JobId) {
+        let jobs = self.jobs.lock().unwrap();
+        if let Some(entry) = jobs.iter().find(|entry| entry.info.id == id && !entry.info.status.is_done()) {
+            log::info!("Cancelled job {}.", entry.info.name);
+            entry.cancel.send_replace(true);
+        }
+    }
+}
+
+fn set_status(jobs: &Mutex<Vec<Entry>>, id: JobId, status: JobStatus) {
+    let mut jobs = jobs.lock().unwrap();
+    if let Some(entry) = jobs.iter_mut().find(|entry| entry.info.id == id) {
+        // A cancelled job stays cancelled, even if it finished what it was doing.
+        if entry.info.status != JobStatus::Cancelled {
+            entry.info.status = status;
+        }
+    }
+}
+
+/// What a running job gets from the scheduler.
+pub struct Job {
+    id: JobId,
+    gate: Gate,
+    paused: watch::Receiver<bool>,
+    cancelled: watch::Receiver<bool>,
+    jobs: Arc<Mutex<Vec<Entry>>>,
+}
+
+impl Job {
+    /// Waits while the jobs, or just this one, are paused. Returns false if the job was
+    /// cancelled, and then it should stop.
+    pub async fn proceed(&mut self) -> bool {
+        let Job { gate, paused, cancelled, .. } = self;
+        let resumed = async {
+            loop {
+                // Only fails once the job is no longer listed.
+                if paused.wait_for(|paused| !paused).await.is_err() {
+                    break;
+                }
+                gate.resumed().await;
+                if !*paused.borrow() {
+                    break;
+                }
+            }
+        };
+        tokio::select! {
+            _ = resumed => {},
+            _ = async { let _ = cancelled.wait_for(|cancelled| *cancelled).await; } => {},
+        }
+        let cancelled = *self.cancelled.borrow();
+        if cancelled {
+            set_status(&self.jobs, self.id, JobStatus::Cancelled);
+        }
+        !cancelled
+    }
+
+    /// Shows how far the job has got in the jobs screen.
+    pub fn progress(&self, progress: impl Into<String>) {
+        let progress = progress.into();
+        self.update(|info| info.progress = Some(progress));
+    }
+
+    /// Makes this a batch job that goes through these items, one by one.
+    pub fn items(&self, names: impl IntoIterator<Item = String>) {
+        let items = names.into_iter().map(|name| BatchItem { name, status: ItemStatus::Pending }).collect();
+        self.update(|info| info.items = items);
+    }
+
+    /// Sets how the item at `ind` of `Job::items` is going.
+    pub fn item(&self, ind: usize, status: ItemStatus) {
+        self.update(|info| {
+            if let Some(item) = info.items.get_mut(ind) {
+                item.status = status;
+            }
+        });
+    }
+
+    fn update(&self, update: impl FnOnce(&mut JobInfo)) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(entry) = jobs.iter_mut().find(|entry| entry.info.id == self.id) {
+            update(&mut entry.info);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Gate(watch::Receiver<bool>);
+
+impl Gate {
+    /// Returns right away, unless the jobs are paused. Then, once they are resumed.
+    pub async fn resumed(&mut self) {
+        // Only fails if the scheduler was dropped, when the app is closing anyway.
+        let _ = self.0.wait_for(|paused| !paused).await;
+    }
+}
@@ -0,0 +1,77 @@
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+use crate::widgets::LOGO_SIZE;
+use crate::widgets::artwork::ART_SIZE;
+
+// The logo is only drawn if the playlists still get this many rows under it.
+const PLAYLIST_ROWS_WITH_LOGO: u16 = 10;
+
+// The details of the selected playlist are only drawn if the playlists still get this many rows over them.
+const PLAYLIST_ROWS_WITH_DETAILS: u16 = 8;
+// Rows of the details, with their borders.
+const DETAILS_HEIGHT: u16 = 7;
+
+// The album art is only drawn if the titles of the songs still get this many columns beside it.
+const SONG_COLUMNS_WITH_ART: u16 = 50;
+
+// Areas of the main screens. They are only computed again when the terminal is resized.
+pub struct AppLayout {
+    pub area: Rect,
+    pub logo: Option<Rect>,
+    pub playlists: Rect,
+    /// The playlists, and the details of the selected one under them, if there's room for them.
+    pub playlists_with_details: Option<(Rect, Rect)>,
+    pub songs: Rect,
+    pub player: Rect,
+    /// The songs, and the album art at their right, if there's room for it.
+    pub songs_with_art: Option<(Rect, Rect)>,
+}
+
+impl AppLayout {
+    /// Splits `area`, leaving `player_height` rows for the player at the bottom of the songs screen.
+    pub fn new(area: Rect, player_height: u16) -> Self {
+        let (logo, playlists) =
+            if area.width >= LOGO_SIZE.0 && area.height >= LOGO_SIZE.1 + PLAYLIST_ROWS_WITH_LOGO {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(LOGO_SIZE.1), Constraint::Min(0)].as_ref())
+                    .split(area);
+                (Some(chunks[0]), chunks[1])
+            } else {
+                (None, area)
+            };
+
+        let playlists_with_details = (playlists.height >= DETAILS_HEIGHT + PLAYLIST_ROWS_WITH_DETAILS).then(|| {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(DETAILS_HEIGHT)].as_ref())
+                .split(playlists);
+            (rows[0], rows[1])
+        });
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(player_height)].as_ref())
+            .split(area);
+
+        let songs = chunks[0];
+        let art_width = ART_SIZE.0 + 2;
+        let songs_with_art = (songs.width >= art_width + SONG_COLUMNS_WITH_ART && songs.height >= ART_SIZE.1 + 2).then(|| {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(art_width)].as_ref())
+                .split(songs);
+            (columns[0], Rect { height: ART_SIZE.1 + 2, ..columns[1] })
+        });
+
+        Self {
+            area,
+            logo,
+            playlists,
+            playlists_with_details,
+            songs,
+            player: chunks[1],
+            songs_with_art,
+        }
+    }
+}
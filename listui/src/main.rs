@@ -1,34 +1,328 @@
 mod widgets;
 mod app;
+mod config;
+mod events;
+mod exclusions;
+mod import;
+mod input;
+mod jobs;
+mod layout;
+mod notifications;
+mod remote;
 mod utils;
+#[cfg(test)]
+mod tests;
 
-use std::fs::File;
-use std::{fs::create_dir_all, path::PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::{fs::create_dir_all, path::{Path, PathBuf}};
 use app::ListuiApp;
 use argh::FromArgs;
+use config::{parse_cli_setting, ConfigError, Settings};
+use exclusions::Exclusions;
+use remote::Request;
+use listui_lib::api::{MediaSource, MusicBrainzClient, RSS_PREFIX};
 use listui_lib::db::Database;
+use listui_lib::downloader::{AudioFormat, DownloadResult, Downloader};
+use listui_lib::transcoder;
+use listui_lib::export::ExportFormat;
+use listui_lib::stats::StatsFormat;
+use listui_lib::models::{Playlist, Track};
 use simplelog::{Config, LevelFilter, WriteLogger};
-use utils::{get_local_playlist, parse_playlist_url};
+use utils::{get_playlist_file, is_playlist_file, parse_bandcamp_url, parse_channel_url, parse_playlist_url, parse_video_url};
 
 #[derive(FromArgs)]
 /// A simple music player for your terminal.
 struct ListuiArgs {
     
-    /// local directory or youtube playlist.
+    /// local directory (saved as a playlist), .m3u/.pls file, youtube playlist, video or channel, or bandcamp album.
     #[argh(positional)]
     pub playlist: Option<String>,
+
+    /// download every missing track of a saved playlist (by title or youtube id), and exit.
+    #[argh(option)]
+    pub download: Option<String>,
+
+    /// start with the default settings, no background jobs and verbose logging, to debug problems.
+    #[argh(switch)]
+    pub safe_mode: bool,
+
+    /// change a setting, like --set DOWNLOAD_FORMAT=opus. It takes priority over the environment and the config file.
+    #[argh(option, long = "set", from_str_fn(parse_cli_setting))]
+    pub settings: Vec<(String, String)>,
+
+    #[argh(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Add(AddArgs),
+    List(ListArgs),
+    Play(PlayArgs),
+    Update(UpdateArgs),
+    Export(ExportArgs),
+    ImportFile(ImportFileArgs),
+    Reencode(ReencodeArgs),
+    Cleanup(CleanupArgs),
+    Duplicates(DuplicatesArgs),
+    Sync(SyncArgs),
+    Tag(TagArgs),
+    Stats(StatsArgs),
+    Config(ConfigArgs),
+    Db(DbArgs),
+}
+
+#[derive(FromArgs)]
+/// Save a youtube playlist, video or channel, or a bandcamp album, without opening lisTUI.
+#[argh(subcommand, name = "add")]
+struct AddArgs {
+
+    /// url of the playlist, video, channel or album.
+    #[argh(positional)]
+    pub url: String,
+}
+
+#[derive(FromArgs)]
+/// Print the saved playlists, one per line: title, tracks, downloaded tracks and id (or url), separated by tabs.
+#[argh(subcommand, name = "list")]
+struct ListArgs {}
+
+#[derive(FromArgs)]
+/// Open lisTUI playing a saved playlist.
+#[argh(subcommand, name = "play")]
+struct PlayArgs {
+
+    /// title or youtube id of the playlist.
+    #[argh(positional)]
+    pub playlist: String,
+
+    /// play the tracks in a random order.
+    #[argh(switch)]
+    pub shuffle: bool,
+}
+
+#[derive(FromArgs)]
+/// Fetch the new tracks of a saved playlist, or of every one with --all.
+#[argh(subcommand, name = "update")]
+struct UpdateArgs {
+
+    /// title or youtube id of the playlist.
+    #[argh(positional)]
+    pub playlist: Option<String>,
+
+    /// update every playlist that was fetched from youtube, another site, a media server, a podcast feed or a directory.
+    #[argh(switch)]
+    pub all: bool,
+}
+
+#[derive(FromArgs)]
+/// Export a saved playlist to a .m3u or .json file.
+#[argh(subcommand, name = "export")]
+struct ExportArgs {
+
+    /// title or youtube id of the playlist.
+    #[argh(positional)]
+    pub playlist: String,
+
+    /// output format: m3u (default) or json.
+    #[argh(option, default = "ExportFormat::M3u")]
+    pub format: ExportFormat,
+
+    /// directory where the file will be written (the current one by default).
+    #[argh(option, short = 'o')]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(FromArgs)]
+/// Import every playlist, video or channel in a text file, one URL per line.
+#[argh(subcommand, name = "import-file")]
+struct ImportFileArgs {
+
+    /// text file with the URLs. Empty lines and lines starting with # are skipped.
+    #[argh(positional)]
+    pub path: PathBuf,
+}
+
+#[derive(FromArgs)]
+/// Convert the downloaded tracks to another format or bitrate, replacing the original files.
+#[argh(subcommand, name = "reencode")]
+struct ReencodeArgs {
+
+    /// format to convert to: mp3, opus or m4a.
+    #[argh(positional)]
+    pub format: AudioFormat,
+
+    /// bitrate of the new files, like 96K (ffmpeg's default one if not set).
+    #[argh(option, from_str_fn(parse_bitrate))]
+    pub bitrate: Option<String>,
+
+    /// only convert the tracks of this playlist (by title or youtube id).
+    #[argh(option)]
+    pub playlist: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// List the downloaded files that no saved track uses anymore, and the space they take.
+#[argh(subcommand, name = "cleanup")]
+struct CleanupArgs {
+
+    /// delete the files instead of only listing them.
+    #[argh(switch)]
+    pub delete: bool,
+
+    /// add the files to this playlist instead, as local tracks. It is created if it doesn't exist.
+    #[argh(option)]
+    pub import: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// List the tracks that are the same song in different playlists, and the space their copies take.
+#[argh(subcommand, name = "duplicates")]
+struct DuplicatesArgs {
+
+    /// make the duplicates share one file, deleting their copies.
+    #[argh(switch)]
+    pub link: bool,
+
+    /// also find the downloads that are the same audio by their fingerprints, computed with fpcalc.
+    #[argh(switch)]
+    pub fingerprint: bool,
+}
+
+#[derive(FromArgs)]
+/// Download every missing track of the saved playlists.
+#[argh(subcommand, name = "sync")]
+struct SyncArgs {
+
+    /// only finish the downloads that were interrupted by closing lisTUI.
+    #[argh(switch)]
+    pub resume: bool,
+}
+
+#[derive(FromArgs)]
+/// Look up the artist, album and year of the downloaded tracks in MusicBrainz, one a second.
+#[argh(subcommand, name = "tag")]
+struct TagArgs {
+
+    /// look up again the tracks that were already looked up.
+    #[argh(switch)]
+    pub again: bool,
+}
+
+#[derive(FromArgs)]
+/// Export how much every track has been played, or the history of every play, to analyze it with other tools.
+#[argh(subcommand, name = "stats")]
+struct StatsArgs {
+
+    /// export the history of every play instead, from the oldest.
+    #[argh(switch)]
+    pub history: bool,
+
+    /// output format: csv (default) or json.
+    #[argh(option, default = "StatsFormat::Csv")]
+    pub format: StatsFormat,
+
+    /// file where the stats are written, instead of printing them.
+    #[argh(option, short = 'o')]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(FromArgs)]
+/// Inspect the settings.
+#[argh(subcommand, name = "config")]
+struct ConfigArgs {
+
+    #[argh(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum ConfigCommand {
+    Show(ConfigShowArgs),
 }
 
+#[derive(FromArgs)]
+/// Print the settings of the config file, and the lines that were skipped.
+#[argh(subcommand, name = "show")]
+struct ConfigShowArgs {
+
+    /// print the value every setting ends up with instead, and where it comes from.
+    #[argh(switch)]
+    pub resolved: bool,
+}
+
+#[derive(FromArgs)]
+/// Back up, restore or check the database of saved playlists.
+#[argh(subcommand, name = "db")]
+struct DbArgs {
+
+    #[argh(subcommand)]
+    pub command: DbCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum DbCommand {
+    Backup(DbBackupArgs),
+    Restore(DbRestoreArgs),
+    Check(DbCheckArgs),
+}
+
+#[derive(FromArgs)]
+/// Write a copy of the database, that is whole even if lisTUI is running.
+#[argh(subcommand, name = "backup")]
+struct DbBackupArgs {
+
+    /// file where the copy is written. It must not exist.
+    #[argh(positional)]
+    pub path: PathBuf,
+}
+
+#[derive(FromArgs)]
+/// Replace the database with a backup, keeping the current one next to it as .old. lisTUI must be closed.
+#[argh(subcommand, name = "restore")]
+struct DbRestoreArgs {
+
+    /// backup written by `listui db backup`.
+    #[argh(positional)]
+    pub path: PathBuf,
+}
+
+#[derive(FromArgs)]
+/// Check the database for damage and, if it is fine, compact it.
+#[argh(subcommand, name = "check")]
+struct DbCheckArgs {}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let args: ListuiArgs = argh::from_env();
 
-    // Load config file.
+    // Load config file, unless in safe mode.
     let config_path = utils::get_config_path().expect("Failed to get config path.");
-    let _ = dotenvy::from_path(config_path);
+    let (file_settings, config_errors) = if args.safe_mode { (Vec::new(), Vec::new()) }
+        else { config::read_config(&config_path) };
+
+    let settings = if args.safe_mode { Settings::defaults() }
+        else { Settings::resolve(&args.settings, &file_settings) };
+    settings.apply();
+
+    if let Some(Command::Config(ConfigArgs { command: ConfigCommand::Show(show) })) = args.command {
+        show_config(&config_path, settings, &file_settings, &config_errors, show);
+        return Ok(());
+    }
     
     let log_path = utils::get_log_path().expect("Failed to get log path.");
-    let _ = WriteLogger::init(LevelFilter::Info, Config::default(), File::create(log_path).unwrap());   
+    let log_level = if args.safe_mode { LevelFilter::Debug } else { LevelFilter::Info };
+    let _ = WriteLogger::init(log_level, Config::default(), File::create(log_path).unwrap());   
+    if args.safe_mode {
+        log::info!("Started in safe mode.");
+    }
+    for error in &config_errors {
+        log::warn!("Skipped {} {error}", config_path.display());
+    }
 
     let database_path = utils::get_db_path().expect("Failed to get database path.");
     let download_dir = utils::get_download_dir().expect("Failed to get download directory.");
@@ -36,23 +330,91 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create directory to download all songs (If it does not exist).
     create_dir_all(&download_dir).expect("Failed to create download directory");
 
+    // The app shows them itself, once it starts.
+    let opens_app = args.command.is_none() || matches!(args.command, Some(Command::Play(_)));
+    if !opens_app || args.download.is_some() {
+        for error in &config_errors {
+            eprintln!("Skipped config {error}");
+        }
+    }
+
+    #[cfg(unix)]
+    if opens_app && args.download.is_none() && forward_to_running(&args)? {
+        return Ok(());
+    }
+
+    // The database can't be open while it's replaced.
+    if let Some(Command::Db(DbArgs { command: DbCommand::Restore(restore) })) = args.command {
+        return restore_database(&database_path, restore);
+    }
+
+    if let Some(command) = args.command {
+        let dao = open_database(&database_path, &download_dir)?;
+        return match command {
+            Command::Add(add) => add_url(&dao, add),
+            Command::List(_) => list_playlists(&dao, &download_dir),
+            Command::Play(play) => {
+                let playlist = find_playlist(&dao, &play.playlist)?;
+                let mut app = ListuiApp::new(download_dir, dao)?;
+                app.play_playlist(playlist.id, play.shuffle);
+                run_app(app, args.safe_mode, &config_errors)
+            }
+            Command::Update(update) => update_playlists(&dao, &download_dir, update),
+            Command::Export(export) => export_playlist(&dao, &download_dir, export),
+            Command::ImportFile(import) => import_file(&dao, import),
+            Command::Reencode(reencode) => reencode_tracks(&dao, &download_dir, reencode),
+            Command::Cleanup(cleanup) => cleanup_files(&dao, &download_dir, cleanup),
+            Command::Duplicates(duplicates) => share_duplicates(&dao, &download_dir, duplicates),
+            Command::Sync(sync) => sync_playlists(&dao, &download_dir, sync),
+            Command::Tag(tag) => tag_tracks(&dao, &download_dir, tag),
+            Command::Stats(stats) => export_stats(&dao, stats),
+            Command::Db(DbArgs { command: DbCommand::Backup(backup) }) => backup_database(&dao, backup),
+            Command::Db(DbArgs { command: DbCommand::Check(_) }) => check_database(&dao, &database_path),
+            Command::Config(_) | Command::Db(DbArgs { command: DbCommand::Restore(_) }) => unreachable!("Handled before opening the database."),
+        };
+    }
+
+    if let Some(playlist) = args.download {
+        let dao = open_database(&database_path, &download_dir)?;
+        return download_playlist(&dao, &download_dir, &playlist);
+    }
+
     let app: Option<ListuiApp> = {
 
-        let dao = Database::new(&database_path)?;
+        let dao = open_database(&database_path, &download_dir)?;
+        utils::sync_music_dirs(&dao);
         if let Some(arg) = args.playlist.as_ref() {
                         
-            let playlist_ytid = parse_playlist_url(arg);
-            match playlist_ytid {
-                Some(yt_id) => Some(ListuiApp::new_open_playlist(download_dir, dao, yt_id)?),
-                None => {
+            let playlist_ytid = parse_playlist_url(arg).or_else(|| parse_bandcamp_url(arg));
+            let video_ytid = parse_video_url(arg);
+            let channel = parse_channel_url(arg);
+            match (playlist_ytid, video_ytid, channel) {
+                (Some(yt_id), _, _) => Some(ListuiApp::new_open_playlist(download_dir, dao, yt_id)?),
+                (None, Some(yt_id), _) => Some(ListuiApp::new_open_video(download_dir, dao, yt_id)?),
+                (None, None, Some(channel)) => Some(ListuiApp::new_open_channel(download_dir, dao, channel)?),
+                (None, None, None) => {
 
                     let path = PathBuf::from(arg).canonicalize()?;
-                    match get_local_playlist(&path) {
-                        Some(tracks) => Some(ListuiApp::with_tracks(path, tracks)?),
-                        None => {
-                            eprintln!("Directory not found.");
-                            None
-                        },
+                    if is_playlist_file(&path) {
+                        let playlist_name = path.file_stem()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or(String::from("Unknown playlist."));
+
+                        match get_playlist_file(&path) {
+                            Some(tracks) => Some(ListuiApp::with_tracks(download_dir, playlist_name, tracks)?),
+                            None => {
+                                eprintln!("Failed to read playlist file.");
+                                None
+                            },
+                        }
+                    }
+                    // Directories are saved as playlists, like the ones in MUSIC_DIRS.
+                    else if path.is_dir() {
+                        Some(ListuiApp::new_open_folder(download_dir, dao, path)?)
+                    }
+                    else {
+                        eprintln!("Directory not found.");
+                        None
                     }
                 }
             }
@@ -60,7 +422,498 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         else { Some(ListuiApp::new(download_dir, dao)?) }
     };
 
-    if let Some(mut app) = app { app.run()?; }
+    if let Some(app) = app {
+        run_app(app, args.safe_mode, &config_errors)?;
+    }
        
    Ok(())
 }
+
+// Opens the database, renaming the files downloaded by older versions of lisTUI first.
+fn open_database(database_path: &Path, download_dir: &Path) -> Result<Database, Box<dyn std::error::Error>> {
+
+    let dao = Database::new(database_path)?;
+    match utils::rename_old_downloads(&dao, download_dir) {
+        Ok(0) => {}
+        Ok(renamed) => log::info!("Renamed {renamed} downloads to include the id of their video."),
+        Err(e) => log::warn!("Failed to rename the old downloads: {e}"),
+    }
+    Ok(dao)
+}
+
+fn run_app(mut app: ListuiApp, safe_mode: bool, config_errors: &[ConfigError]) -> Result<(), Box<dyn std::error::Error>> {
+
+    // Other `listui`s send what they are asked to open here, until the app is closed.
+    #[cfg(unix)]
+    let _socket = listen(&app);
+    if safe_mode { app.set_safe_mode(); }
+    if utils::check_updates() { app.check_for_updates(); }
+    app.check_ytdlp();
+    app.rank_instances();
+    app.resume_downloads()?;
+    app.start_auto_sync(utils::auto_sync())?;
+    app.show_config_errors(config_errors);
+    app.run()?;
+    Ok(())
+}
+
+// Sends what this `listui` was asked to open to the one that is running, if there's one. Just
+// `listui` isn't sent anywhere, but it doesn't start a second player either.
+#[cfg(unix)]
+fn forward_to_running(args: &ListuiArgs) -> Result<bool, Box<dyn std::error::Error>> {
+
+    let Some(path) = remote::socket_path() else { return Ok(false) };
+    let request = match (&args.command, &args.playlist) {
+        (Some(Command::Play(play)), _) => Request::Play(play.playlist.clone(), play.shuffle),
+        // Paths are relative to where this one was run.
+        (_, Some(target)) => Request::Open(fs::canonicalize(target).map_or_else(|_| target.clone(), |path| path.display().to_string())),
+        (_, None) if remote::is_running(&path) => return Err("lisTUI is already running.".into()),
+        (_, None) => return Ok(false),
+    };
+
+    if !remote::forward(&path, &request)? {
+        return Ok(false);
+    }
+    println!("Sent to the lisTUI that is already running.");
+    Ok(true)
+}
+
+// A lisTUI that can't listen for the others still plays, they just start on their own.
+#[cfg(unix)]
+fn listen(app: &ListuiApp) -> Option<remote::SocketFile> {
+
+    let path = remote::socket_path()?;
+    let result = remote::bind(&path).and_then(|(listener, socket)| app.serve(listener).map(|_| socket));
+    match result {
+        Ok(socket) => Some(socket),
+        Err(e) => {
+            log::warn!("Failed to listen on {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+// Prints the config file, or where the value of every setting comes from.
+fn show_config(path: &Path, settings: Settings, file_settings: &[(String, String)], errors: &[ConfigError], args: ConfigShowArgs) {
+
+    if args.resolved {
+        for setting in settings.with_defaults().iter() {
+            println!("{setting}");
+        }
+    }
+    else {
+        println!("# {}", path.display());
+        for (key, value) in file_settings {
+            println!("{key}={}", config::shown_value(key, value));
+        }
+    }
+
+    for error in errors {
+        eprintln!("Skipped config {error}");
+    }
+}
+
+// Finds a saved playlist by its youtube id or title.
+fn find_playlist(dao: &Database, name: &str) -> Result<Playlist, Box<dyn std::error::Error>> {
+
+    Ok(utils::find_playlist(dao, name)?.ok_or(format!("Playlist {name} not found."))?)
+}
+
+fn add_url(dao: &Database, args: AddArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let source = utils::YoutubeSource::default();
+    let imported = runtime.block_on(import::fetch_url(&source, &args.url, None))?;
+    let (title, yt_ids) = import::save(dao, imported)?;
+    println!("Saved {title} ({} tracks)", yt_ids.len());
+    Ok(())
+}
+
+// Tab separated, so scripts can split the lines. Playlists created by the user have no id.
+fn list_playlists(dao: &Database, download_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+
+    for playlist in dao.get_playlists()? {
+        let tracks = dao.get_tracks(playlist.id)?;
+        let total = tracks.len();
+        let downloaded = total - utils::missing_tracks(download_dir, tracks).len();
+        println!("{}\t{total}\t{downloaded}\t{}", playlist.title, playlist.yt_id.as_deref().unwrap_or("-"));
+    }
+    Ok(())
+}
+
+// Like pressing u in the playlists menu: the excluded videos are left out, and the files of
+// renamed tracks follow their new title.
+fn update_playlists(dao: &Database, download_dir: &Path, args: UpdateArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    let playlists = match (&args.playlist, args.all) {
+        (Some(name), false) => vec![find_playlist(dao, name)?],
+        (None, true) => dao.get_playlists()?.into_iter().filter(|playlist| playlist.yt_id.is_some()).collect(),
+        _ => return Err("Pass the playlist to update, or --all to update every one.".into())
+    };
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let source = utils::YoutubeSource::default();
+
+    // Scripts can tell from the exit code if any of them failed.
+    let mut failed = 0;
+    for playlist in &playlists {
+        match update_playlist(dao, download_dir, &runtime, &source, playlist) {
+            Ok(report) => println!("{}: {report}", playlist.title),
+            Err(e) => {
+                println!("{}: {e}", playlist.title);
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{failed} of {} playlists could not be updated.", playlists.len()).into())
+    }
+}
+
+// Returns what changed, like "+12 new, −3 removed, 0 renamed".
+fn update_playlist(dao: &Database, download_dir: &Path, runtime: &tokio::runtime::Runtime, source: &utils::YoutubeSource, playlist: &Playlist) -> Result<String, Box<dyn std::error::Error>> {
+
+    let yt_id = playlist.yt_id.as_deref().ok_or("This playlist was created by you, it can't be updated.")?;
+    if let Some(dir) = yt_id.strip_prefix(utils::FOLDER_PREFIX) {
+        let (_, diff) = utils::sync_music_dir(dao, Path::new(dir))?;
+        return Ok(diff.to_string());
+    }
+    let (details, videos) = runtime.block_on(source.fetch_playlist(yt_id, None))?;
+    dao.sync_playlist(playlist.id, &details)?;
+    if yt_id.starts_with(RSS_PREFIX) {
+        dao.save_episodes(playlist.id, videos)?;
+        return Ok(String::from("episodes updated"));
+    }
+
+    let exclusions = Exclusions::saved(dao, playlist.id);
+    let videos = videos.into_iter().filter(|video| !exclusions.excludes(video)).collect();
+    let diff = dao.update_tracks(playlist.id, videos)?;
+    for (track, title) in &diff.renamed {
+        utils::rename_track_file(download_dir, track, title);
+    }
+    Ok(diff.to_string())
+}
+
+fn export_playlist(dao: &Database, download_dir: &Path, args: ExportArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    let playlist = find_playlist(dao, &args.playlist)?;
+
+    let output_dir = match args.output {
+        Some(dir) => dir,
+        None => std::env::current_dir()?
+    };
+
+    let path = utils::export_playlist(dao, &playlist, download_dir, &output_dir, args.format)?;
+    println!("Playlist exported to {}", path.display());
+    Ok(())
+}
+
+fn download_playlist(dao: &Database, download_dir: &Path, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+
+    let playlist = find_playlist(dao, name)?;
+    download_tracks(dao, download_dir, &playlist)
+}
+
+fn sync_playlists(dao: &Database, download_dir: &Path, args: SyncArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    let mut playlists = dao.get_playlists()?;
+    if args.resume {
+        let queue = utils::download_queue(dao)?;
+        playlists.retain(|playlist| queue.contains(&playlist.id));
+    }
+
+    if playlists.is_empty() {
+        println!("Nothing to download.");
+    }
+    for playlist in playlists {
+        println!("{}:", playlist.title);
+        download_tracks(dao, download_dir, &playlist)?;
+    }
+    Ok(())
+}
+
+// The playlist is in the download queue until every missing track has been tried, so closing
+// lisTUI in the middle doesn't lose what was left.
+fn download_tracks(dao: &Database, download_dir: &Path, playlist: &Playlist) -> Result<(), Box<dyn std::error::Error>> {
+
+    let tracks = dao.get_tracks(playlist.id)?;
+    let total = tracks.len();
+    let missing = utils::missing_tracks(download_dir, tracks);
+    let mut downloaded = total - missing.len();
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let downloader = utils::get_downloader();
+
+    utils::queue_download(dao, playlist.id)?;
+    let mut failed = Vec::new();
+    for track in missing {
+        let path = utils::get_track_path(download_dir, &track);
+        match runtime.block_on(downloader.download_id(track.source().unwrap_or_default(), &path, None)) {
+            Some(DownloadResult::Completed(_)) => downloaded += 1,
+            Some(DownloadResult::Failed(reason)) => failed.push(format!("{} ({reason})", track.title)),
+            _ => failed.push(track.title.clone())
+        }
+        println!("[{}/{total}] {}", downloaded + failed.len(), track.title);
+    }
+    utils::dequeue_download(dao, playlist.id)?;
+
+    println!("{downloaded}/{total} downloaded.");
+    for title in failed {
+        println!("Failed: {title}");
+    }
+    Ok(())
+}
+
+fn import_file(dao: &Database, args: ImportFileArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    let urls = import::read_url_file(&args.path)?;
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let source = utils::YoutubeSource::default();
+
+    // One after the other, so the APIs don't rate limit us.
+    let mut summary = import::ImportSummary::default();
+    for (ind, url) in urls.iter().enumerate() {
+        println!("[{}/{}] Importing {url}", ind + 1, urls.len());
+        let result = runtime.block_on(import::fetch_url(&source, url, None))
+            .map_err(|e| e.to_string())
+            .and_then(|imported| import::save(dao, imported).map_err(|e| e.to_string()));
+        match result {
+            Ok((title, _)) => {
+                println!("      Saved {title}");
+                summary.imported.push(title);
+            },
+            Err(e) => {
+                println!("      Failed: {e}");
+                summary.failed.push((url.clone(), e));
+            }
+        }
+    }
+
+    println!("{}", summary.report());
+    Ok(())
+}
+
+fn parse_bitrate(value: &str) -> Result<String, String> {
+
+    match value.strip_suffix(['K', 'k']).map(str::parse::<u32>) {
+        Some(Ok(bitrate)) if bitrate > 0 => Ok(format!("{bitrate}k")),
+        _ => Err(format!("Invalid bitrate {value}, expected something like 96K."))
+    }
+}
+
+fn reencode_tracks(dao: &Database, download_dir: &Path, args: ReencodeArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    let playlists = match &args.playlist {
+        Some(name) => vec![find_playlist(dao, name)?],
+        None => dao.get_playlists()?
+    };
+
+    // The same file can be in many playlists. Local files are left alone.
+    let mut paths = HashSet::new();
+    let mut tracks = Vec::new();
+    for playlist in playlists {
+        for track in dao.get_tracks(playlist.id)? {
+            let path = utils::get_track_path(download_dir, &track);
+            let local = track.source().is_none_or(|source| source.starts_with("file://"));
+            let converted = path.extension().is_some_and(|ext| ext == args.format.extension()) && args.bitrate.is_none();
+            if !local && !converted && path.exists() && paths.insert(path.clone()) {
+                tracks.push((track.title, path));
+            }
+        }
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let tools = utils::get_tools();
+    let (mut size_before, mut size_after, mut failed) = (0, 0, 0);
+    for (ind, (title, path)) in tracks.iter().enumerate() {
+        // Written next to the original, which is only replaced once the conversion has worked.
+        let output = path.with_extension(args.format.extension());
+        let temp = path.with_extension(format!("reencode.{}", args.format.extension()));
+        let result = runtime.block_on(transcoder::reencode(&tools, path, &temp, args.format, args.bitrate.as_deref()))
+            .and_then(|_| {
+                let before = fs::metadata(path)?.len();
+                fs::rename(&temp, &output)?;
+                if output != *path { fs::remove_file(path)?; }
+                Ok((before, fs::metadata(&output)?.len()))
+            });
+
+        match result {
+            Ok((before, after)) => {
+                println!("[{}/{}] {title}: {} -> {}", ind + 1, tracks.len(), megabytes(before), megabytes(after));
+                size_before += before;
+                size_after += after;
+            },
+            Err(e) => {
+                println!("[{}/{}] {title}: {e}", ind + 1, tracks.len());
+                failed += 1;
+            }
+        }
+    }
+
+    println!("Re-encoded {} of {} tracks, from {} to {}.", tracks.len() - failed, tracks.len(), megabytes(size_before), megabytes(size_after));
+    Ok(())
+}
+
+fn cleanup_files(dao: &Database, download_dir: &Path, args: CleanupArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    if args.delete && args.import.is_some() {
+        return Err("Use either --delete or --import, not both.".into());
+    }
+
+    let orphans = utils::orphan_files(dao, download_dir)?;
+    let mut total = 0;
+    for path in &orphans {
+        let size = fs::metadata(path)?.len();
+        println!("{} ({})", path.display(), megabytes(size));
+        total += size;
+    }
+
+    if let Some(name) = args.import {
+        let playlist = match find_playlist(dao, &name) {
+            Ok(playlist) => playlist,
+            Err(_) => dao.create_playlist(&name)?
+        };
+        let videos = orphans.iter().map(|path| utils::local_video(path)).collect();
+        dao.save_tracks(videos, playlist.id)?;
+        println!("Added {} files to {}.", orphans.len(), playlist.title);
+    }
+    else if args.delete {
+        for path in &orphans {
+            fs::remove_file(path)?;
+        }
+        println!("Deleted {} files, {} reclaimed.", orphans.len(), megabytes(total));
+    }
+    else {
+        println!("{} files not used by any track, {} can be reclaimed. Run again with --delete to delete them, or --import <playlist> to keep them.", orphans.len(), megabytes(total));
+    }
+    Ok(())
+}
+
+fn share_duplicates(dao: &Database, download_dir: &Path, args: DuplicatesArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    let playlists: HashMap<i32, String> = dao.get_playlists()?.into_iter().map(|playlist| (playlist.id, playlist.title)).collect();
+    let mut duplicates: Vec<(Vec<Track>, bool)> = utils::find_duplicates(dao, download_dir)?.into_iter().map(|tracks| (tracks, false)).collect();
+    if args.fingerprint {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let fingerprinted = runtime.block_on(utils::fingerprint_tracks(dao, download_dir, &utils::get_tools()))?;
+        let groups = duplicates.into_iter().map(|(tracks, _)| tracks).collect();
+        duplicates = utils::add_same_audio(groups, fingerprinted, download_dir);
+    }
+
+    let mut reclaimable = 0;
+    for (tracks, same_audio) in &duplicates {
+        println!("{}:{}", tracks[0].title, if *same_audio { " (same audio)" } else { "" });
+        let mut sizes = Vec::new();
+        for track in tracks {
+            let path = utils::get_track_path(download_dir, track);
+            let size = fs::metadata(&path).map(|metadata| metadata.len()).ok();
+            let playlist = track.playlist_id.and_then(|id| playlists.get(&id)).map(String::as_str).unwrap_or("no playlist");
+            match size {
+                Some(size) => println!("    {} [{playlist}] ({})", track.title, megabytes(size)),
+                None => println!("    {} [{playlist}] (not downloaded)", track.title),
+            }
+            sizes.push(size.unwrap_or_default());
+        }
+        // The biggest file is kept.
+        reclaimable += sizes.iter().sum::<u64>() - sizes.iter().max().copied().unwrap_or_default();
+    }
+
+    if args.link {
+        let mut freed = 0;
+        for (tracks, _) in &duplicates {
+            freed += utils::share_file(download_dir, tracks)?;
+        }
+        println!("{} songs share one file now, {} reclaimed.", duplicates.len(), megabytes(freed));
+    }
+    else {
+        println!("{} songs are in more than one file, {} can be reclaimed. Run again with --link to make them share one file.", duplicates.len(), megabytes(reclaimable));
+    }
+    Ok(())
+}
+
+fn tag_tracks(dao: &Database, download_dir: &Path, args: TagArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let client = MusicBrainzClient::default();
+    let tracks = utils::tracks_to_tag(dao, download_dir, args.again)?;
+    let total = tracks.len();
+    let mut found = 0;
+    for (ind, track) in tracks.iter().enumerate() {
+        // Failed lookups aren't saved, so they are tried again the next time.
+        match runtime.block_on(client.lookup(&track.title, track.artist.as_deref(), track.duration)) {
+            Ok(recording) => {
+                utils::save_metadata(dao, track.id, recording.as_ref())?;
+                match recording {
+                    Some(recording) => {
+                        found += 1;
+                        let album = recording.album.map(|album| format!(", {album}")).unwrap_or_default();
+                        let year = recording.year.map(|year| format!(" ({year})")).unwrap_or_default();
+                        println!("[{}/{total}] {}: {}{album}{year}", ind + 1, track.title, recording.artist);
+                    }
+                    None => println!("[{}/{total}] {}: not found", ind + 1, track.title)
+                }
+            }
+            Err(e) => println!("[{}/{total}] {}: {e}", ind + 1, track.title)
+        }
+    }
+
+    println!("{found}/{total} found in MusicBrainz.");
+    Ok(())
+}
+
+fn export_stats(dao: &Database, args: StatsArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    let content = utils::export_stats(dao, args.history, args.format)?;
+    match args.output {
+        Some(path) => {
+            fs::write(&path, content)?;
+            println!("Stats exported to {}", path.display());
+        }
+        None => print!("{content}")
+    }
+    Ok(())
+}
+
+fn backup_database(dao: &Database, args: DbBackupArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    dao.backup(&args.path)?;
+    let problems = dao.check_integrity()?;
+    println!("Database backed up to {}", args.path.display());
+    if !problems.is_empty() {
+        eprintln!("The database was already damaged, so the backup is too: {}", problems.join("; "));
+    }
+    Ok(())
+}
+
+fn restore_database(database_path: &Path, args: DbRestoreArgs) -> Result<(), Box<dyn std::error::Error>> {
+
+    #[cfg(unix)]
+    if remote::socket_path().is_some_and(|path| remote::is_running(&path)) {
+        return Err("Close lisTUI before restoring the database.".into());
+    }
+
+    Database::restore(database_path, &args.path)?;
+    println!("Database restored from {}", args.path.display());
+    Ok(())
+}
+
+// Compacting a damaged database could lose even more, so it's only done when the check passes.
+fn check_database(dao: &Database, database_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+
+    let problems = dao.check_integrity()?;
+    if !problems.is_empty() {
+        for problem in &problems {
+            println!("{problem}");
+        }
+        return Err("The database is damaged. Restore a backup with `listui db restore`.".into());
+    }
+
+    let size = || fs::metadata(database_path).map(|metadata| metadata.len()).unwrap_or(0);
+    let before = size();
+    dao.vacuum()?;
+    println!("The database is fine. Compacted from {} to {}.", megabytes(before), megabytes(size()));
+    Ok(())
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
@@ -0,0 +1,325 @@
+//! Notifications about what happens while the user is looking at something else.
+//!
+//! Every notification has a kind, and each channel gets the kinds listed in its setting, like
+//! `NOTIFY_DESKTOP=failure,finished` or `NOTIFY_STATUS=track`. The rest of the app only says
+//! what happened, and the `Dispatcher` decides how the user is told.
+//!
+//! Notifications about tracks can also be posted as JSON to WEBHOOK_URL, and the tracks that
+//! are played are scrobbled to ListenBrainz if LISTENBRAINZ_TOKEN is set.
+
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use listui_lib::api::{ListenBrainzClient, WebhookClient, WebhookEvent, LISTENBRAINZ_URL};
+use listui_lib::models::Track;
+use listui_lib::scrobble::{Listen, Scrobbler};
+use tokio::runtime::Handle;
+
+use crate::jobs::Gate;
+
+/// For how long a notification is shown next to the title of the playlists menu.
+const STATUS_DURATION: Duration = Duration::from_secs(10);
+
+/// For how long the colors of the screen are reversed by the flash channel (until the next redraw after it).
+const FLASH_DURATION: Duration = Duration::from_millis(200);
+
+/// What a notification is about.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Another track has started playing.
+    Track,
+    /// The current track has been played until the end.
+    Ended,
+    /// Something failed in the background, like the download of a track.
+    Failure,
+    /// A background job has finished, like the download of a whole playlist.
+    Finished,
+    /// A newer version of lisTUI is out.
+    Update,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 5] = [Kind::Track, Kind::Ended, Kind::Failure, Kind::Finished, Kind::Update];
+
+    /// The name used in the settings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Track => "track",
+            Kind::Ended => "ended",
+            Kind::Failure => "failure",
+            Kind::Finished => "finished",
+            Kind::Update => "update",
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Kind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s.trim())
+            .ok_or_else(|| format!("unknown notification \"{}\"", s.trim()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub kind: Kind,
+    pub message: String,
+    /// The track it's about, and the title of its playlist.
+    pub track: Option<(Track, Option<String>)>,
+}
+
+impl Notification {
+    pub fn new(kind: Kind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into(), track: None }
+    }
+
+    pub fn with_track(mut self, track: Track, playlist: Option<String>) -> Self {
+        self.track = Some((track, playlist));
+        self
+    }
+
+    fn webhook_event(&self) -> WebhookEvent {
+        WebhookEvent {
+            event: String::from(self.kind.name()),
+            message: self.message.clone(),
+            title: self.track.as_ref().map(|(track, _)| track.title.clone()),
+            artist: self.track.as_ref().and_then(|(track, _)| track.artist.clone()),
+            yt_id: self.track.as_ref().and_then(|(track, _)| track.source().map(String::from)),
+            playlist: self.track.as_ref().and_then(|(_, playlist)| playlist.clone()),
+        }
+    }
+}
+
+/// Where notifications can be delivered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    /// Next to the title of the playlists menu, for a few seconds.
+    Status,
+    /// The terminal bell.
+    Bell,
+    /// The colors of the screen are reversed for a moment, for terminals without a bell.
+    Flash,
+    /// A desktop notification, sent with notify-send.
+    Desktop,
+    /// A POST request to WEBHOOK_URL.
+    Webhook,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 5] = [Channel::Status, Channel::Bell, Channel::Flash, Channel::Desktop, Channel::Webhook];
+
+    /// The setting with the kinds of notifications this channel gets.
+    pub fn setting(&self) -> &'static str {
+        match self {
+            Channel::Status => "NOTIFY_STATUS",
+            Channel::Bell => "NOTIFY_BELL",
+            Channel::Flash => "NOTIFY_FLASH",
+            Channel::Desktop => "NOTIFY_DESKTOP",
+            Channel::Webhook => "NOTIFY_WEBHOOK",
+        }
+    }
+
+    /// The kinds this channel gets if its setting isn't set.
+    pub fn default_kinds(&self) -> Vec<Kind> {
+        match self {
+            Channel::Status => vec![Kind::Failure, Kind::Finished],
+            Channel::Bell | Channel::Flash | Channel::Desktop => Vec::new(),
+            Channel::Webhook => vec![Kind::Track, Kind::Ended, Kind::Failure],
+        }
+    }
+}
+
+/// Parses a list of kinds separated by commas, or `none`.
+pub fn parse_kinds(value: &str) -> Result<Vec<Kind>, String> {
+    if value.trim().is_empty() || value.trim() == "none" {
+        return Ok(Vec::new());
+    }
+    value.split(',').map(str::parse).collect()
+}
+
+/// Formats a list of kinds like `parse_kinds` expects them.
+pub fn format_kinds(kinds: &[Kind]) -> String {
+    match kinds {
+        [] => String::from("none"),
+        _ => kinds.iter().map(Kind::name).collect::<Vec<_>>().join(","),
+    }
+}
+
+/// Delivers every notification to the channels that get its kind.
+pub struct Dispatcher {
+    routes: Vec<(Channel, Vec<Kind>)>,
+    status: Option<(String, Instant)>,
+    flashed_at: Option<Instant>,
+    webhook: Option<WebhookClient>,
+    // Every service the tracks are scrobbled to, and the listen of the track that is playing.
+    scrobblers: Vec<Arc<dyn Scrobbler>>,
+    listen: Option<Listen>,
+    // Where the webhook and scrobbling requests are sent from, and what they wait on while the background jobs are paused.
+    runtime: Handle,
+    gate: Option<Gate>,
+}
+
+impl Dispatcher {
+    pub fn new(routes: Vec<(Channel, Vec<Kind>)>, runtime: Handle) -> Self {
+        Self { routes, status: None, flashed_at: None, webhook: None, scrobblers: Vec::new(), listen: None, runtime, gate: None }
+    }
+
+    pub fn with_webhook(mut self, webhook: WebhookClient) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    pub fn with_scrobbler(mut self, scrobbler: Arc<dyn Scrobbler>) -> Self {
+        self.scrobblers.push(scrobbler);
+        self
+    }
+
+    pub fn with_gate(mut self, gate: Gate) -> Self {
+        self.gate = Some(gate);
+        self
+    }
+
+    /// Reads the routes from the NOTIFY_* settings, the webhook from WEBHOOK_URL and ListenBrainz
+    /// from LISTENBRAINZ_TOKEN and LISTENBRAINZ_URL.
+    pub fn from_env(runtime: Handle) -> Self {
+        let routes = Channel::ALL
+            .into_iter()
+            .map(|channel| {
+                let kinds = env::var(channel.setting())
+                    .ok()
+                    .and_then(|value| parse_kinds(&value).ok())
+                    .unwrap_or_else(|| channel.default_kinds());
+                (channel, kinds)
+            })
+            .collect();
+        let mut dispatcher = Self::new(routes, runtime);
+        if let Ok(url) = env::var("WEBHOOK_URL") {
+            dispatcher = dispatcher.with_webhook(WebhookClient::new(&url));
+        }
+        if let Ok(token) = env::var("LISTENBRAINZ_TOKEN").map(|token| token.trim().to_string()) {
+            if !token.is_empty() {
+                let url = env::var("LISTENBRAINZ_URL").unwrap_or_else(|_| String::from(LISTENBRAINZ_URL));
+                dispatcher = dispatcher.with_scrobbler(Arc::new(ListenBrainzClient::new(&url, &token)));
+            }
+        }
+        dispatcher
+    }
+
+    /// The channels that get notifications of this kind.
+    pub fn channels(&self, kind: Kind) -> Vec<Channel> {
+        self.routes
+            .iter()
+            .filter(|(_, kinds)| kinds.contains(&kind))
+            .map(|(channel, _)| *channel)
+            .collect()
+    }
+
+    pub fn dispatch(&mut self, notification: Notification) {
+        log::info!("Notification ({}): {}", notification.kind.name(), notification.message);
+        for channel in self.channels(notification.kind) {
+            match channel {
+                Channel::Status => self.status = Some((notification.message.clone(), Instant::now())),
+                Channel::Bell => ring_bell(),
+                Channel::Flash => self.flashed_at = Some(Instant::now()),
+                Channel::Desktop => send_desktop(&notification.message),
+                Channel::Webhook => self.post_webhook(&notification),
+            }
+        }
+        self.scrobble(&notification);
+    }
+
+    // Requests that fail are only logged, playback shouldn't depend on the webhook.
+    fn post_webhook(&self, notification: &Notification) {
+        let Some(webhook) = self.webhook.clone() else { return };
+        let event = notification.webhook_event();
+        self.spawn(async move {
+            if let Err(e) = webhook.post(&event).await {
+                log::warn!("Failed to post the {} event to the webhook: {e}", event.event);
+            }
+        });
+    }
+
+    // Scrobbling doesn't depend on the routes: every track that starts playing is sent as
+    // playing now, and submitted as listened once it's played until the end.
+    fn scrobble(&mut self, notification: &Notification) {
+        if self.scrobblers.is_empty() {
+            return;
+        }
+        let Some((track, _)) = &notification.track else { return };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        let listen = match notification.kind {
+            Kind::Track => {
+                self.listen = Listen::from_track(track, now);
+                self.listen.clone()
+            }
+            Kind::Ended => {
+                // If it wasn't seen starting, it's guessed from its duration, known once it has been played.
+                let started = self.listen.take().filter(|listen| listen.title == track.title);
+                let listened_at = started.map_or_else(|| now - track.duration.unwrap_or(0) as i64, |listen| listen.listened_at);
+                Listen::from_track(track, listened_at)
+            }
+            _ => return,
+        };
+        let Some(listen) = listen else { return };
+        let submit = notification.kind == Kind::Ended;
+        for scrobbler in self.scrobblers.clone() {
+            let listen = listen.clone();
+            self.spawn(async move {
+                let result = if submit { scrobbler.submit(&listen).await } else { scrobbler.now_playing(&listen).await };
+                if let Err(e) = result {
+                    log::warn!("Failed to scrobble \"{}\" to {}: {e}", listen.title, scrobbler.name());
+                }
+            });
+        }
+    }
+
+    // Runs a request in the background, once the background jobs aren't paused.
+    fn spawn(&self, request: impl Future<Output = ()> + Send + 'static) {
+        let mut gate = self.gate.clone();
+        self.runtime.spawn(async move {
+            if let Some(gate) = gate.as_mut() {
+                gate.resumed().await;
+            }
+            request.await;
+        });
+    }
+
+    /// The last notification delivered to the status channel, unless it's too old.
+    pub fn status(&self) -> Option<&str> {
+        self.status
+            .as_ref()
+            .filter(|(_, shown_at)| shown_at.elapsed() < STATUS_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// Whether the screen should be drawn with its colors reversed, right after a flash.
+    pub fn is_flashing(&self) -> bool {
+        self.flashed_at.is_some_and(|flashed_at| flashed_at.elapsed() < FLASH_DURATION)
+    }
+}
+
+fn ring_bell() {
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+}
+
+// notify-send is waited for in another thread, so it doesn't block the app nor linger as a zombie.
+fn send_desktop(message: &str) {
+    let mut command = Command::new("notify-send");
+    command.arg("lisTUI").arg(message).stdout(Stdio::null()).stderr(Stdio::null());
+    thread::spawn(move || {
+        if let Err(e) = command.status() {
+            log::warn!("Failed to send a desktop notification: {e}");
+        }
+    });
+}
@@ -0,0 +1,102 @@
+//! Only one lisTUI plays at a time. The first one listens on a socket next to the database, and
+//! running `listui <url>` or `listui play <playlist>` again sends the request there instead of
+//! starting a second player, that would fight over the audio device and the database.
+//!
+//! Requests are one line each, like `open https://...` or `play shuffle My playlist`, and the
+//! running instance answers `ok` once it has them.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::utils;
+
+// How long the running instance has to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What is asked of the running instance.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    /// A playlist, video or channel URL, a playlist file or a directory, like `listui <url>` takes.
+    Open(String),
+    /// A saved playlist to play, by title or youtube id, and whether to shuffle it.
+    Play(String, bool),
+}
+
+impl Request {
+    pub fn to_line(&self) -> String {
+        match self {
+            Request::Open(target) => format!("open {target}"),
+            Request::Play(playlist, true) => format!("play shuffle {playlist}"),
+            Request::Play(playlist, false) => format!("play inorder {playlist}"),
+        }
+    }
+
+    pub fn parse(line: &str) -> Option<Self> {
+        let (command, rest) = line.trim_end_matches(['\r', '\n']).split_once(' ')?;
+        match command {
+            "open" => Some(Request::Open(String::from(rest))),
+            "play" => {
+                let (order, playlist) = rest.split_once(' ')?;
+                Some(Request::Play(String::from(playlist), order == "shuffle"))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The socket of the instance using the database.
+pub fn socket_path() -> Option<PathBuf> {
+    utils::get_db_path().map(|path| path.with_extension("sock"))
+}
+
+/// Sends `request` to the running instance. Returns false if there's none, and then this one
+/// should start.
+#[cfg(unix)]
+pub fn forward(path: &Path, request: &Request) -> io::Result<bool> {
+    let mut stream = match UnixStream::connect(path) {
+        Ok(stream) => stream,
+        // The socket of an instance that crashed is still there, but nobody listens.
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    writeln!(stream, "{}", request.to_line())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    match reply.trim() {
+        "ok" => Ok(true),
+        reply => Err(io::Error::other(format!("The running lisTUI answered \"{reply}\"."))),
+    }
+}
+
+/// Whether another instance is listening.
+#[cfg(unix)]
+pub fn is_running(path: &Path) -> bool {
+    UnixStream::connect(path).is_ok()
+}
+
+/// Listens for requests, once `forward` has found no other instance. The socket is removed
+/// when the `SocketFile` is dropped.
+#[cfg(unix)]
+pub fn bind(path: &Path) -> io::Result<(UnixListener, SocketFile)> {
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    Ok((listener, SocketFile(path.to_path_buf())))
+}
+
+#[cfg(unix)]
+pub struct SocketFile(PathBuf);
+
+#[cfg(unix)]
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
@@ -0,0 +1,2213 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use argh::FromArgs;
+use crossterm::event::KeyCode;
+use ratatui::backend::TestBackend;
+use ratatui::style::Color;
+use ratatui::widgets::{Block, Borders};
+use ratatui::Terminal;
+
+use listui_lib::album;
+use listui_lib::artwork::{Artwork, Protocol};
+use listui_lib::api::{FetchProgress, FetchSource, ListenBrainzClient, MusicBrainzClient, ResponseCache, WebhookClient};
+use listui_lib::db::Database;
+use listui_lib::downloader::{DownloadOptions, DownloadResult, Downloader, MockDownloader, Watchdog, YtDlpDownloader};
+use listui_lib::fingerprint::{self, Fingerprint};
+use listui_lib::models::{NewPlaylist, NewVideo, Restriction, Track};
+use listui_lib::player::DAMAGED;
+use listui_lib::spectrum::{self, SampleTap};
+use listui_lib::stats::{self, StatsFormat};
+use listui_lib::tools::{Tools, YtdlpStatus};
+
+use crate::app::OUTPUT_RETRY;
+use crate::config::{parse_cli_setting, parse_config, Settings};
+use crate::events::AppEvent;
+use crate::exclusions::Exclusions;
+use crate::notifications::{Channel, Dispatcher, Kind, Notification};
+use crate::remote::{self, Request};
+use crate::utils;
+use crate::widgets::artwork::draw_artwork;
+use crate::widgets::visualizer::Visualizer;
+
+use super::{Harness, MockSource};
+
+fn source() -> MockSource {
+    MockSource::default().with_playlist(
+        "PLtest",
+        "Test playlist",
+        &["First song", "Second song", "Third song"],
+    )
+}
+
+fn harness() -> Harness {
+    Harness::new(source())
+}
+
+#[test]
+fn import_play_skip_delete() {
+    let mut h = harness();
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Enter);
+    h.wait_for("First song");
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("First song");
+
+    h.press(KeyCode::Char('n'));
+    h.wait_for_player("Second song");
+
+    h.press(KeyCode::Char('q'));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Char('d'));
+    h.wait_for_absence("Test playlist");
+}
+
+#[test]
+fn now_playing_shows_the_track_and_the_ones_after_it() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.wait_for("First song");
+
+    h.press(KeyCode::Char('v'));
+    h.wait_for("Nothing is playing.");
+    h.press(KeyCode::Char('v'));
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("Second song");
+
+    h.press(KeyCode::Char('v'));
+    h.wait_for("Up next:");
+    let screen = h.screen();
+    assert!(screen.contains("Shuffle: off   Repeat: playlist"));
+    // The playlist starts over after the last track.
+    let next = |screen: &str, title: &str| screen.lines().position(|line| line.contains(title)).unwrap();
+    assert!(next(&screen, "Up next:") < next(&screen, "Third song"));
+    assert!(next(&screen, "Third song") < next(&screen, "First song"));
+
+    // The player keys still work.
+    h.press(KeyCode::Char('n'));
+    h.wait_for_played(2);
+    h.press(KeyCode::Char('q'));
+    h.wait_for_player("Third song");
+}
+
+// A playlist with more tracks than fit in the screen.
+fn long_source() -> MockSource {
+    let titles: Vec<String> = (1..=40).map(|n| format!("Song number {n}")).collect();
+    let titles: Vec<&str> = titles.iter().map(String::as_str).collect();
+    MockSource::default().with_playlist("PLlong", "Long playlist", &titles)
+}
+
+#[test]
+fn long_lists_show_the_position_and_a_scrollbar() {
+    let mut h = Harness::new(long_source());
+
+    h.app.fetch_new_playlist(String::from("PLlong"));
+    h.wait_for("Long playlist");
+    assert!(!h.screen().contains("║"));
+
+    h.press(KeyCode::Enter);
+    h.wait_for("-/40");
+    for _ in 0..3 {
+        h.press(KeyCode::Down);
+    }
+    h.wait_for("3/40");
+    assert!(h.screen().contains("║"));
+
+    h.press(KeyCode::Char('s'));
+    h.type_text("number 1");
+    h.press(KeyCode::Down);
+    // "Song number 1" and the ten from 10 to 19.
+    h.wait_for("1/11");
+}
+
+#[test]
+fn search_finds_the_start_of_words_and_phrases() {
+    let mut h = harness();
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.wait_for("Third song");
+
+    h.press(KeyCode::Char('s'));
+    h.type_text("SEC");
+    h.wait_for_absence("First song");
+    assert!(h.screen().contains("Second song"));
+    assert!(!h.screen().contains("Third song"));
+
+    // Words are matched from their start.
+    for _ in 0..3 {
+        h.press(KeyCode::Backspace);
+    }
+    h.type_text("ond");
+    h.wait_for_absence("Second song");
+
+    for _ in 0..3 {
+        h.press(KeyCode::Backspace);
+    }
+    h.type_text("\"third song\"");
+    h.wait_for("Third song");
+    assert!(!h.screen().contains("First song"));
+
+    h.press(KeyCode::Esc);
+    h.wait_for("First song");
+}
+
+#[test]
+fn lists_move_a_page_at_a_time_and_to_either_end() {
+    let mut h = Harness::new(long_source());
+    h.app.fetch_new_playlist(String::from("PLlong"));
+    h.wait_for("Long playlist");
+    h.press(KeyCode::Enter);
+
+    h.press(KeyCode::Char('G'));
+    h.wait_for("40/40");
+    h.press(KeyCode::Char('g'));
+    h.wait_for("1/40");
+    h.press(KeyCode::End);
+    h.wait_for("40/40");
+    h.press(KeyCode::Home);
+    h.wait_for("1/40");
+
+    // A page is more than one track, but less than all of them.
+    h.press(KeyCode::PageDown);
+    h.wait_for_absence(" 1/40 ");
+    assert!(!h.screen().contains(" 2/40 "));
+    assert!(!h.screen().contains(" 40/40 "));
+    h.press(KeyCode::PageUp);
+    h.wait_for(" 1/40 ");
+    h.press(KeyCode::PageUp);
+    h.wait_for(" 1/40 ");
+}
+
+#[test]
+fn tracks_are_selected_and_played_with_the_mouse() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    // The cell where an item is drawn: the first row of a list with it, not the titles on the
+    // borders, like the one of the playlist's details, that could mention it too.
+    let cell = |h: &Harness, text: &str| {
+        let screen = h.screen();
+        let (row, line) = screen.lines().enumerate().find(|(_, line)| line.starts_with('│') && line.contains(text)).unwrap();
+        let column = line[..line.find(text).unwrap()].chars().count();
+        (column as u16, row as u16)
+    };
+
+    let (column, row) = cell(&h, "Test playlist");
+    h.click(column, row);
+    h.click(column, row);
+    h.wait_for("First song");
+
+    let (column, row) = cell(&h, "Second song");
+    h.click(column, row);
+    h.wait_for("2/3");
+    assert!(h.player.played_files().is_empty());
+    h.click(column, row);
+    h.wait_for_player("Second song");
+
+    // The borders aren't an item.
+    h.click(0, 0);
+    h.click(0, 0);
+    h.wait_for("2/3");
+}
+
+#[test]
+fn plays_next_track_when_one_ends() {
+    let mut h = harness();
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("First song");
+
+    h.wait_for_player("Second song");
+    h.wait_for_player("Third song");
+    h.wait_for_played(3);
+    assert_eq!(h.downloader.downloaded(), vec!["PLtest-0", "PLtest-1", "PLtest-2"]);
+}
+
+#[test]
+fn shuffle_plays_every_track_once_per_cycle() {
+    let mut h = harness();
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Char('r'));
+    // Started from the middle, the tracks above it are still played in the first cycle.
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_played(2);
+
+    // Previous goes back to the track that was played, not the one above in the list.
+    h.press(KeyCode::Char('b'));
+    h.wait_for_played(3);
+    for played in 4..=8 {
+        h.press(KeyCode::Char('n'));
+        h.wait_for_played(played);
+    }
+    let played: Vec<String> = h
+        .player
+        .played_files()
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(played[2], played[0]);
+
+    let mut first_cycle = vec![played[0].clone(), played[3].clone(), played[4].clone()];
+    let mut second_cycle = played[5..8].to_vec();
+    first_cycle.sort();
+    second_cycle.sort();
+    assert_eq!(first_cycle, vec!["First song [PLtest-0].mp3", "Second song [PLtest-1].mp3", "Third song [PLtest-2].mp3"]);
+    assert_eq!(second_cycle, first_cycle);
+    assert_ne!(played[5], played[4]);
+}
+
+#[test]
+fn skips_tracks_that_fail_to_download() {
+    let mut h = Harness::with_downloader(source(), MockDownloader::new(Vec::new()).failing("PLtest-1"));
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("First song");
+    h.wait_for_played(1);
+
+    h.press(KeyCode::Char('n'));
+    h.wait_for("Second song is no longer available.");
+    h.press(KeyCode::Esc);
+    h.wait_for_player("Third song");
+    h.wait_for_played(2);
+    let played: Vec<String> = h
+        .player
+        .played_files()
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(played, vec!["First song [PLtest-0].mp3", "Third song [PLtest-2].mp3"]);
+}
+
+#[test]
+fn failures_are_shown_next_to_the_playlists_title() {
+    let mut h = Harness::with_downloader(source(), MockDownloader::new(Vec::new()).failing("PLtest-1"));
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Second song is no longer available.");
+    h.press(KeyCode::Esc);
+    h.press(KeyCode::Char('q'));
+
+    // Track changes aren't routed to the status by default.
+    h.wait_for("Playlists (press h for help) - Second song could not be downloaded");
+    assert!(!h.screen().contains("Playing"));
+}
+
+#[test]
+fn substitutes_unavailable_track() {
+    let source = source().with_alternate("PLtest-1", "reupload", "Second song (Official Audio)");
+    let mut h = Harness::with_downloader(source, MockDownloader::new(Vec::new()).failing("PLtest-1"));
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Second song is no longer available.");
+
+    h.press(KeyCode::Char('y'));
+    h.wait_for("Second song will now be played from Second song (Official Audio).");
+    h.press(KeyCode::Enter);
+
+    // The track keeps its title, but is downloaded from the new video.
+    h.press(KeyCode::Char('b'));
+    h.wait_for_player("Second song");
+    h.wait_for_played(2);
+    assert!(h.downloader.downloaded().contains(&String::from("reupload")));
+}
+
+#[test]
+fn shows_download_progress() {
+    let downloader = MockDownloader::new(Vec::new()).with_delay(Duration::from_secs(1));
+    let mut h = Harness::with_downloader(source(), downloader);
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("Downloading 50% (1.0 MiB/s, 0:01 left)");
+    h.wait_for_played(1);
+}
+
+#[test]
+fn skipping_a_track_cancels_its_download() {
+    let downloader = MockDownloader::new(Vec::new()).with_delay(Duration::from_secs(1));
+    let mut h = Harness::with_downloader(source(), downloader);
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("Downloading 50%");
+
+    h.press(KeyCode::Char('n'));
+    h.wait_for_played(1);
+    assert_eq!(h.downloader.cancelled(), vec!["PLtest-0"]);
+    assert_eq!(h.player.played_files(), vec![h.dir().join("Second song [PLtest-1].mp3")]);
+    assert!(!h.dir().join("First song [PLtest-0].mp3").exists());
+}
+
+#[test]
+fn tracks_downloaded_in_another_format_are_played() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    fs::write(h.dir().join("First song [PLtest-0].opus"), "").unwrap();
+
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_played(1);
+    assert_eq!(h.player.played_files(), vec![h.dir().join("First song [PLtest-0].opus")]);
+    assert!(h.downloader.downloaded().is_empty());
+}
+
+#[test]
+fn damaged_downloads_are_downloaded_again() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    let path = h.dir().join("First song [PLtest-0].mp3");
+    fs::write(&path, DAMAGED).unwrap();
+
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_played(1);
+    assert_eq!(h.downloader.downloaded(), vec!["PLtest-0"]);
+    assert_eq!(h.player.played_files(), vec![path.clone()]);
+    assert!(!fs::read(&path).unwrap().starts_with(DAMAGED));
+}
+
+#[test]
+fn damaged_files_are_found_by_decoding_them() {
+    let dir = std::env::temp_dir().join(format!("listui-verify-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let ffmpeg = dir.join("ffmpeg");
+    fs::write(&ffmpeg, "#!/bin/sh\nif grep -q DAMAGED \"$5\"; then echo \"$5: Invalid data found when processing input\" >&2; exit 1; fi\n").unwrap();
+    fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(dir.join("fine.mp3"), "").unwrap();
+    fs::write(dir.join("cut.mp3"), DAMAGED).unwrap();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let tools = Tools { ffmpeg: Some(ffmpeg), ..Default::default() };
+    assert_eq!(runtime.block_on(tools.check_audio(&dir.join("fine.mp3"))).unwrap(), None);
+    let problem = runtime.block_on(tools.check_audio(&dir.join("cut.mp3"))).unwrap().unwrap();
+    assert!(problem.ends_with("Invalid data found when processing input"), "{problem}");
+
+    let missing = Tools { ffmpeg: Some(dir.join("missing")), ..Default::default() };
+    assert!(runtime.block_on(missing.check_audio(&dir.join("fine.mp3"))).is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn download_whole_playlist() {
+    let mut h = Harness::with_downloader(source(), MockDownloader::new(Vec::new()).failing("PLtest-2"));
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    fs::write(h.dir().join("First song [PLtest-0].mp3"), "").unwrap();
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('g'));
+    h.wait_for("Test playlist: 2/3 downloaded, 1 failed");
+    assert_eq!(h.downloader.downloaded(), vec!["PLtest-1"]);
+}
+
+#[test]
+fn paused_jobs_resume_where_they_were_left() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Char('z'));
+    h.wait_for("background jobs paused (press z)");
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('g'));
+    std::thread::sleep(Duration::from_millis(200));
+    h.wait_for("background jobs paused");
+    assert!(h.downloader.downloaded().is_empty());
+
+    h.press(KeyCode::Char('z'));
+    h.wait_for("Test playlist: 3/3 downloaded");
+    assert!(!h.screen().contains("background jobs paused"));
+    assert_eq!(h.downloader.downloaded(), vec!["PLtest-0", "PLtest-1", "PLtest-2"]);
+}
+
+#[test]
+fn jobs_are_listed_and_cancelled() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Char('z'));
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('g'));
+    h.press(KeyCode::Char('j'));
+    h.wait_for("[queued] Download Test playlist");
+    assert!(h.screen().contains("Background jobs - paused"));
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('c'));
+    h.wait_for("[cancelled] Download Test playlist");
+    h.press(KeyCode::Char('z'));
+    h.press(KeyCode::Char('q'));
+    h.wait_for("Playlists");
+
+    // It also left the download queue, so it isn't resumed either.
+    h.app.resume_downloads().unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    h.wait_for("Playlists");
+    assert!(h.downloader.downloaded().is_empty());
+}
+
+#[test]
+fn batch_jobs_show_how_each_item_went() {
+    let mut h = Harness::with_downloader(source(), MockDownloader::new(Vec::new()).failing("PLtest-2"));
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('g'));
+    h.wait_for("Test playlist: 2/3 downloaded, 1 failed");
+    h.press(KeyCode::Char('j'));
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Download Test playlist - finished: 2 done, 1 failed");
+    assert!(h.screen().contains("✓ First song"));
+    assert!(h.screen().contains("✗ Third song: the mock download failed"));
+
+    h.press(KeyCode::Esc);
+    h.wait_for_absence("finished: 2 done");
+    h.wait_for("[finished] Download Test playlist");
+}
+
+#[test]
+fn songs_show_whether_they_are_downloaded() {
+    let mut h = Harness::with_downloader(source(), MockDownloader::new(Vec::new()).failing("PLtest-2"));
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('g'));
+    h.wait_for("Test playlist: 2/3 downloaded, 1 failed");
+    h.press(KeyCode::Enter);
+    h.wait_for("↓ First song");
+    h.wait_for("↓ Second song");
+    h.wait_for("! Third song");
+}
+
+#[test]
+fn deleted_downloads_are_downloaded_again() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    let path = h.dir().join("First song [PLtest-0].mp3");
+    fs::write(&path, "").unwrap();
+
+    h.press(KeyCode::Enter);
+    h.wait_for("↓ First song");
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('D'));
+    h.wait_for("Deleted the download, it will be downloaded again when played.");
+    h.wait_for_absence("↓ First song");
+    assert!(!path.exists());
+
+    h.press(KeyCode::Enter);
+    h.wait_for_played(1);
+    assert_eq!(h.downloader.downloaded(), vec!["PLtest-0"]);
+}
+
+#[test]
+fn the_details_of_the_selected_playlist_are_shown() {
+    let mut h = Harness::new(source().with_playlist_details("PLtest", "Test channel", "Songs to test with"));
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Down);
+    h.wait_for("By Test channel");
+    h.wait_for("Songs to test with");
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let today = &stats::utc_time(now)[..10];
+    h.wait_for(&format!("Saved {today}, synced {today}"));
+}
+
+#[test]
+fn batch_jobs_can_be_paused_and_cancelled_on_their_own() {
+    let downloader = MockDownloader::new(Vec::new()).with_delay(Duration::from_millis(500));
+    let mut h = Harness::with_downloader(source(), downloader);
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('g'));
+    h.press(KeyCode::Char('j'));
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Download Test playlist (z to pause, c to cancel)");
+
+    h.press(KeyCode::Char('z'));
+    h.wait_for("Download Test playlist - paused");
+    // The track being downloaded is finished, but the next one isn't started.
+    std::thread::sleep(Duration::from_millis(1200));
+    assert!(h.downloader.downloaded().len() < 3);
+
+    h.press(KeyCode::Char('c'));
+    h.wait_for("Download Test playlist - cancelled");
+    assert!(h.screen().contains("skipped"));
+    h.press(KeyCode::Esc);
+    h.wait_for("[cancelled] Download Test playlist");
+}
+
+#[test]
+fn play_command_opens_a_saved_playlist_playing() {
+    let args = crate::ListuiArgs::from_args(&["listui"], &["play", "Test playlist", "--shuffle"]).unwrap();
+    let Some(crate::Command::Play(play)) = args.command else { panic!("Not the play command.") };
+    assert_eq!(play.playlist, "Test playlist");
+    assert!(play.shuffle);
+
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.app.play_playlist(1, play.shuffle);
+    h.wait_for("Test playlist ⤨");
+    h.wait_for_played(1);
+}
+
+#[test]
+fn other_instances_forward_what_they_are_asked_to_open() {
+    let mut h = harness();
+    let path = h.dir().join("db.sock");
+    let (listener, socket) = remote::bind(&path).unwrap();
+    h.app.serve(listener).unwrap();
+
+    let request = Request::Open(String::from("https://www.youtube.com/playlist?list=PLtest"));
+    assert_eq!(Request::parse(&request.to_line()), Some(request.clone()));
+    assert!(remote::forward(&path, &request).unwrap());
+    h.wait_for("Test playlist");
+
+    let request = Request::Play(String::from("Test playlist"), false);
+    assert!(remote::forward(&path, &request).unwrap());
+    h.wait_for_played(1);
+
+    // Once it's closed, the next one starts on its own.
+    drop(socket);
+    assert!(!remote::forward(&path, &request).unwrap());
+}
+
+#[test]
+fn unknown_playlist_shows_error() {
+    let mut h = harness();
+
+    h.app.fetch_new_playlist(String::from("PLmissing"));
+    h.wait_for("Couldn't find playlist with id PLmissing.");
+
+    h.press(KeyCode::Enter);
+    h.wait_for("Playlists");
+}
+
+#[test]
+fn open_m3u_file_from_prompt() {
+    let mut h = harness();
+    let path = h.dir().join("list.m3u");
+    fs::write(&path, "#EXTM3U\n#EXTINF:1,Local song\nlocal.mp3\n").unwrap();
+    fs::write(h.dir().join("local.mp3"), "").unwrap();
+
+    h.press(KeyCode::Char('i'));
+    h.type_text(&path.to_string_lossy());
+    h.press(KeyCode::Enter);
+    h.wait_for("Local song");
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("Local song");
+    assert_eq!(h.player.played_files(), vec![h.dir().join("local.mp3")]);
+    assert!(h.downloader.downloaded().is_empty());
+}
+
+#[test]
+fn bulk_import_from_url_file() {
+    let mut h = Harness::new(source().with_video("dQw4w9WgXcQ", "Some single"));
+    let path = h.dir().join("urls.txt");
+    fs::write(
+        &path,
+        "# Migrated subscriptions\n\
+         https://www.youtube.com/playlist?list=PLtest\n\
+         \n\
+         https://www.youtube.com/watch?v=dQw4w9WgXcQ\n\
+         https://www.youtube.com/playlist?list=PLmissing\n",
+    )
+    .unwrap();
+
+    h.press(KeyCode::Char('I'));
+    h.type_text(&path.to_string_lossy());
+    h.press(KeyCode::Enter);
+    h.wait_for("Imported 2 of 3 URLs.");
+    assert!(h.screen().contains("https://www.youtube.com/playlist?list=PLmissing"));
+
+    h.press(KeyCode::Enter);
+    h.wait_for("Test playlist");
+    h.wait_for("Singles");
+}
+
+#[test]
+fn export_playlist_to_json() {
+    let mut h = harness();
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Char('E'));
+    h.wait_for("Playlist exported to");
+
+    let exported = fs::read_to_string(h.dir().join("Test playlist.json")).unwrap();
+    assert!(exported.contains("\"title\": \"Second song\""));
+    assert!(exported.contains("\"yt_id\": \"PLtest-1\""));
+}
+
+#[test]
+fn database_is_backed_up_and_restored() {
+    let mut h = harness();
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Char('B'));
+    h.wait_for("Database backed up to");
+
+    let backup = fs::read_dir(h.dir()).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.file_name().unwrap().to_string_lossy().starts_with("listui-backup-"))
+        .unwrap();
+    let database = h.dir().join("restored.sqlite");
+    Database::restore(&database, &backup).unwrap();
+
+    let restored = Database::new(&database).unwrap();
+    assert!(restored.check_integrity().unwrap().is_empty());
+    assert_eq!(restored.get_playlists().unwrap()[0].title, "Test playlist");
+    assert_eq!(restored.get_tracks(restored.get_playlists().unwrap()[0].id).unwrap().len(), 3);
+
+    // A file that isn't a database is refused, and the restored one is left as it was.
+    let garbage = h.dir().join("garbage.sqlite");
+    fs::write(&garbage, "not a database").unwrap();
+    assert!(Database::restore(&database, &garbage).is_err());
+    assert_eq!(restored.get_playlists().unwrap().len(), 1);
+}
+
+#[test]
+fn add_track_to_user_playlist() {
+    let mut h = harness();
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Char('c'));
+    h.type_text("Favourites");
+    h.press(KeyCode::Enter);
+    h.wait_for("Favourites");
+
+    h.press(KeyCode::Up);
+    h.press(KeyCode::Enter);
+    h.wait_for("Second song");
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('a'));
+    h.wait_for("Add to playlist");
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Added Second song to Favourites");
+
+    h.press(KeyCode::Esc);
+    h.press(KeyCode::Char('q'));
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Second song");
+    assert!(!h.screen().contains("First song"));
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('x'));
+    h.wait_for_absence("Second song");
+}
+
+#[test]
+fn add_single_video_from_prompt() {
+    let mut h = Harness::new(source().with_video("dQw4w9WgXcQ", "Single song"));
+
+    h.press(KeyCode::Char('a'));
+    h.type_text("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42");
+    h.press(KeyCode::Enter);
+    h.wait_for_player("Single song");
+    assert_eq!(h.downloader.downloaded(), vec!["dQw4w9WgXcQ"]);
+
+    h.press(KeyCode::Char('q'));
+    h.wait_for("Singles");
+}
+
+#[test]
+fn add_channel_uploads_from_prompt() {
+    let mut h = Harness::new(source().with_playlist(
+        "UUabcdefghijklmnopqrstuv",
+        "Uploads from Some channel",
+        &["Newest upload"],
+    ));
+
+    h.press(KeyCode::Char('a'));
+    h.type_text("https://www.youtube.com/channel/UCabcdefghijklmnopqrstuv/videos");
+    h.press(KeyCode::Enter);
+    h.wait_for("Uploads from Some channel");
+
+    h.press(KeyCode::Enter);
+    h.wait_for("Newest upload");
+}
+
+#[test]
+fn weekly_digest_of_followed_channels() {
+    let source = source()
+        .with_playlist("UUabcdefghijklmnopqrstuv", "Uploads from Some channel", &["Older upload"])
+        .with_recent_upload("UUabcdefghijklmnopqrstuv", "new-video", "Brand new single");
+    let mut h = Harness::new(source);
+
+    h.app.fetch_new_playlist(String::from("UUabcdefghijklmnopqrstuv"));
+    h.wait_for("Uploads from Some channel");
+
+    h.press(KeyCode::Char('w'));
+    h.wait_for("New this week");
+    h.wait_for("Brand new single");
+    assert!(!h.screen().contains("Older upload"));
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_played(1);
+    assert_eq!(h.downloader.downloaded(), vec!["new-video"]);
+}
+
+#[test]
+fn shows_audio_diagnostics() {
+    let mut h = harness();
+
+    h.press(KeyCode::Char('o'));
+    h.wait_for("Backend: mock");
+    h.wait_for("Switch to the rodio backend");
+
+    h.press(KeyCode::Esc);
+    h.wait_for("Playlists");
+}
+
+#[test]
+fn add_bandcamp_album_from_prompt() {
+    let album = "https://artist.bandcamp.com/album/some-album";
+    let mut h = Harness::new(source().with_playlist(album, "Some album", &["Opening track"]));
+
+    h.press(KeyCode::Char('a'));
+    h.type_text(&format!("{album}?from=discover"));
+    h.press(KeyCode::Enter);
+    h.wait_for("Some album");
+
+    h.press(KeyCode::Enter);
+    h.wait_for("Opening track");
+}
+
+#[test]
+fn paste_url_into_prompt() {
+    let mut h = harness();
+
+    h.press(KeyCode::Char('a'));
+    h.paste("https://www.youtube.com/playlist?list=PLtest\nignored line");
+    h.wait_for("list=PLtest");
+    h.press(KeyCode::Enter);
+    h.wait_for("Test playlist");
+}
+
+#[test]
+fn import_playlist_from_library() {
+    let mut h = Harness::new(source().with_playlist("subsonic:42", "Server mix", &["Track from server"]));
+
+    h.press(KeyCode::Char('l'));
+    h.wait_for("Library (press Enter to import)");
+    h.wait_for("Server mix");
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Playlists (press h for help)");
+    h.press(KeyCode::Enter);
+    h.wait_for("Track from server");
+}
+
+#[test]
+fn import_jellyfin_album_from_library() {
+    let mut h = Harness::new(
+        source()
+            .with_playlist("jellyfin:abc", "Some album", &["Album opener"])
+            .with_playlist("subsonic:42", "Server mix", &["Track from server"]),
+    );
+
+    h.press(KeyCode::Char('l'));
+    h.wait_for("Server mix");
+    h.wait_for("Some album");
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Playlists (press h for help)");
+    h.press(KeyCode::Enter);
+    h.wait_for("Album opener");
+}
+
+#[test]
+fn small_terminal_shows_size_hint() {
+    let mut h = harness();
+
+    h.resize(60, 10);
+    h.wait_for("Playlists (press h for help)");
+    assert!(!h.screen().contains("$$"));
+
+    h.press(KeyCode::Char('o'));
+    h.wait_for("at least 40x11");
+
+    h.press(KeyCode::Esc);
+    h.resize(80, 30);
+    h.wait_for("$$");
+}
+
+#[test]
+fn set_playlist_color_and_emoji() {
+    let mut h = harness();
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Char('t'));
+    h.type_text("purple-ish");
+    h.press(KeyCode::Enter);
+    h.wait_for("Unknown color purple-ish.");
+    h.press(KeyCode::Esc);
+
+    h.press(KeyCode::Char('t'));
+    h.type_text("green 🎸");
+    h.press(KeyCode::Enter);
+    // The cell after a wide character isn't redrawn by the test backend, so only look for the emoji.
+    h.wait_for("🎸");
+
+    h.press(KeyCode::Enter);
+    h.wait_for("First song");
+    assert!(h.screen().contains("🎸"));
+}
+
+#[test]
+fn subscribe_to_podcast() {
+    let feed = "https://example.com/feed.xml";
+    let mut h = Harness::new(source().with_playlist(
+        &format!("rss:{feed}"),
+        "Some podcast",
+        &["First episode", "Second episode"],
+    ));
+
+    h.press(KeyCode::Char('p'));
+    h.type_text(feed);
+    h.press(KeyCode::Enter);
+    h.wait_for("Some podcast");
+
+    h.press(KeyCode::Enter);
+    h.wait_for("● First episode");
+    h.wait_for("● Second episode");
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("First episode");
+    h.wait_for_player("Second episode");
+    assert!(!h.screen().contains("● First episode"));
+    assert!(h.screen().contains("● Second episode"));
+}
+
+#[test]
+fn marks_restricted_tracks() {
+    let mut h = Harness::new(source().with_restriction("PLtest-1", Restriction::RegionBlocked));
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Enter);
+    h.wait_for("⚠ Second song");
+    assert!(!h.screen().contains("⚠ First song"));
+}
+
+#[test]
+fn safe_mode_shows_banner_and_skips_background_checks() {
+    let mut h = Harness::new(source().with_restriction("PLtest-1", Restriction::RegionBlocked));
+    h.app.set_safe_mode();
+
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    assert!(h.screen().contains("SAFE MODE"));
+
+    h.press(KeyCode::Enter);
+    h.wait_for("Second song");
+    assert!(h.screen().contains("SAFE MODE"));
+    assert!(!h.screen().contains("⚠"));
+}
+
+#[test]
+fn invalid_config_lines_are_reported_and_skipped() {
+    let (settings, errors) = parse_config(
+        "# Comment\nDOWNLOAD_DIR=\"/tmp/music\"\nPRELOAD_MB=lots\nNOT_A_SETTING=1\nREGION=ES # Spain\nbroken line\n",
+    );
+    assert_eq!(
+        settings,
+        vec![
+            (String::from("DOWNLOAD_DIR"), String::from("/tmp/music")),
+            (String::from("REGION"), String::from("ES")),
+        ]
+    );
+    assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), vec![3, 4, 6]);
+
+    let mut h = harness();
+    h.app.show_config_errors(&errors);
+    h.wait_for("line 3 (PRELOAD_MB): expected a number of megabytes");
+    assert!(h.screen().contains("line 4 (NOT_A_SETTING): unknown setting"));
+
+    h.press(KeyCode::Enter);
+    h.wait_for("Playlists");
+}
+
+#[test]
+fn api_timeouts_must_be_positive() {
+    let (settings, errors) = parse_config("API_TIMEOUT=0\nAPI_CONNECT_TIMEOUT=5\nAPI_RETRIES=none\n");
+    assert_eq!(settings, vec![(String::from("API_CONNECT_TIMEOUT"), String::from("5"))]);
+    assert_eq!(
+        errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        vec![
+            "line 1 (API_TIMEOUT): expected a number of seconds, got \"0\"",
+            "line 3 (API_RETRIES): expected a number of retries, got \"none\"",
+        ]
+    );
+}
+
+#[test]
+fn command_line_settings_take_priority_over_the_config_file() {
+    let setting = |key: &str, value: &str| (String::from(key), String::from(value));
+    let cli = vec![parse_cli_setting("REGION=US").unwrap()];
+    let file = vec![setting("REGION", "ES"), setting("PRELOAD_MB", "8")];
+    assert!(parse_cli_setting("PRELOAD_MB=lots").is_err());
+    assert!(parse_cli_setting("YTDLP_PATH=/nonexistent/yt-dlp").is_err());
+    assert!(parse_cli_setting("YTDLP_PATH=yt-dlp_linux").is_ok());
+
+    let settings = Settings::resolve(&cli, &file);
+    let resolved: Vec<String> = settings
+        .iter()
+        .filter(|s| ["REGION", "PRELOAD_MB", "AUTO_SUBSTITUTE"].contains(&s.key))
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        resolved,
+        vec!["REGION=US (command line)", "PRELOAD_MB=8 (config file)", "AUTO_SUBSTITUTE is not set"]
+    );
+}
+
+#[test]
+fn whats_new_is_shown_after_upgrading() {
+    let dao = Database::in_memory().unwrap();
+    dao.set_state("version", "0.2.4").unwrap();
+    let mut h = Harness::with_database(MockSource::default(), dao);
+    assert!(h.screen().contains("What's new in lisTUI"));
+
+    h.press(KeyCode::Enter);
+    h.wait_for("Playlists");
+    assert!(!h.screen().contains("What's new in lisTUI"));
+
+    h.press(KeyCode::Char('h'));
+    h.press(KeyCode::Char('n'));
+    h.wait_for("What's new in lisTUI");
+    h.press(KeyCode::Enter);
+    h.wait_for("Playlists");
+
+    // New users and the same version don't get it.
+    assert!(!harness().screen().contains("What's new in lisTUI"));
+}
+
+#[test]
+fn newer_release_is_shown_and_remembered() {
+    let source = MockSource::default().with_release("99.0.0", "Everything is faster.");
+    let mut h = Harness::new(source);
+    h.app.check_for_updates();
+    h.wait_for("lisTUI 99.0.0 is out (press v)");
+
+    h.press(KeyCode::Char('v'));
+    h.wait_for("Everything is faster.");
+    h.press(KeyCode::Enter);
+    h.wait_for_absence("Everything is faster.");
+
+    // Checked again the next day, the release is taken from the database until then.
+    h.app.check_for_updates();
+    assert!(h.app.try_recv_event().is_none());
+    h.wait_for("lisTUI 99.0.0 is out");
+}
+
+#[test]
+fn outdated_ytdlp_is_offered_to_be_updated() {
+    let status = YtdlpStatus::Outdated { version: String::from("2023.03.04"), latest: String::from("2024.12.13") };
+    let mut h = Harness::new(source().with_ytdlp(status));
+    h.app.check_ytdlp();
+    h.wait_for("yt-dlp 2024.12.13 is out, you have 2023.03.04.");
+
+    // Skipped, it can still be updated from the playlists menu.
+    h.press(KeyCode::Char('n'));
+    h.wait_for_absence("yt-dlp 2024.12.13 is out");
+    h.press(KeyCode::Char('Y'));
+    h.wait_for("yt-dlp 2024.12.13 installed.");
+}
+
+#[test]
+fn missing_ytdlp_is_offered_to_be_installed() {
+    let mut h = Harness::new(source().with_ytdlp(YtdlpStatus::Missing));
+    h.app.check_ytdlp();
+    h.wait_for("yt-dlp was not found");
+    h.press(KeyCode::Char('y'));
+    h.wait_for("Playlists");
+    h.wait_for("installed.");
+}
+
+#[test]
+fn instance_ranking_is_used_and_remembered() {
+    let source = MockSource::default()
+        .with_instance("https://fast.example", Some(80))
+        .with_instance("https://slow.example", Some(900))
+        .with_instance("https://down.example", None);
+    let mut h = Harness::new(source);
+    let expected = ["https://fast.example", "https://slow.example", "https://down.example"];
+
+    h.app.rank_instances();
+    h.wait_until(|_| utils::instance_ranking() == expected, "the ranking");
+
+    // The instances are only probed once a day.
+    utils::set_instance_ranking(Vec::new());
+    h.app.rank_instances();
+    assert!(h.app.try_recv_event().is_none());
+    assert_eq!(utils::instance_ranking(), expected);
+}
+
+#[test]
+fn webhook_gets_the_routed_notifications_as_json() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                content_length = length.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        String::from_utf8(body).unwrap()
+    });
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut dispatcher = Dispatcher::new(vec![(Channel::Webhook, vec![Kind::Track])], runtime.handle().clone())
+        .with_webhook(WebhookClient::new(&url));
+    let track = Track {
+        id: 1,
+        title: String::from("First song"),
+        source_id: Some(String::from("PLtest-0")),
+        playlist_id: Some(1),
+        played: None,
+        position: None,
+        restriction: None,
+        removed: false,
+        source_url: None,
+        artist: Some(String::from("Some artist")),
+        album: None,
+        track_number: None,
+        duration: None,
+        play_count: 0,
+        trim_start: None,
+        trim_end: None,
+        year: None,
+        metadata_checked: false,
+        uploaded_at: None,
+    };
+
+    // Only track changes are routed to the webhook.
+    dispatcher.dispatch(Notification::new(Kind::Failure, "Second song could not be downloaded"));
+    dispatcher.dispatch(
+        Notification::new(Kind::Track, "Playing First song").with_track(track, Some(String::from("Test playlist"))),
+    );
+    assert_eq!(
+        server.join().unwrap(),
+        r#"{"event":"track","message":"Playing First song","title":"First song","artist":"Some artist","yt_id":"PLtest-0","playlist":"Test playlist"}"#
+    );
+}
+
+#[test]
+fn playing_tracks_are_scrobbled_to_listenbrainz() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let (mut request_line, mut authorization, mut content_length) = (String::new(), String::new(), 0);
+        reader.read_line(&mut request_line).unwrap();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                content_length = length.trim().parse().unwrap();
+            }
+            if line.to_lowercase().starts_with("authorization:") {
+                authorization = line["authorization:".len()..].trim().to_string();
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        (request_line.trim().to_string(), authorization, String::from_utf8(body).unwrap())
+    });
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    // Scrobbling doesn't depend on the routes.
+    let mut dispatcher = Dispatcher::new(vec![], runtime.handle().clone())
+        .with_scrobbler(Arc::new(ListenBrainzClient::new(&url, "secret-token")));
+    let track = Track {
+        id: 1,
+        title: String::from("First song"),
+        source_id: Some(String::from("PLtest-0")),
+        playlist_id: Some(1),
+        played: None,
+        position: None,
+        restriction: None,
+        removed: false,
+        source_url: None,
+        artist: Some(String::from("Some artist")),
+        album: None,
+        track_number: None,
+        duration: None,
+        play_count: 0,
+        trim_start: None,
+        trim_end: None,
+        year: None,
+        metadata_checked: false,
+        uploaded_at: None,
+    };
+
+    dispatcher.dispatch(
+        Notification::new(Kind::Track, "Playing First song").with_track(track, Some(String::from("Test playlist"))),
+    );
+    let (request_line, authorization, body) = server.join().unwrap();
+    assert_eq!(request_line, "POST /1/submit-listens HTTP/1.1");
+    assert_eq!(authorization, "Token secret-token");
+    assert_eq!(
+        body,
+        format!(
+            r#"{{"listen_type":"playing_now","payload":[{{"track_metadata":{{"artist_name":"Some artist","track_name":"First song","additional_info":{{"media_player":"lisTUI","submission_client":"lisTUI","submission_client_version":"{}","origin_url":"https://www.youtube.com/watch?v=PLtest-0"}}}}}}]}}"#,
+            env!("CARGO_PKG_VERSION")
+        )
+    );
+}
+
+#[test]
+fn flash_is_only_shown_for_the_routed_notifications() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut dispatcher = Dispatcher::new(vec![(Channel::Flash, vec![Kind::Track, Kind::Failure])], runtime.handle().clone());
+    assert!(!dispatcher.is_flashing());
+
+    dispatcher.dispatch(Notification::new(Kind::Finished, "Test playlist downloaded"));
+    assert!(!dispatcher.is_flashing());
+
+    dispatcher.dispatch(Notification::new(Kind::Failure, "Second song could not be downloaded"));
+    assert!(dispatcher.is_flashing());
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(!dispatcher.is_flashing());
+}
+
+#[test]
+fn loading_screen_shows_the_fetched_videos_and_the_instances_tried() {
+    let mut h = Harness::new(source());
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    let what = String::from("playlist PLtest");
+    let down = FetchSource::Invidious(String::from("https://down.example"));
+    let up = FetchSource::Invidious(String::from("https://up.example"));
+
+    h.send(AppEvent::FetchProgress(FetchProgress::Trying { what: what.clone(), source: down.clone() }));
+    h.send(AppEvent::FetchProgress(FetchProgress::Failed { what: what.clone(), source: down, error: String::from("timed out") }));
+    h.send(AppEvent::FetchProgress(FetchProgress::Trying { what, source: up }));
+    h.send(AppEvent::FetchProgress(FetchProgress::Page { videos: 50, total: Some(120) }));
+
+    let screen = h.screen();
+    assert!(screen.contains("Fetched 50 of 120 videos."));
+    assert!(screen.contains("50/120"));
+    assert!(screen.contains("✗ Invidious instance: https://down.example"));
+    assert!(screen.contains("… Invidious instance: https://up.example"));
+    h.wait_for("Test playlist");
+}
+
+#[test]
+fn slow_instances_can_be_skipped_while_importing() {
+    let mut h = Harness::new(source().with_stalled_playlist("PLtest"));
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("… Invidious instance: https://slow.example - page 1 of 4");
+    h.wait_for("Press n to skip to the next instance");
+
+    h.press(KeyCode::Char('n'));
+    h.wait_for("Test playlist");
+}
+
+#[test]
+fn shift_u_updates_the_playlist_skipping_the_cache() {
+    let source = source();
+    let invalidated = source.invalidated();
+    let mut h = Harness::new(source);
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Char('u'));
+    h.wait_for("Playlists (press h for help)");
+    assert!(invalidated.lock().unwrap().is_empty());
+
+    h.press(KeyCode::Char('U'));
+    h.wait_for("Playlists (press h for help)");
+    assert_eq!(*invalidated.lock().unwrap(), vec!["PLtest"]);
+}
+
+#[test]
+fn cached_responses_expire_and_can_be_invalidated() {
+    let dir = std::env::temp_dir().join(format!("listui-cache-test-{}", std::process::id()));
+    let cache = ResponseCache::new(&dir, Duration::from_secs(60));
+    let url = "https://example.com/api/v1/playlists/PLtest?page=1";
+
+    cache.put("PLtest", url, "{}");
+    assert_eq!(cache.get("PLtest", url).as_deref(), Some("{}"));
+    assert_eq!(ResponseCache::new(&dir, Duration::ZERO).get("PLtest", url), None);
+
+    cache.invalidate("PLtest").unwrap();
+    assert_eq!(cache.get("PLtest", url), None);
+    // Nothing to forget, but it isn't an error either.
+    cache.invalidate("PLtest").unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn orphan_files_are_the_downloads_no_track_uses() {
+    let dir = std::env::temp_dir().join(format!("listui-orphans-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let dao = Database::in_memory().unwrap();
+    let playlist = dao.create_playlist("Mine").unwrap();
+    let video = NewVideo::new(String::from("Kept song"), String::from("kept"));
+    dao.save_tracks(vec![video], playlist.id).unwrap();
+
+    for file in ["Kept song [kept].mp3", "Renamed song.opus", "Deleted song.mp3", "Kept song [kept].reencode.opus", "notes.txt"] {
+        fs::write(dir.join(file), "").unwrap();
+    }
+
+    let orphans = utils::orphan_files(&dao, &dir).unwrap();
+    assert_eq!(orphans, vec![dir.join("Deleted song.mp3"), dir.join("Renamed song.opus")]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn tracks_with_the_same_title_get_a_file_each() {
+    let dir = std::env::temp_dir().join(format!("listui-rename-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let dao = Database::in_memory().unwrap();
+    let mix = dao.save_playlist(NewPlaylist::new("Mix", "PLmix")).unwrap();
+    dao.save_tracks(vec![NewVideo::new("Intro", "first-id"), NewVideo::new("Intro", "second-id")], mix.id).unwrap();
+    fs::write(dir.join("Intro.mp3"), "").unwrap();
+
+    // The file downloaded before the id was in its name goes to the first one.
+    assert_eq!(utils::rename_old_downloads(&dao, &dir).unwrap(), 1);
+    let tracks = dao.get_tracks(mix.id).unwrap();
+    assert_eq!(utils::get_track_path(&dir, &tracks[0]), dir.join("Intro [first-id].mp3"));
+    assert!(utils::get_track_path(&dir, &tracks[0]).exists());
+    assert!(!utils::get_track_path(&dir, &tracks[1]).exists());
+
+    // The files of a directory are only renamed once.
+    fs::write(dir.join("Intro.mp3"), "").unwrap();
+    assert_eq!(utils::rename_old_downloads(&dao, &dir).unwrap(), 0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn interrupted_downloads_are_resumed() {
+    let dao = Database::in_memory().unwrap();
+    dao.set_state("version", env!("CARGO_PKG_VERSION")).unwrap();
+    let playlist = dao.save_playlist(NewPlaylist::new("Test playlist", "PLtest")).unwrap();
+    let videos = ["First song", "Second song"].map(|title| NewVideo::new(String::from(title), format!("{title}-id")));
+    dao.save_tracks(videos.to_vec(), playlist.id).unwrap();
+    utils::queue_download(&dao, playlist.id).unwrap();
+    // A playlist that was deleted in the middle of its download.
+    utils::queue_download(&dao, 99).unwrap();
+
+    let mut h = Harness::with_database(MockSource::default(), dao);
+    fs::write(h.dir().join("First song [First song-id].mp3"), "").unwrap();
+    h.app.resume_downloads().unwrap();
+    h.wait_for("Test playlist: 2/2 downloaded");
+    assert_eq!(h.downloader.downloaded(), vec!["Second song-id"]);
+}
+
+#[test]
+fn updating_a_playlist_keeps_the_saved_tracks() {
+    let dao = Database::in_memory().unwrap();
+    let playlist = dao.save_playlist(NewPlaylist::new("Mix", "PLmix")).unwrap();
+    let video = |title: &str| NewVideo::new(String::from(title), format!("{title}-id"));
+    dao.save_tracks(vec![video("First"), video("Second")], playlist.id).unwrap();
+    let before = dao.get_tracks(playlist.id).unwrap();
+    let favourites = dao.create_playlist("Favourites").unwrap();
+    dao.add_track(favourites.id, before[0].id).unwrap();
+
+    dao.update_tracks(playlist.id, vec![video("Second"), video("Third")]).unwrap();
+    let after = dao.get_tracks(playlist.id).unwrap();
+    let summary: Vec<(i32, &str, bool)> = after.iter().map(|t| (t.id, t.title.as_str(), t.removed)).collect();
+    assert_eq!(summary, vec![(before[0].id, "First", true), (before[1].id, "Second", false), (before[1].id + 1, "Third", false)]);
+    assert_eq!(dao.get_tracks(favourites.id).unwrap()[0].id, before[0].id);
+
+    // It's back in the playlist.
+    dao.update_tracks(playlist.id, vec![video("First"), video("Second"), video("Third")]).unwrap();
+    assert!(dao.get_tracks(playlist.id).unwrap().iter().all(|t| !t.removed));
+    assert_eq!(dao.get_tracks(playlist.id).unwrap().len(), 3);
+}
+
+#[test]
+fn stuck_downloads_are_killed_by_the_watchdog() {
+    let dir = std::env::temp_dir().join(format!("listui-watchdog-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let download = |script: &str, watchdog: Watchdog| {
+        let ytdlp = dir.join("yt-dlp");
+        fs::write(&ytdlp, format!("#!/bin/sh\n{script}\n")).unwrap();
+        fs::set_permissions(&ytdlp, fs::Permissions::from_mode(0o755)).unwrap();
+        let downloader = YtDlpDownloader::new(1)
+            .with_tools(Tools { ytdlp: Some(ytdlp), ffmpeg: None, fpcalc: None, installed_ytdlp: None })
+            .with_watchdog(watchdog);
+        match runtime.block_on(downloader.download_id("stuck", &dir.join("Stuck.mp3"), None)) {
+            Some(DownloadResult::Failed(reason)) => reason,
+            _ => panic!("The download should have failed."),
+        }
+    };
+
+    let stall = Watchdog { timeout: None, stall_timeout: Some(Duration::from_secs(1)) };
+    assert_eq!(download("sleep 30", stall), "it stalled for 1 seconds");
+
+    // Receiving something keeps the download alive, until it takes too long.
+    let receiving = "while true; do echo '[listui] 100 1000 NA 50000 3'; sleep 0.1; done";
+    let timeout = Watchdog { timeout: Some(Duration::from_secs(2)), stall_timeout: Some(Duration::from_secs(1)) };
+    assert_eq!(download(receiving, timeout), "it took longer than 2 seconds");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn downloads_are_limited_until_the_limit_is_turned_off() {
+    let dir = std::env::temp_dir().join(format!("listui-rate-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (ytdlp, args) = (dir.join("yt-dlp"), dir.join("args"));
+    fs::write(&ytdlp, format!("#!/bin/sh\necho \"$@\" > {}\nexit 1\n", args.display())).unwrap();
+    fs::set_permissions(&ytdlp, fs::Permissions::from_mode(0o755)).unwrap();
+    let tools = Tools { ytdlp: Some(ytdlp), ffmpeg: None, fpcalc: None, installed_ytdlp: None };
+    let downloader = YtDlpDownloader::new(1)
+        .with_tools(tools)
+        .with_options(DownloadOptions { rate_limit: Some(String::from("500K")), ..DownloadOptions::default() });
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    runtime.block_on(downloader.download_id("limited", &dir.join("Limited.mp3"), None));
+    assert!(fs::read_to_string(&args).unwrap().contains("--limit-rate 500K"));
+    assert_eq!(downloader.toggle_rate_limit(), Some(false));
+    runtime.block_on(downloader.download_id("unlimited", &dir.join("Unlimited.mp3"), None));
+    assert!(!fs::read_to_string(&args).unwrap().contains("--limit-rate"));
+    assert_eq!(downloader.toggle_rate_limit(), Some(true));
+
+    // There's nothing to turn on without a limit.
+    assert_eq!(YtDlpDownloader::new(1).toggle_rate_limit(), None);
+    assert!(parse_cli_setting("DOWNLOAD_RATE_LIMIT=1.5M").is_ok());
+    assert!(parse_cli_setting("DOWNLOAD_RATE_LIMIT=fast").is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn updating_a_playlist_shows_what_changed() {
+    let dao = Database::in_memory().unwrap();
+    dao.set_state("version", env!("CARGO_PKG_VERSION")).unwrap();
+    let playlist = dao.save_playlist(NewPlaylist::new("Test playlist", "PLtest")).unwrap();
+    let videos = [("First song", "PLtest-0"), ("Old title", "PLtest-1"), ("Gone song", "PLtest-9")]
+        .map(|(title, yt_id)| NewVideo::new(String::from(title), String::from(yt_id)));
+    dao.save_tracks(videos.to_vec(), playlist.id).unwrap();
+
+    let mut h = Harness::with_database(source(), dao);
+    fs::write(h.dir().join("Old title [PLtest-1].mp3"), "").unwrap();
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('u'));
+    h.wait_for("Test playlist has been updated: +1 new, −1 removed, 1 renamed.");
+    let screen = h.screen();
+    assert!(screen.contains("New: Third song"));
+    assert!(screen.contains("Removed: Gone song"));
+    assert!(screen.contains("Renamed: Old title -> Second song"));
+    assert!(h.dir().join("Second song [PLtest-1].mp3").exists());
+
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Char('u'));
+    h.wait_for("Playlists (press h for help) - Test playlist is up to date");
+}
+
+#[test]
+fn playlists_are_synced_in_the_background() {
+    let dao = Database::in_memory().unwrap();
+    dao.set_state("version", env!("CARGO_PKG_VERSION")).unwrap();
+    let playlist = dao.save_playlist(NewPlaylist::new("Test playlist", "PLtest")).unwrap();
+    let videos = [("First song", "PLtest-0"), ("Old title", "PLtest-1"), ("Gone song", "PLtest-9")]
+        .map(|(title, yt_id)| NewVideo::new(String::from(title), String::from(yt_id)));
+    dao.save_tracks(videos.to_vec(), playlist.id).unwrap();
+    dao.save_playlist(NewPlaylist::new("Deleted playlist", "PLgone")).unwrap();
+
+    let mut h = Harness::with_database(source(), dao);
+    h.resize(120, 30);
+    fs::write(h.dir().join("Old title [PLtest-1].mp3"), "").unwrap();
+    h.app.start_auto_sync(utils::AutoSync::Startup).unwrap();
+    h.wait_for("Synced 2 playlists: +1 new, −1 removed, 1 renamed, 1 failed");
+    // Nothing is shown over the playlists menu.
+    assert!(!h.screen().contains("has been updated"));
+    assert!(h.dir().join("Second song [PLtest-1].mp3").exists());
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Third song");
+    // The track that left the playlist is kept, marked as removed.
+    let screen = h.screen();
+    let gone = screen.lines().find(|line| line.contains("Gone song")).unwrap();
+    assert!(gone.contains('✗'));
+    assert!(!screen.lines().any(|line| line.contains("First song") && line.contains('✗')));
+}
+
+#[test]
+fn every_playlist_is_updated_at_once() {
+    let mut h = harness();
+    h.press(KeyCode::Char('A'));
+    h.wait_for("There's nothing to update");
+
+    let source = source().with_playlist("PLother", "Other playlist", &["Other song"]);
+    let mut h = Harness::new(source);
+    h.resize(120, 30);
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.app.fetch_new_playlist(String::from("PLother"));
+    h.wait_for("Other playlist");
+
+    h.press(KeyCode::Char('A'));
+    h.wait_for("Sync playlists - finished: 2 done");
+    assert!(h.screen().contains("✓ Test playlist"));
+    assert!(h.screen().contains("✓ Other playlist"));
+    h.press(KeyCode::Esc);
+    h.wait_for("Synced 2 playlists, nothing changed");
+}
+
+#[test]
+fn excluded_videos_are_left_out_when_updating() {
+    let source = MockSource::default().with_playlist("PLmix", "Mix", &["Song", "Song (Live)", "Other song"]);
+    let mut h = Harness::new(source);
+    h.app.fetch_new_playlist(String::from("PLmix"));
+    h.wait_for("Mix");
+
+    h.press(KeyCode::Char('x'));
+    h.wait_for("Videos to leave out");
+    h.type_text("title=\\blive\\b");
+    h.press(KeyCode::Enter);
+    h.wait_for("Mix has been updated: +0 new, −1 removed, 0 renamed.");
+    assert!(h.screen().contains("Removed: Song (Live)"));
+
+    // The prompt starts with the saved rules.
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Char('x'));
+    h.wait_for("title=\\blive\\b");
+}
+
+#[test]
+fn exclusion_rules_match_titles_durations_and_channels() {
+    let exclusions = Exclusions::parse("title=live|remix ; max=10:00; channel=Reuploads - Topic").unwrap();
+    let video = |title: &str| NewVideo::new(String::from(title), String::from("id"));
+    assert!(exclusions.excludes(&video("Song (LIVE)")));
+    assert!(!exclusions.excludes(&video("Song")));
+    assert!(exclusions.excludes(&video("Long mix").with_duration(Some(3600))));
+    assert!(!exclusions.excludes(&video("Short song").with_duration(Some(200))));
+    assert!(exclusions.excludes(&video("Song").with_channel(Some(String::from("Reuploads - Topic")))));
+    assert_eq!(exclusions.to_string(), "title=live|remix; max=10:00; channel=reuploads");
+
+    assert!(Exclusions::parse("none").unwrap().is_empty());
+    assert!(Exclusions::parse("title=(").is_err());
+    assert!(Exclusions::parse("shorter=1:00").is_err());
+}
+
+#[test]
+fn album_art_is_encoded_for_every_protocol() {
+    let red = Artwork::from_pixels(1, 1, vec![[255, 0, 0]]);
+    assert_eq!(red.kitty(2, 1), "\x1b_Ga=T,f=24,s=1,v=1,c=2,r=1,q=2,m=0;/wAA\x1b\\");
+    assert!(red.sixel().ends_with("#180@$-\x1b\\"));
+    assert!(red.iterm(2, 1).starts_with("\x1b]1337;File=inline=1;"));
+    assert_eq!("KITTY".parse::<Protocol>(), Ok(Protocol::Kitty));
+    assert!("png".parse::<Protocol>().is_err());
+
+    // Without a graphics protocol, the top half of each cell is one pixel and the bottom half
+    // the one under it.
+    let art = Artwork::from_pixels(1, 2, vec![[255, 0, 0], [0, 0, 255]]);
+    let mut terminal = Terminal::new(TestBackend::new(4, 3)).unwrap();
+    terminal.draw(|frame| {
+        let block = Block::default().borders(Borders::ALL);
+        draw_artwork(frame, frame.size(), block, &art, Protocol::Blocks);
+    }).unwrap();
+    let cell = terminal.backend().buffer().get(1, 1);
+    assert_eq!((cell.symbol(), cell.fg, cell.bg), ("▀", Color::Rgb(255, 0, 0), Color::Rgb(0, 0, 255)));
+}
+
+#[test]
+fn the_visualizer_shows_the_spectrum_of_what_is_played() {
+    // Nothing has been played yet.
+    assert_eq!(SampleTap::default().bands(4), vec![0.0; 4]);
+
+    let sine: Vec<f32> = (0..spectrum::WINDOW).map(|ind| 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * ind as f32 / 44100.0).sin()).collect();
+    let bands = spectrum::bands(&sine, 44100, 32);
+    let loudest = (0..bands.len()).max_by(|a, b| bands[*a].total_cmp(&bands[*b])).unwrap();
+    // 1kHz is a bit over halfway between 50Hz and 16kHz, in pitch. Half of full scale is -6dB.
+    assert_eq!(loudest, 16);
+    assert!((bands[loudest] - 0.9).abs() < 0.05, "{}", bands[loudest]);
+    assert!(bands[0] < 0.5 && bands[31] < 0.5, "{bands:?}");
+
+    let mut terminal = Terminal::new(TestBackend::new(4, 2)).unwrap();
+    terminal.draw(|frame| frame.render_widget(Visualizer { bands: &[0.0, 0.5, 1.0, 0.25], color: Color::Green }, frame.size())).unwrap();
+    let rows: Vec<String> = (0..2).map(|y| (0..4).map(|x| terminal.backend().buffer().get(x, y).symbol().to_string()).collect()).collect();
+    assert_eq!(rows, vec!["  █ ", " ██▄"]);
+}
+
+#[test]
+fn rename_playlist_and_keep_the_name_after_updating() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Char('r'));
+    h.wait_for("New name of the playlist");
+    // The prompt starts with the current name.
+    for _ in "playlist".chars() {
+        h.press(KeyCode::Backspace);
+    }
+    h.type_text("mix");
+    h.press(KeyCode::Enter);
+    h.wait_for("Test mix");
+
+    h.press(KeyCode::Char('u'));
+    h.wait_for("Test mix is up to date");
+    assert!(!h.screen().contains("Test playlist"));
+}
+
+#[test]
+fn tracks_keep_ids_and_urls_apart() {
+    let dao = Database::in_memory().unwrap();
+    let playlist = dao.save_playlist(NewPlaylist::new("Mix", "PLmix")).unwrap();
+    let videos = vec![
+        NewVideo::new(String::from("Video"), String::from("dQw4w9WgXcQ")),
+        NewVideo::new(String::from("Episode"), String::from("https://example.com/episode.mp3")),
+    ];
+    dao.save_tracks(videos, playlist.id).unwrap();
+
+    let tracks = dao.get_tracks(playlist.id).unwrap();
+    let sources: Vec<(Option<&str>, Option<&str>)> = tracks.iter().map(|t| (t.source_id.as_deref(), t.source_url.as_deref())).collect();
+    assert_eq!(sources, vec![(Some("dQw4w9WgXcQ"), None), (None, Some("https://example.com/episode.mp3"))]);
+    assert_eq!(tracks[1].source(), Some("https://example.com/episode.mp3"));
+}
+
+#[test]
+fn the_uploading_channel_is_the_artist_until_there_is_one() {
+    let dao = Database::in_memory().unwrap();
+    let playlist = dao.save_playlist(NewPlaylist::new("Mix", "PLmix")).unwrap();
+    dao.save_tracks(vec![NewVideo::new("First", "first-id"), NewVideo::new("Second", "second-id").with_artist(Some(String::from("Tagged")))], playlist.id).unwrap();
+    assert!(dao.get_tracks(playlist.id).unwrap()[0].artist.is_none());
+
+    let fetched = vec![
+        NewVideo::new("First", "first-id").with_channel(Some(String::from("Some Band - Topic"))),
+        NewVideo::new("Second", "second-id").with_channel(Some(String::from("SomeBandVEVO"))),
+        NewVideo::new("Third", "third-id").with_channel(Some(String::from("SomeBandVEVO"))),
+    ];
+    dao.update_tracks(playlist.id, fetched).unwrap();
+    let artists: Vec<Option<String>> = dao.get_tracks(playlist.id).unwrap().into_iter().map(|t| t.artist).collect();
+    assert_eq!(artists, ["Some Band", "Tagged", "SomeBand"].map(|artist| Some(String::from(artist))));
+}
+
+#[test]
+fn moved_tracks_keep_their_place_and_are_played_in_order() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+
+    h.press(KeyCode::Enter);
+    h.wait_for("Third song");
+    for _ in 0..3 {
+        h.press(KeyCode::Down);
+    }
+    h.press(KeyCode::Char('K'));
+    h.press(KeyCode::Char('K'));
+    h.press(KeyCode::Char('q'));
+    h.press(KeyCode::Char('u'));
+    h.wait_for("Test playlist is up to date");
+
+    h.press(KeyCode::Enter);
+    h.wait_for("Third song");
+    let screen = h.screen();
+    let order: Vec<usize> = ["Third song", "First song", "Second song"].iter().map(|title| screen.find(title).unwrap()).collect();
+    assert!(order.windows(2).all(|pair| pair[0] < pair[1]));
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("Third song");
+    h.press(KeyCode::Char('n'));
+    h.wait_for_player("First song");
+}
+
+#[test]
+fn albums_are_numbered_and_get_the_artist_of_most_tracks() {
+    let playlist = NewPlaylist::new("Album - Some album", "OLAK5uy_test");
+    let videos = vec![
+        NewVideo::new("Opening", "opening-id").with_channel(Some(String::from("Some Band - Topic"))),
+        NewVideo::new("Duet", "duet-id").with_channel(Some(String::from("Guest - Topic"))),
+        NewVideo::new("Closing", "closing-id"),
+        NewVideo::new("Bonus", "bonus-id").with_channel(Some(String::from("Some Band - Topic"))),
+    ];
+    let videos = album::tag_album(&playlist.yt_id.clone(), &playlist, videos);
+    let tags: Vec<(Option<&str>, Option<i32>, Option<&str>)> = videos.iter()
+        .map(|v| (v.album.as_deref(), v.track_number, v.artist.as_deref()))
+        .collect();
+    assert_eq!(tags, vec![
+        (Some("Some album"), Some(1), Some("Some Band")),
+        (Some("Some album"), Some(2), Some("Guest")),
+        (Some("Some album"), Some(3), Some("Some Band")),
+        (Some("Some album"), Some(4), Some("Some Band")),
+    ]);
+
+    // Compilations keep the artist of every track, and regular playlists aren't albums.
+    let compilation = ["A", "B", "C"].map(|artist| NewVideo::new(artist, artist).with_artist(Some(String::from(artist))));
+    assert_eq!(album::album_artist(&compilation), None);
+    assert!(album::tag_album("PLtest", &playlist, compilation.to_vec()).iter().all(|v| v.album.is_none()));
+    assert_eq!(utils::parse_playlist_url("https://music.youtube.com/playlist?list=OLAK5uy_test").as_deref(), Some("OLAK5uy_test"));
+
+    let dao = Database::in_memory().unwrap();
+    dao.set_state("version", env!("CARGO_PKG_VERSION")).unwrap();
+    let saved = dao.save_playlist(playlist).unwrap();
+    dao.save_tracks(videos, saved.id).unwrap();
+    let mut h = Harness::with_database(MockSource::default(), dao);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for(" 1. Opening");
+    h.wait_for(" 4. Bonus");
+}
+
+#[test]
+fn duplicated_songs_share_one_file() {
+    let dir = std::env::temp_dir().join(format!("listui-duplicates-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let dao = Database::in_memory().unwrap();
+    let mix = dao.save_playlist(NewPlaylist::new("Mix", "PLmix")).unwrap();
+    let other = dao.save_playlist(NewPlaylist::new("Other", "PLother")).unwrap();
+    dao.save_tracks(vec![NewVideo::new("Song", "song-id"), NewVideo::new("Another song", "another-id")], mix.id).unwrap();
+    dao.save_tracks(vec![NewVideo::new("Song (Official Video)", "song-upload-id"), NewVideo::new("Song, renamed", "song-id")], other.id).unwrap();
+    // Tracks that already use the same file aren't duplicates, like after sharing it.
+    dao.save_tracks(vec![NewVideo::new("Another song", "another-upload-id")], other.id).unwrap();
+    fs::write(dir.join("Song [song-id].mp3"), "short").unwrap();
+    fs::write(dir.join("Song (Official Video) [song-upload-id].opus"), "the biggest").unwrap();
+    fs::write(dir.join("Another song [another-id].mp3"), "shared").unwrap();
+    fs::hard_link(dir.join("Another song [another-id].mp3"), dir.join("Another song [another-upload-id].mp3")).unwrap();
+
+    let duplicates = utils::find_duplicates(&dao, &dir).unwrap();
+    let titles: Vec<Vec<&str>> = duplicates.iter().map(|tracks| tracks.iter().map(|t| t.title.as_str()).collect()).collect();
+    assert_eq!(titles, vec![vec!["Song", "Song (Official Video)", "Song, renamed"]]);
+
+    assert_eq!(utils::share_file(&dir, &duplicates[0]).unwrap(), 5);
+    for track in &duplicates[0] {
+        assert_eq!(fs::read_to_string(utils::get_track_path(&dir, track)).unwrap(), "the biggest");
+    }
+    assert!(utils::find_duplicates(&dao, &dir).unwrap().is_empty());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn different_uploads_of_the_same_song_are_found_by_their_audio() {
+    let items = |seed: u32| (0..300).scan(seed, |x, _| {
+        *x = x.wrapping_mul(1664525).wrapping_add(1013904223);
+        Some(*x)
+    }).collect::<Vec<u32>>();
+    let song = Fingerprint::new(items(1));
+    // The other upload starts a second later, and encoding it again changed some of the items.
+    let upload = Fingerprint::new(items(1)[8..].iter().enumerate().map(|(ind, item)| if ind % 10 == 0 { item ^ 0xff } else { *item }).collect());
+    let other = Fingerprint::new(items(2));
+    assert!(song.matches(&upload));
+    assert!(!song.matches(&other));
+    assert_eq!(fingerprint::matching_pairs(&[&song, &other, &upload]), vec![(0, 2)]);
+    assert_eq!(Fingerprint::from_fpcalc("DURATION=120\nFINGERPRINT=1,2,3\n"), Some(Fingerprint::new(vec![1, 2, 3])));
+    assert_eq!(song.to_string().parse::<Fingerprint>().unwrap(), song);
+
+    let dir = std::env::temp_dir().join(format!("listui-fingerprint-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let fpcalc = dir.join("fpcalc");
+    fs::write(&fpcalc, format!("#!/bin/sh\necho \"$4\" >> {}\necho DURATION=120\necho \"FINGERPRINT=$(cat \"$4\")\"\n", dir.join("calls").display())).unwrap();
+    fs::set_permissions(&fpcalc, fs::Permissions::from_mode(0o755)).unwrap();
+    let tools = Tools { ytdlp: None, ffmpeg: None, fpcalc: Some(fpcalc), installed_ytdlp: None };
+
+    let dao = Database::in_memory().unwrap();
+    let mix = dao.save_playlist(NewPlaylist::new("Mix", "PLmix")).unwrap();
+    dao.save_tracks(vec![NewVideo::new("Song", "song-id"), NewVideo::new("Cover", "cover-id"), NewVideo::new("Nothing alike", "upload-id"), NewVideo::new("Not downloaded", "missing-id")], mix.id).unwrap();
+    fs::write(dir.join("Song [song-id].mp3"), song.to_string()).unwrap();
+    fs::write(dir.join("Cover [cover-id].mp3"), other.to_string()).unwrap();
+    fs::write(dir.join("Nothing alike [upload-id].mp3"), upload.to_string()).unwrap();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let fingerprinted = runtime.block_on(utils::fingerprint_tracks(&dao, &dir, &tools)).unwrap();
+    assert_eq!(fingerprinted.len(), 3);
+    let duplicates = utils::add_same_audio(utils::find_duplicates(&dao, &dir).unwrap(), fingerprinted, &dir);
+    let titles: Vec<(Vec<&str>, bool)> = duplicates.iter().map(|(tracks, same_audio)| (tracks.iter().map(|t| t.title.as_str()).collect(), *same_audio)).collect();
+    assert_eq!(titles, vec![(vec!["Song", "Nothing alike"], true)]);
+
+    // The fingerprints are saved, so the files aren't fingerprinted again.
+    runtime.block_on(utils::fingerprint_tracks(&dao, &dir, &tools)).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("calls")).unwrap().lines().count(), 3);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn tracks_get_their_artist_album_and_year_from_musicbrainz() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/ws/2", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            request.push_str(&line);
+        }
+        // The first one is too long to be the same recording, and the bootleg isn't an official release.
+        let body = r#"{"recordings": [
+            {"score": 100, "title": "Song", "length": 600000, "artist-credit": [{"name": "Band"}]},
+            {"score": 95, "title": "Song", "length": 201000,
+             "artist-credit": [{"name": "Band", "joinphrase": " feat. "}, {"name": "Guest"}],
+             "releases": [
+                {"title": "Song", "status": "Official", "date": "2001-05-01", "release-group": {"primary-type": "Single"}},
+                {"title": "The Album", "status": "Official", "date": "2003", "release-group": {"primary-type": "Album"}},
+                {"title": "Live Somewhere", "status": "Bootleg", "date": "1999", "release-group": {"primary-type": "Album"}}
+             ]}
+        ]}"#;
+        write!(reader.get_mut(), "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        request
+    });
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let client = MusicBrainzClient::new(&url);
+    let recording = runtime.block_on(client.lookup("Band - Song (Official Video)", Some("Some channel"), Some(200))).unwrap();
+    let request = server.join().unwrap();
+    // The artist is taken from the title, and the bracketed part left out.
+    assert!(request.starts_with("GET /ws/2/recording?query=recording%3A%22Song%22+AND+artist%3A%22Band%22&fmt=json"), "{request}");
+    assert!(request.to_lowercase().contains("user-agent: listui/"), "{request}");
+    let recording = recording.unwrap();
+    assert_eq!((recording.artist.as_str(), recording.album.as_deref(), recording.year), ("Band feat. Guest", Some("The Album"), Some(2003)));
+
+    let dir = std::env::temp_dir().join(format!("listui-musicbrainz-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let dao = Database::in_memory().unwrap();
+    let mix = dao.save_playlist(NewPlaylist::new("Mix", "PLmix")).unwrap();
+    dao.save_tracks(vec![NewVideo::new("Band - Song (Official Video)", "song-id"), NewVideo::new("Not downloaded", "missing-id")], mix.id).unwrap();
+    fs::write(dir.join("Band - Song (Official Video) [song-id].mp3"), "").unwrap();
+    let tracks = utils::tracks_to_tag(&dao, &dir, false).unwrap();
+    assert_eq!(tracks.iter().map(|t| t.title.as_str()).collect::<Vec<_>>(), vec!["Band - Song (Official Video)"]);
+
+    utils::save_metadata(&dao, tracks[0].id, Some(&recording)).unwrap();
+    let track = &dao.get_tracks(mix.id).unwrap()[0];
+    assert_eq!((track.artist.as_deref(), track.album.as_deref(), track.year), (Some("Band feat. Guest"), Some("The Album"), Some(2003)));
+    // It isn't looked up again, unless asked to.
+    assert!(utils::tracks_to_tag(&dao, &dir, false).unwrap().is_empty());
+    assert_eq!(utils::tracks_to_tag(&dao, &dir, true).unwrap().len(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn listening_stats_are_exported_to_csv_and_json() {
+    let dao = Database::in_memory().unwrap();
+    let mix = dao.save_playlist(NewPlaylist::new("Mix", "PLmix")).unwrap();
+    dao.save_tracks(vec![NewVideo::new("Song, with a comma", "song-id"), NewVideo::new("Favourite", "favourite-id"), NewVideo::new("Never played", "other-id")], mix.id).unwrap();
+    let tracks = dao.get_tracks(mix.id).unwrap();
+    dao.record_play(tracks[1].id, Some(200)).unwrap();
+    dao.record_play(tracks[0].id, Some(100)).unwrap();
+    dao.record_play(tracks[1].id, None).unwrap();
+
+    let csv = utils::export_stats(&dao, false, StatsFormat::Csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "title,artist,album,yt_id,plays,duration,listened,last_played");
+    assert!(lines[1].starts_with("Favourite,,,favourite-id,2,200,400,20"), "{csv}");
+    assert!(lines[2].starts_with("\"Song, with a comma\",,,song-id,1,100,100,20"), "{csv}");
+    assert_eq!(lines[3], "Never played,,,other-id,0,,,");
+
+    let history = utils::export_stats(&dao, true, StatsFormat::Json).unwrap();
+    // One object per play, from the oldest.
+    let titles: Vec<&str> = history.lines().filter_map(|line| line.trim().strip_prefix("\"title\": ")).collect();
+    assert_eq!(titles, vec!["\"Favourite\",", "\"Song, with a comma\",", "\"Favourite\","]);
+    assert_eq!(history.matches("\"played_at\": \"20").count(), 3);
+
+    assert_eq!(stats::utc_time(951782400), "2000-02-29T00:00:00Z");
+    assert_eq!(stats::utc_time(1792072980), "2026-10-15T14:03:00Z");
+}
+
+#[test]
+fn local_files_can_be_slotted_between_downloaded_tracks() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    let recording = h.dir().join("recordings").join("My recording.mp3");
+    fs::create_dir_all(recording.parent().unwrap()).unwrap();
+    fs::write(&recording, "").unwrap();
+
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('i'));
+    h.wait_for("Path of the file to add after the selected track");
+    h.type_text(&recording.display().to_string());
+    h.press(KeyCode::Enter);
+    h.wait_for("My recording");
+
+    // Updating doesn't mark it as removed from YouTube.
+    h.press(KeyCode::Char('q'));
+    h.press(KeyCode::Char('u'));
+    h.wait_for("Test playlist is up to date");
+    h.press(KeyCode::Enter);
+    h.wait_for("My recording");
+    let screen = h.screen();
+    let order: Vec<usize> = ["First song", "My recording", "Second song"].iter().map(|title| screen.find(title).unwrap()).collect();
+    assert!(order.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(!screen.contains('✗'));
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("First song");
+    h.press(KeyCode::Char('n'));
+    h.wait_for_player("My recording");
+    h.wait_for_played(2);
+    assert_eq!(h.downloader.downloaded(), vec!["PLtest-0"]);
+}
+
+#[test]
+fn music_dirs_are_playlists_kept_in_sync_with_their_files() {
+    let dir = std::env::temp_dir().join(format!("listui-folder-test-{}", std::process::id())).join("Road trip");
+    fs::create_dir_all(&dir).unwrap();
+    for file in ["B side.opus", "A side.mp3", "cover.jpg"] {
+        fs::write(dir.join(file), "").unwrap();
+    }
+
+    let dao = Database::in_memory().unwrap();
+    dao.set_state("version", env!("CARGO_PKG_VERSION")).unwrap();
+    let (playlist, _) = utils::sync_music_dir(&dao, &dir).unwrap();
+    let titles: Vec<String> = dao.get_tracks(playlist.id).unwrap().into_iter().map(|t| t.title).collect();
+    assert_eq!((playlist.title.as_str(), titles), ("Road trip", vec![String::from("A side"), String::from("B side")]));
+    // Syncing it again doesn't save it twice.
+    assert_eq!(utils::sync_music_dir(&dao, &dir).unwrap().0.id, playlist.id);
+
+    let mut h = Harness::with_database(MockSource::default(), dao);
+    fs::remove_file(dir.join("A side.mp3")).unwrap();
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('u'));
+    h.wait_for("Road trip has been updated: +0 new, −1 removed, 0 renamed.");
+
+    h.press(KeyCode::Enter);
+    fs::write(dir.join("A side.mp3"), "").unwrap();
+    h.press(KeyCode::Char('u'));
+    h.wait_for("Road trip has been updated: +1 new, −0 removed, 0 renamed.");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Enter);
+    h.wait_for("B side");
+    assert!(!h.screen().contains('✗'));
+    fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn marked_tracks_are_moved_added_and_removed_together() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Char('c'));
+    h.type_text("Favourites");
+    h.press(KeyCode::Enter);
+    h.wait_for("Favourites");
+
+    h.press(KeyCode::Up);
+    h.press(KeyCode::Enter);
+    h.wait_for("Third song");
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char(' '));
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char(' '));
+    h.wait_for("Test playlist (2 marked)");
+    assert_eq!(h.screen().matches('✔').count(), 2);
+
+    // The first one can't go any higher, the other one goes up to it.
+    h.press(KeyCode::Char('K'));
+    h.press(KeyCode::Char('a'));
+    h.wait_for("Add to playlist");
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Added 2 tracks to Favourites");
+    h.press(KeyCode::Esc);
+    h.wait_for_absence("marked");
+
+    h.press(KeyCode::Char('q'));
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Third song");
+    assert!(!h.screen().contains("Second song"));
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char(' '));
+    h.press(KeyCode::Char(' '));
+    h.press(KeyCode::Char('x'));
+    h.wait_for_absence("First song");
+    assert!(!h.screen().contains("Third song"));
+
+    h.press(KeyCode::Char('q'));
+    h.press(KeyCode::Up);
+    h.press(KeyCode::Enter);
+    h.wait_for("Second song");
+    let screen = h.screen();
+    let order: Vec<usize> = ["First song", "Third song", "Second song"].iter().map(|title| screen.find(title).unwrap()).collect();
+    assert!(order.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn tracks_are_sorted_and_each_playlist_remembers_it() {
+    let source = MockSource::default().with_playlist("PLfruit", "Fruit", &["Banana", "apple", "Cherry"]);
+    let mut h = Harness::new(source);
+    h.app.fetch_new_playlist(String::from("PLfruit"));
+    h.wait_for("Fruit");
+    let order = |h: &Harness| {
+        let screen = h.screen();
+        let mut titles = ["Banana", "apple", "Cherry"];
+        titles.sort_by_key(|title| screen.find(title));
+        titles
+    };
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Cherry");
+    h.press(KeyCode::Char('O'));
+    h.wait_for("Fruit (by title A-Z)");
+    assert_eq!(order(&h), ["apple", "Banana", "Cherry"]);
+
+    // Nothing has been played yet, so there's no duration nor play count to sort by.
+    h.press(KeyCode::Char('O'));
+    h.wait_for("Fruit (by date added)");
+    assert_eq!(order(&h), ["Cherry", "apple", "Banana"]);
+    h.press(KeyCode::Char('K'));
+    h.wait_for("Go back to the playlist's order");
+
+    h.press(KeyCode::Char('O'));
+    h.wait_for_absence("(by ");
+    assert_eq!(order(&h), ["Banana", "apple", "Cherry"]);
+    h.press(KeyCode::Char('O'));
+    h.press(KeyCode::Char('q'));
+    h.press(KeyCode::Enter);
+    h.wait_for("Fruit (by title A-Z)");
+    assert_eq!(order(&h), ["apple", "Banana", "Cherry"]);
+}
+
+#[test]
+fn trimmed_tracks_start_where_they_were_trimmed() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+
+    h.press(KeyCode::Char('t'));
+    h.wait_for("like 0:30-3:45");
+    h.type_text("0:01-");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("00:01 ▶ 00:01");
+
+    // The trim is saved with the track.
+    h.press(KeyCode::Char('q'));
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('t'));
+    h.wait_for("0:01-");
+}
+
+#[test]
+fn trims_are_read_in_minutes_and_seconds() {
+    assert_eq!(utils::parse_trim("0:30-3:45"), Ok((Some(30), Some(225))));
+    assert_eq!(utils::parse_trim("1:05 -"), Ok((Some(65), None)));
+    assert_eq!(utils::parse_trim("-200"), Ok((None, Some(200))));
+    assert_eq!(utils::parse_trim("0-1:00:00"), Ok((None, Some(3600))));
+    assert_eq!(utils::parse_trim("None"), Ok((None, None)));
+    assert!(utils::parse_trim("3:45").is_err());
+    assert!(utils::parse_trim("4:00-3:00").is_err());
+    assert!(utils::parse_trim("a:30-").is_err());
+
+    assert_eq!(utils::trim_str(Some(30), Some(225)), "0:30-3:45");
+    assert_eq!(utils::trim_str(Some(65), None), "1:05-");
+    assert_eq!(utils::trim_str(None, Some(200)), "-3:20");
+}
+
+#[test]
+fn transient_messages_are_shown_in_the_status_bar_and_go_away() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Char('c'));
+    h.type_text("Favourites");
+    h.press(KeyCode::Enter);
+    h.wait_for("Favourites");
+
+    // The error doesn't take the whole screen, the playlists are still there.
+    h.press(KeyCode::Char('u'));
+    h.wait_for("This playlist was created by you, it can't be updated.");
+    assert!(h.screen().contains("Test playlist"));
+    let last_line = h.screen().lines().last().unwrap().to_string();
+    assert!(last_line.contains("it can't be updated"));
+
+    h.press(KeyCode::Up);
+    h.press(KeyCode::Enter);
+    h.wait_for("Second song");
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Char('a'));
+    h.wait_for("Add to playlist");
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Added Second song to Favourites");
+    assert!(h.screen().contains("First song"));
+    h.wait_for_absence("Added Second song to Favourites");
+}
+
+#[test]
+fn channel_uploads_can_be_sorted_by_the_newest() {
+    let source = MockSource::default()
+        .with_playlist("UUnews", "Uploads from News", &["Monday", "Wednesday", "Tuesday"])
+        .with_upload_times("UUnews", &[1_700_000_000, 1_700_172_800, 1_700_086_400]);
+    let mut h = Harness::new(source);
+    h.app.fetch_new_playlist(String::from("UUnews"));
+    h.wait_for("Uploads from News");
+    let order = |h: &Harness| {
+        let screen = h.screen();
+        let mut titles = ["Monday", "Wednesday", "Tuesday"];
+        titles.sort_by_key(|title| screen.find(title));
+        titles
+    };
+
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for("Tuesday");
+    h.press(KeyCode::Char('O'));
+    h.press(KeyCode::Char('O'));
+    h.press(KeyCode::Char('O'));
+    h.wait_for("Uploads from News (by newest upload)");
+    assert_eq!(order(&h), ["Wednesday", "Tuesday", "Monday"]);
+
+    // The order is remembered, with the upload dates.
+    h.press(KeyCode::Char('q'));
+    h.press(KeyCode::Enter);
+    h.wait_for("Uploads from News (by newest upload)");
+    assert_eq!(order(&h), ["Wednesday", "Tuesday", "Monday"]);
+}
+
+#[test]
+fn the_volume_is_shown_big_for_a_moment_when_it_changes() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.wait_for("First song");
+    h.press(KeyCode::Char('v'));
+    h.wait_for("Nothing is playing.");
+
+    // Even in the now playing view, that doesn't show the volume.
+    h.press(KeyCode::Char('-'));
+    h.wait_for("Volume 90%");
+    assert!(h.screen().contains("███ ███ █ █"));
+    h.wait_for_absence("Volume 90%");
+    assert!(!h.screen().contains("███ ███ █ █"));
+}
+
+#[test]
+fn the_track_is_paused_when_the_audio_device_is_disconnected() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("00:00 ▶ 00:01");
+
+    // The output is checked on every tick.
+    h.player.lose_output();
+    h.send(AppEvent::Tick);
+    h.wait_for("The audio device was disconnected, the track is paused.");
+    h.wait_for_player("▮▮");
+}
+
+#[test]
+fn the_output_is_opened_again_once_there_is_an_audio_device() {
+    let mut h = harness();
+    h.app.fetch_new_playlist(String::from("PLtest"));
+    h.wait_for("Test playlist");
+    h.press(KeyCode::Enter);
+    h.press(KeyCode::Down);
+    h.press(KeyCode::Enter);
+    h.wait_for_player("00:00 ▶ 00:01");
+
+    h.player.lose_output();
+    h.player.set_devices_available(false);
+    h.send(AppEvent::Tick);
+    h.wait_for("The audio device was disconnected, and there's no other one.");
+    h.wait_for_player("▮▮");
+
+    // Like headphones that reconnect.
+    h.player.set_devices_available(true);
+    std::thread::sleep(OUTPUT_RETRY);
+    h.send(AppEvent::Tick);
+    h.wait_for("press p to go on.");
+
+    h.press(KeyCode::Char('p'));
+    h.wait_for_player("▶");
+}
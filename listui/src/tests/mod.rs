@@ -0,0 +1,448 @@
+//! End-to-end tests of the app.
+//!
+//! The app is drawn on ratatui's `TestBackend`, with an in-memory database, mock
+//! player and downloader, and playlists served by `MockSource`, so no terminal,
+//! sound card or network is needed.
+
+mod flows;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use listui_lib::api::{ApiError, ApiProgressCallback, FetchProgress, FetchSource, InstanceHealth, MediaSource, Release, RemotePlaylist, JELLYFIN_PREFIX, SUBSONIC_PREFIX};
+use listui_lib::db::Database;
+use listui_lib::models::{NewPlaylist, NewVideo, Restriction};
+use listui_lib::downloader::MockDownloader;
+use listui_lib::player::MockPlayer;
+use listui_lib::tools::YtdlpStatus;
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use tokio::sync::Notify;
+
+use crate::app::ListuiApp;
+use crate::events::AppEvent;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serves playlists from memory.
+#[derive(Default)]
+pub struct MockSource {
+    playlists: HashMap<String, (NewPlaylist, Vec<NewVideo>)>,
+    videos: HashMap<String, NewVideo>,
+    restrictions: Vec<(String, Restriction)>,
+    alternates: HashMap<String, NewVideo>,
+    recent_uploads: HashMap<String, Vec<NewVideo>>,
+    release: Option<Release>,
+    ytdlp: Option<YtdlpStatus>,
+    instances: Vec<InstanceHealth>,
+    invalidated: Arc<Mutex<Vec<String>>>,
+    // Playlists whose first instance hangs until it's skipped.
+    stalled: Vec<String>,
+    skip: Arc<Notify>,
+}
+
+impl MockSource {
+    pub fn with_playlist(mut self, yt_id: &str, title: &str, tracks: &[&str]) -> Self {
+        let playlist = NewPlaylist::new(title, yt_id);
+        let videos = tracks
+            .iter()
+            .enumerate()
+            .map(|(ind, title)| NewVideo::new(String::from(*title), format!("{yt_id}-{ind}")))
+            .collect();
+
+        self.playlists.insert(String::from(yt_id), (playlist, videos));
+        self
+    }
+
+    /// Sets who made a playlist and its description.
+    pub fn with_playlist_details(mut self, yt_id: &str, author: &str, description: &str) -> Self {
+        let (playlist, _) = self.playlists.get_mut(yt_id).expect("No such playlist.");
+        *playlist = playlist.clone().with_details(Some(String::from(author)), Some(String::from(description)), None);
+        self
+    }
+
+    /// Sets when the videos of a playlist were uploaded, in the order they were given.
+    pub fn with_upload_times(mut self, yt_id: &str, times: &[i64]) -> Self {
+        let (_, videos) = self.playlists.get_mut(yt_id).expect("No such playlist.");
+        for (video, time) in videos.iter_mut().zip(times) {
+            video.uploaded_at = Some(*time);
+        }
+        self
+    }
+
+    pub fn with_video(mut self, yt_id: &str, title: &str) -> Self {
+        let video = NewVideo::new(String::from(title), String::from(yt_id));
+
+        self.videos.insert(String::from(yt_id), video);
+        self
+    }
+
+    pub fn with_alternate(mut self, yt_id: &str, alternate_id: &str, title: &str) -> Self {
+        let video = NewVideo::new(String::from(title), String::from(alternate_id));
+
+        self.alternates.insert(String::from(yt_id), video);
+        self
+    }
+
+    pub fn with_recent_upload(mut self, uploads_id: &str, yt_id: &str, title: &str) -> Self {
+        let video = NewVideo::new(String::from(title), String::from(yt_id));
+
+        self.recent_uploads.entry(String::from(uploads_id)).or_default().push(video);
+        self
+    }
+
+    pub fn with_restriction(mut self, yt_id: &str, restriction: Restriction) -> Self {
+        self.restrictions.push((String::from(yt_id), restriction));
+        self
+    }
+
+    pub fn with_release(mut self, version: &str, notes: &str) -> Self {
+        self.release = Some(Release {
+            version: String::from(version),
+            notes: String::from(notes),
+            url: format!("https://github.com/gulis1/lisTUI/releases/tag/v{version}"),
+        });
+        self
+    }
+
+    /// The yt-dlp that is found when it's checked. It's up to date otherwise.
+    pub fn with_ytdlp(mut self, status: YtdlpStatus) -> Self {
+        self.ytdlp = Some(status);
+        self
+    }
+
+    /// The playlists whose cache has been invalidated, shared with the app.
+    pub fn invalidated(&self) -> Arc<Mutex<Vec<String>>> {
+        Arc::clone(&self.invalidated)
+    }
+
+    /// Fetching `yt_id` hangs on a slow instance until it's skipped, and then it's fetched from a fast one.
+    pub fn with_stalled_playlist(mut self, yt_id: &str) -> Self {
+        self.stalled.push(String::from(yt_id));
+        self
+    }
+
+    /// An Invidious instance, that answers in `latency_ms` (or is down, if `None`).
+    pub fn with_instance(mut self, url: &str, latency_ms: Option<u64>) -> Self {
+        self.instances.push(InstanceHealth {
+            url: String::from(url),
+            latency: latency_ms.map(Duration::from_millis),
+        });
+        self
+    }
+}
+
+#[async_trait]
+impl MediaSource for MockSource {
+    async fn fetch_playlist(
+        &self,
+        yt_id: &str,
+        callback: Option<ApiProgressCallback>,
+    ) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
+        let progress = |progress| {
+            if let Some(callback) = &callback {
+                callback(progress);
+            }
+        };
+        let what = format!("playlist {yt_id}");
+        if self.stalled.iter().any(|id| id == yt_id) {
+            let slow = FetchSource::Invidious(String::from("https://slow.example"));
+            progress(FetchProgress::Trying { what: what.clone(), source: slow.clone() });
+            // The progress events are sent by separate tasks, so they are spaced out to arrive in order.
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            progress(FetchProgress::PageOf { page: 1, pages: 4 });
+            self.skip.notified().await;
+            progress(FetchProgress::Failed { what: what.clone(), source: slow, error: ApiError::Skipped.to_string() });
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            progress(FetchProgress::Trying { what, source: FetchSource::Invidious(String::from("https://fast.example")) });
+        } else {
+            progress(FetchProgress::Trying { what, source: FetchSource::YouTube });
+        }
+
+        self.playlists
+            .get(yt_id)
+            .cloned()
+            .ok_or(ApiError::NotFoundError(String::from(yt_id)))
+    }
+
+    async fn fetch_channel_uploads(&self, channel: &str) -> Result<String, ApiError> {
+        // Same rule as YouTube: the uploads playlist of UCxyz is UUxyz.
+        channel
+            .strip_prefix("UC")
+            .map(|id| format!("UU{id}"))
+            .ok_or(ApiError::ChannelNotFoundError(String::from(channel)))
+    }
+
+    async fn fetch_video(&self, yt_id: &str) -> Result<NewVideo, ApiError> {
+        self.videos
+            .get(yt_id)
+            .cloned()
+            .ok_or(ApiError::VideoNotFoundError(String::from(yt_id)))
+    }
+
+    async fn fetch_restrictions(
+        &self,
+        yt_ids: &[String],
+    ) -> Result<Vec<(String, Restriction)>, ApiError> {
+        Ok(self
+            .restrictions
+            .iter()
+            .filter(|(yt_id, _)| yt_ids.contains(yt_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn find_alternate(&self, _title: &str, yt_id: &str) -> Result<Option<NewVideo>, ApiError> {
+        Ok(self.alternates.get(yt_id).cloned())
+    }
+
+    async fn fetch_recent_uploads(&self, uploads_id: &str, _since: u64) -> Result<Vec<NewVideo>, ApiError> {
+        Ok(self.recent_uploads.get(uploads_id).cloned().unwrap_or_default())
+    }
+
+    async fn fetch_latest_release(&self) -> Result<Option<Release>, ApiError> {
+        Ok(self.release.clone())
+    }
+
+    async fn rank_instances(&self) -> Vec<InstanceHealth> {
+        self.instances.clone()
+    }
+
+    async fn check_ytdlp(&self, _online: bool) -> Result<YtdlpStatus, ApiError> {
+        Ok(self.ytdlp.clone().unwrap_or(YtdlpStatus::UpToDate))
+    }
+
+    // Installs the latest version there is, if the installed one was outdated.
+    async fn install_ytdlp(&self) -> Result<String, ApiError> {
+        match &self.ytdlp {
+            Some(YtdlpStatus::Outdated { latest, .. }) => Ok(latest.clone()),
+            _ => Ok(String::from("2024.12.13")),
+        }
+    }
+
+    fn skip_source(&self) {
+        self.skip.notify_one();
+    }
+
+    fn invalidate_cache(&self, yt_id: &str) {
+        self.invalidated.lock().unwrap().push(String::from(yt_id));
+    }
+
+    // The playlists with a Subsonic or Jellyfin id make up the library.
+    async fn fetch_library(&self) -> Result<Vec<RemotePlaylist>, ApiError> {
+        let mut library: Vec<RemotePlaylist> = self
+            .playlists
+            .iter()
+            .filter(|(yt_id, _)| {
+                yt_id.starts_with(SUBSONIC_PREFIX) || yt_id.starts_with(JELLYFIN_PREFIX)
+            })
+            .map(|(yt_id, (playlist, _))| RemotePlaylist {
+                yt_id: yt_id.clone(),
+                title: playlist.title.clone(),
+            })
+            .collect();
+
+        library.sort_by(|a, b| a.yt_id.cmp(&b.yt_id));
+        Ok(library)
+    }
+}
+
+/// Drives a `ListuiApp` like a user would, and inspects what it draws.
+pub struct Harness {
+    pub app: ListuiApp,
+    pub player: Arc<MockPlayer>,
+    pub downloader: Arc<MockDownloader>,
+    terminal: Terminal<TestBackend>,
+    dir: PathBuf,
+}
+
+impl Harness {
+    pub fn new(source: MockSource) -> Self {
+        Self::with_downloader(source, MockDownloader::new(Vec::new()))
+    }
+
+    pub fn with_downloader(source: MockSource, downloader: MockDownloader) -> Self {
+        let dao = Database::in_memory().expect("Failed to create database.");
+        Self::with_parts(source, downloader, dao)
+    }
+
+    /// Starts the app with an existing database.
+    pub fn with_database(source: MockSource, dao: Database) -> Self {
+        Self::with_parts(source, MockDownloader::new(Vec::new()), dao)
+    }
+
+    fn with_parts(source: MockSource, downloader: MockDownloader, dao: Database) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "listui-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).expect("Failed to create test directory.");
+
+        // Every track lasts one second.
+        let player = Arc::new(MockPlayer::new(1));
+        let downloader = Arc::new(downloader);
+        let app = ListuiApp::with_parts(
+            dir.clone(),
+            dao,
+            player.clone(),
+            downloader.clone(),
+            Arc::new(source),
+        )
+        .expect("Failed to create app.");
+
+        let mut harness = Self {
+            app,
+            player,
+            downloader,
+            terminal: Terminal::new(TestBackend::new(80, 30)).unwrap(),
+            dir,
+        };
+        harness.draw();
+        harness
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn press(&mut self, key: KeyCode) {
+        self.settle();
+        self.app.handle_event(AppEvent::Key(KeyEvent::from(key)));
+        self.settle();
+        self.draw();
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        self.settle();
+        self.app.handle_event(AppEvent::Paste(String::from(text)));
+        self.settle();
+        self.draw();
+    }
+
+    /// Clicks with the left button on a cell of the screen.
+    pub fn click(&mut self, column: u16, row: u16) {
+        let kind = MouseEventKind::Down(MouseButton::Left);
+        self.settle();
+        self.app.handle_event(AppEvent::Mouse(MouseEvent { kind, column, row, modifiers: KeyModifiers::NONE }));
+        self.settle();
+        self.draw();
+    }
+
+    /// Handles an event as if it came from a background task.
+    pub fn send(&mut self, event: AppEvent) {
+        self.app.handle_event(event);
+        self.draw();
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.terminal.backend_mut().resize(width, height);
+        self.app.handle_event(AppEvent::Resize(width, height));
+        self.draw();
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            self.press(KeyCode::Char(c));
+        }
+    }
+
+    /// Handles events from background tasks until the screen contains `text`.
+    pub fn wait_for(&mut self, text: &str) {
+        self.wait_until(|screen| screen.contains(text), text);
+    }
+
+    /// Handles events from background tasks until the screen no longer contains `text`.
+    pub fn wait_for_absence(&mut self, text: &str) {
+        self.wait_until(|screen| !screen.contains(text), text);
+    }
+
+    /// Handles events from background tasks until the player (the bottom of the
+    /// songs screen) contains `text`.
+    pub fn wait_for_player(&mut self, text: &str) {
+        self.wait_until(
+            |screen| {
+                let lines: Vec<&str> = screen.lines().collect();
+                lines[lines.len() - 5..].join("\n").contains(text)
+            },
+            text,
+        );
+    }
+
+    /// Handles events from background tasks until the player has played `count` files.
+    pub fn wait_for_played(&mut self, count: usize) {
+        let player = Arc::clone(&self.player);
+        self.wait_until(
+            |_| player.played_files().len() >= count,
+            &format!("{count} played files"),
+        );
+    }
+
+    pub fn screen(&self) -> String {
+        let buffer = self.terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        buffer
+            .content
+            .chunks(width)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn wait_until(&mut self, condition: impl Fn(&str) -> bool, description: &str) {
+        let start = Instant::now();
+        loop {
+            while let Some(event) = self.app.try_recv_event() {
+                self.app.handle_event(event);
+            }
+            self.draw();
+
+            if condition(&self.screen()) {
+                return;
+            }
+            if start.elapsed() > TIMEOUT {
+                panic!("Timed out waiting for {description:?}:\n{}", self.screen());
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    // Handles the results of what was read from or saved to the database in the background, like
+    // the tracks of a playlist being opened, so the next input finds them. What else arrives
+    // meanwhile is handled too.
+    fn settle(&mut self) {
+        let start = Instant::now();
+        loop {
+            // Checked before handling the events, since results are sent before the work is done.
+            let busy = self.app.has_db_work();
+            while let Some(event) = self.app.try_recv_event() {
+                self.app.handle_event(event);
+            }
+            if !busy && !self.app.has_db_work() {
+                return;
+            }
+            if start.elapsed() > TIMEOUT {
+                panic!("Timed out waiting for the database:\n{}", self.screen());
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn draw(&mut self) {
+        let app = &mut self.app;
+        self.terminal.draw(|frame| app.draw(frame)).unwrap();
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
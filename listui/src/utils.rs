@@ -1,83 +1,991 @@
-use std::fs::{create_dir_all, read_dir};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fs::{create_dir_all, hard_link, metadata, read_dir, read_to_string, remove_file, write};
+use std::io;
 use std::path::{Path, PathBuf};
-use listui_lib::models::{Track, NewVideo, NewPlaylist};
-use listui_lib::api::{ApiClient, ApiError, ApiProgressCallback};
+use listui_lib::album;
+use listui_lib::artwork::Protocol;
+use listui_lib::db::{Database, DbError, TrackDiff};
+use listui_lib::export::{ExportFormat, ExportedPlaylist};
+use listui_lib::models::{Playlist, Track, NewVideo, NewPlaylist, Restriction};
+use listui_lib::api::{ApiClient, ApiError, ApiProgressCallback, InstanceHealth, MediaSource, JellyfinClient, Recording, Release, RemotePlaylist, ResponseCache, RetryPolicy, SourceSkip, SubsonicClient, DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT, JELLYFIN_PREFIX, SUBSONIC_PREFIX, normalize_title};
+use listui_lib::downloader::{AudioFormat, DownloadOptions, Watchdog, YtDlpDownloader};
+use listui_lib::tools::{Tools, YtdlpStatus};
+use listui_lib::fingerprint::{self, Fingerprint};
+use listui_lib::stats::{self, StatsFormat};
+use listui_lib::player::{AudioDevice, Player, PlayerError, RodioPlayer};
+#[cfg(feature = "mpv")]
+use listui_lib::player::MpvPlayer;
+#[cfg(feature = "gstreamer")]
+use listui_lib::player::GstPlayer;
+use async_trait::async_trait;
 use regex::Regex;
 use std::env;
 use std::process::{Command, Stdio};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-#[derive(Debug)]
-pub enum Message {
-    SongFinished,
-    NewPlaylist(Result<(NewPlaylist, Vec<NewVideo>), ApiError>),
-    PlaylistUpdate(Result<(i32, Vec<NewVideo>), ApiError>),
-    DownloadProgress(String)
-}
+// The Invidious instances from the fastest to the slowest, the last time they were probed.
+static INSTANCE_RANKING: RwLock<Vec<String>> = RwLock::new(Vec::new());
 
-#[derive(Debug)]
-pub struct ProbingError {}
-impl std::error::Error for ProbingError {}
-impl std::fmt::Display for ProbingError {
-    
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Please install ffmpeg and yt-dlp fist.")
-    }
-}
+// The ids of the playlists whose download was started and hasn't finished, one per line.
+const DOWNLOAD_QUEUE_KEY: &str = "download_queue";
+// The download directory whose files have been renamed by `rename_old_downloads`.
+const RENAMED_DOWNLOADS_KEY: &str = "renamed_downloads";
+
+// Playlists made from a directory of the user's have its path as their id, after this.
+pub const FOLDER_PREFIX: &str = "folder:";
 
 pub fn parse_playlist_url(url: &str) -> Option<String> {
     
-    let re = Regex::new(r"^https?://(?:w{3}.)?(?:(?:youtube\.com)|(?:youtu\.be))/.+\?(?:.+&)*list=(PL.+?)(?:&|$)").expect("Failed to compile regex.");
+    let re = Regex::new(r"^https?://(?:w{3}.|music\.)?(?:(?:youtube\.com)|(?:youtu\.be))/.+\?(?:.+&)*list=((?:PL|OLAK5uy_).+?)(?:&|$)").expect("Failed to compile regex.");
     Some(String::from(re.captures(url)  .and_then(|c| c.get(1))?.as_str()))
 }
 
-// On success, returns the id of the new playlist stored in the DB.
-pub async fn get_youtube_playlist(playlist_id: &str, callback: Option<ApiProgressCallback>) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
+// Bandcamp albums and tracks are fetched using yt-dlp, so the whole URL is kept.
+pub fn parse_bandcamp_url(url: &str) -> Option<String> {
+
+    let re = Regex::new(r"^https?://[\w-]+\.bandcamp\.com/(?:album|track)/[\w-]+").expect("Failed to compile regex.");
+    Some(String::from(re.find(url)?.as_str()))
+}
+
+// Returns the channel id (UC...) or handle (@name) of a channel URL.
+pub fn parse_channel_url(url: &str) -> Option<String> {
+
+    let re = Regex::new(r"^https?://(?:(?:w{3}|m)\.)?youtube\.com/(?:channel/(UC[\w-]{22})|(@[\w.-]+))(?:[/?#]|$)").expect("Failed to compile regex.");
+    let captures = re.captures(url)?;
+    Some(String::from(captures.get(1).or(captures.get(2))?.as_str()))
+}
+
+pub fn parse_video_url(url: &str) -> Option<String> {
+
+    let re = Regex::new(r"^https?://(?:(?:w{3}|m|music)\.)?(?:youtube\.com/(?:watch\?(?:.+&)*v=|shorts/)|youtu\.be/)([\w-]{11})(?:[&?#]|$)").expect("Failed to compile regex.");
+    Some(String::from(re.captures(url)?.get(1)?.as_str()))
+}
+
+// Fetches playlists from YouTube's API if an API key is set, or from Invidious otherwise.
+#[derive(Default)]
+pub struct YoutubeSource {
+    // Skips the instance the playlist being imported is fetched from.
+    skip: SourceSkip
+}
+
+#[async_trait]
+impl MediaSource for YoutubeSource {
+
+    async fn fetch_playlist(&self, playlist_id: &str, callback: Option<ApiProgressCallback>) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
+
+        if let Some(id) = playlist_id.strip_prefix(SUBSONIC_PREFIX) {
+            let client = get_subsonic_client().ok_or(ApiError::RequestError(String::from("No Subsonic server configured.")))?;
+            return client.fetch_playlist(id).await;
+        }
+        if let Some(id) = playlist_id.strip_prefix(JELLYFIN_PREFIX) {
+            let client = get_jellyfin_client().ok_or(ApiError::RequestError(String::from("No Jellyfin server configured.")))?;
+            return client.fetch_playlist(id).await;
+        }
+
+        let (playlist, videos) = get_api_client(callback).with_skip(self.skip.clone()).fetch_playlist(playlist_id).await?;
+        let videos = album::tag_album(playlist_id, &playlist, videos);
+        Ok((playlist, videos))
+    }
+
+    async fn fetch_video(&self, video_id: &str) -> Result<NewVideo, ApiError> {
+
+        get_api_client(None).fetch_video(video_id).await
+    }
+
+    async fn fetch_channel_uploads(&self, channel: &str) -> Result<String, ApiError> {
+
+        get_api_client(None).fetch_channel_uploads(channel).await
+    }
+
+    // Only YouTube videos are checked, and region blocks only if REGION is set when using YouTube's API.
+    async fn fetch_restrictions(&self, yt_ids: &[String]) -> Result<Vec<(String, Restriction)>, ApiError> {
+
+        let yt_ids: Vec<String> = yt_ids.iter().filter(|id| !id.contains(':')).cloned().collect();
+        if yt_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let client = get_api_client(None);
+        let region = env::var("REGION").ok().map(|region| region.to_uppercase());
+        client.fetch_restrictions(&yt_ids, region.as_deref()).await
+    }
+
+    async fn fetch_recent_uploads(&self, uploads_id: &str, since: u64) -> Result<Vec<NewVideo>, ApiError> {
+
+        get_api_client(None).fetch_recent_uploads(uploads_id, since).await
+    }
+
+    // Only YouTube videos can be substituted, tracks from other sites or servers are left alone.
+    async fn find_alternate(&self, title: &str, yt_id: &str) -> Result<Option<NewVideo>, ApiError> {
+
+        if yt_id.contains(':') {
+            return Ok(None);
+        }
+
+        ApiClient::from_invidious(None).with_tools(get_tools()).find_alternate(title, yt_id).await
+    }
+
+    // Playlists from every configured server.
+    async fn fetch_library(&self) -> Result<Vec<RemotePlaylist>, ApiError> {
+
+        let (subsonic, jellyfin) = (get_subsonic_client(), get_jellyfin_client());
+        if subsonic.is_none() && jellyfin.is_none() {
+            return Err(ApiError::RequestError(String::from("No media server configured, set the SUBSONIC_* or JELLYFIN_* settings.")));
+        }
+
+        let mut playlists = Vec::new();
+        if let Some(client) = subsonic {
+            playlists.extend(client.fetch_playlists().await?);
+        }
+        if let Some(client) = jellyfin {
+            playlists.extend(client.fetch_playlists().await?);
+        }
+        Ok(playlists)
+    }
+
+    async fn fetch_latest_release(&self) -> Result<Option<Release>, ApiError> {
+        get_api_client(None).fetch_latest_release().await
+    }
+
+    fn skip_source(&self) {
+        self.skip.skip();
+    }
+
+    fn invalidate_cache(&self, playlist_id: &str) {
+        get_api_client(None).invalidate_cache(playlist_id)
+    }
+
+    async fn rank_instances(&self) -> Vec<InstanceHealth> {
+        ApiClient::from_invidious(None).rank_instances().await
+    }
+
+    fn is_available(&self) -> bool {
+        let tools = get_tools();
+        probe_ytdlp(&tools) && probe_ffmpeg(&tools)
+    }
+
+    async fn check_ytdlp(&self, online: bool) -> Result<YtdlpStatus, ApiError> {
+
+        let Some(version) = get_tools().ytdlp_version().await else { return Ok(YtdlpStatus::Missing) };
+        if !online {
+            return Ok(YtdlpStatus::UpToDate);
+        }
+        match get_api_client(None).fetch_latest_ytdlp().await? {
+            Some(release) if is_newer_version(&release.version, &version) => Ok(YtdlpStatus::Outdated { version, latest: release.version }),
+            _ => Ok(YtdlpStatus::UpToDate)
+        }
+    }
+
+    // Only the one in the data directory is replaced, never the one in YTDLP_PATH or in the PATH.
+    async fn install_ytdlp(&self) -> Result<String, ApiError> {
+
+        if env::var_os("YTDLP_PATH").is_some() {
+            return Err(ApiError::RequestError(String::from("YTDLP_PATH is set, so that yt-dlp is used. Update it yourself, or unset YTDLP_PATH.")));
+        }
+        let path = installed_ytdlp_path().ok_or(ApiError::RequestError(String::from("There's no data directory to install yt-dlp into.")))?;
+        get_api_client(None).install_ytdlp(&path).await?;
+        log::info!("Installed yt-dlp in {}.", path.display());
+        get_tools().ytdlp_version().await
+            .ok_or(ApiError::RequestError(format!("The yt-dlp installed in {} can't be run.", path.display())))
+    }
+}
+
+// Whether to look for new releases of lisTUI, set with CHECK_UPDATES.
+pub fn check_updates() -> bool {
+    env::var("CHECK_UPDATES").is_ok_and(|value| value == "true")
+}
+
+// Compares versions like "0.3.0" number by number. Anything that isn't a number counts as 0.
+pub fn is_newer_version(version: &str, current: &str) -> bool {
+    let numbers = |version: &str| -> Vec<u32> {
+        version.split('.').map(|number| number.parse().unwrap_or(0)).collect()
+    };
+    numbers(version) > numbers(current)
+}
+
+// YouTube's API if YT_API_KEY is set, Invidious otherwise.
+fn get_api_client(callback: Option<ApiProgressCallback>) -> ApiClient {
+
+    let client = match env::var("YT_API_KEY") {
+        Ok(key) => ApiClient::from_youtube(key, callback),
+        Err(_) => ApiClient::from_invidious(callback)
+    };
+    let (connect_timeout, request_timeout) = api_timeouts();
+    let client = client.with_tools(get_tools())
+        .with_instance_ranking(&instance_ranking())
+        .with_timeouts(connect_timeout, request_timeout)
+        .with_retry_policy(retry_policy());
+    match get_response_cache() {
+        Some(cache) => client.with_cache(cache),
+        None => client
+    }
+}
+
+// Playlist pages are cached for API_CACHE_MINUTES (30 by default, 0 disables the cache).
+fn get_response_cache() -> Option<ResponseCache> {
+    let minutes = env::var("API_CACHE_MINUTES").ok().and_then(|value| value.parse().ok()).unwrap_or(30);
+    let dir = get_cache_dir()?.join("responses");
+    (minutes > 0).then(|| ResponseCache::new(&dir, Duration::from_secs(minutes * 60)))
+}
+
+// The timeouts to connect and to get an answer, set in seconds with API_CONNECT_TIMEOUT and API_TIMEOUT.
+pub fn api_timeouts() -> (Duration, Duration) {
+    let seconds = |key: &str, default: Duration| {
+        env::var(key).ok().and_then(|value| value.parse().ok()).map(Duration::from_secs).unwrap_or(default)
+    };
+    (seconds("API_CONNECT_TIMEOUT", DEFAULT_CONNECT_TIMEOUT), seconds("API_TIMEOUT", DEFAULT_REQUEST_TIMEOUT))
+}
+
+// How many times failed requests are retried, set with API_RETRIES.
+pub fn retry_policy() -> RetryPolicy {
+    let default = RetryPolicy::default();
+    RetryPolicy {
+        retries: env::var("API_RETRIES").ok().and_then(|value| value.parse().ok()).unwrap_or(default.retries),
+        ..default
+    }
+}
+
+// Makes every API client try the Invidious instances in this order.
+pub fn set_instance_ranking(ranking: Vec<String>) {
+    if let Ok(mut current) = INSTANCE_RANKING.write() {
+        *current = ranking;
+    }
+}
+
+pub fn instance_ranking() -> Vec<String> {
+    INSTANCE_RANKING.read().map(|ranking| ranking.clone()).unwrap_or_default()
+}
+
+// The yt-dlp, ffmpeg and fpcalc binaries set with YTDLP_PATH, FFMPEG_PATH and FPCALC_PATH, or the ones in the PATH.
+// The yt-dlp installed by lisTUI goes before the one in the PATH.
+pub fn get_tools() -> Tools {
+    Tools {
+        ytdlp: env::var_os("YTDLP_PATH").map(PathBuf::from),
+        ffmpeg: env::var_os("FFMPEG_PATH").map(PathBuf::from),
+        fpcalc: env::var_os("FPCALC_PATH").map(PathBuf::from),
+        installed_ytdlp: installed_ytdlp_path()
+    }
+}
+
+// Where lisTUI installs its own yt-dlp, in the data directory.
+pub fn installed_ytdlp_path() -> Option<PathBuf> {
+    let name = if cfg!(windows) { "yt-dlp.exe" } else { "yt-dlp" };
+    dirs::data_dir().map(|dir| dir.join("listui").join("bin").join(name))
+}
+
+// Whether unavailable tracks are replaced without asking, set with AUTO_SUBSTITUTE.
+pub fn auto_substitute() -> bool {
+    env::var("AUTO_SUBSTITUTE").is_ok_and(|value| value == "true")
+}
+
+// How the thumbnail of the track being played is drawn, set with ALBUM_ART. `None` if it's off.
+pub fn album_art() -> Option<Protocol> {
+    env::var("ALBUM_ART").ok()
+        .filter(|value| value != "off")
+        .and_then(|value| value.parse().ok())
+}
+
+// Whether the spectrum of the track being played is drawn in the player, set with VISUALIZER.
+// Only the rodio backend supports it.
+pub fn visualizer() -> bool {
+    env::var("VISUALIZER").is_ok_and(|value| value == "true")
+}
+
+// Whether the track is paused when the audio device disappears, set with PAUSE_ON_DISCONNECT.
+pub fn pause_on_disconnect() -> bool {
+    env::var("PAUSE_ON_DISCONNECT").map_or(true, |value| value != "false")
+}
+
+// Whether the artist, album and year of the tracks are looked up in MusicBrainz as they are played,
+// set with MUSICBRAINZ.
+pub fn musicbrainz() -> bool {
+    env::var("MUSICBRAINZ").is_ok_and(|value| value == "true")
+}
+
+// Where the thumbnail of a video is kept. Only YouTube videos have one.
+pub fn get_thumbnail_path(track: &Track) -> Option<PathBuf> {
+    let id = track.source_id.as_deref()
+        .filter(|id| track.source_url.is_none() && !id.starts_with(SUBSONIC_PREFIX) && !id.starts_with(JELLYFIN_PREFIX))?;
+    let mut path = get_cache_dir()?;
+    path.push("thumbnails");
+    path.push(format!("{id}.jpg"));
+    Some(path)
+}
+
+// Client for the Subsonic server set with SUBSONIC_URL, SUBSONIC_USER and SUBSONIC_PASSWORD.
+pub fn get_subsonic_client() -> Option<SubsonicClient> {
 
-    let yt_api_key = env::var("YT_API_KEY");
-    let client = match yt_api_key {
-        Ok(key) => {
-            // if print_messages { println!("Fetching videos from YouTube api...") };
-            ApiClient::from_youtube(key, callback)
+    let url = env::var("SUBSONIC_URL").ok()?;
+    let user = env::var("SUBSONIC_USER").ok()?;
+    let password = env::var("SUBSONIC_PASSWORD").ok()?;
+    Some(SubsonicClient::new(&url, &user, &password))
+}
+
+// Client for the Jellyfin server set with JELLYFIN_URL and JELLYFIN_API_KEY.
+pub fn get_jellyfin_client() -> Option<JellyfinClient> {
+
+    let url = env::var("JELLYFIN_URL").ok()?;
+    let api_key = env::var("JELLYFIN_API_KEY").ok()?;
+    Some(JellyfinClient::new(&url, &api_key))
+}
+
+// Format the tracks are downloaded in, from the DOWNLOAD_FORMAT setting.
+pub fn download_format() -> AudioFormat {
+    env::var("DOWNLOAD_FORMAT").ok().and_then(|format| format.parse().ok()).unwrap_or_default()
+}
+
+// The arguments yt-dlp is run with, from the DOWNLOAD_FORMAT, DOWNLOAD_QUALITY, EMBED_THUMBNAIL, DOWNLOAD_RATE_LIMIT
+// and YTDLP_ARGS settings.
+pub fn download_options() -> DownloadOptions {
+
+    DownloadOptions {
+        format: download_format(),
+        quality: env::var("DOWNLOAD_QUALITY").ok(),
+        embed_thumbnail: env::var("EMBED_THUMBNAIL").map_or(true, |value| value != "false"),
+        rate_limit: env::var("DOWNLOAD_RATE_LIMIT").ok().filter(|rate| !rate.is_empty()),
+        extra_args: env::var("YTDLP_ARGS")
+            .map(|args| args.split_whitespace().map(String::from).collect())
+            .unwrap_or_default()
+    }
+}
+
+// When yt-dlp is given up, from DOWNLOAD_TIMEOUT_MINUTES and DOWNLOAD_STALL_MINUTES. 0 disables each limit.
+pub fn download_watchdog() -> Watchdog {
+
+    let default = Watchdog::default();
+    let minutes = |key: &str, default: Option<Duration>| match env::var(key).ok().and_then(|value| value.parse::<u64>().ok()) {
+        Some(0) => None,
+        Some(minutes) => Some(Duration::from_secs(minutes * 60)),
+        None => default
+    };
+    Watchdog {
+        timeout: minutes("DOWNLOAD_TIMEOUT_MINUTES", default.timeout),
+        stall_timeout: minutes("DOWNLOAD_STALL_MINUTES", default.stall_timeout)
+    }
+}
+
+/// When every playlist is fetched again in the background, from the AUTO_SYNC setting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AutoSync {
+    Off,
+    /// Once, when lisTUI starts.
+    Startup,
+    /// When lisTUI starts, and every time this much time passes.
+    Every(Duration),
+}
+
+// AUTO_SYNC is off, startup or a number of minutes. 0 is the same as off.
+pub fn auto_sync() -> AutoSync {
+    match env::var("AUTO_SYNC").ok().as_deref() {
+        Some("startup") => AutoSync::Startup,
+        Some(minutes) => match minutes.parse::<u64>() {
+            Ok(minutes) if minutes > 0 => AutoSync::Every(Duration::from_secs(minutes * 60)),
+            _ => AutoSync::Off,
         },
-        Err(_) => {
-            // if print_messages { println!("Fetching videos from Invidious api. This can take up to a few minutes.") };
-            ApiClient::from_invidious(callback)
+        None => AutoSync::Off,
+    }
+}
+
+pub fn get_downloader() -> YtDlpDownloader {
+
+    let mut downloader = YtDlpDownloader::new(3)
+        .with_options(download_options())
+        .with_tools(get_tools())
+        .with_watchdog(download_watchdog());
+    if let Some(client) = get_subsonic_client() {
+        downloader = downloader.with_subsonic(client);
+    }
+    if let Some(client) = get_jellyfin_client() {
+        downloader = downloader.with_jellyfin(client);
+    }
+    downloader
+}
+
+// Creates the player selected with PLAYER_BACKEND (rodio by default).
+// If it can't be created, the other available backends are tried.
+pub fn get_player() -> Result<Arc<dyn Player>, Box<dyn Error>> {
+
+    let selected = env::var("PLAYER_BACKEND").unwrap_or_default().to_lowercase();
+    let mut backends = vec!["rodio", "gstreamer", "mpv"];
+    match backends.iter().position(|backend| *backend == selected) {
+        Some(pos) => {
+            let backend = backends.remove(pos);
+            backends.insert(0, backend);
+        },
+        None if !selected.is_empty() => log::warn!("Unknown player backend {selected}, using the default one."),
+        None => {}
+    }
+
+    let mut error: Box<dyn Error> = "No player backend available.".into();
+    for backend in backends {
+        match create_player(backend) {
+            Ok(player) => return Ok(player),
+            Err(e) => {
+                log::warn!("Could not create {backend} player: {e}");
+                error = e;
+            }
         }
+    }
+
+    Err(error)
+}
+
+// Creates a rodio player on `device` (or the default one), preloading the
+// tracks smaller than PRELOAD_MB once decoded.
+pub fn create_rodio_player(device: Option<&AudioDevice>) -> Result<RodioPlayer, PlayerError> {
+
+    let player = match device {
+        Some(device) => RodioPlayer::with_device(Some(device))?,
+        None => RodioPlayer::try_default()?
     };
-    let (playlist, videos) = client.fetch_playlist(playlist_id).await?;
-    // if print_messages { println!("Succesfully fetched {}, containing {} songs.", playlist.title, videos.len()); }
-
-    Ok((playlist, videos))
-}
-
-// Returns a list of the tracks inside a local directory. Only works with mp3 files currently.
-pub fn get_local_playlist(path: &Path) -> Option<Vec<Track>> {
-
-    if path.is_dir() {
-        
-        let path = path.canonicalize().ok()?;
-        let tracks = read_dir(path).ok()?
-            .flatten()
-            .enumerate()
-            .filter_map(|(ind, entry)| {
-                let filename = entry.file_name();
-                let filename = filename.to_string_lossy();
-                if filename.ends_with(".mp3") {
-                    Some(Track{
-                        id: ind as i32,
-                        title: entry.path().with_extension("").file_name().unwrap().to_string_lossy().to_string(),
-                        yt_id: None,
-                        playlist_id: None,
-                    })
+
+    let preload_mb = env::var("PRELOAD_MB").ok().and_then(|mb| mb.parse::<usize>().ok()).unwrap_or(0);
+    let player = player.with_preload(preload_mb * 1024 * 1024);
+    Ok(if visualizer() { player.with_visualizer() } else { player })
+}
+
+// Player backends this build supports.
+pub fn available_backends() -> Vec<&'static str> {
+
+    let mut backends = vec!["rodio"];
+    if cfg!(feature = "gstreamer") { backends.push("gstreamer"); }
+    if cfg!(feature = "mpv") { backends.push("mpv"); }
+    backends
+}
+
+pub fn create_player(backend: &str) -> Result<Arc<dyn Player>, Box<dyn Error>> {
+
+    match backend {
+        "rodio" => Ok(Arc::new(create_rodio_player(None)?)),
+        #[cfg(feature = "gstreamer")]
+        "gstreamer" => Ok(Arc::new(GstPlayer::try_default()?)),
+        #[cfg(feature = "mpv")]
+        "mpv" => Ok(Arc::new(MpvPlayer::try_default()?)),
+        other => Err(format!("lisTUI was built without {other} support (enable the \"{other}\" feature).").into()),
+    }
+}
+
+// Returns true if the file is a playlist that can be parsed by `get_playlist_file`.
+pub fn is_playlist_file(path: &Path) -> bool {
+
+    path.is_file() && path.extension()
+        .map(|ext| matches!(ext.to_string_lossy().to_lowercase().as_str(), "m3u" | "m3u8" | "pls"))
+        .unwrap_or(false)
+}
+
+// Returns the tracks listed in a .m3u, .m3u8 or .pls file.
+//
+// Local files are stored as `file://` ids, so the player can find them no matter the directory
+// they are in. Any other URL is kept as is and it will be downloaded with yt-dlp when played.
+pub fn get_playlist_file(path: &Path) -> Option<Vec<Track>> {
+
+    let content = read_to_string(path).ok()?;
+    let base_dir = path.canonicalize().ok()?.parent()?.to_path_buf();
+    let entries = match path.extension()?.to_string_lossy().to_lowercase().as_str() {
+        "m3u" | "m3u8" => parse_m3u(&content),
+        "pls" => parse_pls(&content),
+        _ => return None
+    };
+
+    let tracks = entries.into_iter()
+        .enumerate()
+        .map(|(ind, (location, title))| {
+
+            let (title, location) = if location.contains("://") && !location.starts_with("file://") {
+                (title.unwrap_or(location.clone()), location)
+            }
+            else {
+                let file_path = base_dir.join(location.trim_start_matches("file://"));
+                let title = title.unwrap_or_else(|| file_path.with_extension("").file_name().unwrap_or_default().to_string_lossy().to_string());
+                (title, format!("file://{}", file_path.display()))
+            };
+
+            Track {
+                id: ind as i32,
+                title,
+                source_id: None,
+                playlist_id: None,
+                played: None,
+                position: None,
+                restriction: None,
+                removed: false,
+                source_url: Some(location),
+                artist: None,
+                album: None,
+                track_number: None,
+                duration: None,
+                play_count: 0,
+                trim_start: None,
+                trim_end: None,
+                year: None,
+                metadata_checked: false,
+                uploaded_at: None,
+            }
+        })
+        .collect();
+
+    Some(tracks)
+}
+
+// Returns the location and title (if any) of every entry in a m3u file.
+fn parse_m3u(content: &str) -> Vec<(String, Option<String>)> {
+
+    let mut entries = Vec::new();
+    let mut title: Option<String> = None;
+    for line in content.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:<duration>,<title>
+            title = info.split_once(',').map(|(_, t)| t.trim().to_string()).filter(|t| !t.is_empty());
+        }
+        else if !line.starts_with('#') {
+            entries.push((line.to_string(), title.take()));
+        }
+    }
+
+    entries
+}
+
+// Returns the location and title (if any) of every entry in a pls file.
+fn parse_pls(content: &str) -> Vec<(String, Option<String>)> {
+
+    let mut entries: BTreeMap<usize, (Option<String>, Option<String>)> = BTreeMap::new();
+    for line in content.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let key = key.to_lowercase();
+        if let Some(Ok(n)) = key.strip_prefix("file").map(str::parse::<usize>) {
+            entries.entry(n).or_default().0 = Some(value.trim().to_string());
+        }
+        else if let Some(Ok(n)) = key.strip_prefix("title").map(str::parse::<usize>) {
+            entries.entry(n).or_default().1 = Some(value.trim().to_string());
+        }
+    }
+
+    entries.into_values()
+        .filter_map(|(location, title)| Some((location?, title)))
+        .collect()
+}
+
+// Returns the path where the audio file of a track is (or will be) stored.
+pub fn get_track_path(dir: &Path, track: &Track) -> PathBuf {
+
+    if let Some(path) = track.source_url.as_ref().and_then(|url| url.strip_prefix("file://")) {
+        return PathBuf::from(path);
+    }
+
+    let filename = track_filename(track, &track.title);
+
+    // Media servers always stream mp3.
+    let format = match track.source_id.as_deref() {
+        Some(id) if id.starts_with(SUBSONIC_PREFIX) || id.starts_with(JELLYFIN_PREFIX) => AudioFormat::Mp3,
+        _ => download_format()
+    };
+
+    // Tracks downloaded before changing DOWNLOAD_FORMAT are still found.
+    let path_with = |format: AudioFormat| dir.join(format!("{filename}.{}", format.extension()));
+    AudioFormat::ALL.into_iter()
+        .map(path_with)
+        .find(|path| path.exists())
+        .unwrap_or_else(|| path_with(format))
+}
+
+// The name of the file of a track titled `title`, without its extension. The id of the video is
+// part of it, like "Song [dQw4w9WgXcQ]", so tracks with the same title don't overwrite each other.
+fn track_filename(track: &Track, title: &str) -> String {
+
+    match (track.source(), track.source_id.as_deref()) {
+        (Some(_), Some(id)) => format!("{} [{}]", sanitize_filename(title), sanitize_filename(id)),
+        (Some(_), None) => sanitize_filename(title),
+        (None, _) => String::from(title)
+    }
+}
+
+// Renames the files downloaded before their name had the id of the video, like "Song.mp3" to
+// "Song [dQw4w9WgXcQ].mp3", once for every download directory. When several tracks had the same
+// title, the first one keeps the file and the others are downloaded again.
+pub fn rename_old_downloads(dao: &Database, dir: &Path) -> Result<usize, DbError> {
+
+    let dir_key = dir.to_string_lossy();
+    if dao.get_state(RENAMED_DOWNLOADS_KEY)?.is_some_and(|renamed| renamed == dir_key) {
+        return Ok(0);
+    }
+
+    let mut renamed = 0;
+    for playlist in dao.get_playlists()? {
+        for track in dao.get_tracks(playlist.id)? {
+            if track.is_local() || track.source_id.is_none() { continue; }
+
+            let filename = track_filename(&track, &track.title);
+            for format in AudioFormat::ALL {
+                let old_path = dir.join(format!("{}.{}", sanitize_filename(&track.title), format.extension()));
+                let new_path = dir.join(format!("{filename}.{}", format.extension()));
+                if !old_path.exists() || new_path.exists() { continue; }
+                match std::fs::rename(&old_path, &new_path) {
+                    Ok(_) => renamed += 1,
+                    Err(e) => log::warn!("Failed to rename {} to {}: {e}", old_path.display(), new_path.display())
                 }
-                else { None }
-            })
-            .collect();
+            }
+        }
+    }
 
-        Some(tracks)
+    dao.set_state(RENAMED_DOWNLOADS_KEY, &dir_key)?;
+    Ok(renamed)
+}
+
+// Moves the downloaded file of a track that has been renamed, so it's still found with its new title.
+pub fn rename_track_file(dir: &Path, track: &Track, title: &str) {
+
+    let path = get_track_path(dir, track);
+    let Some(ext) = path.extension().filter(|_| path.exists()) else { return };
+    let new_path = path.with_file_name(format!("{}.{}", track_filename(track, title), ext.to_string_lossy()));
+    if let Err(e) = std::fs::rename(&path, &new_path) {
+        log::warn!("Failed to rename {} to {}: {e}", path.display(), new_path.display());
+    }
+}
+
+// Tracks that have to be downloaded before they can be played.
+pub fn missing_tracks(dir: &Path, tracks: Vec<Track>) -> Vec<Track> {
+
+    tracks.into_iter()
+        .filter(|track| track.source().is_some_and(|source| !source.starts_with("file://")))
+        .filter(|track| !get_track_path(dir, track).exists())
+        .collect()
+}
+
+// The playlists that were being downloaded when lisTUI was closed, in the order they were started.
+pub fn download_queue(dao: &Database) -> Result<Vec<i32>, DbError> {
+
+    Ok(dao.get_state(DOWNLOAD_QUEUE_KEY)?
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|id| id.parse().ok())
+        .collect())
+}
+
+// Remembers that a playlist is being downloaded, so the download can be resumed after a restart.
+pub fn queue_download(dao: &Database, playlist_id: i32) -> Result<(), DbError> {
+
+    let mut queue = download_queue(dao)?;
+    if !queue.contains(&playlist_id) {
+        queue.push(playlist_id);
+        save_download_queue(dao, &queue)?;
     }
+    Ok(())
+}
+
+pub fn dequeue_download(dao: &Database, playlist_id: i32) -> Result<(), DbError> {
+
+    let mut queue = download_queue(dao)?;
+    queue.retain(|id| *id != playlist_id);
+    save_download_queue(dao, &queue)
+}
+
+fn save_download_queue(dao: &Database, queue: &[i32]) -> Result<(), DbError> {
+
+    let ids: Vec<String> = queue.iter().map(ToString::to_string).collect();
+    dao.set_state(DOWNLOAD_QUEUE_KEY, &ids.join("\n"))
+}
 
-    else { None }
+// Downloaded files that no saved track points to, like the ones left behind after renaming or deleting tracks.
+pub fn orphan_files(dao: &Database, dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+
+    let mut referenced = HashSet::new();
+    for playlist in dao.get_playlists()? {
+        referenced.extend(dao.get_tracks(playlist.id)?.iter().map(|track| get_track_path(dir, track)));
+    }
+
+    // Conversions of the reencode command that haven't finished yet.
+    let is_reencoding = |path: &Path| path.with_extension("").extension().is_some_and(|ext| ext == "reencode");
+    let mut orphans: Vec<PathBuf> = read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| is_audio_file(path) && !is_reencoding(path) && !referenced.contains(path))
+        .collect();
+
+    orphans.sort();
+    Ok(orphans)
+}
+
+// Groups the tracks of the saved playlists that are the same song: the same video, or titles with the same
+// words (like "Song [Official Video]" and "Song (audio)"). Groups whose tracks use the same file are left out.
+pub fn find_duplicates(dao: &Database, dir: &Path) -> Result<Vec<Vec<Track>>, DbError> {
+
+    let mut groups: Vec<Vec<Track>> = Vec::new();
+    let mut keys: HashMap<String, usize> = HashMap::new();
+    let mut seen = HashSet::new();
+    for playlist in dao.get_playlists()? {
+        for track in dao.get_tracks(playlist.id)? {
+            let Some(source) = track.source().filter(|source| !source.starts_with("file://")).map(String::from) else { continue };
+            if !seen.insert(track.id) { continue; }
+
+            let title = normalize_title(&track.title);
+            let ind = match keys.get(&source).or_else(|| keys.get(&title).filter(|_| !title.is_empty())) {
+                Some(ind) => *ind,
+                None => {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                }
+            };
+            keys.insert(source, ind);
+            if !title.is_empty() {
+                keys.insert(title, ind);
+            }
+            groups[ind].push(track);
+        }
+    }
+
+    Ok(groups.into_iter()
+        .filter(|tracks| tracks.iter().map(|track| file_id(&get_track_path(dir, track))).collect::<HashSet<_>>().len() > 1)
+        .collect())
+}
+
+// Fingerprints the downloaded tracks of the saved playlists with fpcalc. Fingerprints are saved, and only
+// computed again when a file changes. Files that can't be fingerprinted are left out.
+pub async fn fingerprint_tracks(dao: &Database, dir: &Path, tools: &Tools) -> Result<Vec<(Track, Fingerprint)>, DbError> {
+
+    let mut fingerprinted = Vec::new();
+    let mut seen = HashSet::new();
+    for playlist in dao.get_playlists()? {
+        for track in dao.get_tracks(playlist.id)? {
+            if track.source().is_none_or(|source| source.starts_with("file://")) || !seen.insert(track.id) { continue; }
+            let path = get_track_path(dir, &track);
+            let Ok(size) = metadata(&path).map(|metadata| metadata.len() as i64) else { continue };
+
+            let key = path.to_string_lossy();
+            let fingerprint = match dao.get_fingerprint(&key, size)?.and_then(|data| data.parse().ok()) {
+                Some(fingerprint) => fingerprint,
+                None => match Fingerprint::compute(tools, &path).await {
+                    Ok(fingerprint) => {
+                        dao.set_fingerprint(&key, size, &fingerprint.to_string())?;
+                        fingerprint
+                    }
+                    Err(e) => {
+                        log::warn!("{e}");
+                        continue;
+                    }
+                }
+            };
+            fingerprinted.push((track, fingerprint));
+        }
+    }
+    Ok(fingerprinted)
+}
+
+// Adds the tracks whose audio is the same to the groups of duplicates, joining the groups they are in. Each
+// group is returned with whether its tracks were found by their audio.
+pub fn add_same_audio(groups: Vec<Vec<Track>>, fingerprinted: Vec<(Track, Fingerprint)>, dir: &Path) -> Vec<(Vec<Track>, bool)> {
+
+    let fingerprints: Vec<&Fingerprint> = fingerprinted.iter().map(|(_, fingerprint)| fingerprint).collect();
+    let pairs = fingerprint::matching_pairs(&fingerprints);
+
+    let mut merged: Vec<(Vec<Track>, bool)> = groups.into_iter().map(|tracks| (tracks, false)).collect();
+    let group_of = |merged: &Vec<(Vec<Track>, bool)>, track: &Track| merged.iter().position(|(tracks, _)| tracks.iter().any(|other| other.id == track.id));
+    for (first, second) in pairs {
+        let (first, second) = (&fingerprinted[first].0, &fingerprinted[second].0);
+        let ind = match (group_of(&merged, first), group_of(&merged, second)) {
+            (Some(ind), Some(other)) if ind == other => ind,
+            (Some(ind), Some(other)) => {
+                let (tracks, _) = merged.remove(other);
+                let ind = if other < ind { ind - 1 } else { ind };
+                merged[ind].0.extend(tracks);
+                ind
+            }
+            (Some(ind), None) => {
+                merged[ind].0.push(second.clone());
+                ind
+            }
+            (None, Some(ind)) => {
+                merged[ind].0.push(first.clone());
+                ind
+            }
+            (None, None) => {
+                merged.push((vec![first.clone(), second.clone()], false));
+                merged.len() - 1
+            }
+        };
+        merged[ind].1 = true;
+    }
+
+    merged.into_iter()
+        .filter(|(tracks, _)| tracks.iter().map(|track| file_id(&get_track_path(dir, track))).collect::<HashSet<_>>().len() > 1)
+        .collect()
+}
+
+// The downloaded tracks of the saved playlists that haven't been looked up in MusicBrainz yet, or
+// all of them with `again`. Files of the user's are left out, since they have their own tags.
+pub fn tracks_to_tag(dao: &Database, dir: &Path, again: bool) -> Result<Vec<Track>, DbError> {
+
+    let mut tracks = Vec::new();
+    let mut seen = HashSet::new();
+    for playlist in dao.get_playlists()? {
+        for track in dao.get_tracks(playlist.id)? {
+            if track.is_local() || (track.metadata_checked && !again) || !seen.insert(track.id) { continue; }
+            if get_track_path(dir, &track).exists() {
+                tracks.push(track);
+            }
+        }
+    }
+    Ok(tracks)
+}
+
+// Saves what MusicBrainz knows about a track, or that it knows nothing, so it isn't looked up again.
+pub fn save_metadata(dao: &Database, track_id: i32, recording: Option<&Recording>) -> Result<(), DbError> {
+    dao.set_metadata(
+        track_id,
+        recording.map(|recording| recording.artist.as_str()),
+        recording.and_then(|recording| recording.album.as_deref()),
+        recording.and_then(|recording| recording.year)
+    )
+}
+
+// The listening stats of the tracks of the saved playlists, or the history of every play, in `format`.
+pub fn export_stats(dao: &Database, history: bool, format: StatsFormat) -> Result<String, DbError> {
+
+    let plays = dao.get_plays()?;
+    if history {
+        return Ok(stats::export(&stats::history(&plays), format));
+    }
+
+    let mut tracks = Vec::new();
+    let mut seen = HashSet::new();
+    for playlist in dao.get_playlists()? {
+        tracks.extend(dao.get_tracks(playlist.id)?.into_iter().filter(|track| seen.insert(track.id)));
+    }
+    Ok(stats::export(&stats::track_stats(&tracks, &plays), format))
+}
+
+// Tells the files apart, but not the hard links to the same file. Files that don't exist are told by their path.
+#[cfg(unix)]
+fn file_id(path: &Path) -> Result<(u64, u64), PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+    metadata(path)
+        .map(|metadata| (metadata.dev(), metadata.ino()))
+        .map_err(|_| path.to_path_buf())
+}
+
+#[cfg(not(unix))]
+fn file_id(path: &Path) -> Result<(u64, u64), PathBuf> {
+    Err(path.to_path_buf())
+}
+
+// Makes the tracks of a group of duplicates use the same downloaded file (the biggest one, usually the best
+// quality), by hard linking it where the others are stored. Their own files are deleted. Returns how many
+// bytes were freed.
+pub fn share_file(dir: &Path, tracks: &[Track]) -> io::Result<u64> {
+
+    let Some((shared, _)) = tracks.iter()
+        .map(|track| get_track_path(dir, track))
+        .filter_map(|path| metadata(&path).ok().map(|metadata| (path, metadata.len())))
+        .max_by_key(|(_, size)| *size) else { return Ok(0) };
+    let extension = shared.extension().unwrap_or_default().to_string_lossy().to_string();
+
+    let mut freed = 0;
+    for track in tracks {
+        let path = get_track_path(dir, track);
+        if path == shared { continue; }
+        if path.exists() {
+            freed += metadata(&path)?.len();
+            remove_file(&path)?;
+        }
+        hard_link(&shared, path.with_extension(&extension))?;
+    }
+
+    Ok(freed)
+}
+
+// A track for a file of the user's, titled like the file.
+pub fn local_video(path: &Path) -> NewVideo {
+    let title = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    NewVideo::new(title, format!("file://{}", path.canonicalize().unwrap_or(path.to_path_buf()).display()))
+}
+
+// Files in one of the formats tracks are downloaded in.
+fn is_audio_file(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| AudioFormat::ALL.iter().any(|format| ext == format.extension()))
+}
+
+// The directories set with MUSIC_DIRS, separated like the ones in the PATH.
+pub fn music_dirs() -> Vec<PathBuf> {
+    env::var_os("MUSIC_DIRS")
+        .map(|dirs| env::split_paths(&dirs).filter(|dir| !dir.as_os_str().is_empty()).collect())
+        .unwrap_or_default()
+}
+
+// The audio files inside a directory (not its subdirectories), sorted by name.
+pub fn local_videos(dir: &Path) -> io::Result<Vec<NewVideo>> {
+
+    let mut paths: Vec<PathBuf> = read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| is_audio_file(path))
+        .collect();
+
+    paths.sort();
+    Ok(paths.iter().map(|path| local_video(path)).collect())
+}
+
+// Saves a directory as a playlist, or brings the one it already has up to date with the files in it.
+// The tracks whose file isn't there anymore are marked as removed, and come back with it.
+pub fn sync_music_dir(dao: &Database, dir: &Path) -> Result<(Playlist, TrackDiff), Box<dyn Error>> {
+
+    let dir = dir.canonicalize()?;
+    let videos = local_videos(&dir)?;
+    let yt_id = format!("{FOLDER_PREFIX}{}", dir.display());
+    let saved = dao.get_playlists()?.into_iter().find(|playlist| playlist.yt_id.as_deref() == Some(yt_id.as_str()));
+    let playlist = match saved {
+        Some(playlist) => dao.sync_playlist(playlist.id, &NewPlaylist::new(playlist.title.clone(), yt_id))?,
+        None => {
+            let title = dir.file_name().map_or_else(|| dir.display().to_string(), |name| name.to_string_lossy().to_string());
+            dao.save_playlist(NewPlaylist::new(title, yt_id))?
+        }
+    };
+
+    let mut diff = dao.update_tracks(playlist.id, videos)?;
+    let missing: Vec<Track> = dao.get_tracks(playlist.id)?
+        .into_iter()
+        .filter(|track| track.playlist_id == Some(playlist.id) && track.is_local() && !track.removed)
+        .filter(|track| !get_track_path(&dir, track).exists())
+        .collect();
+    dao.set_removed(&missing.iter().map(|track| track.id).collect::<Vec<i32>>(), true)?;
+    diff.removed.extend(missing.into_iter().map(|track| track.title));
+    Ok((playlist, diff))
+}
+
+// Keeps the playlists of the MUSIC_DIRS in sync with their files. The directories that can't be read,
+// like the ones in a drive that isn't mounted, are left as they were.
+pub fn sync_music_dirs(dao: &Database) {
+    for dir in music_dirs() {
+        if let Err(e) = sync_music_dir(dao, &dir) {
+            log::warn!("Failed to sync {}: {e}", dir.display());
+        }
+    }
+}
+
+// Removes characters that are not allowed in filenames.
+pub fn sanitize_filename(name: &str) -> String {
+    name.replace(['/', '\\', ':', '*', '<', '>', '|', '\"'], "")
+}
+
+// Finds a saved playlist by its youtube id or title, ignoring case.
+pub fn find_playlist(dao: &Database, name: &str) -> Result<Option<Playlist>, DbError> {
+
+    Ok(dao.get_playlists()?
+        .into_iter()
+        .find(|p| p.yt_id.as_deref() == Some(name) || p.title.eq_ignore_ascii_case(name)))
+}
+
+// Exports a playlist saved in the database into `output_dir`, returning the path of the new file.
+pub fn export_playlist(dao: &Database, playlist: &Playlist, download_dir: &Path, output_dir: &Path, format: ExportFormat) -> Result<PathBuf, Box<dyn Error>> {
+
+    let tracks = dao.get_tracks(playlist.id)?;
+    let exported = ExportedPlaylist::new(playlist, &tracks, |track| {
+        Some(get_track_path(download_dir, track)).filter(|path| path.exists())
+    });
+
+    let path = output_dir.join(format!("{}.{}", sanitize_filename(&playlist.title), format.extension()));
+    write(&path, exported.export(format))?;
+    Ok(path)
 }
 
 pub fn time_str(s1: i32, s2: i32, paused: bool) -> String {
@@ -94,9 +1002,58 @@ pub fn time_str(s1: i32, s2: i32, paused: bool) -> String {
     else { format!("{:02}:{:02}:{:02} {separator} {:02}:{:02}:{:02}", h1, m1, s1, h2, m2, s2) }
 }
 
-pub fn probe_ytdlp() -> bool {
+/// Parses where a track starts and stops playing, like "0:30-3:45", "1:05-" or "-200", into
+/// seconds. Either side can be left empty, and "none" plays the whole track.
+pub fn parse_trim(value: &str) -> Result<(Option<i32>, Option<i32>), String> {
 
-    let child = Command::new("yt-dlp")
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("none") {
+        return Ok((None, None));
+    }
+    let (start, end) = value.split_once('-').ok_or_else(|| format!("\"{value}\" is not like 0:30-3:45."))?;
+    let (start, end) = (parse_seconds(start)?.filter(|start| *start > 0), parse_seconds(end)?);
+    if let (Some(start), Some(end)) = (start, end) {
+        if start >= end {
+            return Err(format!("The track would stop at {} before starting at {}.", minutes_str(end), minutes_str(start)));
+        }
+    }
+    Ok((start, end))
+}
+
+/// Parses "3:45", "1:02:03" or "225" into seconds, and an empty string into `None`.
+pub fn parse_seconds(time: &str) -> Result<Option<i32>, String> {
+
+    let time = time.trim();
+    if time.is_empty() {
+        return Ok(None);
+    }
+    let parts: Vec<&str> = time.split(':').collect();
+    if parts.len() > 3 {
+        return Err(format!("\"{time}\" is not a time."));
+    }
+    parts.iter()
+        .try_fold(0, |total: i32, part| part.parse::<u16>().ok().map(|part| total * 60 + part as i32))
+        .map(Some)
+        .ok_or_else(|| format!("\"{time}\" is not a time."))
+}
+
+/// Shows where a track starts and stops playing, like `parse_trim` reads it.
+pub fn trim_str(start: Option<i32>, end: Option<i32>) -> String {
+
+    match (start, end) {
+        (None, None) => String::from("none"),
+        (start, end) => format!("{}-{}", start.map(minutes_str).unwrap_or_default(), end.map(minutes_str).unwrap_or_default()),
+    }
+}
+
+// Like "3:45", or "75:00" for long tracks.
+fn minutes_str(seconds: i32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+pub fn probe_ytdlp(tools: &Tools) -> bool {
+
+    let child = Command::new(tools.ytdlp_path())
         .arg("--help")
         .stderr(Stdio::null())
         .stdout(Stdio::null())
@@ -105,9 +1062,9 @@ pub fn probe_ytdlp() -> bool {
     child.is_ok()
 }
 
-pub fn probe_ffmpeg() -> bool {
+pub fn probe_ffmpeg(tools: &Tools) -> bool {
 
-    let child = Command::new("ffmpeg")
+    let child = Command::new(tools.ffmpeg_path())
         .arg("-help")
         .stderr(Stdio::null())
         .stdout(Stdio::null())
@@ -124,6 +1081,14 @@ fn get_data_dir() -> PathBuf {
        data_dir
 }
 
+// Directory for files that can be regenerated, like transcoded tracks.
+pub fn get_cache_dir() -> Option<PathBuf> {
+    let mut cache_dir = dirs::cache_dir()?;
+    cache_dir.push("listui");
+    create_dir_all(&cache_dir).ok()?;
+    Some(cache_dir)
+}
+
 pub fn get_log_path() -> Option<PathBuf> {
 
     match env::var("LOG_PATH") {
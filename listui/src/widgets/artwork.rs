@@ -0,0 +1,62 @@
+use listui_lib::artwork::{Artwork, Protocol};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, Clear, Widget};
+use ratatui::Frame;
+
+/// Cells the art fills, inside its borders. Thumbnails are 16:9, and so are 32x9 cells of 1:2.
+pub const ART_SIZE: (u16, u16) = (32, 9);
+
+/// Draws the thumbnail of the track being played. With blocks, it's drawn right away; with the
+/// other protocols its cells are left blank, and the image is written there by `escape_sequence`
+/// once the frame has been drawn.
+pub fn draw_artwork(frame: &mut Frame, area: Rect, block: Block, artwork: &Artwork, protocol: Protocol) {
+
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+    if protocol == Protocol::Blocks {
+        frame.render_widget(HalfBlocks(artwork), inner);
+    }
+}
+
+/// What has to be written to the terminal, with the cursor at the top left cell of `area`, to
+/// show the art with a graphics protocol. `cell_size` is the size of a cell in pixels.
+pub fn escape_sequence(artwork: &Artwork, protocol: Protocol, area: Rect, cell_size: (u16, u16)) -> Option<String> {
+
+    match protocol {
+        Protocol::Blocks => None,
+        Protocol::Kitty => Some(artwork.kitty(area.width, area.height)),
+        Protocol::Iterm => Some(artwork.iterm(area.width, area.height)),
+        Protocol::Sixel => {
+            // Sixel images are as large as their pixels, so they are scaled to the cells first.
+            let (width, height) = ((area.width * cell_size.0) as u32, (area.height * cell_size.1) as u32);
+            let pixels = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| artwork.sample(x, y, width, height))
+                .collect();
+            Some(Artwork::from_pixels(width, height, pixels).sixel())
+        }
+    }
+}
+
+// Two pixels in every cell: the upper one is the color of "▀", and the lower one its background.
+struct HalfBlocks<'a>(&'a Artwork);
+
+impl Widget for HalfBlocks<'_> {
+
+    fn render(self, area: Rect, buf: &mut Buffer) {
+
+        let (width, height) = (area.width as u32, area.height as u32 * 2);
+        for row in 0..area.height {
+            for column in 0..area.width {
+                let [r, g, b] = self.0.sample(column as u32, row as u32 * 2, width, height);
+                let [br, bg, bb] = self.0.sample(column as u32, row as u32 * 2 + 1, width, height);
+                buf.get_mut(area.x + column, area.y + row)
+                    .set_symbol("▀")
+                    .set_style(Style::default().fg(Color::Rgb(r, g, b)).bg(Color::Rgb(br, bg, bb)));
+            }
+        }
+    }
+}
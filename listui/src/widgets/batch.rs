@@ -0,0 +1,129 @@
+use listui_lib::models::Drawable;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::symbols;
+use ratatui::widgets::{Clear, LineGauge};
+use ratatui::Frame;
+
+use crate::jobs::{ItemStatus, JobInfo, JobStatus};
+
+use super::list::ListWidget;
+use super::ACC_COLOR;
+
+// A line of the batch screen, with how its item went.
+struct ItemLine {
+    text: String,
+    status: ItemStatus,
+}
+
+impl Drawable for ItemLine {
+
+    fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    fn get_icon(&self) -> Option<&str> {
+        match self.status {
+            ItemStatus::Pending => Some("·"),
+            ItemStatus::Running => Some("…"),
+            ItemStatus::Done => Some("✓"),
+            ItemStatus::Failed(_) => Some("✗"),
+        }
+    }
+
+    fn get_color(&self) -> Option<&str> {
+        match self.status {
+            ItemStatus::Pending => Some("darkgray"),
+            ItemStatus::Failed(_) => Some("red"),
+            _ => None,
+        }
+    }
+}
+
+// The items of a batch job, like the tracks of a playlist being downloaded, drawn over the
+// screen it was opened from. It sums up how they went once the job is over.
+pub struct BatchWidget {
+    items: ListWidget<ItemLine>,
+    ratio: f64,
+    label: String,
+    accent: Color,
+}
+
+impl Default for BatchWidget {
+
+    fn default() -> Self {
+        Self { items: ListWidget::empty("Batch"), ratio: 0.0, label: String::new(), accent: ACC_COLOR }
+    }
+}
+
+impl BatchWidget {
+
+    pub fn set_accent(&mut self, accent: Color) {
+        self.accent = accent;
+    }
+
+    /// Shows the current state of `job`, keeping the selection where it was.
+    pub fn update(&mut self, job: &JobInfo) {
+
+        let selected = self.items.get_selected();
+        let lines: Vec<ItemLine> = job.items.iter()
+            .map(|item| {
+                let text = match &item.status {
+                    ItemStatus::Failed(reason) => format!("{}: {reason}", item.name),
+                    _ => item.name.clone(),
+                };
+                ItemLine { text, status: item.status.clone() }
+            })
+            .collect();
+
+        let (done, failed, _) = job.counts();
+        let total = job.items.len();
+        let title = match (job.status, job.summary()) {
+            (JobStatus::Finished, Some(summary)) => format!("{} - finished: {summary}", job.name),
+            (JobStatus::Cancelled, Some(summary)) => format!("{} - cancelled: {summary}", job.name),
+            _ if job.paused => format!("{} - paused (z to resume, c to cancel)", job.name),
+            _ => format!("{} (z to pause, c to cancel)", job.name),
+        };
+        self.ratio = if total > 0 { (done + failed) as f64 / total as f64 } else { 0.0 };
+        self.label = format!("{}/{total}", done + failed);
+
+        let len = lines.len();
+        self.items = ListWidget::with_items(&title, lines);
+        self.items.set_accent(self.accent);
+        if let Some(selected) = selected.filter(|_| len > 0) {
+            self.items.select_ind(selected.min(len - 1));
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.items.next();
+    }
+
+    pub fn previous(&mut self) {
+        self.items.previous();
+    }
+
+    /// Draws the items in a box in the middle of `area`, with a bar under them.
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+
+        let width = area.width.saturating_sub(4).min(80);
+        let height = area.height.saturating_sub(4).min(self.items.total_len() as u16 + 3).max(4.min(area.height));
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+        let list = Rect { height: popup.height.saturating_sub(1), ..popup };
+        let bar = Rect { y: list.bottom(), height: popup.height - list.height, ..popup };
+
+        frame.render_widget(Clear, popup);
+        self.items.draw(frame, list);
+        let gauge = LineGauge::default()
+            .label(self.label.as_str())
+            .ratio(self.ratio)
+            .line_set(symbols::line::THICK)
+            .gauge_style(Style::default().fg(self.accent));
+        frame.render_widget(gauge, bar);
+    }
+}
@@ -0,0 +1,91 @@
+use listui_lib::models::Drawable;
+use listui_lib::player::{AudioDevice, PlayerInfo};
+use ratatui::Frame;
+use ratatui::layout::{Rect, Layout, Constraint, Direction};
+use ratatui::widgets::Paragraph;
+
+use super::list::ListWidget;
+
+// Something the audio output can be switched to.
+pub enum OutputOption {
+    Backend(&'static str, String),
+    Device(AudioDevice, String),
+}
+
+impl OutputOption {
+
+    pub fn backend(backend: &'static str) -> Self {
+        Self::Backend(backend, format!("Switch to the {backend} backend"))
+    }
+
+    pub fn device(device: AudioDevice) -> Self {
+        let label = format!("Use device {} / {}{}", device.host, device.name, if device.is_default { " (default)" } else { "" });
+        Self::Device(device, label)
+    }
+}
+
+impl Drawable for OutputOption {
+
+    fn get_text(&self) -> &str {
+        match self {
+            Self::Backend(_, label) | Self::Device(_, label) => label
+        }
+    }
+}
+
+// Shows information about the audio output, and lets the user switch backends and devices.
+pub struct DiagnosticsWidget {
+    options: ListWidget<OutputOption>,
+}
+
+impl super::MinSize for DiagnosticsWidget {
+
+    // The information and one option.
+    fn min_size(&self) -> (u16, u16) {
+        (40, 11)
+    }
+}
+
+impl DiagnosticsWidget {
+
+    pub fn new(options: Vec<OutputOption>) -> Self {
+
+        Self {
+            options: ListWidget::with_items("Audio output (press Enter to switch)", options)
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.options.next();
+    }
+
+    pub fn previous(&mut self) {
+        self.options.previous();
+    }
+
+    pub fn get_selected(&self) -> Option<&OutputOption> {
+        self.options.get_selected().map(|ind| self.options.get_ind(ind))
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, info: &PlayerInfo) {
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(8), Constraint::Min(3)].as_ref())
+            .split(area);
+
+        let unknown = || String::from("not reported");
+        let text = format!(
+            "Backend: {}\nDevice: {}\nSample rate: {}\nChannels: {}\nBuffer underruns: {}\nStream errors: {}",
+            info.backend,
+            info.device.clone().unwrap_or_else(unknown),
+            info.sample_rate.map(|rate| format!("{rate} Hz")).unwrap_or_else(unknown),
+            info.channels.map(|channels| channels.to_string()).unwrap_or_else(unknown),
+            info.underruns.map(|count| count.to_string()).unwrap_or_else(unknown),
+            info.stream_errors.map(|count| count.to_string()).unwrap_or_else(unknown),
+        );
+
+        frame.render_widget(Paragraph::new(text).block(super::BLOCK.clone().title("Diagnostics")), chunks[0]);
+        self.options.draw(frame, chunks[1]);
+    }
+}
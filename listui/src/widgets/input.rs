@@ -0,0 +1,51 @@
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::{Style, Modifier};
+use ratatui::text::Span;
+use ratatui::widgets::{Clear, Paragraph};
+
+// Single line text prompt, drawn as a popup over the current screen.
+pub struct InputWidget {
+    title: String,
+    value: String,
+}
+
+impl InputWidget {
+
+    pub fn new(title: &str) -> Self {
+
+        Self {
+            title: String::from(title),
+            value: String::new(),
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.value.push(c);
+    }
+
+    pub fn pop(&mut self) {
+        self.value.pop();
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+
+        let width = area.width.saturating_sub(4).min(80);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + area.height.saturating_sub(3) / 2,
+            width,
+            height: 3.min(area.height),
+        };
+
+        let p = Paragraph::new(format!("{}▏", self.value))
+            .block(super::BLOCK.clone().title(Span::styled(self.title.as_str(), Style::default().add_modifier(Modifier::BOLD))));
+
+        frame.render_widget(Clear, popup);
+        frame.render_widget(p, popup);
+    }
+}
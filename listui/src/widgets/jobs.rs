@@ -0,0 +1,91 @@
+use listui_lib::models::Drawable;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+
+use crate::jobs::{JobId, JobInfo};
+
+use super::list::ListWidget;
+
+// A line of the jobs screen.
+struct JobLine {
+    id: JobId,
+    text: String,
+    done: bool,
+}
+
+impl Drawable for JobLine {
+
+    fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    fn get_color(&self) -> Option<&str> {
+        self.done.then_some("darkgray")
+    }
+}
+
+// Lists the background jobs, from the newest one, so they can be cancelled.
+pub struct JobsWidget {
+    jobs: ListWidget<JobLine>,
+}
+
+impl super::MinSize for JobsWidget {
+
+    // The borders and one job.
+    fn min_size(&self) -> (u16, u16) {
+        (40, 3)
+    }
+}
+
+impl Default for JobsWidget {
+
+    fn default() -> Self {
+        Self { jobs: ListWidget::empty("Background jobs") }
+    }
+}
+
+impl JobsWidget {
+
+    /// Shows the current state of the jobs, keeping the selection where it was.
+    pub fn update(&mut self, jobs: Vec<JobInfo>, paused: bool) {
+
+        let selected = self.jobs.get_selected();
+        let lines: Vec<JobLine> = jobs.into_iter()
+            .map(|job| {
+                let status = if job.paused && !job.status.is_done() { "paused" } else { job.status.name() };
+                let mut text = format!("[{status}] {}", job.name);
+                if let Some(progress) = job.progress {
+                    text.push_str(&format!(" - {progress}"));
+                }
+                JobLine { id: job.id, text, done: job.status.is_done() }
+            })
+            .collect();
+
+        let title = match (lines.is_empty(), paused) {
+            (true, _) => "Background jobs - nothing to do",
+            (false, true) => "Background jobs - paused (press z to resume, c to cancel, Enter for details)",
+            (false, false) => "Background jobs (press z to pause them, c to cancel, Enter for details)",
+        };
+        let len = lines.len();
+        self.jobs = ListWidget::with_items(title, lines);
+        if let Some(selected) = selected.filter(|_| len > 0) {
+            self.jobs.select_ind(selected.min(len - 1));
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.jobs.next();
+    }
+
+    pub fn previous(&mut self) {
+        self.jobs.previous();
+    }
+
+    pub fn get_selected(&self) -> Option<JobId> {
+        self.jobs.get_selected().map(|ind| self.jobs.get_ind(ind).id)
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        self.jobs.draw(frame, area);
+    }
+}
@@ -1,25 +1,103 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::str::FromStr;
+
 use listui_lib::models::Drawable;
 use ratatui::style::{Style, Color, Modifier};
-use ratatui::text::Span;
-use ratatui::widgets::{ListState, ListItem, List};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::block::Title;
+use ratatui::widgets::{ListState, ListItem, List, Scrollbar, ScrollbarOrientation, ScrollbarState};
 use ratatui::Frame;
-use ratatui::layout::Rect;
+use ratatui::layout::{Alignment, Margin, Rect};
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, Rng};
+
+/// What the items of a `ListWidget` are sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SortKey {
+    /// The order of the items, like the order of the tracks in their playlist.
+    #[default]
+    Original,
+    /// Title A-Z.
+    Title,
+    /// The shortest first.
+    Duration,
+    /// The last added first.
+    Added,
+    /// The last uploaded first, like a podcast feed.
+    Uploaded,
+    /// The most played first.
+    PlayCount,
+}
+
+impl SortKey {
+    const ALL: [SortKey; 6] = [SortKey::Original, SortKey::Title, SortKey::Duration, SortKey::Added, SortKey::Uploaded, SortKey::PlayCount];
+
+    /// The name it's saved with.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortKey::Original => "original",
+            SortKey::Title => "title",
+            SortKey::Duration => "duration",
+            SortKey::Added => "added",
+            SortKey::Uploaded => "uploaded",
+            SortKey::PlayCount => "plays",
+        }
+    }
+
+    // Shown after the title of the list.
+    fn label(&self) -> &'static str {
+        match self {
+            SortKey::Original => "",
+            SortKey::Title => "title A-Z",
+            SortKey::Duration => "duration",
+            SortKey::Added => "date added",
+            SortKey::Uploaded => "newest upload",
+            SortKey::PlayCount => "play count",
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SortKey::ALL
+            .into_iter()
+            .find(|key| key.name() == s.trim())
+            .ok_or_else(|| format!("unknown sort key \"{}\"", s.trim()))
+    }
+}
 
 // Generic list widget, that support drawing a filtered view of itself.
 // The filtering is only computed when the search query changes.
 pub struct ListWidget<T: Drawable> {
 
     title: String,
+    accent: Color,
     state: ListState,
     items: Vec<T>,
     
     shuffled: bool,
     ordered_items: Vec<usize>,
     last_query: Option<String>,
+    // The items the search found, by their position in `items`, so they are found again in a new order.
+    found: HashSet<usize>,
     filtered_indexes: Vec<usize>,
-    filter_state: ListState
+    filter_state: ListState,
+    // Positions in `items` of the marked items, so they stay marked after shuffling.
+    marked: HashSet<usize>,
+    sort: SortKey,
+    // Where the list was last drawn, to move a page at a time and find the item under the mouse.
+    area: Rect,
+}
+
+impl<T: Drawable> super::MinSize for ListWidget<T> {
+
+    // The borders and one item.
+    fn min_size(&self) -> (u16, u16) {
+        (20, 3)
+    }
 }
 
 impl<T: Drawable> ListWidget<T> {
@@ -28,13 +106,18 @@ impl<T: Drawable> ListWidget<T> {
 
         Self {
             title: String::from(title),
+            accent: super::ACC_COLOR,
             state: ListState::default(),
             items: Vec::new(),
             shuffled: false,
             ordered_items: Vec::new(),
             last_query: None,
+            found: HashSet::new(),
             filtered_indexes: Vec::new(),
             filter_state: ListState::default(),
+            marked: HashSet::new(),
+            sort: SortKey::Original,
+            area: Rect::default(),
         }
     }
     
@@ -42,17 +125,28 @@ impl<T: Drawable> ListWidget<T> {
 
         Self {
             title: String::from(title),
+            accent: super::ACC_COLOR,
             state: ListState::default(),
             
             ordered_items: (0..items.len()).collect(),
             shuffled: false,
             items,
             last_query: None,
+            found: HashSet::new(),
             filtered_indexes: Vec::new(),
             filter_state: ListState::default(),
+            marked: HashSet::new(),
+            sort: SortKey::Original,
+            area: Rect::default(),
         }
     }
 
+    /// Sorts the items by `sort`, like `set_sort` does.
+    pub fn sorted_by(mut self, sort: SortKey) -> Self {
+        self.set_sort(sort);
+        self
+    }
+
     pub fn get_selected(&self) -> Option<usize> {
 
         if self.is_filtered() {
@@ -96,6 +190,56 @@ impl<T: Drawable> ListWidget<T> {
         }
     }
 
+    /// Selects the first item.
+    pub fn first(&mut self) {
+        self.select_ind(0);
+    }
+
+    /// Selects the last item.
+    pub fn last(&mut self) {
+        if let Some(last) = self.shown_len().checked_sub(1) {
+            self.select_ind(last);
+        }
+    }
+
+    /// Moves the selection down as many items as fit in the list, stopping at the last one.
+    pub fn page_down(&mut self) {
+        let selected = if self.is_filtered() { self.filter_state.selected() } else { self.state.selected() };
+        let ind = selected.map_or(0, |ind| ind + self.page_height());
+        self.select_ind(ind.min(self.shown_len().saturating_sub(1)));
+    }
+
+    /// Moves the selection up as many items as fit in the list, stopping at the first one.
+    pub fn page_up(&mut self) {
+        let selected = if self.is_filtered() { self.filter_state.selected() } else { self.state.selected() };
+        self.select_ind(selected.map_or(0, |ind| ind.saturating_sub(self.page_height())));
+    }
+
+    // How many items fit in the list, inside its borders.
+    fn page_height(&self) -> usize {
+        (self.area.height.saturating_sub(2) as usize).max(1)
+    }
+
+    /// Selects the item drawn at a position of the screen, like the one clicked. Returns false if
+    /// there's no item there.
+    pub fn select_at(&mut self, column: u16, row: u16) -> bool {
+
+        let inside = column > self.area.left() && column + 1 < self.area.right()
+            && row > self.area.top() && row + 1 < self.area.bottom();
+        let offset = if self.is_filtered() { self.filter_state.offset() } else { self.state.offset() };
+        let ind = offset + row.saturating_sub(self.area.top() + 1) as usize;
+        if !inside || ind >= self.shown_len() {
+            return false;
+        }
+        self.select_ind(ind);
+        true
+    }
+
+    // How many items are shown, with the filter if there's one.
+    fn shown_len(&self) -> usize {
+        if self.is_filtered() { self.filtered_indexes.len() } else { self.items.len() }
+    }
+
     pub fn select_ind(&mut self, ind: usize) {
 
         let filtered = self.is_filtered();
@@ -109,6 +253,14 @@ impl<T: Drawable> ListWidget<T> {
 
     pub fn filter(&mut self, query: &str) {
 
+        let lowercase = query.to_lowercase();
+        self.filter_with(query, |item| item.get_text().to_ascii_lowercase().contains(&lowercase));
+    }
+
+    /// Like `filter`, but the items shown for `query` are the ones `keep` takes, like the tracks
+    /// the database found.
+    pub fn filter_with(&mut self, query: &str, keep: impl Fn(&T) -> bool) {
+
         // self.last_query cannot be none is self.filtered is true
         // so using unwrap shuold be safe here.
         //
@@ -116,17 +268,54 @@ impl<T: Drawable> ListWidget<T> {
         let query = query.to_lowercase();
         if !self.is_filtered() || self.last_query.as_ref().unwrap() != &query {
 
-            self.filtered_indexes = self.ordered_items.iter()
-                .enumerate()
-                .filter(|(_, i)| self.items[**i].get_text().to_ascii_lowercase().contains(&query))
-                .map(|(ind, _)| ind)
-                .collect();
-
-            self.filter_state = ListState::default();
+            self.found = (0..self.items.len()).filter(|i| keep(&self.items[*i])).collect();
             self.last_query = Some(query);
+            self.show_found();
         }    
     }
 
+    // Shows the items the search found in the current order, from the top.
+    fn show_found(&mut self) {
+
+        self.filtered_indexes = self.ordered_items.iter()
+            .enumerate()
+            .filter(|(_, i)| self.found.contains(*i))
+            .map(|(ind, _)| ind)
+            .collect();
+        self.filter_state = ListState::default();
+    }
+
+    /// Marks the selected item, or unmarks it if it already was.
+    pub fn toggle_mark(&mut self) {
+
+        if let Some(ind) = self.get_selected() {
+            let item = self.ordered_items[ind];
+            if !self.marked.remove(&item) {
+                self.marked.insert(item);
+            }
+        }
+    }
+
+    /// The indexes of the marked items, like the ones `get_ind` takes, in the order they are drawn.
+    pub fn marked(&self) -> Vec<usize> {
+        (0..self.ordered_items.len())
+            .filter(|ind| self.marked.contains(&self.ordered_items[*ind]))
+            .collect()
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.title = String::from(title);
+    }
+
+    /// Changes the color of the borders and the selected item.
+    pub fn set_accent(&mut self, accent: Color) {
+        self.accent = accent;
+    }
+
     pub fn clear_filter(&mut self) {
         self.last_query = None;
     }
@@ -136,85 +325,263 @@ impl<T: Drawable> ListWidget<T> {
     }
 
     pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
-        
-        if self.is_filtered() { self.draw_filtered(frame, area); }
-        else { self.draw_all(frame, area); }
+        self.draw_with_badges(frame, area, |_| None);
     }
 
-    fn draw_all(&mut self, frame: &mut Frame, area: Rect) {
+    /// Draws the items with the badge `badge` gives each of them, like whether a track is downloaded,
+    /// before their number and icon.
+    pub fn draw_with_badges(&mut self, frame: &mut Frame, area: Rect, badge: impl Fn(&T) -> Option<Span<'static>>) {
+
+        self.area = area;
+        if self.is_filtered() { self.draw_filtered(frame, area, &badge); }
+        else { self.draw_all(frame, area, &badge); }
+    }
+
+    fn draw_all(&mut self, frame: &mut Frame, area: Rect, badge: &dyn Fn(&T) -> Option<Span<'static>>) {
 
         let items: Vec<ListItem> = self.ordered_items
             .iter()
             .map(|i| {
-                let lines = Span::from(self.items[*i].get_text());
-                ListItem::new(lines).style(Style::default())
+                let item = &self.items[*i];
+                ListItem::new(item_line(item, self.marked.contains(i), self.accent, badge(item))).style(Style::default())
             })
             .collect();
         
+        let mut title = self.title.clone();
+        if self.shuffled {
+            title.push_str(" ⤨  ");
+        }
+        if self.sort != SortKey::Original && !self.shuffled {
+            title.push_str(&format!(" (by {})", self.sort.label()));
+        }
+        if !self.marked.is_empty() {
+            title.push_str(&format!(" ({} marked)", self.marked.len()));
+        }
+        let len = items.len();
         let list = List::new(items)
-            .block(super::BLOCK.clone().title(Span::styled(self.title.as_str(), Style::default().add_modifier(Modifier::BOLD))))
+            .block(super::BLOCK.clone().border_style(Style::default().fg(self.accent))
+                .title(Span::styled(title, Style::default().add_modifier(Modifier::BOLD)))
+                .title(position_title(self.state.selected(), len)))
             .highlight_style(
                 Style::default()
                     .fg(Color::Black)
-                    .bg(super::ACC_COLOR)
+                    .bg(self.accent)
                     .add_modifier(Modifier::BOLD),
             )
             .highlight_symbol(">> ");
 
         frame.render_stateful_widget(list, area, &mut self.state);
+        draw_scrollbar(frame, area, self.state.selected(), len);
     }
 
-    fn draw_filtered(&mut self, frame: &mut Frame, area: Rect)  {
+    fn draw_filtered(&mut self, frame: &mut Frame, area: Rect, badge: &dyn Fn(&T) -> Option<Span<'static>>)  {
 
         let filtered: Vec<ListItem> = self.filtered_indexes
             .iter()
             .map(|ind| {
 
-                let lines = Span::from(self.items[self.ordered_items[*ind]].get_text());
-                ListItem::new(lines).style(Style::default())
+                let item = self.ordered_items[*ind];
+                let badge = badge(&self.items[item]);
+                ListItem::new(item_line(&self.items[item], self.marked.contains(&item), self.accent, badge)).style(Style::default())
             })
             .collect();
         
         let title = format!(" ≫  Search: {} ", self.last_query.as_ref().expect("No query to search."));
+        let len = filtered.len();
         let list = List::new(filtered)
-            .block(super::BLOCK.clone().title(title.as_str()))
+            .block(super::BLOCK.clone().border_style(Style::default().fg(self.accent))
+                .title(title.as_str())
+                .title(position_title(self.filter_state.selected(), len)))
             .highlight_style(
                 Style::default()
                     .fg(Color::Black)
-                    .bg(super::ACC_COLOR)
+                    .bg(self.accent)
                     .add_modifier(Modifier::BOLD),
             )
             .highlight_symbol(">> ");
 
         frame.render_stateful_widget(list, area, &mut self.filter_state);
+        draw_scrollbar(frame, area, self.filter_state.selected(), len);
     }
 
     pub fn get_ind(&self, ind: usize) -> &T {
         &self.items[self.ordered_items[ind]]
     }
 
+    pub fn get_ind_mut(&mut self, ind: usize) -> &mut T {
+        &mut self.items[self.ordered_items[ind]]
+    }
+
     pub fn total_len(&self) -> usize {
         self.items.len()
     }
 
+    /// Every item, in their original order.
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffled
+    }
+
+    /// Position of the first item for which `pred` is true.
+    pub fn position(&self, pred: impl Fn(&T) -> bool) -> Option<usize> {
+        self.ordered_items.iter().position(|i| pred(&self.items[*i]))
+    }
+
+    /// Swaps two items, and keeps the selection on the one that was selected.
+    pub fn swap(&mut self, ind: usize, other: usize) {
+
+        let (first, second) = (self.ordered_items[ind], self.ordered_items[other]);
+        self.items.swap(first, second);
+        // The marks go with the items.
+        if self.marked.contains(&first) != self.marked.contains(&second) {
+            for item in [first, second] {
+                if !self.marked.remove(&item) {
+                    self.marked.insert(item);
+                }
+            }
+        }
+        match self.state.selected() {
+            Some(selected) if selected == ind => self.state.select(Some(other)),
+            Some(selected) if selected == other => self.state.select(Some(ind)),
+            _ => {}
+        }
+    }
+
+    pub fn sort_key(&self) -> SortKey {
+        self.sort
+    }
+
+    /// Sorts the items, turning off shuffle. The selected item stays selected.
+    pub fn set_sort(&mut self, sort: SortKey) {
+
+        if self.shuffled {
+            self.toggle_shuffle();
+        }
+        let selected = self.state.selected().map(|ind| self.ordered_items[ind]);
+        self.sort = sort;
+        self.sort_items();
+        self.state.select(selected.and_then(|item| self.ordered_items.iter().position(|i| *i == item)));
+
+        // The results of the search are positions in the old order.
+        if self.is_filtered() {
+            self.show_found();
+        }
+    }
+
+    /// Sorts by the next key, skipping the ones none of the items has a value for. Returns the new key.
+    pub fn cycle_sort(&mut self) -> SortKey {
+
+        let current = SortKey::ALL.iter().position(|key| *key == self.sort).unwrap_or(0);
+        let next = (1..=SortKey::ALL.len())
+            .map(|offset| SortKey::ALL[(current + offset) % SortKey::ALL.len()])
+            .find(|key| match key {
+                SortKey::Original | SortKey::Title => true,
+                SortKey::Duration => self.items.iter().any(|item| item.get_duration().is_some()),
+                SortKey::Added => self.items.iter().any(|item| item.get_added().is_some()),
+                SortKey::Uploaded => self.items.iter().any(|item| item.get_uploaded().is_some()),
+                SortKey::PlayCount => self.items.iter().any(|item| item.get_play_count().is_some_and(|count| count > 0)),
+            })
+            .unwrap_or_default();
+        self.set_sort(next);
+        next
+    }
+
+    // Items without a value for the key go last, in their original order.
+    fn sort_items(&mut self) {
+
+        let items = &self.items;
+        self.ordered_items = (0..items.len()).collect();
+        match self.sort {
+            SortKey::Original => {},
+            SortKey::Title => self.ordered_items.sort_by_cached_key(|i| items[*i].get_text().to_lowercase()),
+            SortKey::Duration => self.ordered_items.sort_by_key(|i| (items[*i].get_duration().is_none(), items[*i].get_duration())),
+            SortKey::Added => self.ordered_items.sort_by_key(|i| Reverse(items[*i].get_added())),
+            SortKey::Uploaded => self.ordered_items.sort_by_key(|i| Reverse(items[*i].get_uploaded())),
+            SortKey::PlayCount => self.ordered_items.sort_by_key(|i| Reverse(items[*i].get_play_count())),
+        }
+    }
+
     pub fn toggle_shuffle(&mut self) {
         
 
         if self.shuffled {
-            self.ordered_items = (0..self.items.len()).collect();
+            self.sort_items();
             self.state = ListState::default();
             self.shuffled = false;
-            self.title.pop();
-            self.title.pop();
-            self.title.pop();
-            self.title.pop();
         }
         else {
             let mut rng = thread_rng();
             self.ordered_items.shuffle(&mut rng);
             self.state = ListState::default();
             self.shuffled = true;
-            self.title.push_str(" ⤨  ");
         }   
     }
+
+    /// Shuffles the items again, once every one of them has been played. The last one doesn't
+    /// come first, so no item is played twice in a row.
+    pub fn reshuffle(&mut self) {
+
+        let Some(&last) = self.ordered_items.last() else { return };
+        let mut rng = thread_rng();
+        self.ordered_items.shuffle(&mut rng);
+        if self.ordered_items.len() > 1 && self.ordered_items[0] == last {
+            let other = rng.gen_range(1..self.ordered_items.len());
+            self.ordered_items.swap(0, other);
+        }
+        self.state = ListState::default();
+        if self.is_filtered() {
+            self.show_found();
+        }
+    }
+}
+
+// Where the selection is, like "123/1500", on the right of the top border.
+fn position_title(selected: Option<usize>, len: usize) -> Title<'static> {
+
+    let position = match selected {
+        Some(ind) => format!(" {}/{len} ", ind + 1),
+        None => format!(" -/{len} "),
+    };
+    Title::from(position).alignment(Alignment::Right)
+}
+
+// Draws a scrollbar over the right border, only if the items don't fit in the list.
+fn draw_scrollbar(frame: &mut Frame, area: Rect, selected: Option<usize>, len: usize) {
+
+    let visible = area.height.saturating_sub(2) as usize;
+    if len <= visible {
+        return;
+    }
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None);
+    let mut state = ScrollbarState::new(len).position(selected.unwrap_or(0));
+    frame.render_stateful_widget(scrollbar, area.inner(&Margin { vertical: 1, horizontal: 0 }), &mut state);
+}
+
+// The text of an item, after its mark, badge and emoji, and with its color.
+fn item_line<'a, T: Drawable>(item: &'a T, marked: bool, accent: Color, badge: Option<Span<'static>>) -> Line<'a> {
+
+    let style = item.get_color().and_then(super::parse_color)
+        .map(|color| Style::default().fg(color))
+        .unwrap_or_default();
+
+    let mut spans = Vec::new();
+    if marked {
+        spans.push(Span::styled("✔ ", Style::default().fg(accent)));
+    }
+    if let Some(badge) = badge {
+        spans.push(badge);
+    }
+    if let Some(number) = item.get_number() {
+        spans.push(Span::raw(format!("{number:>2}. ")));
+    }
+    if let Some(icon) = item.get_icon() {
+        spans.push(Span::raw(format!("{icon} ")));
+    }
+    spans.push(Span::styled(item.get_text(), style));
+    Line::from(spans)
 }
@@ -1,7 +1,9 @@
+use listui_lib::api::{FetchProgress, FetchSource};
 use ratatui::Frame;
 use ratatui::layout::{Rect, Layout, Constraint, Alignment};
-use ratatui::style::Style;
-use ratatui::widgets::Paragraph;
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Gauge, Paragraph};
 
 
 static FIGURE: &str  = 
@@ -19,9 +21,18 @@ r"
 ;;;;;;            ;;;;;;      
 `;;;;'            `;;;;'      ";
 
+/// The most sources listed under the label, the last ones tried.
+const MAX_SOURCES: usize = 5;
+
 pub struct LoadingWidget {
     label: String,
     frame: u16,
+    // The videos fetched so far, and how many there are, if it's known.
+    fetched: Option<(usize, Option<usize>)>,
+    // Every source tried, and whether it failed.
+    sources: Vec<(FetchSource, bool)>,
+    // The last page fetched from the current source, and how many it should have.
+    page: Option<(usize, usize)>,
 }
 
 impl LoadingWidget {
@@ -30,7 +41,10 @@ impl LoadingWidget {
 
         Self {
             label: label.to_string(),
-            frame: 0
+            frame: 0,
+            fetched: None,
+            sources: Vec::new(),
+            page: None
         }
     }
 
@@ -38,24 +52,100 @@ impl LoadingWidget {
         self.label = label;
     }
 
+    /// Shows what the client is fetching: the label says what it's doing now, and the
+    /// fetched videos and the sources tried are drawn below it.
+    pub fn show_progress(&mut self, progress: FetchProgress) {
+
+        match &progress {
+            FetchProgress::Trying { source, .. } => {
+                // Another source starts from scratch.
+                self.fetched = None;
+                self.page = None;
+                self.sources.push((source.clone(), false));
+            },
+            FetchProgress::Failed { source, .. } => {
+                if let Some(tried) = self.sources.iter_mut().rev().find(|(tried, _)| tried == source) {
+                    tried.1 = true;
+                }
+            },
+            FetchProgress::Page { videos, total } => self.fetched = Some((*videos, *total)),
+            // Shown next to the source, the label still says how many videos there are.
+            FetchProgress::PageOf { page, pages } => {
+                self.page = Some((*page, *pages));
+                return;
+            },
+            FetchProgress::Checking { .. } | FetchProgress::Searching { .. } => ()
+        }
+        self.label = progress.to_string();
+    }
+
     pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
         
-        if area.height < 20 {
+        if area.height < super::FIGURE_SCREEN_HEIGHT {
             frame.render_widget(Paragraph::new(self.label.as_str()).style(Style::default().fg(super::ACC_COLOR)).alignment(Alignment::Center), area);  
         }
         else {
             let h = if self.frame < 4 { self.frame } else { 8 - self.frame };
+            let sources = &self.sources[self.sources.len().saturating_sub(MAX_SOURCES)..];
+            let skippable = self.skippable();
             let chunks = Layout::default()
                 .direction(ratatui::layout::Direction::Vertical)
-                .constraints([Constraint::Length(1 + h), Constraint::Length(area.height - 1 - h)])
+                .constraints([
+                    Constraint::Length(1 + h),
+                    Constraint::Length(self.fetched.map_or(0, |_| 1)),
+                    Constraint::Length(sources.len() as u16),
+                    Constraint::Length(skippable as u16),
+                    Constraint::Min(0)
+                ])
                 .margin(1)
                 .split(area);
             
             self.frame = (self.frame + 1) % 8;
             frame.render_widget(Paragraph::new(self.label.as_str()).style(Style::default().fg(super::ACC_COLOR)).alignment(Alignment::Center), chunks[0]);
-            frame.render_widget(Paragraph::new(FIGURE).style(Style::default().fg(super::ACC_COLOR)).alignment(Alignment::Center), chunks[1]); 
+            if let Some((videos, total)) = self.fetched {
+                self.draw_fetched(frame, chunks[1], videos, total);
+            }
+            let lines: Vec<Line> = sources.iter()
+                .enumerate()
+                .map(|(ind, (source, failed))| {
+                    let (mark, color) = if *failed { ("✗", Color::Red) } else { ("…", super::ACC_COLOR) };
+                    let mut line = format!("{mark} {source}");
+                    // The page is only known for the source being used.
+                    if let (Some((page, pages)), true) = (self.page, ind + 1 == sources.len() && !failed) {
+                        line.push_str(&format!(" - page {page} of {pages}"));
+                    }
+                    Line::styled(line, Style::default().fg(color))
+                })
+                .collect();
+            frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center), chunks[2]);
+            if skippable {
+                frame.render_widget(Paragraph::new("Press n to skip to the next instance").style(Style::default().fg(Color::DarkGray)).alignment(Alignment::Center), chunks[3]);
+            }
+            frame.render_widget(Paragraph::new(FIGURE).style(Style::default().fg(super::ACC_COLOR)).alignment(Alignment::Center), chunks[4]); 
         }
     }
+
+    // Whether the source being used is an instance, that can be skipped if it's too slow.
+    fn skippable(&self) -> bool {
+        matches!(self.sources.last(), Some((FetchSource::Invidious(_) | FetchSource::Piped(_), false)))
+    }
+
+    // A progress bar if the total is known, or just the count otherwise.
+    fn draw_fetched(&self, frame: &mut Frame, area: Rect, videos: usize, total: Option<usize>) {
+
+        let Some(total) = total.filter(|total| *total > 0) else {
+            frame.render_widget(Paragraph::new(format!("{videos} videos")).style(Style::default().fg(super::ACC_COLOR)).alignment(Alignment::Center), area);
+            return;
+        };
+
+        let width = area.width.min(40);
+        let bar = Rect::new(area.x + (area.width - width) / 2, area.y, width, area.height);
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(super::ACC_COLOR))
+            .ratio((videos as f64 / total as f64).min(1.0))
+            .label(format!("{videos}/{total}"));
+        frame.render_widget(gauge, bar);
+    }
 }
 
 
@@ -1,12 +1,23 @@
 pub mod list;
 pub mod player;
 pub mod loading;
+pub mod input;
+pub mod diagnostics;
+pub mod jobs;
+pub mod batch;
+pub mod artwork;
+pub mod now_playing;
+pub mod visualizer;
+pub mod status_bar;
+pub mod osd;
 
-use ratatui::style::{Style, Color,};
-use ratatui::widgets::{Paragraph, Block, Borders,BorderType};
+use ratatui::style::{Style, Color, Modifier};
+use ratatui::widgets::{Paragraph, Block, Borders,BorderType, Clear, Wrap};
 use ratatui::Frame;
 use ratatui::layout::{Rect, Alignment, Layout, Constraint};
 use lazy_static::lazy_static;
+use listui_lib::models::Playlist;
+use listui_lib::stats::utc_time;
 
 
 static LOGO: &str =
@@ -42,22 +53,122 @@ Playlists menu:
 
     ↵    play.
     ↑/↓  select.
-    U    update playlist.
+    PgUp/PgDn  move a page up/down (Home/End or Shift+G for the first/last playlist).
+    U    update playlist (Shift+U to fetch every page again, skipping the cache).
+         While it's fetched, N skips the Invidious or Piped instance if it's too slow.
+    Shift+A  update every playlist at once, showing how each one goes.
+    G    download every track of the playlist, to listen offline.
     D    delete playlist (Does not delete files from disk).
+    A    add a YouTube playlist, video, channel or Bandcamp album.
+    P    subscribe to a podcast (RSS feed).
+    C    create a new playlist.
+    T    set the color and emoji of a playlist.
+    R    rename a playlist.
+    I    open a .m3u/.pls file (Shift+I to import a text file of URLs).
+    L    import a playlist from your Subsonic or Jellyfin server.
+    W    new videos from your channels this week.
+    E    export playlist to .m3u (Shift+E for .json).
+    X    videos to leave out of the playlist when it's updated, by title (a regex), length or channel,
+         like \"title=live|remix; max=15:00; channel=Some Channel\".
+    O    audio output diagnostics (Shift+O to sort the playlists by title or date added).
+    V    see what changed in the new version of lisTUI, if there is one.
+    J    background jobs, like playlist downloads. Press C there to cancel one, or Enter to see
+         how each of its tracks went, and pause or cancel just that one.
+    Z    pause the background jobs (downloads, update checks, webhook), or resume them.
+    Shift+Y  download the latest yt-dlp into lisTUI's data directory, when downloads fail.
+    Shift+B  back up the database into DOWNLOAD_DIR, and check it for damage.
+    Shift+V  verify the downloads of the playlist. The damaged ones are downloaded again.
+    Shift+L  turn the DOWNLOAD_RATE_LIMIT off, or back on (also in the tracks menu).
     Q    quit.
 
 Tracks menu:
 
     ↵    play.                          N    play next.
     ↑/↓  select.                        B    play previous.
-    ←/→  jump 5s.                       B    play previous.
+    ←/→  jump 5s.                       G    go to the first track (Shift+G the last).
+    PgUp/PgDn  move a page up/down (Home/End go to the first/last track).
     +/-  volume up/down.                S    search.
-    F    follow mode.                   R    toffle shuffle.
+    F    follow mode.                   R    toggle shuffle.
+    A    add to one of your playlists.  X    remove from this playlist.
+    I    add a local file after the track.
+    D    download the track (Shift+D deletes its download, to download it again).
+    Shift+L    turn the DOWNLOAD_RATE_LIMIT off, or back on.
+    T    set where the track starts and stops playing, like 0:30-3:45, to skip its intro.
+    V    now playing: the track in big, with what comes after it.
+    Space      mark the track, to add, remove, download or move
+               every marked track at once (Esc unmarks them).
+    Shift+K/J  move the track up/down.
+    Shift+O    sort by title, duration, date added, newest upload or play
+               count, or go back to the playlist's order. Each playlist remembers it.
+    O    audio output diagnostics.
     Q    go back to last screen.    
     
 
+Press N to see what's new in this version, or any other key to close this screen.";
+
+// Shown once after upgrading, and from the controls screen.
+static WHATS_NEW: &str =
+"\
+New in the playlists menu:
+
+    A    add YouTube videos, channels and Bandcamp albums too, not only playlists.
+    C    create an empty playlist. Press A on any track to add it there, and X to remove it.
+    T    give a playlist its own color and emoji.
+    R    rename a playlist, like the long ones imported from YouTube.
+    P    subscribe to a podcast.
+    L    import playlists from your Subsonic or Jellyfin server.
+    W    videos uploaded this week by the channels you added.
+    G    download a whole playlist, to listen offline.
+    E    export a playlist to .m3u (Shift+E for .json).
+    I    open a .m3u/.pls file (Shift+I to import a text file of URLs).
+    O    audio output diagnostics (also in the tracks menu).
+    J    see what lisTUI is doing in the background, and cancel it.
+    X    leave the live versions, long compilations or some channels out of a playlist.
+    Z    pause everything lisTUI does in the background, when you need the bandwidth.
+    Shift+Y  download the latest yt-dlp, which is used instead of yours from then on.
+
+Also new:
+
+    - The download shows its progress, and skipping a track cancels it.
+    - Videos that are no longer available can be replaced by another upload.
+    - Press Shift+K/J in the tracks menu to move a track up or down. The new order is saved.
+    - Click a playlist or track to select it, and double click it to open or play it. Click the progress bar to seek.
+    - Move a page at a time in any list with PgUp/PgDn, and to the top or bottom with G/Shift+G (or Home/End).
+      In the playlists menu G still downloads the playlist, so Home goes to the top there.
+    - The loading screen shows which instance a playlist is fetched from, and which page. Press N to skip a slow one.
+    - Press Shift+O to sort the tracks by title, duration, date added, newest upload or play count.
+    - Press V in the tracks menu for a full screen view of the track being played, to keep on a second monitor.
+    - Press T in the tracks menu to skip the intro or outro of a track every time it's played.
+    - Press I in the tracks menu to add a file of yours after the selected track, in any playlist.
+    - Press Space in the tracks menu to mark tracks, and then A, X, D or Shift+K/J to add, remove, download or move all of them.
+    - Downloads can be opus or m4a, see DOWNLOAD_FORMAT in the README.
+    - Invalid lines of the config file are reported. Run `listui config show --resolved` to check your settings.
+    - Start with `listui --safe-mode` if a setting breaks something.
+    - Press Enter on a download in the background jobs (J) to see how each track went, and pause or cancel just that one.
+
 Press any key to close this screen.";
 
+/// Area a widget needs to be drawn properly, as (width, height).
+pub trait MinSize {
+    fn min_size(&self) -> (u16, u16);
+}
+
+// Size of the logo, with some margin around it.
+pub const LOGO_SIZE: (u16, u16) = (50, 10);
+
+// The figures of the loading and error screens are only drawn if there is this much height.
+pub const FIGURE_SCREEN_HEIGHT: u16 = 20;
+
+// Parses a color name ("red", "lightgreen"...), hex code ("#ff8800") or 256 color index.
+pub fn parse_color(color: &str) -> Option<Color> {
+    color.parse::<Color>().ok().filter(|color| *color != Color::Reset)
+}
+
+// Returns true if an area of size `min` fits inside `area`.
+pub fn fits(area: Rect, min: (u16, u16)) -> bool {
+    area.width >= min.0 && area.height >= min.1
+}
+
 // Accent color.
 pub const ACC_COLOR: Color = Color::LightBlue;
 lazy_static! {
@@ -81,9 +192,18 @@ pub fn draw_controls_screen(frame: &mut Frame, area: Rect) {
     frame.render_widget(p, area);
 }
 
+pub fn draw_whats_new(frame: &mut Frame, area: Rect) {
+
+    let p = Paragraph::new(WHATS_NEW)
+        .block(BLOCK.clone().title(format!("What's new in lisTUI {}", env!("CARGO_PKG_VERSION"))))
+        .alignment(Alignment::Left);
+
+    frame.render_widget(p, area);
+}
+
 pub fn draw_error_msg(frame: &mut Frame, msg: &str) {
 
-    if frame.size().height < 20 {
+    if frame.size().height < FIGURE_SCREEN_HEIGHT {
         frame.render_widget(Paragraph::new(msg).style(Style::default().fg(Color::Red)).alignment(Alignment::Center), frame.size());  
     }
     else {
@@ -98,6 +218,49 @@ pub fn draw_error_msg(frame: &mut Frame, msg: &str) {
     }
 }
 
+// Shown instead of a screen that doesn't fit in the terminal.
+pub fn draw_size_hint(frame: &mut Frame, min: (u16, u16)) {
+
+    let msg = format!("Please make the terminal bigger :( (at least {}x{})", min.0, min.1);
+    let p = Paragraph::new(msg)
+        .style(Style::default().fg(Color::Red))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(p, frame.size());
+}
+
+pub fn draw_message(frame: &mut Frame, msg: &str) {
+
+    let area = frame.size();
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([Constraint::Length(area.height / 2), Constraint::Min(1)])
+        .margin(1)
+        .split(area);
+
+    frame.render_widget(Paragraph::new(msg).style(Style::default().fg(ACC_COLOR)).alignment(Alignment::Center), chunks[1]);
+}
+
+// Draws `text` over the top right corner of the screen, where only borders are drawn.
+pub fn draw_banner(frame: &mut Frame, text: &str) {
+
+    let area = frame.size();
+    let width = (text.chars().count() as u16 + 2).min(area.width.saturating_sub(2));
+    let banner = Rect::new(area.right().saturating_sub(width + 1), area.top(), width, 1.min(area.height));
+    let p = Paragraph::new(format!(" {text} "))
+        .style(Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD));
+
+    frame.render_widget(Clear, banner);
+    frame.render_widget(p, banner);
+}
+
+// Reverses the colors of everything drawn so far, for the flash notifications.
+pub fn draw_flash(frame: &mut Frame) {
+    let area = frame.size();
+    frame.buffer_mut().set_style(area, Style::default().add_modifier(Modifier::REVERSED));
+}
+
 pub fn draw_logo(frame: &mut Frame, area: Rect) {
 
     let p = Paragraph::new(LOGO)
@@ -105,4 +268,34 @@ pub fn draw_logo(frame: &mut Frame, area: Rect) {
         .style(Style::default().fg(ACC_COLOR));
 
     frame.render_widget(p, area);
-}
\ No newline at end of file
+}
+
+// Who made the selected playlist, when it was saved and synced, and its description, under the
+// playlists menu.
+pub fn draw_playlist_details(frame: &mut Frame, area: Rect, playlist: &Playlist) {
+
+    // Like 2026-10-15, in UTC.
+    let day = |time: i64| utc_time(time)[..10].to_string();
+    let mut lines = Vec::new();
+    if let Some(author) = &playlist.author {
+        lines.push(format!("By {author}"));
+    }
+    match (playlist.created_at, playlist.last_synced_at) {
+        (Some(created), Some(synced)) => lines.push(format!("Saved {}, synced {}", day(created), day(synced))),
+        (Some(created), None) => lines.push(format!("Saved {}", day(created))),
+        (None, Some(synced)) => lines.push(format!("Synced {}", day(synced))),
+        (None, None) => {}
+    }
+    if let Some(url) = &playlist.thumbnail_url {
+        lines.push(format!("Thumbnail: {url}"));
+    }
+    if let Some(description) = &playlist.description {
+        lines.push(description.clone());
+    }
+
+    let p = Paragraph::new(lines.join("\n"))
+        .block(BLOCK.clone().title(playlist.title.as_str()))
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(p, area);
+}
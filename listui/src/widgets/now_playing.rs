@@ -0,0 +1,118 @@
+use listui_lib::models::Track;
+use ratatui::Frame;
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Gauge, Paragraph, Wrap};
+
+use crate::utils;
+
+/// What the now playing screen shows, taken from the player and the songs list.
+pub struct NowPlaying<'a> {
+    pub track: Option<&'a Track>,
+    /// Seconds played and the length of the track, once it's playing.
+    pub progress: Option<(u64, u64)>,
+    pub paused: bool,
+    pub volume: i32,
+    pub shuffled: bool,
+    /// The next tracks, in the order they will be played.
+    pub upcoming: Vec<&'a Track>,
+}
+
+// The track being played, filling the screen so it can be read from afar.
+pub struct NowPlayingWidget {
+    accent: Color,
+}
+
+impl super::MinSize for NowPlayingWidget {
+
+    // The title, the artist, the progress bar and the indicators.
+    fn min_size(&self) -> (u16, u16) {
+        (40, 12)
+    }
+}
+
+impl Default for NowPlayingWidget {
+
+    fn default() -> Self {
+        Self { accent: super::ACC_COLOR }
+    }
+}
+
+impl NowPlayingWidget {
+
+    /// Changes the color of the borders and the progress bar.
+    pub fn set_accent(&mut self, accent: Color) {
+        self.accent = accent;
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect, now: &NowPlaying) {
+
+        let block = super::BLOCK.clone()
+            .border_style(Style::default().fg(self.accent))
+            .title("Now playing (press v or q to go back)");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Length(2),
+                Constraint::Length(2),
+                Constraint::Length(2),
+                Constraint::Min(0),
+            ])
+            .horizontal_margin(2)
+            .split(inner);
+
+        let Some(track) = now.track else {
+            frame.render_widget(Paragraph::new("Nothing is playing.").alignment(Alignment::Center), chunks[1]);
+            return;
+        };
+
+        let title = Paragraph::new(track.title.as_str())
+            .style(Style::default().fg(self.accent).add_modifier(Modifier::BOLD))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+        frame.render_widget(title, chunks[1]);
+
+        let by = match (&track.artist, &track.album) {
+            (Some(artist), Some(album)) => format!("{artist} - {album}"),
+            (Some(artist), None) => artist.clone(),
+            (None, Some(album)) => album.clone(),
+            (None, None) => String::new(),
+        };
+        frame.render_widget(Paragraph::new(by).alignment(Alignment::Center), chunks[2]);
+
+        let (label, ratio) = match now.progress {
+            Some((progress, duration)) if duration > 0 => {
+                (utils::time_str(progress as i32, duration as i32, now.paused), (progress as f64 / duration as f64).min(1.0))
+            }
+            _ => (String::from("Loading..."), 0.0),
+        };
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(self.accent))
+            .ratio(ratio)
+            .label(label);
+        frame.render_widget(gauge, Rect { height: 1, ..chunks[3] });
+
+        // Playlists always start over after their last track.
+        let indicators = format!(
+            "{}   Shuffle: {}   Repeat: playlist   Volume: {}%",
+            if now.paused { "▮▮ Paused" } else { "▶ Playing" },
+            if now.shuffled { "on" } else { "off" },
+            now.volume
+        );
+        frame.render_widget(Paragraph::new(indicators).alignment(Alignment::Center), chunks[4]);
+
+        if now.upcoming.is_empty() {
+            return;
+        }
+        let rows = chunks[5].height.saturating_sub(1) as usize;
+        let mut lines = vec![Line::styled("Up next:", Style::default().fg(self.accent))];
+        lines.extend(now.upcoming.iter().take(rows).map(|track| Line::from(track.title.as_str())));
+        frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center), chunks[5]);
+    }
+}
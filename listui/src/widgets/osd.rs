@@ -0,0 +1,131 @@
+use std::time::{Duration, Instant};
+
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols;
+use ratatui::widgets::{Borders, Clear, LineGauge, Paragraph};
+use ratatui::Frame;
+
+use super::{ACC_COLOR, BLOCK};
+
+/// Time the overlay stays on screen after the last change.
+pub const OSD_DURATION: Duration = Duration::from_secs(1);
+
+// Rows of the big characters, each one 3 cells wide (1 for the colon).
+const GLYPH_HEIGHT: u16 = 5;
+
+fn glyph(c: char) -> [&'static str; 5] {
+    match c {
+        '0' => ["███", "█ █", "█ █", "█ █", "███"],
+        '1' => ["  █", "  █", "  █", "  █", "  █"],
+        '2' => ["███", "  █", "███", "█  ", "███"],
+        '3' => ["███", "  █", "███", "  █", "███"],
+        '4' => ["█ █", "█ █", "███", "  █", "  █"],
+        '5' => ["███", "█  ", "███", "  █", "███"],
+        '6' => ["███", "█  ", "███", "█ █", "███"],
+        '7' => ["███", "  █", "  █", "  █", "  █"],
+        '8' => ["███", "█ █", "███", "█ █", "███"],
+        '9' => ["███", "█ █", "███", "  █", "███"],
+        '%' => ["█ █", "  █", " █ ", "█  ", "█ █"],
+        ':' => [" ", "█", " ", "█", " "],
+        _ => ["   ", "   ", "   ", "   ", "   "],
+    }
+}
+
+// The rows of `text` in big characters, a space apart.
+fn big_text(text: &str) -> Vec<String> {
+    (0..GLYPH_HEIGHT as usize)
+        .map(|row| text.chars().map(|c| glyph(c)[row]).collect::<Vec<&str>>().join(" "))
+        .collect()
+}
+
+/// A value that was just changed, like the volume or the position of the track, drawn big in
+/// the middle of the screen for a moment, over whatever is there.
+pub struct Osd {
+    // The value in big characters, the line under it, how full its bar is and when it was shown.
+    shown: Option<(String, String, f64, Instant)>,
+    accent: Color,
+}
+
+impl Default for Osd {
+    fn default() -> Self {
+        Self { shown: None, accent: ACC_COLOR }
+    }
+}
+
+impl Osd {
+
+    pub fn set_accent(&mut self, accent: Color) {
+        self.accent = accent;
+    }
+
+    /// Volume from 0 to 200%, although the bar is full at 100%.
+    pub fn volume(&mut self, volume: i32) {
+        self.show(format!("{volume}%"), format!("Volume {volume}%"), volume as f64 / 100.0);
+    }
+
+    /// Where the track is, in seconds, out of its `duration`.
+    pub fn seek(&mut self, position: u64, duration: u64) {
+        let ratio = if duration > 0 { position as f64 / duration as f64 } else { 0.0 };
+        self.show(clock(position, duration), format!("{} / {}", clock(position, duration), clock(duration, duration)), ratio);
+    }
+
+    fn show(&mut self, value: String, label: String, ratio: f64) {
+        self.shown = Some((value, label, ratio.clamp(0.0, 1.0), Instant::now()));
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.shown.as_ref().is_some_and(|(_, _, _, shown_at)| shown_at.elapsed() < OSD_DURATION)
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+
+        let Some((value, label, ratio, _)) = self.shown.as_ref().filter(|_| self.is_shown()) else { return };
+        let area = frame.size();
+        let big = big_text(value);
+        let big_width = big[0].chars().count() as u16;
+        let label_width = label.chars().count() as u16;
+
+        // On small terminals only the line with the bar is drawn.
+        let big_size = (big_width.max(label_width + 10) + 4, GLYPH_HEIGHT + 4);
+        let big_fits = area.width >= big_size.0 && area.height >= big_size.1;
+        let (width, height) = if big_fits {
+            big_size
+        } else {
+            ((label_width + 14).min(area.width), 3.min(area.height))
+        };
+        let osd = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let block = BLOCK.clone().borders(Borders::ALL).border_style(Style::default().fg(self.accent));
+        let inner = block.inner(osd);
+
+        frame.render_widget(Clear, osd);
+        frame.render_widget(block, osd);
+        if inner.height == 0 {
+            return;
+        }
+        if big_fits {
+            let p = Paragraph::new(big.join("\n"))
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(self.accent).add_modifier(Modifier::BOLD));
+            frame.render_widget(p, Rect { height: GLYPH_HEIGHT, y: inner.y + 1, ..inner });
+        }
+        let gauge = LineGauge::default()
+            .label(label.as_str())
+            .ratio(*ratio)
+            .line_set(symbols::line::THICK)
+            .gauge_style(Style::default().fg(self.accent));
+        frame.render_widget(gauge, Rect { y: inner.bottom() - 1, height: 1, ..inner });
+    }
+}
+
+// Like 03:45, or 1:02:03 if the track is an hour or longer.
+fn clock(seconds: u64, duration: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if duration >= 3600 { format!("{hours}:{minutes:02}:{seconds:02}") }
+    else { format!("{minutes:02}:{seconds:02}") }
+}
@@ -1,16 +1,21 @@
-use anyhow::Result;
 use std::sync::Arc;
 use std::path::{PathBuf, Path};
-use std::ffi::OsStr;
 use std::time::Duration;
 
-use listui_lib::downloader::DownloadResult;
-use listui_lib::{models::Track, player::Player, downloader::Downloader};
+use listui_lib::downloader::{DownloadProgress, DownloadResult};
+use listui_lib::{models::Track, player::{Player, PlayerError, PlayerInfo}, downloader::Downloader};
+use listui_lib::transcoder;
 use tokio::sync::MutexGuard;
 use tokio::{runtime, task::JoinHandle, sync::{Mutex, mpsc}, time::sleep};
-use ratatui::{Frame, layout::{Rect, Layout, Direction, Constraint}, widgets::{Gauge, Borders, Paragraph}, style::Style};
+use ratatui::{Frame, layout::{Rect, Layout, Direction, Constraint}, widgets::{Gauge, Borders, Paragraph}, style::{Color, Style}};
 
+use crate::events::AppEvent;
 use crate::utils;
+use super::visualizer::Visualizer;
+
+// Columns of the visualizer, one per band, when the player is wide enough. Narrower than this, it isn't drawn.
+const VISUALIZER_WIDTH: u16 = 48;
+const VISUALIZER_MIN_WIDTH: u16 = 8;
 
 
 #[derive(Debug, Default)]
@@ -18,33 +23,55 @@ struct PlayerData {
 
     current_track: Option<Track>,
     end_timer: Option<JoinHandle<()>>,
-    downloading: bool
+    downloading: bool,
+    // Progress of the current track's download, if yt-dlp reported any.
+    download_progress: Option<DownloadProgress>,
+    // Percentage of the current track that has been transcoded.
+    transcoding: Option<u8>
 }
 
 pub struct PlayerWidget {
 
-    downloader: Arc<Downloader>,
+    downloader: Arc<dyn Downloader>,
     data: Arc<Mutex<PlayerData>>,
     dir: PathBuf,
-    sender: mpsc::Sender<utils::Message>,
+    sender: mpsc::Sender<AppEvent>,
     runtime: Arc<runtime::Runtime>,
-    player: Arc<Player>
+    player: Arc<dyn Player>,
+    accent: Color,
+    // Where the progress bar was last drawn, to seek where it's clicked.
+    gauge_area: Rect
+}
+
+impl super::MinSize for PlayerWidget {
+
+    // The title line and the progress bar.
+    fn min_size(&self) -> (u16, u16) {
+        (25, 5)
+    }
 }
 
 impl PlayerWidget {
  
-    pub fn try_new(path: &Path, runtime: Arc<runtime::Runtime>, sender: mpsc::Sender<utils::Message>, max_downloads: usize) -> Result<Self> {
+    pub fn new(path: &Path, runtime: Arc<runtime::Runtime>, sender: mpsc::Sender<AppEvent>, player: Arc<dyn Player>, downloader: Arc<dyn Downloader>) -> Self {
         
-        Ok(Self {
-            downloader: Arc::new(Downloader::new(max_downloads)),
+        Self {
+            downloader,
             data: Arc::new(Mutex::new(PlayerData::default())),
             dir: path.to_path_buf(),
             sender,
             runtime,
-            player: Arc::new(Player::try_default()?)
-        })
+            player,
+            accent: super::ACC_COLOR,
+            gauge_area: Rect::default()
+        }
     }   
 
+    /// Changes the color of the borders and the progress bar.
+    pub fn set_accent(&mut self, accent: Color) {
+        self.accent = accent;
+    }
+
     pub fn play(&mut self, track: Track) {
         
         let mut player_data = self.data.blocking_lock();
@@ -57,50 +84,116 @@ impl PlayerWidget {
         }
  
         self.player.stop();
+        // The previous track won't be played anymore, so there's no point in downloading it.
+        self.cancel_download(&mut player_data);
         player_data.current_track.replace(track.clone());
         
         let player = Arc::clone(&self.player);
         let player_data = Arc::clone(&self.data);
-        let mut path = self.dir.clone();
+        let dir = self.dir.clone();
         let downloader = Arc::clone(&self.downloader);
         let sender = self.sender.clone();
         let runtime = Arc::clone(&self.runtime);
         self.runtime.spawn(async move {
-            
-            let mut filename = if track.yt_id.is_some() { track.title.replace(['/', '\\', ':', '*', '<', '>', '|', '\"'], "") }
-                else { track.title.clone()};
-
-            filename.push_str(".mp3");
-            path.push(OsStr::new(&filename));
-            if !path.exists() { 
-                let yt_id = track.yt_id.expect("No youtube id available.");
-                let mut guard = player_data.lock().await;
-                guard.downloading = true;
-                drop(guard);
-                let res = downloader.download_id(&yt_id, &path).await;
-                match res {
-                    None => return, // Another task is trying to play this track.
-                    Some(DownloadResult::Failed) => {
-                        sender.send(utils::Message::SongFinished).await.expect("Failed to send message.");
-                        return;
-                    },
-                    _ => {}
+
+            // A download that can't be played is deleted and downloaded again, once.
+            let mut downloaded_again = false;
+            loop {
+                let path = utils::get_track_path(&dir, &track);
+                if !path.exists() { 
+                    let source = match track.source().map(String::from) {
+                        Some(source) if !source.starts_with("file://") => source,
+                        _ => {
+                            log::error!("File {} not found.", path.display());
+                            sender.send(AppEvent::SongFinished).await.expect("Failed to send message.");
+                            return;
+                        }
+                    };
+                    let mut guard = player_data.lock().await;
+                    guard.downloading = true;
+                    guard.download_progress = None;
+                    drop(guard);
+
+                    let progress_data = Arc::clone(&player_data);
+                    let track_id = track.id;
+                    let progress_callback = Box::new(move |progress| {
+                        if let Ok(mut data) = progress_data.try_lock() {
+                            if data.current_track.as_ref().map(|t| t.id) == Some(track_id) {
+                                data.download_progress = Some(progress);
+                            }
+                        }
+                    });
+                    let res = downloader.download_id(&source, &path, Some(progress_callback)).await;
+                    match res {
+                        None => {
+                            // Another task is downloading this track, so wait for it while it's still the current one.
+                            while !path.exists() {
+                                sleep(Duration::from_millis(500)).await;
+                                if player_data.lock().await.current_track.as_ref().map(|t| t.id) != Some(track.id) { return; }
+                            }
+                        },
+                        Some(DownloadResult::Failed(reason)) => {
+                            log::warn!("{} could not be downloaded: {reason}", track.title);
+                            sender.send(AppEvent::TrackUnavailable(track)).await.expect("Failed to send message.");
+                            return;
+                        },
+                        Some(DownloadResult::Cancelled) => return,
+                        _ => {}
+                    }
                 }
-            }
-            
-            let mut data_guard = player_data.lock().await;
-            if data_guard.current_track.is_some() && data_guard.current_track.as_ref().unwrap().id == track.id { 
+                
+                let mut data_guard = player_data.lock().await;
+                if data_guard.current_track.as_ref().map(|t| t.id) != Some(track.id) { return; }
 
                 data_guard.downloading = false;
+                data_guard.download_progress = None;
                 player.stop();
                 if let Some(timer) = data_guard.end_timer.take() { timer.abort(); }
-                if let Err(_e) = player.play_file(&path) {
-                    // TODO: log error
-                    sender.send(utils::Message::SongFinished).await.expect("Failed to send message.");
+                let mut result = player.play_file(&path);
+                let undecodable = matches!(result, Err(PlayerError::DecodingError(_) | PlayerError::BackendError(_)));
+
+                if let Err(PlayerError::DecodingError(e)) = &result {
+                    // Convert the file into something that can be decoded, and try again.
+                    log::warn!("Failed to decode {}: {e}", path.display());
+                    data_guard.transcoding = Some(0);
+                    drop(data_guard);
+
+                    let transcoded = transcode_track(&path, &player_data).await;
+                    data_guard = player_data.lock().await;
+                    data_guard.transcoding = None;
+                    if data_guard.current_track.as_ref().map(|t| t.id) != Some(track.id) { return; }
+                    result = transcoded.and_then(|transcoded| player.play_file(&transcoded));
                 }
-                else {
-                    set_timer(&player, &runtime, &mut data_guard, sender, 1);
+
+                match result {
+                    // Like a download that was cut short. The user's own files are left alone.
+                    Err(e) if undecodable && !downloaded_again && !track.is_local() && track.source().is_some() => {
+                        log::warn!("Failed to play {}, downloading it again: {e}", path.display());
+                        drop(data_guard);
+                        if let Err(e) = std::fs::remove_file(&path) {
+                            log::error!("Failed to delete {}: {e}", path.display());
+                            sender.send(AppEvent::SongFinished).await.expect("Failed to send message.");
+                            return;
+                        }
+                        downloaded_again = true;
+                        continue;
+                    },
+                    Err(e) => {
+                        log::error!("Failed to play {}: {e}", path.display());
+                        sender.send(AppEvent::SongFinished).await.expect("Failed to send message.");
+                    },
+                    Ok(_) => {
+                        // Resume podcast episodes where they were left, and skip the trimmed start of tracks.
+                        if let Some(position) = track.position.filter(|position| *position > 0).or(track.trim_start) {
+                            player.seek(position as u64);
+                        }
+                        set_timer(&player, &runtime, &mut data_guard, sender.clone(), 1);
+                        drop(data_guard);
+                        // So the download progress is replaced by the playback right away.
+                        sender.send(AppEvent::SongStarted).await.expect("Failed to send message.");
+                    }
                 }
+                return;
             }
         });
     }
@@ -123,7 +216,13 @@ impl PlayerWidget {
             
             match (self.player.get_progress(), self.player.get_duration()) {
                 (None, _) | (_, None) => {
-                    if data_guard.downloading { (String::from("Downloading..."), 0.0) }
+                    if let Some(pcent) = data_guard.transcoding { (format!("Transcoding... {pcent}%"), pcent as f64 / 100.0) }
+                    else if data_guard.downloading {
+                        match data_guard.download_progress {
+                            Some(progress) => (format!("Downloading {progress}"), progress.percent.unwrap_or(0.0) / 100.0),
+                            None => (String::from("Downloading..."), 0.0)
+                        }
+                    }
                     else { (String::new(), 0.0) }
                 },
                 (Some(progress), Some(duration)) => {
@@ -144,27 +243,94 @@ impl PlayerWidget {
         };
         
         let gauge = Gauge::default()
-            .block(super::BLOCK.clone().borders(Borders::ALL ^ Borders::BOTTOM).title(title))
-            .gauge_style(Style::default().fg(super::ACC_COLOR))
+            .block(super::BLOCK.clone().borders(Borders::ALL ^ Borders::BOTTOM).border_style(Style::default().fg(self.accent)).title(title))
+            .gauge_style(Style::default().fg(self.accent))
             .ratio(ratio)
             .label(label);
                 
-        let p = Paragraph::new(format!("\nVolume: {}% (press H for help)", self.player.get_volume()))
-            .block(super::BLOCK.clone().borders(Borders::ALL ^ Borders::TOP));
+        let volume = format!("\nVolume: {}% (press H for help)", self.player.get_volume());
+        let block = super::BLOCK.clone().borders(Borders::ALL ^ Borders::TOP).border_style(Style::default().fg(self.accent));
+        let inner = block.inner(chunks[1]);
+        let p = Paragraph::new(volume.as_str()).block(block);
     
         frame.render_widget(gauge, chunks[0]);
         frame.render_widget(p, chunks[1]);
+        self.gauge_area = chunks[0];
+
+        // The spectrum goes on the right, next to the volume, while the track plays.
+        if let Some(tap) = self.player.sample_tap().filter(|_| self.player.is_playing() && !self.player.is_paused()) {
+            let start = (volume.trim().chars().count() as u16 + 2).min(inner.width);
+            let width = (inner.width - start).min(VISUALIZER_WIDTH);
+            let area = Rect { x: inner.right() - width, width, ..inner };
+            if area.width >= VISUALIZER_MIN_WIDTH {
+                frame.render_widget(Visualizer { bands: &tap.bands(area.width as usize), color: self.accent }, area);
+            }
+        }
 
     }
 
     pub fn stop(&mut self) {
         let mut data = self.data.blocking_lock();
-        data.downloading = false;
+        self.cancel_download(&mut data);
+        data.transcoding = None;
         stop_timer(&mut data);
         data.current_track.take();
         self.player.stop();
     }
 
+    // Cancels the download of the current track, if it is still running.
+    fn cancel_download(&self, data: &mut PlayerData) {
+        if data.downloading {
+            if let Some(source) = data.current_track.as_ref().and_then(|t| t.source().map(String::from)) {
+                let downloader = Arc::clone(&self.downloader);
+                self.runtime.spawn(async move { downloader.cancel(&source).await });
+            }
+        }
+        data.downloading = false;
+        data.download_progress = None;
+    }
+
+    /// Stops playing and replaces the player, keeping the volume.
+    pub fn set_player(&mut self, player: Arc<dyn Player>) {
+        self.stop();
+        let diff = self.player.get_volume() - player.get_volume();
+        if diff > 0 { player.increase_volume(diff); }
+        else { player.decrease_volume(-diff); }
+        self.player = player;
+    }
+
+    /// Seconds of the current track that have been played.
+    pub fn progress(&self) -> Option<u64> {
+        self.player.get_progress()
+    }
+
+    /// Length of the current track in seconds, once it's known.
+    pub fn duration(&self) -> Option<u64> {
+        self.player.get_duration()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.player.is_paused()
+    }
+
+    pub fn volume(&self) -> i32 {
+        self.player.get_volume()
+    }
+
+    pub fn player_info(&self) -> PlayerInfo {
+        self.player.info()
+    }
+
+    /// Whether the output device is gone, like unplugged headphones.
+    pub fn output_lost(&self) -> bool {
+        self.player.output_lost()
+    }
+
+    /// Opens the output again after the device went away, on the one there is now.
+    pub fn reopen_output(&self) -> Result<(), PlayerError> {
+        self.player.reopen_output()
+    }
+
     pub fn toggle_pause(&mut self) {
 
         let mut data = self.data.blocking_lock();
@@ -195,6 +361,19 @@ impl PlayerWidget {
         }
     }
 
+    /// Seeks to the part of the track under a position of the progress bar, like the one clicked.
+    /// Returns false if the position isn't on the bar.
+    pub fn seek_at(&mut self, column: u16, row: u16) -> bool {
+
+        // The bar has borders on every side but the bottom.
+        let (left, right) = (self.gauge_area.left() + 1, self.gauge_area.right().saturating_sub(1));
+        if row <= self.gauge_area.top() || row >= self.gauge_area.bottom() || column < left || column >= right {
+            return false;
+        }
+        self.seek_percentage((column - left) as u64 * 100 / (right - left) as u64);
+        true
+    }
+
     pub fn forward(&mut self, seconds: u64)  {
 
         let mut guard = self.data.blocking_lock();
@@ -215,18 +394,34 @@ impl PlayerWidget {
     }
 }
 
-fn set_timer(player: &Arc<Player>, runtime: &runtime::Runtime, data: &mut MutexGuard<PlayerData>, sender: mpsc::Sender<utils::Message>, extra_seconds: u64) {
+// Transcodes a track into the cache directory, unless that was already done before.
+async fn transcode_track(path: &Path, player_data: &Arc<Mutex<PlayerData>>) -> Result<PathBuf, PlayerError> {
+
+    let cache_dir = utils::get_cache_dir().ok_or(PlayerError::BackendError(String::from("No cache directory.")))?;
+    let output = cache_dir.join(path.with_extension("flac").file_name().unwrap_or_default());
+    if !output.exists() {
+        transcoder::transcode(&utils::get_tools(), path, &output, |pcent| {
+            if let Ok(mut data) = player_data.try_lock() { data.transcoding = Some(pcent); }
+        }).await?;
+    }
+
+    Ok(output)
+}
+
+fn set_timer(player: &Arc<dyn Player>, runtime: &runtime::Runtime, data: &mut MutexGuard<PlayerData>, sender: mpsc::Sender<AppEvent>, extra_seconds: u64) {
     
     stop_timer(data);
+    // Trimmed tracks end early.
+    let trim_end = data.current_track.as_ref().and_then(|track| track.trim_end).map(|end| end as u64);
     let seconds = player.get_duration()
         .map(|duration| {
-            duration.saturating_sub(player.get_progress().unwrap())
+            trim_end.map_or(duration, |end| end.min(duration)).saturating_sub(player.get_progress().unwrap())
         })
         .unwrap_or(0);
 
     data.end_timer.replace(runtime.spawn(async move {
         sleep(Duration::from_secs(seconds + extra_seconds)).await;
-        sender.send(utils::Message::SongFinished).await.expect("TODO: remove expect");
+        sender.send(AppEvent::SongFinished).await.expect("TODO: remove expect");
     }));   
 }
 
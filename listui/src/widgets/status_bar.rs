@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Clear, Paragraph};
+use ratatui::Frame;
+
+use super::ACC_COLOR;
+
+/// Time a message stays in the status bar.
+pub const MESSAGE_DURATION: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+/// A line at the bottom of the screen for messages that don't need the whole screen, like
+/// "Volume 80%" or a playlist that can't be updated. Each one replaces the previous, and they
+/// go away on their own.
+#[derive(Default)]
+pub struct StatusBar {
+    message: Option<(String, Level, Instant)>,
+}
+
+impl StatusBar {
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.show(message, Level::Info);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.show(message, Level::Error);
+    }
+
+    pub fn show(&mut self, message: impl Into<String>, level: Level) {
+        self.message = Some((message.into(), level, Instant::now()));
+    }
+
+    pub fn clear(&mut self) {
+        self.message = None;
+    }
+
+    /// The message being shown, until it expires.
+    pub fn message(&self) -> Option<(&str, Level)> {
+        self.message.as_ref()
+            .filter(|(_, _, shown_at)| shown_at.elapsed() < MESSAGE_DURATION)
+            .map(|(message, level, _)| (message.as_str(), *level))
+    }
+
+    /// Draws the message over the last line of the screen, if there is one.
+    pub fn draw(&self, frame: &mut Frame) {
+
+        let Some((message, level)) = self.message() else { return };
+        let area = frame.size();
+        if area.height == 0 {
+            return;
+        }
+        let line = Rect::new(area.x, area.bottom() - 1, area.width, 1);
+        let style = match level {
+            Level::Info => Style::default().fg(Color::Black).bg(ACC_COLOR),
+            Level::Error => Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD),
+        };
+        // Only the first line of the message fits.
+        let text = message.lines().next().unwrap_or_default();
+
+        frame.render_widget(Clear, line);
+        frame.render_widget(Paragraph::new(format!(" {text}")).style(style), line);
+    }
+}
@@ -0,0 +1,31 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::widgets::Widget;
+
+// Eighths of a cell, from empty to full.
+const LEVELS: [&str; 9] = [" ", "▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+
+/// The spectrum of the track being played, one bar per column from the lowest frequencies to
+/// the highest. Bands go from 0 to 1, and a full one fills the height of the area.
+pub struct Visualizer<'a> {
+    pub bands: &'a [f32],
+    pub color: Color,
+}
+
+impl Widget for Visualizer<'_> {
+
+    fn render(self, area: Rect, buf: &mut Buffer) {
+
+        for (column, band) in self.bands.iter().take(area.width as usize).enumerate() {
+            let mut eighths = (band.clamp(0.0, 1.0) * area.height as f32 * 8.0).round() as usize;
+            for row in (0..area.height).rev() {
+                let level = eighths.min(8);
+                eighths -= level;
+                buf.get_mut(area.x + column as u16, area.y + row)
+                    .set_symbol(LEVELS[level])
+                    .set_style(Style::default().fg(self.color));
+            }
+        }
+    }
+}
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE playlist_track;
+
+CREATE TABLE playlist_old (
+
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    title TEXT NOT NULL,
+    yt_id TEXT NOT NULL UNIQUE
+);
+
+INSERT INTO playlist_old (id, title, yt_id) SELECT id, title, yt_id FROM playlist WHERE yt_id IS NOT NULL;
+DROP TABLE playlist;
+ALTER TABLE playlist_old RENAME TO playlist;
@@ -0,0 +1,23 @@
+-- Playlists created by the user don't have a youtube id.
+CREATE TABLE playlist_new (
+
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    title TEXT NOT NULL,
+    yt_id TEXT UNIQUE
+);
+
+INSERT INTO playlist_new (id, title, yt_id) SELECT id, title, yt_id FROM playlist;
+DROP TABLE playlist;
+ALTER TABLE playlist_new RENAME TO playlist;
+
+-- A track can be in multiple playlists. track.playlist_id is still the playlist it was imported from.
+CREATE TABLE playlist_track (
+
+    playlist_id INTEGER NOT NULL,
+    track_id INTEGER NOT NULL,
+    PRIMARY KEY (playlist_id, track_id),
+    FOREIGN KEY(playlist_id) REFERENCES playlist(id) ON DELETE CASCADE,
+    FOREIGN KEY(track_id) REFERENCES track(id) ON DELETE CASCADE
+);
+
+INSERT INTO playlist_track (playlist_id, track_id) SELECT playlist_id, id FROM track WHERE playlist_id IS NOT NULL;
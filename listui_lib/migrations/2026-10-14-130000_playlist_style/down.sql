@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE playlist DROP COLUMN emoji;
+ALTER TABLE playlist DROP COLUMN color;
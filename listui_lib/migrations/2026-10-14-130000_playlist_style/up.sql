@@ -0,0 +1,3 @@
+-- Color and emoji chosen by the user, to tell playlists apart.
+ALTER TABLE playlist ADD COLUMN color TEXT;
+ALTER TABLE playlist ADD COLUMN emoji TEXT;
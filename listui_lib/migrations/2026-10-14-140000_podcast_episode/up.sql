@@ -0,0 +1,3 @@
+-- Only set for podcast episodes. The position is where the user stopped listening, in seconds.
+ALTER TABLE track ADD COLUMN played BOOLEAN;
+ALTER TABLE track ADD COLUMN position INTEGER;
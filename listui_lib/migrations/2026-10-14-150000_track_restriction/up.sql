@@ -0,0 +1,2 @@
+-- Why the track can't be downloaded, if it's age restricted or blocked in the user's region.
+ALTER TABLE track ADD COLUMN restriction TEXT;
@@ -0,0 +1,6 @@
+-- Values the app remembers between runs, like the last version that opened the database.
+CREATE TABLE app_state (
+
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
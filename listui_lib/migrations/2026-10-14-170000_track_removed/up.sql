@@ -0,0 +1,2 @@
+-- Set when the track isn't in its playlist on YouTube anymore. It's kept, along with its file.
+ALTER TABLE track ADD COLUMN removed BOOLEAN NOT NULL DEFAULT 0;
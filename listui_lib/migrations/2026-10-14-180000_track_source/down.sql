@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+UPDATE track SET source_id = source_url WHERE source_url IS NOT NULL;
+ALTER TABLE track DROP COLUMN source_url;
+ALTER TABLE track RENAME COLUMN source_id TO yt_id;
@@ -0,0 +1,5 @@
+-- The id of the track where it comes from (a YouTube video, or a song of a media server), or
+-- the URL it's played from (a local file, a podcast episode, a Bandcamp track...).
+ALTER TABLE track RENAME COLUMN yt_id TO source_id;
+ALTER TABLE track ADD COLUMN source_url TEXT;
+UPDATE track SET source_url = source_id, source_id = NULL WHERE source_id LIKE '%://%';
@@ -0,0 +1,2 @@
+-- Who made the track. For YouTube videos it's the channel that uploaded it, until it's tagged properly.
+ALTER TABLE track ADD COLUMN artist TEXT;
@@ -0,0 +1,3 @@
+-- Where the track is in the playlist, which the user can change. Until then, tracks are in the order they were saved.
+ALTER TABLE playlist_track ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+UPDATE playlist_track SET position = track_id;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE track DROP COLUMN track_number;
+ALTER TABLE track DROP COLUMN album;
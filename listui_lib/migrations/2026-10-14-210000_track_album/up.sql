@@ -0,0 +1,3 @@
+-- Set for the tracks of albums: the title of the album, and where the track is in it.
+ALTER TABLE track ADD COLUMN album TEXT;
+ALTER TABLE track ADD COLUMN track_number INTEGER;
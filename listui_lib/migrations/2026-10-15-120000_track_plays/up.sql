@@ -0,0 +1,4 @@
+-- How long the track is, in seconds, and how many times it has been played until the end. The
+-- duration is only known once the track has been played.
+ALTER TABLE track ADD COLUMN duration INTEGER;
+ALTER TABLE track ADD COLUMN play_count INTEGER NOT NULL DEFAULT 0;
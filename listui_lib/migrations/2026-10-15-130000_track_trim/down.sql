@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE track DROP COLUMN trim_end;
+ALTER TABLE track DROP COLUMN trim_start;
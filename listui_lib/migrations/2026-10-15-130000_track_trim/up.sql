@@ -0,0 +1,4 @@
+-- Where the track starts and stops playing, in seconds from its beginning, to skip long intros
+-- and outros every time it's played.
+ALTER TABLE track ADD COLUMN trim_start INTEGER;
+ALTER TABLE track ADD COLUMN trim_end INTEGER;
@@ -0,0 +1,8 @@
+-- Chromaprint fingerprints of the downloaded files, to find the same song in different uploads.
+-- They are computed again if the file changes size.
+CREATE TABLE fingerprint (
+
+    path TEXT PRIMARY KEY NOT NULL,
+    size BIGINT NOT NULL,
+    data TEXT NOT NULL
+);
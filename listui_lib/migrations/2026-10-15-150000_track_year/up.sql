@@ -0,0 +1,4 @@
+-- The year the track was released, and whether it has been looked up in MusicBrainz already
+-- (found or not), so it isn't looked up again.
+ALTER TABLE track ADD COLUMN year INTEGER;
+ALTER TABLE track ADD COLUMN metadata_checked BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,9 @@
+-- Every time a track has been played until the end, and when (in seconds since the epoch), to
+-- export the listening history.
+CREATE TABLE play (
+
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    track_id INTEGER NOT NULL,
+    played_at BIGINT NOT NULL,
+    FOREIGN KEY(track_id) REFERENCES track(id) ON DELETE CASCADE
+);
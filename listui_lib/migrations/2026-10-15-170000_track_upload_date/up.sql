@@ -0,0 +1,2 @@
+-- Unix timestamp of when the video was uploaded, if the API says it, to sort by the newest.
+ALTER TABLE track ADD COLUMN uploaded_at BIGINT;
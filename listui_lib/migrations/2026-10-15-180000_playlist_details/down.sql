@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE playlist DROP COLUMN last_synced_at;
+ALTER TABLE playlist DROP COLUMN created_at;
+ALTER TABLE playlist DROP COLUMN thumbnail_url;
+ALTER TABLE playlist DROP COLUMN description;
+ALTER TABLE playlist DROP COLUMN author;
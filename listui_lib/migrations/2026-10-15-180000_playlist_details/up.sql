@@ -0,0 +1,7 @@
+-- Who made a playlist, its description and thumbnail, as the API says them, and when it was
+-- saved and last updated (in seconds since the epoch).
+ALTER TABLE playlist ADD COLUMN author TEXT;
+ALTER TABLE playlist ADD COLUMN description TEXT;
+ALTER TABLE playlist ADD COLUMN thumbnail_url TEXT;
+ALTER TABLE playlist ADD COLUMN created_at BIGINT;
+ALTER TABLE playlist ADD COLUMN last_synced_at BIGINT;
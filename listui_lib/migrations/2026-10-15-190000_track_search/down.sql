@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER track_fts_update;
+DROP TRIGGER track_fts_delete;
+DROP TRIGGER track_fts_insert;
+DROP TABLE track_fts;
@@ -0,0 +1,22 @@
+-- Full-text index of the titles, artists and albums of the tracks, for the search. It keeps no
+-- copy of the text, the triggers below keep it in step with the track table.
+CREATE VIRTUAL TABLE track_fts USING fts5(
+    title, artist, album,
+    content = 'track', content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO track_fts(track_fts) VALUES ('rebuild');
+
+CREATE TRIGGER track_fts_insert AFTER INSERT ON track BEGIN
+    INSERT INTO track_fts(rowid, title, artist, album) VALUES (new.id, new.title, new.artist, new.album);
+END;
+
+CREATE TRIGGER track_fts_delete AFTER DELETE ON track BEGIN
+    INSERT INTO track_fts(track_fts, rowid, title, artist, album) VALUES ('delete', old.id, old.title, old.artist, old.album);
+END;
+
+CREATE TRIGGER track_fts_update AFTER UPDATE OF title, artist, album ON track BEGIN
+    INSERT INTO track_fts(track_fts, rowid, title, artist, album) VALUES ('delete', old.id, old.title, old.artist, old.album);
+    INSERT INTO track_fts(rowid, title, artist, album) VALUES (new.id, new.title, new.artist, new.album);
+END;
@@ -0,0 +1,58 @@
+//! Albums: playlists whose tracks are the songs of a record, in order.
+//!
+//! YouTube Music has a playlist for every album (its id starts with `OLAK5uy_`), usually uploaded
+//! by the topic channel of the artist. Their tracks get the title of the album and their track
+//! number, and the artist of the album, unless it's a compilation of songs by different artists.
+
+use std::collections::HashMap;
+
+use crate::models::{NewPlaylist, NewVideo};
+
+/// Start of the ids of the album playlists of YouTube Music.
+pub const ALBUM_PREFIX: &str = "OLAK5uy_";
+
+/// Whether the playlist with this id (or URL) is an album.
+pub fn is_album(playlist_id: &str) -> bool {
+    playlist_id.starts_with(ALBUM_PREFIX) || playlist_id.contains(".bandcamp.com/album/")
+}
+
+/// The title of the album, without the "Album - " that YouTube adds to its playlists.
+pub fn album_title(playlist: &NewPlaylist) -> &str {
+    playlist.title.strip_prefix("Album - ").unwrap_or(&playlist.title)
+}
+
+/// The artist of most tracks, unless it's a compilation. More than half of the tracks with an
+/// artist have to be by the same one, since many albums have a song or two with guests.
+pub fn album_artist(videos: &[NewVideo]) -> Option<&str> {
+
+    let artists: Vec<&str> = videos.iter().filter_map(|video| video.artist.as_deref()).collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for artist in &artists {
+        *counts.entry(artist).or_default() += 1;
+    }
+    counts.into_iter()
+        .max_by_key(|(_, count)| *count)
+        .filter(|(_, count)| count * 2 > artists.len())
+        .map(|(artist, _)| artist)
+}
+
+/// Numbers the tracks of an album in the order they were fetched. Tracks without an
+/// artist get the one of the album. Playlists that aren't albums are left as they are.
+pub fn tag_album(playlist_id: &str, playlist: &NewPlaylist, mut videos: Vec<NewVideo>) -> Vec<NewVideo> {
+
+    if !is_album(playlist_id) {
+        return videos;
+    }
+
+    let title = album_title(playlist);
+    let artist = album_artist(&videos).map(String::from);
+    for (ind, video) in videos.iter_mut().enumerate() {
+        video.album = Some(String::from(title));
+        video.track_number = Some(ind as i32 + 1);
+        if video.artist.is_none() {
+            video.artist = artist.clone();
+        }
+    }
+
+    videos
+}
@@ -0,0 +1,71 @@
+//! Responses of the playlist APIs, saved on disk for a while.
+//!
+//! Every page of a playlist is saved as soon as it arrives, so fetching it again (or after
+//! restarting in the middle of a fetch) only requests the pages that aren't saved yet. The
+//! responses of each playlist are in their own directory, so they can be forgotten at once.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration
+}
+
+impl ResponseCache {
+
+    /// Saves the responses into `dir`, and keeps them for `ttl`.
+    pub fn new(dir: &Path, ttl: Duration) -> Self {
+
+        Self {
+            dir: dir.to_path_buf(),
+            ttl
+        }
+    }
+
+    /// The body of the response to `url`, if it was saved less than `ttl` ago.
+    pub fn get(&self, playlist_id: &str, url: &str) -> Option<String> {
+
+        let path = self.path(playlist_id, url);
+        let age = fs::metadata(&path).ok()?
+            .modified().ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())?;
+
+        if age < self.ttl { fs::read_to_string(path).ok() }
+        else { None }
+    }
+
+    /// Saves the body of the response to `url`, a request about the playlist `playlist_id`.
+    pub fn put(&self, playlist_id: &str, url: &str, body: &str) {
+
+        let path = self.path(playlist_id, url);
+        let result = path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, body));
+
+        if let Err(e) = result {
+            log::warn!("Failed to cache the response of {playlist_id} in {}: {e}", path.display());
+        }
+    }
+
+    /// Forgets every saved response about a playlist.
+    pub fn invalidate(&self, playlist_id: &str) -> io::Result<()> {
+
+        match fs::remove_dir_all(self.dir.join(hash(playlist_id))) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(())
+        }
+    }
+
+    // URLs are hashed. Besides making them valid file names, the API key of YouTube's isn't written to disk.
+    fn path(&self, playlist_id: &str, url: &str) -> PathBuf {
+        self.dir.join(hash(playlist_id)).join(format!("{}.json", hash(url)))
+    }
+}
+
+fn hash(text: &str) -> String {
+    format!("{:x}", md5::compute(text))
+}
@@ -0,0 +1,97 @@
+//! Releases of lisTUI, published on GitHub, and of yt-dlp, whose binaries can be installed from there.
+
+use std::path::Path;
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use tokio::fs;
+
+use super::{convert_reqwest_err, ApiError};
+
+/// Repository whose releases are checked.
+pub const LISTUI_REPO: &str = "gulis1/lisTUI";
+
+/// Repository of yt-dlp, which publishes a binary for every system with each release.
+pub const YTDLP_REPO: &str = "yt-dlp/yt-dlp";
+
+// The binary is tens of MB, too much for the timeout of the API requests.
+const YTDLP_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GithubRelease {
+    tag_name: String,
+    body: Option<String>,
+    html_url: String
+}
+
+/// A published version of lisTUI.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    /// Without the `v` of the tag, like `0.3.0`.
+    pub version: String,
+    pub notes: String,
+    pub url: String
+}
+
+/// Gets the latest release of `repo` (owner/name), or `None` if it hasn't published any.
+pub async fn fetch_latest_release(client: &reqwest::Client, repo: &str) -> Result<Option<Release>, ApiError> {
+
+    let response = client.get(format!("https://api.github.com/repos/{repo}/releases/latest"))
+        // GitHub rejects requests without one.
+        .header("User-Agent", "listui")
+        .send().await
+        .map_err(convert_reqwest_err)?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let content = response.error_for_status()
+        .map_err(convert_reqwest_err)?
+        .text_with_charset("utf-8").await
+        .map_err(|_| ApiError::DecodingError)?;
+    let release = serde_json::from_str::<GithubRelease>(&content).map_err(|_| ApiError::ParsingError)?;
+
+    Ok(Some(Release {
+        version: String::from(release.tag_name.trim_start_matches('v')),
+        notes: release.body.unwrap_or_default(),
+        url: release.html_url
+    }))
+}
+
+/// The name of the yt-dlp binary for this system in its releases. Where there's none, the
+/// zipapp, which needs Python.
+pub fn ytdlp_asset() -> &'static str {
+
+    if cfg!(windows) { "yt-dlp.exe" }
+    else if cfg!(target_os = "macos") { "yt-dlp_macos" }
+    else if cfg!(all(target_os = "linux", target_arch = "x86_64")) { "yt-dlp_linux" }
+    else if cfg!(all(target_os = "linux", target_arch = "aarch64")) { "yt-dlp_linux_aarch64" }
+    else { "yt-dlp" }
+}
+
+/// Downloads the latest yt-dlp binary as `path`. It's written next to it first, so the one that
+/// was there is only replaced once the download has finished.
+pub async fn install_ytdlp(client: &reqwest::Client, path: &Path) -> Result<(), ApiError> {
+
+    let bytes = client.get(format!("https://github.com/{YTDLP_REPO}/releases/latest/download/{}", ytdlp_asset()))
+        .header("User-Agent", "listui")
+        .timeout(YTDLP_DOWNLOAD_TIMEOUT)
+        .send().await
+        .and_then(|r| r.error_for_status())
+        .map_err(convert_reqwest_err)?
+        .bytes().await
+        .map_err(convert_reqwest_err)?;
+
+    let io_err = |e: std::io::Error| ApiError::RequestError(format!("Failed to save yt-dlp in {}: {e}", path.display()));
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).await.map_err(io_err)?;
+    }
+    let partial = path.with_extension("part");
+    fs::write(&partial, &bytes).await.map_err(io_err)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o755)).await.map_err(io_err)?;
+    }
+    fs::rename(&partial, path).await.map_err(io_err)
+}
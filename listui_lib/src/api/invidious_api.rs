@@ -1,4 +1,4 @@
-/// Module with structs for Invidious' API.
+//! Module with structs for Invidious' API.
 
 
 use serde::{Serialize, Deserialize};
@@ -9,7 +9,11 @@ pub struct Video {
 
     pub title: String,
     pub video_id: String,
-    pub index: i32
+    pub author: Option<String>,
+    pub index: i32,
+    /// Some instances don't send it.
+    #[serde(default)]
+    pub length_seconds: Option<i32>
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -17,5 +21,41 @@ pub struct Video {
 pub struct PlaylistResponse {
     pub title: String,
     pub playlist_id: String,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub playlist_thumbnail: Option<String>,
+    /// Including the deleted and private videos. Some instances don't send it.
+    #[serde(default)]
+    pub video_count: usize,
     pub videos: Vec<Video>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelVideo {
+    pub title: String,
+    pub video_id: String,
+    pub author: Option<String>,
+    /// Unix timestamp of the upload.
+    pub published: u64
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelVideosResponse {
+    pub videos: Vec<ChannelVideo>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoResponse {
+    pub title: String,
+    pub video_id: String,
+    pub author: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveUrlResponse {
+    pub ucid: Option<String>,
 }
\ No newline at end of file
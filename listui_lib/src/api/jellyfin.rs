@@ -0,0 +1,122 @@
+//! Client for the music libraries of a Jellyfin server.
+
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+
+use crate::models::{NewPlaylist, NewVideo};
+use super::{convert_reqwest_err, download_file, ApiError, RemotePlaylist};
+
+/// Prefix of the ids of playlists, albums and tracks that come from a Jellyfin server.
+pub const JELLYFIN_PREFIX: &str = "jellyfin:";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Item {
+    id: String,
+    name: String,
+    #[serde(rename = "Type")]
+    item_type: String,
+    album_artist: Option<String>,
+    #[serde(default)]
+    artists: Vec<String>,
+    /// The track number, for songs.
+    index_number: Option<i32>,
+    overview: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct ItemsResponse {
+    items: Vec<Item>
+}
+
+/// Client for a Jellyfin server, authenticated with an API key.
+#[derive(Clone)]
+pub struct JellyfinClient {
+    client: reqwest::Client,
+    url: String,
+    api_key: String
+}
+
+impl JellyfinClient {
+
+    pub fn new(url: &str, api_key: &str) -> Self {
+
+        Self {
+            client: reqwest::Client::new(),
+            url: String::from(url.trim_end_matches('/')),
+            api_key: String::from(api_key)
+        }
+    }
+
+    /// Gets all the music playlists and albums in the server.
+    pub async fn fetch_playlists(&self) -> Result<Vec<RemotePlaylist>, ApiError> {
+
+        let content = self.fetch_items("IncludeItemTypes=Playlist,MusicAlbum&Recursive=true&SortBy=SortName").await?;
+        Ok(content.items.into_iter()
+            .map(|item| RemotePlaylist {
+                yt_id: format!("{JELLYFIN_PREFIX}{}", item.id),
+                title: match (item.item_type.as_str(), item.album_artist) {
+                    ("MusicAlbum", Some(artist)) => format!("{artist} - {} (album)", item.name),
+                    ("MusicAlbum", None) => format!("{} (album)", item.name),
+                    _ => item.name
+                }
+            })
+            .collect())
+    }
+
+    /// Gets a playlist or album and all its tracks.
+    pub async fn fetch_playlist(&self, item_id: &str) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
+
+        let playlist = self.fetch_items(&format!("Ids={item_id}&Fields=Overview")).await?
+            .items.into_iter().next()
+            .ok_or(ApiError::NotFoundError(String::from(item_id)))?;
+
+        // Playlists are already returned in their own order.
+        let is_album = playlist.item_type == "MusicAlbum";
+        let sorting = if is_album { "&SortBy=ParentIndexNumber,IndexNumber,SortName" } else { "" };
+        let tracks = self.fetch_items(&format!("ParentId={item_id}&IncludeItemTypes=Audio&Recursive=true{sorting}")).await?;
+
+        let videos = tracks.items.into_iter()
+            .map(|track| {
+                let artist = track.artists.first().cloned().or(track.album_artist);
+                let title = match track.artists.first() {
+                    Some(artist) => format!("{artist} - {}", track.name),
+                    None => track.name
+                };
+                let mut video = NewVideo::new(title, format!("{JELLYFIN_PREFIX}{}", track.id)).with_artist(artist);
+                if is_album {
+                    video.album = Some(playlist.name.clone());
+                    video.track_number = track.index_number;
+                }
+                video
+            })
+            .collect();
+
+        let details = NewPlaylist::new(playlist.name, format!("{JELLYFIN_PREFIX}{}", playlist.id))
+            .with_details(playlist.album_artist, playlist.overview, None);
+        Ok((details, videos))
+    }
+
+    /// Downloads a track as mp3 (the server transcodes it if needed) into `file_path`.
+    pub async fn download(&self, track_id: &str, file_path: &Path) -> Result<(), ApiError> {
+
+        let request = self.client.get(format!("{}/Audio/{track_id}/stream?container=mp3&audioCodec=mp3", self.url))
+            .header("X-Emby-Token", &self.api_key);
+
+        download_file(request, file_path).await
+    }
+
+    async fn fetch_items(&self, query: &str) -> Result<ItemsResponse, ApiError> {
+
+        let response = self.client.get(format!("{}/Items?{query}", self.url))
+            .header("X-Emby-Token", &self.api_key)
+            .send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(convert_reqwest_err)?;
+
+        serde_json::from_str::<ItemsResponse>(&response.text_with_charset("utf-8").await
+            .map_err(|_| ApiError::DecodingError)?)
+            .map_err(|_| ApiError::ParsingError)
+    }
+}
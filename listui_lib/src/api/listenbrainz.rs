@@ -0,0 +1,115 @@
+//! ListenBrainz, the open alternative to Last.fm, where the tracks that are played are saved.
+//!
+//! Listens are submitted with the token of the user, found in their ListenBrainz settings.
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::scrobble::{Listen, Scrobbler};
+use super::{convert_reqwest_err, ApiError};
+
+pub const LISTENBRAINZ_URL: &str = "https://api.listenbrainz.org";
+
+#[derive(Serialize, Debug)]
+struct Submission<'a> {
+    listen_type: &'static str,
+    payload: [Payload<'a>; 1]
+}
+
+#[derive(Serialize, Debug)]
+struct Payload<'a> {
+    /// Left out for the track that is playing now.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    listened_at: Option<i64>,
+    track_metadata: TrackMetadata<'a>
+}
+
+#[derive(Serialize, Debug)]
+struct TrackMetadata<'a> {
+    artist_name: &'a str,
+    track_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_name: Option<&'a str>,
+    additional_info: AdditionalInfo<'a>
+}
+
+#[derive(Serialize, Debug)]
+struct AdditionalInfo<'a> {
+    media_player: &'static str,
+    submission_client: &'static str,
+    submission_client_version: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin_url: Option<&'a str>
+}
+
+#[derive(Clone)]
+pub struct ListenBrainzClient {
+    client: reqwest::Client,
+    url: String,
+    token: String
+}
+
+impl ListenBrainzClient {
+
+    /// A client of the ListenBrainz server at `url` (like `https://api.listenbrainz.org`, or a
+    /// self-hosted one) that submits the listens of the user whose token is `token`.
+    pub fn new(url: &str, token: &str) -> Self {
+
+        Self {
+            client: reqwest::Client::new(),
+            url: String::from(url.trim_end_matches('/')),
+            token: String::from(token.trim())
+        }
+    }
+
+    async fn send(&self, listen_type: &'static str, listen: &Listen) -> Result<(), ApiError> {
+
+        let submission = Submission {
+            listen_type,
+            payload: [Payload {
+                listened_at: (listen_type == "single").then_some(listen.listened_at),
+                track_metadata: TrackMetadata {
+                    artist_name: &listen.artist,
+                    track_name: &listen.title,
+                    release_name: listen.album.as_deref(),
+                    additional_info: AdditionalInfo {
+                        media_player: "lisTUI",
+                        submission_client: "lisTUI",
+                        submission_client_version: env!("CARGO_PKG_VERSION"),
+                        duration_ms: listen.duration.map(|duration| duration as i64 * 1000),
+                        origin_url: listen.origin_url.as_deref()
+                    }
+                }
+            }]
+        };
+
+        let body = serde_json::to_string(&submission).map_err(|_| ApiError::ParsingError)?;
+        self.client.post(format!("{}/1/submit-listens", self.url))
+            .header("Authorization", format!("Token {}", self.token))
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(convert_reqwest_err)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Scrobbler for ListenBrainzClient {
+
+    fn name(&self) -> &'static str {
+        "ListenBrainz"
+    }
+
+    async fn now_playing(&self, listen: &Listen) -> Result<(), ApiError> {
+        self.send("playing_now", listen).await
+    }
+
+    async fn submit(&self, listen: &Listen) -> Result<(), ApiError> {
+        self.send("single", listen).await
+    }
+}
@@ -1,10 +1,38 @@
-/// Module with structs for Invidious's API.
+//! Module with structs for Invidious's API.
 
 mod yt_api;
 mod invidious_api;
+mod piped_api;
+mod ytdlp;
+mod subsonic;
+mod jellyfin;
+mod rss;
+mod github;
+mod cache;
+mod webhook;
+mod musicbrainz;
+mod listenbrainz;
 
+pub use subsonic::{SubsonicClient, SUBSONIC_PREFIX};
+pub use jellyfin::{JellyfinClient, JELLYFIN_PREFIX};
+pub use rss::RSS_PREFIX;
+pub use github::{ytdlp_asset, Release, LISTUI_REPO, YTDLP_REPO};
+pub use cache::ResponseCache;
+pub use webhook::{WebhookClient, WebhookEvent};
+pub use musicbrainz::{MusicBrainzClient, Recording};
+pub use listenbrainz::{ListenBrainzClient, LISTENBRAINZ_URL};
+
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+use futures_util::stream::{self, StreamExt};
 use reqwest::{self, Response};
-use crate::models::{NewPlaylist, NewVideo};
+use crate::models::{Drawable, NewPlaylist, NewVideo, Restriction};
+use crate::tools::{Tools, YtdlpStatus};
 
 const YOUTUBE_API_URL: &str = "https://www.googleapis.com/youtube/v3";
 
@@ -17,13 +45,34 @@ static INVIDIOUS_INSTANCES: [&str; 5] =  [
     "https://inv.bp.projectsegfau.lt"
 ];
 
+/// Timeouts of an `ApiClient`, unless it's built with other ones.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// How long an instance has to answer when ranking them, before it's considered down.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Pages of an Invidious playlist that are requested at the same time.
+const PARALLEL_PAGES: usize = 4;
+
+// Piped instances, used when none of the Invidious ones work.
+static PIPED_INSTANCES: [&str; 3] = [
+    "https://pipedapi.kavin.rocks",
+    "https://pipedapi.adminforge.de",
+    "https://api.piped.private.coffee"
+];
+
 #[derive(Debug, Clone)]
 pub enum ApiError {
     
     NotFoundError(String),
+    VideoNotFoundError(String),
+    ChannelNotFoundError(String),
     RequestError(String),
     DecodingError,
     ParsingError,
+    /// The user gave up on the source, with `SourceSkip::skip`.
+    Skipped,
     Unknown
 }
 
@@ -32,25 +81,214 @@ impl std::fmt::Display for ApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ApiError::NotFoundError(id) => write!(f, "Couldn't find playlist with id {id}."),
+            ApiError::VideoNotFoundError(id) => write!(f, "Couldn't find video with id {id}."),
+            ApiError::ChannelNotFoundError(id) => write!(f, "Couldn't find channel {id}."),
             ApiError::RequestError(err) => write!(f, "{}", err),
             ApiError::DecodingError | ApiError::ParsingError => write!(f, "Failed to parse api response."),
+            ApiError::Skipped => write!(f, "Skipped."),
             ApiError::Unknown => write!(f, "Unknown error.")
         }
     }
 }
 
-pub type ApiProgressCallback = Box<dyn Fn(String) + Send + Sync>;
+/// Where an `ApiClient` fetches something from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchSource {
+    YouTube,
+    /// The URL of an Invidious instance.
+    Invidious(String),
+    /// The URL of a Piped instance.
+    Piped(String),
+    YtDlp,
+    /// A podcast feed.
+    Feed,
+}
+
+impl std::fmt::Display for FetchSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchSource::YouTube => write!(f, "YouTube"),
+            FetchSource::Invidious(instance) => write!(f, "Invidious instance: {instance}"),
+            FetchSource::Piped(instance) => write!(f, "Piped instance: {instance}"),
+            FetchSource::YtDlp => write!(f, "yt-dlp"),
+            FetchSource::Feed => write!(f, "its feed"),
+        }
+    }
+}
+
+/// What an `ApiClient` is doing, sent to its progress callback.
+///
+/// How it's shown is up to the caller. The `Display` implementation is a plain sentence,
+/// the one written to the logs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchProgress {
+    /// Started fetching `what` (like "playlist PL..." or "channel @name") from `source`.
+    Trying { what: String, source: FetchSource },
+    /// Fetching from `source` failed. The next source is tried, if there's any left.
+    Failed { what: String, source: FetchSource, error: String },
+    /// Another page was fetched, with `videos` videos so far out of `total`, if it's known.
+    Page { videos: usize, total: Option<usize> },
+    /// Page `page` of a playlist was fetched, out of the `pages` it should have from its video count.
+    PageOf { page: usize, pages: usize },
+    /// Checking the restrictions of `videos` videos with yt-dlp.
+    Checking { videos: usize },
+    /// Searching for another upload of a video.
+    Searching { title: String },
+}
+
+impl std::fmt::Display for FetchProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchProgress::Trying { what, source: FetchSource::YtDlp } => write!(f, "Fetching {what} with yt-dlp."),
+            FetchProgress::Trying { what, source } => write!(f, "Fetching {what} from {source}."),
+            FetchProgress::Failed { what, source: FetchSource::YtDlp, error } => write!(f, "Could not fetch {what} with yt-dlp: {error}"),
+            FetchProgress::Failed { what, source, error } => write!(f, "Could not fetch {what} from {source}: {error}"),
+            FetchProgress::Page { videos, total: Some(total) } => write!(f, "Fetched {videos} of {total} videos."),
+            FetchProgress::Page { videos, total: None } => write!(f, "Fetched {videos} videos."),
+            FetchProgress::PageOf { page, pages } => write!(f, "Fetched page {page} of {pages}."),
+            FetchProgress::Checking { videos } => write!(f, "Checking {videos} videos with yt-dlp."),
+            FetchProgress::Searching { title } => write!(f, "Searching for {title}."),
+        }
+    }
+}
+
+pub type ApiProgressCallback = Box<dyn Fn(FetchProgress) + Send + Sync>;
+
+/// Gives up on the Invidious or Piped instance a playlist is being fetched from, when it's too
+/// slow, so the next one is tried. Clones skip the same fetch.
+#[derive(Debug, Clone, Default)]
+pub struct SourceSkip(Arc<Notify>);
+
+impl SourceSkip {
+
+    /// Skips the instance that is being fetched from now, if any.
+    pub fn skip(&self) {
+        self.0.notify_waiters();
+    }
+
+    // Runs a fetch from one source, that fails with `ApiError::Skipped` if it's skipped first.
+    async fn run<T>(&self, fetch: impl Future<Output = Result<T, ApiError>>) -> Result<T, ApiError> {
+        tokio::select! {
+            result = fetch => result,
+            _ = self.0.notified() => Err(ApiError::Skipped)
+        }
+    }
+}
+
+/// How the requests of an `ApiClient` are retried, when they fail in a way that may be temporary:
+/// the connection couldn't be made, or the server answered with 429 or a 5xx status.
+///
+/// Timeouts aren't retried. A server that hangs usually keeps hanging, so it's better to
+/// try the next instance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt.
+    pub retries: u32,
+    /// The wait before the first retry, that is doubled after every retry.
+    pub backoff: Duration
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { retries: 2, backoff: Duration::from_millis(500) }
+    }
+}
+
+/// How an Invidious instance answered when it was probed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceHealth {
+    pub url: String,
+    /// `None` if it didn't answer, or answered with an error.
+    pub latency: Option<Duration>
+}
+
+/// A playlist in a media server, that hasn't been imported yet.
+#[derive(Debug, Clone)]
+pub struct RemotePlaylist {
+    /// The id that has to be passed to `MediaSource::fetch_playlist` to import it.
+    pub yt_id: String,
+    pub title: String
+}
+
+impl Drawable for RemotePlaylist {
+
+    fn get_text(&self) -> &str {
+        &self.title
+    }
+}
+
+/// Something the app can fetch playlists from.
+#[async_trait]
+pub trait MediaSource: Send + Sync {
+
+    /// Fetches a playlist and all its videos.
+    /// 
+    /// If a callback is provided, it will be called multiple times with information
+    /// about the progress.
+    async fn fetch_playlist(&self, yt_id: &str, callback: Option<ApiProgressCallback>) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError>;
+
+    /// Fetches a single video.
+    async fn fetch_video(&self, yt_id: &str) -> Result<NewVideo, ApiError>;
+
+    /// Returns the id of the playlist with all the uploads of a channel.
+    /// 
+    /// `channel` can be either a channel id (UC...) or a handle (@name).
+    async fn fetch_channel_uploads(&self, channel: &str) -> Result<String, ApiError>;
+
+    /// Finds which of the given YouTube videos will fail to download, and why.
+    async fn fetch_restrictions(&self, _yt_ids: &[String]) -> Result<Vec<(String, Restriction)>, ApiError> { Ok(Vec::new()) }
+
+    /// Searches for another upload of a video that is no longer available.
+    async fn find_alternate(&self, _title: &str, _yt_id: &str) -> Result<Option<NewVideo>, ApiError> { Ok(None) }
+
+    /// Fetches the videos uploaded to a channel since the Unix timestamp `since`, newest first.
+    ///
+    /// `uploads_id` is the id of the playlist with the channel's uploads (UU...).
+    async fn fetch_recent_uploads(&self, _uploads_id: &str, _since: u64) -> Result<Vec<NewVideo>, ApiError> { Ok(Vec::new()) }
+
+    /// Lists the playlists of the user's media server, if one is configured.
+    async fn fetch_library(&self) -> Result<Vec<RemotePlaylist>, ApiError> { Ok(Vec::new()) }
+
+    /// Gets the latest release of lisTUI, if there is one.
+    async fn fetch_latest_release(&self) -> Result<Option<Release>, ApiError> { Ok(None) }
+
+    /// Gives up on the instance a playlist is being fetched from, so the next one is tried.
+    fn skip_source(&self) {}
+
+    /// Forgets the saved responses about a playlist, so it's fetched from scratch the next time.
+    fn invalidate_cache(&self, _yt_id: &str) {}
+
+    /// Probes the Invidious instances, returning them from the fastest to the slowest, and then the ones that are down.
+    async fn rank_instances(&self) -> Vec<InstanceHealth> { Vec::new() }
+
+    /// Returns false if the tools needed to play tracks from this source are missing.
+    fn is_available(&self) -> bool { true }
+
+    /// Checks that yt-dlp can be run and, if `online`, whether a newer version is out.
+    async fn check_ytdlp(&self, _online: bool) -> Result<YtdlpStatus, ApiError> { Ok(YtdlpStatus::UpToDate) }
+
+    /// Installs the latest yt-dlp where lisTUI keeps its own, returning its version.
+    async fn install_ytdlp(&self) -> Result<String, ApiError> {
+        Err(ApiError::RequestError(String::from("yt-dlp can't be installed from here.")))
+    }
+}
 
 /// A `reqwest::Client` wrapper, that can query videos either from ỲouTube
 /// or Invidious.
 /// 
 /// Because playlist queries can take a quite a while, the user can define a callback
-/// function that will be called multiple times with a `String` with information
-/// about the progress.
+/// function that will be called multiple times with a `FetchProgress` telling what it's
+/// doing.
 pub struct ApiClient {
     client: reqwest::Client,
     api_key: Option<String>,
-    callback: Option<ApiProgressCallback>
+    callback: Option<ApiProgressCallback>,
+    tools: Tools,
+    // The Invidious instances, in the order they are tried.
+    instances: Vec<String>,
+    retry: RetryPolicy,
+    cache: Option<ResponseCache>,
+    skip: SourceSkip
 }
 
 impl ApiClient {
@@ -62,9 +300,14 @@ impl ApiClient {
     pub fn from_youtube(api_key: String, callback: Option<ApiProgressCallback>) -> Self {
 
         Self {
-            client: reqwest::Client::new(),
+            client: build_client(DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT),
             api_key: Some(api_key),
-            callback
+            callback,
+            tools: Tools::default(),
+            instances: INVIDIOUS_INSTANCES.map(String::from).to_vec(),
+            retry: RetryPolicy::default(),
+            cache: None,
+            skip: SourceSkip::default()
         }
     }
 
@@ -75,66 +318,448 @@ impl ApiClient {
     pub fn from_invidious(callback: Option<ApiProgressCallback>) -> Self {
 
         Self {
-            client: reqwest::Client::new(),
+            client: build_client(DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT),
             api_key: None,
-            callback
+            callback,
+            tools: Tools::default(),
+            instances: INVIDIOUS_INSTANCES.map(String::from).to_vec(),
+            retry: RetryPolicy::default(),
+            cache: None,
+            skip: SourceSkip::default()
         }
     }
+
+    /// Runs the `yt-dlp` of `tools`, instead of the one in the `PATH`.
+    pub fn with_tools(mut self, tools: Tools) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Gives up connecting to a server after `connect`, and on any request that takes longer than `request`.
+    pub fn with_timeouts(mut self, connect: Duration, request: Duration) -> Self {
+        self.client = build_client(connect, request);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Saves the pages of the playlists in `cache`, and reads them from there while they are fresh.
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Lets `skip` give up on the instances playlists are fetched from.
+    pub fn with_skip(mut self, skip: SourceSkip) -> Self {
+        self.skip = skip;
+        self
+    }
+
+    /// Tries the Invidious instances in the order of `ranking` (usually the result of
+    /// `Self::rank_instances`). Instances that aren't in it are tried last.
+    pub fn with_instance_ranking(mut self, ranking: &[String]) -> Self {
+        self.instances.sort_by_key(|instance| ranking.iter().position(|ranked| ranked == instance).unwrap_or(usize::MAX));
+        self
+    }
+
+    /// Requests the stats of every Invidious instance at the same time, and sorts them by how long they
+    /// took to answer. The ones that are down are kept at the end, in their previous order.
+    pub async fn rank_instances(&self) -> Vec<InstanceHealth> {
+
+        let probes = self.instances.iter().map(|instance| async move {
+            let start = Instant::now();
+            let response = self.client.get(format!("{instance}/api/v1/stats"))
+                .timeout(PROBE_TIMEOUT)
+                .send().await
+                .and_then(|r| r.error_for_status());
+            InstanceHealth { url: instance.clone(), latency: response.ok().map(|_| start.elapsed()) }
+        });
+
+        let mut ranking = futures_util::future::join_all(probes).await;
+        ranking.sort_by_key(|health| health.latency.unwrap_or(Duration::MAX));
+        ranking
+    }
     
     /// Tries to fetch the information about all videos from a YouTube playlist.
     /// 
     /// Depending if `self` was created using `Self::from_youtube` or `Self::from_invidious`, 
-    /// the information will be fetched from either YouTube or Invidious.
+    /// the information will be fetched from either YouTube or Invidious (or Piped, if no
+    /// Invidious instance works). If all of them fail, `yt-dlp` is used to get at least the
+    /// title and the ids of the videos.
+    /// 
+    /// If `yt_id` is an URL from another site, the playlist is extracted using `yt-dlp` instead,
+    /// and podcasts (`rss:` + the URL of the feed) are read from their feed.
     pub async fn fetch_playlist(&self, yt_id: &str) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
 
-        if self.api_key.is_some() {
-            self.send_callback(format!("Fetching playlist {yt_id} from YouTube."));
-            let playlist = self.fetch_youtube_playlist_info(yt_id).await?;
-            let videos = self.fetch_youtube_videos(&playlist.yt_id).await?;
-            Ok((playlist, videos))
+        if let Some(url) = yt_id.strip_prefix(RSS_PREFIX) {
+            self.send_callback(FetchProgress::Trying { what: format!("podcast {url}"), source: FetchSource::Feed });
+            let result = rss::fetch_feed(&self.client, url).await;
+            if let Ok((_, videos)) = &result {
+                self.send_callback(FetchProgress::Page { videos: videos.len(), total: Some(videos.len()) });
+            }
+            result
+        }
+        else if yt_id.contains("://") {
+            self.send_callback(FetchProgress::Trying { what: String::from(yt_id), source: FetchSource::YtDlp });
+            let result = ytdlp::fetch_playlist(&self.tools, yt_id).await;
+            if let Ok((_, videos)) = &result {
+                self.send_callback(FetchProgress::Page { videos: videos.len(), total: Some(videos.len()) });
+            }
+            result
+        }
+        else if self.api_key.is_some() {
+            let what = format!("playlist {yt_id}");
+            self.send_callback(FetchProgress::Trying { what: what.clone(), source: FetchSource::YouTube });
+            let r = match self.fetch_youtube_playlist_info(yt_id).await {
+                Ok(playlist) => self.fetch_youtube_videos(&playlist.yt_id).await.map(|videos| (playlist, videos)),
+                Err(e) => Err(e)
+            };
+            match r {
+                Ok(_) => r,
+                Err(e) => {
+                    self.send_callback(FetchProgress::Failed { what, source: FetchSource::YouTube, error: e.to_string() });
+                    self.fetch_ytdlp_playlist(yt_id, e).await
+                }
+            }
         }
         else {
             // Loop through invidious instances, in case some of them are down.
+            let what = format!("playlist {yt_id}");
             let mut r: Result<(NewPlaylist, Vec<NewVideo>), ApiError> = Err(ApiError::Unknown);
-            for instance in INVIDIOUS_INSTANCES {
-                self.send_callback(format!("Fetching playlist {yt_id} from Invidious instance: {instance}"));
-                r = self.fetch_invidious_playlist(instance, yt_id).await;
+            for instance in &self.instances {
+                let source = FetchSource::Invidious(instance.clone());
+                self.send_callback(FetchProgress::Trying { what: what.clone(), source: source.clone() });
+                r = self.skip.run(self.fetch_invidious_playlist(instance, yt_id)).await;
+                match &r {
+                    Ok(_) => return r,
+                    Err(e) => self.send_callback(FetchProgress::Failed { what: what.clone(), source, error: e.to_string() })
+                }
+            }
+            for instance in PIPED_INSTANCES {
+                let source = FetchSource::Piped(String::from(instance));
+                self.send_callback(FetchProgress::Trying { what: what.clone(), source: source.clone() });
+                r = self.skip.run(self.fetch_piped_playlist(instance, yt_id)).await;
+                match &r {
+                    Ok(_) => return r,
+                    Err(e) => self.send_callback(FetchProgress::Failed { what: what.clone(), source, error: e.to_string() })
+                }
+            }
+            match r {
+                Ok(_) => r,
+                Err(e) => self.fetch_ytdlp_playlist(yt_id, e).await
+            }
+        }
+    }
+
+    // Last resort when every HTTP API failed with `api_error`. If yt-dlp doesn't work either, the
+    // error of the API is returned, since it's usually more informative.
+    async fn fetch_ytdlp_playlist(&self, yt_id: &str, api_error: ApiError) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
+
+        let what = format!("playlist {yt_id}");
+        self.send_callback(FetchProgress::Trying { what: what.clone(), source: FetchSource::YtDlp });
+        match ytdlp::fetch_youtube_playlist(&self.tools, yt_id).await {
+            Ok((playlist, videos)) => {
+                self.send_callback(FetchProgress::Page { videos: videos.len(), total: Some(videos.len()) });
+                Ok((playlist, videos))
+            },
+            Err(e) => {
+                self.send_callback(FetchProgress::Failed { what, source: FetchSource::YtDlp, error: e.to_string() });
+                Err(api_error)
+            }
+        }
+    }
+
+    /// Returns the id of the playlist with all the uploads of a channel.
+    /// 
+    /// `channel` can be either a channel id (UC...) or a handle (@name). Only handles
+    /// need to be queried, the uploads playlist of a channel id is always UU + the rest of the id.
+    pub async fn fetch_channel_uploads(&self, channel: &str) -> Result<String, ApiError> {
+
+        let channel_id = if channel.starts_with('@') {
+            let what = format!("channel {channel}");
+            if self.api_key.is_some() {
+                self.send_callback(FetchProgress::Trying { what, source: FetchSource::YouTube });
+                self.fetch_youtube_channel_id(channel).await?
+            }
+            else {
+                let mut r: Result<String, ApiError> = Err(ApiError::Unknown);
+                for instance in &self.instances {
+                    let source = FetchSource::Invidious(instance.clone());
+                    self.send_callback(FetchProgress::Trying { what: what.clone(), source: source.clone() });
+                    r = self.fetch_invidious_channel_id(instance, channel).await;
+                    match &r {
+                        Ok(_) => break,
+                        Err(e) => self.send_callback(FetchProgress::Failed { what: what.clone(), source, error: e.to_string() })
+                    }
+                }
+                r?
+            }
+        }
+        else { String::from(channel) };
+
+        channel_id.strip_prefix("UC")
+            .map(|id| format!("UU{id}"))
+            .ok_or(ApiError::ChannelNotFoundError(String::from(channel)))
+    }
+
+    /// Finds which videos are age restricted or blocked in `region` (a two letter country code).
+    /// 
+    /// With YouTube's API, the region blocks are only checked if `region` is set. Otherwise
+    /// `yt-dlp` is used, which always checks them from the user's actual location.
+    pub async fn fetch_restrictions(&self, yt_ids: &[String], region: Option<&str>) -> Result<Vec<(String, Restriction)>, ApiError> {
+
+        let Some(api_key) = self.api_key.as_ref() else {
+            self.send_callback(FetchProgress::Checking { videos: yt_ids.len() });
+            return ytdlp::probe_restrictions(&self.tools, yt_ids).await;
+        };
+
+        let mut restrictions = Vec::new();
+        for chunk in yt_ids.chunks(50) {
+
+            let response = self.send(self.client.get(format!("{}/videos?part=contentDetails&maxResults=50&key={}&id={}", YOUTUBE_API_URL, api_key, chunk.join(",")))).await
+                .map_err(convert_reqwest_err)?;
+
+            let content = serde_json::from_str::<yt_api::VideoDetailsResponse>(&response.text_with_charset("utf-8").await
+                .map_err(|_| ApiError::DecodingError)?)
+                .map_err(|_| ApiError::ParsingError)?;
+
+            for video in content.items {
+                let details = video.content_details;
+                let age_restricted = details.content_rating
+                    .and_then(|rating| rating.yt_rating)
+                    .is_some_and(|rating| rating == "ytAgeRestricted");
+
+                let region_blocked = match (details.region_restriction, region) {
+                    (Some(restriction), Some(region)) => {
+                        restriction.blocked.is_some_and(|blocked| blocked.iter().any(|r| r == region))
+                            || restriction.allowed.is_some_and(|allowed| !allowed.iter().any(|r| r == region))
+                    },
+                    _ => false
+                };
+
+                if region_blocked { restrictions.push((video.id, Restriction::RegionBlocked)); }
+                else if age_restricted { restrictions.push((video.id, Restriction::AgeRestricted)); }
+            }
+        }
+
+        Ok(restrictions)
+    }
+
+    /// Gets the latest release of lisTUI from GitHub, or `None` if there isn't any.
+    pub async fn fetch_latest_release(&self) -> Result<Option<Release>, ApiError> {
+        github::fetch_latest_release(&self.client, LISTUI_REPO).await
+    }
+
+    /// Gets the latest release of yt-dlp from GitHub.
+    pub async fn fetch_latest_ytdlp(&self) -> Result<Option<Release>, ApiError> {
+        github::fetch_latest_release(&self.client, YTDLP_REPO).await
+    }
+
+    /// Downloads the latest yt-dlp binary for this system as `path`, replacing the one there.
+    pub async fn install_ytdlp(&self, path: &Path) -> Result<(), ApiError> {
+        github::install_ytdlp(&self.client, path).await
+    }
+
+    /// Fetches the videos uploaded to a channel since the Unix timestamp `since`, newest first.
+    ///
+    /// `uploads_id` is the id of the playlist with the channel's uploads (UU...). Only its
+    /// first page is checked, so at most the last 50 uploads are returned.
+    pub async fn fetch_recent_uploads(&self, uploads_id: &str, since: u64) -> Result<Vec<NewVideo>, ApiError> {
+
+        if let Some(api_key) = self.api_key.as_ref() {
+            self.send_callback(FetchProgress::Trying { what: format!("the uploads of {uploads_id}"), source: FetchSource::YouTube });
+            let response = self.send(self.client.get(format!("{}/playlistItems?maxResults=50&part=snippet,contentDetails&key={}&playlistId={}", YOUTUBE_API_URL, api_key, uploads_id))).await
+                .map_err(convert_reqwest_err)?;
+
+            let content = parse_youtube_response(response).await?;
+            return Ok(content.items.into_iter()
+                .filter(|v| v.snippet.published_at.as_deref().and_then(parse_timestamp).is_some_and(|published| published >= since))
+                .filter_map(|v| {
+                    let uploaded_at = v.upload_time();
+                    Some(NewVideo::new(v.snippet.title, v.snippet.resource_id?.video_id)
+                        .with_channel(v.snippet.video_owner_channel_title)
+                        .with_upload_time(uploaded_at))
+                })
+                .collect());
+        }
+
+        // Invidious has the upload dates in the channel's videos, not in its playlists.
+        let channel_id = uploads_id.strip_prefix("UU")
+            .map(|id| format!("UC{id}"))
+            .ok_or(ApiError::ChannelNotFoundError(String::from(uploads_id)))?;
+
+        let what = format!("the uploads of {channel_id}");
+        let mut r: Result<Vec<NewVideo>, ApiError> = Err(ApiError::Unknown);
+        for instance in &self.instances {
+            let source = FetchSource::Invidious(instance.clone());
+            self.send_callback(FetchProgress::Trying { what: what.clone(), source: source.clone() });
+            r = self.fetch_invidious_recent_uploads(instance, &channel_id, since).await;
+            match &r {
+                Ok(_) => break,
+                Err(e) => self.send_callback(FetchProgress::Failed { what: what.clone(), source, error: e.to_string() })
+            }
+        }
+        r
+    }
+
+    /// Searches for another upload of a video that is no longer available, using `yt-dlp`.
+    ///
+    /// Imported tracks don't store their length, so the candidates are only compared by title.
+    pub async fn find_alternate(&self, title: &str, yt_id: &str) -> Result<Option<NewVideo>, ApiError> {
+
+        self.send_callback(FetchProgress::Searching { title: String::from(title) });
+        let candidates = ytdlp::search(&self.tools, title, 5).await?;
+        Ok(best_alternate(title, candidates.into_iter().filter(|video| video.source() != yt_id)))
+    }
+
+    /// Gets the id of the channel with the given handle, using Youtube's API.
+    async fn fetch_youtube_channel_id(&self, handle: &str) -> Result<String, ApiError> {
+
+        let response = self.send(self.client.get(format!("{}/channels?part=id&key={}&forHandle={}", YOUTUBE_API_URL, self.api_key.as_ref().unwrap(), handle))).await
+            .map_err(convert_reqwest_err)?;
+
+        let content = serde_json::from_str::<yt_api::ChannelsResponse>(&response.text_with_charset("utf-8").await
+            .map_err(|_| ApiError::DecodingError)?)
+            .map_err(|_| ApiError::ParsingError)?;
+
+        content.items.into_iter().next()
+            .map(|channel| channel.id)
+            .ok_or(ApiError::ChannelNotFoundError(String::from(handle)))
+    }
+
+    async fn fetch_invidious_recent_uploads(&self, instance: &str, channel_id: &str, since: u64) -> Result<Vec<NewVideo>, ApiError> {
+
+        let response = self.send(self.client.get(format!("{}/api/v1/channels/{}/videos", instance, channel_id))).await
+            .and_then(|r| r.error_for_status())
+            .map_err(convert_reqwest_err)?;
+
+        let content = serde_json::from_str::<invidious_api::ChannelVideosResponse>(&response.text_with_charset("utf-8").await
+            .map_err(|_| ApiError::DecodingError)?)
+            .map_err(|_| ApiError::ParsingError)?;
+
+        Ok(content.videos.into_iter()
+            .filter(|video| video.published >= since)
+            .map(|video| NewVideo::new(video.title, video.video_id).with_channel(video.author).with_upload_time(i64::try_from(video.published).ok()))
+            .collect())
+    }
+
+    /// Gets the id of the channel with the given handle, using Invidious' API.
+    async fn fetch_invidious_channel_id(&self, instance: &str, handle: &str) -> Result<String, ApiError> {
+
+        let response = self.send(self.client.get(format!("{}/api/v1/resolveurl?url=https://www.youtube.com/{}", instance, handle))).await
+            .map_err(convert_reqwest_err)?;
+
+        let content = serde_json::from_str::<invidious_api::ResolveUrlResponse>(&response.text_with_charset("utf-8").await
+            .map_err(|_| ApiError::DecodingError)?)
+            .map_err(|_| ApiError::ParsingError)?;
+
+        content.ucid.ok_or(ApiError::ChannelNotFoundError(String::from(handle)))
+    }
+
+    /// Tries to fetch the information about a single YouTube video.
+    pub async fn fetch_video(&self, yt_id: &str) -> Result<NewVideo, ApiError> {
+
+        let what = format!("video {yt_id}");
+        if self.api_key.is_some() {
+            self.send_callback(FetchProgress::Trying { what, source: FetchSource::YouTube });
+            self.fetch_youtube_video(yt_id).await
+        }
+        else {
+            let mut r: Result<NewVideo, ApiError> = Err(ApiError::Unknown);
+            for instance in &self.instances {
+                let source = FetchSource::Invidious(instance.clone());
+                self.send_callback(FetchProgress::Trying { what: what.clone(), source: source.clone() });
+                r = self.fetch_invidious_video(instance, yt_id).await;
+                match &r {
+                    Ok(_) => return r,
+                    Err(e) => self.send_callback(FetchProgress::Failed { what: what.clone(), source, error: e.to_string() })
+                }
+            }
+            for instance in PIPED_INSTANCES {
+                let source = FetchSource::Piped(String::from(instance));
+                self.send_callback(FetchProgress::Trying { what: what.clone(), source: source.clone() });
+                r = self.fetch_piped_video(instance, yt_id).await;
                 match &r {
                     Ok(_) => break,
-                    Err(e) => self.send_callback(format!("Cloud not fetch playlist {yt_id} from {instance}: {e}"))    
+                    Err(e) => self.send_callback(FetchProgress::Failed { what: what.clone(), source, error: e.to_string() })
                 }
             }
-            r   
+            r
         }
     }
 
-    /// Gets a playlist's title using Youtube's API.
-    async fn fetch_youtube_playlist_info(&self,  yt_id: &str) -> Result<NewPlaylist, ApiError> {
+    /// Gets a video's title using Youtube's API.
+    async fn fetch_youtube_video(&self, yt_id: &str) -> Result<NewVideo, ApiError> {
 
-        let response = self.client.get(format!("{}/playlists?part=snippet&key={}&id={}", YOUTUBE_API_URL, self.api_key.as_ref().unwrap(), yt_id))
-            .send().await
+        let response = self.send(self.client.get(format!("{}/videos?part=snippet&key={}&id={}", YOUTUBE_API_URL, self.api_key.as_ref().unwrap(), yt_id))).await
             .map_err(convert_reqwest_err)?;
-    
+
         let mut content = parse_youtube_response(response).await?;
+        if content.items.len() == 1 {
+
+            let video = content.items.remove(0);
+            // The snippet of a video (not of a playlist item) is published when it's uploaded.
+            let uploaded_at = video.snippet.published_at.as_deref().and_then(parse_timestamp).and_then(|time| i64::try_from(time).ok());
+            Ok(NewVideo::new(video.snippet.title, video.id).with_channel(video.snippet.channel_title).with_upload_time(uploaded_at))
+        }
+        else { Err(ApiError::VideoNotFoundError(String::from(yt_id))) }
+    }
+
+    /// Gets a video's title using Invidious' API.
+    async fn fetch_invidious_video(&self, instance: &str, yt_id: &str) -> Result<NewVideo, ApiError> {
+
+        let response = self.send(self.client.get(format!("{}/api/v1/videos/{}?fields=title,videoId,author", instance, yt_id))).await
+            .map_err(convert_reqwest_err)?;
+
+        let video = serde_json::from_str::<invidious_api::VideoResponse>(&response.text_with_charset("utf-8").await
+            .map_err(|_| ApiError::DecodingError)?)
+            .map_err(|_| ApiError::ParsingError)?;
+
+        Ok(NewVideo::new(video.title, video.video_id).with_channel(video.author))
+    }
+
+    /// Gets a video's title using Piped's API.
+    async fn fetch_piped_video(&self, instance: &str, yt_id: &str) -> Result<NewVideo, ApiError> {
+
+        let response = self.send(self.client.get(format!("{}/streams/{}", instance, yt_id))).await
+            .map_err(convert_reqwest_err)?;
+
+        let video = serde_json::from_str::<piped_api::StreamResponse>(&response.text_with_charset("utf-8").await
+            .map_err(|_| ApiError::DecodingError)?)
+            .map_err(|_| ApiError::ParsingError)?;
+
+        Ok(NewVideo::new(video.title, String::from(yt_id)).with_channel(video.uploader))
+    }
+
+    /// Gets a playlist's title using Youtube's API.
+    async fn fetch_youtube_playlist_info(&self,  yt_id: &str) -> Result<NewPlaylist, ApiError> {
+
+        let body = self.fetch_playlist_page(yt_id, self.client.get(format!("{}/playlists?part=snippet&key={}&id={}", YOUTUBE_API_URL, self.api_key.as_ref().unwrap(), yt_id))).await?;
+        let mut content = serde_json::from_str::<yt_api::ApiResponse>(&body).map_err(|_| ApiError::ParsingError)?;
         if content.items.len() == 1 {
             
             let playlist = content.items.remove(0);
-            Ok(NewPlaylist {
-                title: playlist.snippet.title,
-                yt_id: playlist.id
-            })  
+            let thumbnail = playlist.snippet.thumbnails.as_ref().and_then(yt_api::Thumbnails::biggest);
+            Ok(NewPlaylist::new(playlist.snippet.title, playlist.id)
+                .with_details(playlist.snippet.channel_title, playlist.snippet.description, thumbnail))
         }
         else { Err(ApiError::NotFoundError(String::from(yt_id))) }
     }
 
     /// Gets information about all songs in a playlist, using Youtube's API.
+    ///
+    /// Unlike Invidious' pages, these are requested one after the other: each page only has
+    /// the token of the next one.
     async fn fetch_youtube_videos(&self, playlist_ytid: &str) -> Result<Vec<NewVideo>, ApiError> {
 
         let mut videos: Vec<NewVideo> = Vec::new();
         let mut next_page_token: Option<String> = None;
         loop {
             
-            let mut url = format!("{}/playlistItems?maxResults=50&part=snippet&key={}&playlistId={}", 
+            let mut url = format!("{}/playlistItems?maxResults=50&part=snippet,contentDetails&key={}&playlistId={}", 
                 YOUTUBE_API_URL,
                 self.api_key.as_ref().unwrap(), 
                 playlist_ytid
@@ -144,23 +769,19 @@ impl ApiClient {
                 url.push_str(&format!("&pageToken={token}"));
             }
 
-            let response = self.client.get(url)
-                .send().await
-                .map_err(convert_reqwest_err)?;
-
-            let content = parse_youtube_response(response).await?;
+            let body = self.fetch_playlist_page(playlist_ytid, self.client.get(url)).await?;
+            let content = serde_json::from_str::<yt_api::ApiResponse>(&body).map_err(|_| ApiError::ParsingError)?;
             videos.extend(content.items.into_iter()
                 .filter(|v| v.snippet.title != "Deleted video" && v.snippet.title  != "Private video" && v.snippet.resource_id.is_some())
                 .filter_map(|v|{
-                    Some(NewVideo {
-                        title: v.snippet.title,
-                        yt_id: v.snippet.resource_id.ok_or(ApiError::ParsingError).ok()?.video_id,
-                        playlist_id: None
-                    })
+                    let uploaded_at = v.upload_time();
+                    let video_id = v.snippet.resource_id.ok_or(ApiError::ParsingError).ok()?.video_id;
+                    Some(NewVideo::new(v.snippet.title, video_id).with_channel(v.snippet.video_owner_channel_title).with_upload_time(uploaded_at))
                 })
             );
 
-            self.send_callback(format!("Fetched {} videos.", videos.len()));
+            let total = usize::try_from(content.page_info.total_results).ok();
+            self.send_callback(FetchProgress::Page { videos: videos.len(), total });
  
             next_page_token = content.next_page_token;
             if next_page_token.is_none() { break; }
@@ -169,52 +790,160 @@ impl ApiClient {
         Ok(videos)
     }
 
-    /// Gets both a playlist's title and all its videos using Youtube's API.
+    /// Gets both a playlist's title and all its videos using Invidious' API.
+    ///
+    /// Once the first page tells how many videos there are, the rest of pages are requested
+    /// `PARALLEL_PAGES` at a time.
     async fn fetch_invidious_playlist(&self, instance: &str, yt_id: &str) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
-        
+
+        let first = self.fetch_invidious_page(instance, yt_id, 1).await?;
+        let playlist = NewPlaylist::new(first.title.clone(), first.playlist_id.clone())
+            .with_details(first.author.clone(), first.description.clone(), first.playlist_thumbnail.clone());
+
+        let (page_len, video_count) = (first.videos.len(), first.video_count);
         let mut videos: Vec<NewVideo> = Vec::new();
-        let mut page: i32 = 1;
         let mut last_index: i32 = -1;
-        let mut playlist: NewPlaylist;
+        if !self.add_invidious_page(&mut videos, &mut last_index, video_count, first.videos) {
+            return Ok((playlist, videos));
+        }
 
-        loop {
-            
-            let response = self.client.get(format!("{}/api/v1/playlists/{}?page={}", instance, yt_id, page)).send().await
-                .map_err(convert_reqwest_err)?;
+        // The pages come back in order, even if they are requested at the same time.
+        let estimated_pages = video_count.div_ceil(page_len).max(1);
+        self.send_callback(FetchProgress::PageOf { page: 1, pages: estimated_pages });
+        let mut pages = stream::iter(2..=estimated_pages)
+            .map(|page| self.fetch_invidious_page(instance, yt_id, page))
+            .buffered(PARALLEL_PAGES);
+        let mut page = 2;
+        while let Some(content) = pages.next().await {
+            if !self.add_invidious_page(&mut videos, &mut last_index, video_count, content?.videos) {
+                return Ok((playlist, videos));
+            }
+            self.send_callback(FetchProgress::PageOf { page, pages: estimated_pages });
+            page += 1;
+        }
 
-            let content = parse_invidious_reponse(response).await?;
-            playlist = NewPlaylist {
-                title: content.title,
-                yt_id: content.playlist_id
-            };
+        // If the count was off, the rest of pages are fetched one by one.
+        while self.add_invidious_page(&mut videos, &mut last_index, video_count, self.fetch_invidious_page(instance, yt_id, page).await?.videos) {
+            self.send_callback(FetchProgress::PageOf { page, pages: page });
+            page += 1;
+        }
 
-            if content.videos.is_empty() { break; }
+        Ok((playlist, videos))
+    }
 
-            // Invidious api paging is a bit weird, and it can return the same videos in multiple pages.
-            // To prevent saving the same video multiple times, the index of the last song in the previous
-            // query is saved, and then it's used to filter the videos in the next query.
-            let x = content.videos.last().unwrap().index;  
+    // Adds the videos of the next page of an Invidious playlist, returning false if it was empty.
+    //
+    // Invidious api paging is a bit weird, and it can return the same videos in multiple pages.
+    // To prevent saving the same video multiple times, the index of the last song in the previous
+    // page is saved, and then it's used to filter the videos in the next one.
+    fn add_invidious_page(&self, videos: &mut Vec<NewVideo>, last_index: &mut i32, video_count: usize, page: Vec<invidious_api::Video>) -> bool {
 
-            videos.extend(content.videos.into_iter()
-            .filter(|v| v.index > last_index && v.title != "[Deleted video]" && v.title  != "[Private video]")
+        let Some(last) = page.last().map(|v| v.index) else { return false };
+        videos.extend(page.into_iter()
+            .filter(|v| v.index > *last_index && v.title != "[Deleted video]" && v.title  != "[Private video]")
             .map(|v| {
-                NewVideo {
-                    title: v.title,
-                    yt_id: v.video_id,
-                    playlist_id: None
-                }
+                NewVideo::new(v.title, v.video_id).with_channel(v.author).with_duration(v.length_seconds)
             }));
 
-            self.send_callback(format!("Fetched {} videos.", videos.len()));
+        self.send_callback(FetchProgress::Page { videos: videos.len(), total: Some(video_count) });
+        *last_index = last;
+        true
+    }
 
-            last_index = x;
-            page += 1;
+    async fn fetch_invidious_page(&self, instance: &str, yt_id: &str, page: usize) -> Result<invidious_api::PlaylistResponse, ApiError> {
+
+        let body = self.fetch_playlist_page(yt_id, self.client.get(format!("{}/api/v1/playlists/{}?page={}", instance, yt_id, page))).await?;
+        serde_json::from_str::<invidious_api::PlaylistResponse>(&body).map_err(|_| ApiError::ParsingError)
+    }
+
+    /// Gets both a playlist's title and all its videos using Piped's API.
+    async fn fetch_piped_playlist(&self, instance: &str, yt_id: &str) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
+
+        let body = self.fetch_playlist_page(yt_id, self.client.get(format!("{}/playlists/{}", instance, yt_id))).await?;
+        let content = serde_json::from_str::<piped_api::PlaylistResponse>(&body).map_err(|_| ApiError::ParsingError)?;
+
+        let playlist = NewPlaylist::new(content.name, yt_id)
+            .with_details(content.uploader, content.description, content.thumbnail_url);
+
+        let mut videos: Vec<NewVideo> = Vec::new();
+        let mut streams = content.related_streams;
+        let mut next_page = content.nextpage;
+        loop {
+
+            let empty = streams.is_empty();
+            videos.extend(streams.into_iter()
+                .filter(|v| v.title != "[Deleted video]" && v.title != "[Private video]")
+                .filter_map(|v| {
+                    let id = String::from(v.video_id()?);
+                    let uploaded_at = v.upload_time();
+                    Some(NewVideo::new(v.title, id).with_channel(v.uploader_name).with_duration(v.duration).with_upload_time(uploaded_at))
+                }));
+
+            self.send_callback(FetchProgress::Page { videos: videos.len(), total: None });
+
+            // The next page is identified by an opaque token.
+            let Some(token) = next_page.filter(|_| !empty) else { break };
+            let request = self.client.get(format!("{}/nextpage/playlists/{}", instance, yt_id)).query(&[("nextpage", token)]);
+            let body = self.fetch_playlist_page(yt_id, request).await?;
+            let content = serde_json::from_str::<piped_api::NextPageResponse>(&body).map_err(|_| ApiError::ParsingError)?;
+
+            streams = content.related_streams;
+            next_page = content.nextpage;
         }
 
         Ok((playlist, videos))
     }
 
-    fn send_callback(&self, progress: String) {
+    // The body of a page of the playlist `yt_id`, from the cache if it was saved recently.
+    // Only successful responses are saved.
+    async fn fetch_playlist_page(&self, yt_id: &str, request: reqwest::RequestBuilder) -> Result<String, ApiError> {
+
+        let url = request.try_clone()
+            .and_then(|request| request.build().ok())
+            .map(|request| request.url().to_string());
+        let cached = self.cache.as_ref().zip(url.as_ref());
+        if let Some(body) = cached.and_then(|(cache, url)| cache.get(yt_id, url)) {
+            return Ok(body);
+        }
+
+        let response = self.send(request).await.map_err(convert_reqwest_err)?;
+        let success = response.status().is_success();
+        let body = response.text_with_charset("utf-8").await.map_err(|_| ApiError::DecodingError)?;
+        if let Some((cache, url)) = cached.filter(|_| success) {
+            cache.put(yt_id, url, &body);
+        }
+        Ok(body)
+    }
+
+    /// Forgets the saved pages of a playlist, so the next fetch gets all of them again.
+    pub fn invalidate_cache(&self, yt_id: &str) {
+        if let Some(Err(e)) = self.cache.as_ref().map(|cache| cache.invalidate(yt_id)) {
+            log::warn!("Failed to clear the cache of {yt_id}: {e}");
+        }
+    }
+
+    // Sends `request`, retrying it as `self.retry` says. Only the last response or error is returned.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Response, reqwest::Error> {
+
+        let mut backoff = self.retry.backoff;
+        for _ in 0..self.retry.retries {
+            // Requests with a streamed body can't be sent again, but none of them have one.
+            let Some(attempt) = request.try_clone() else { break };
+            let temporary = match attempt.send().await {
+                Ok(response) if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS || response.status().is_server_error() => {
+                    format!("{} answered {}", response.url(), response.status())
+                },
+                Err(e) if e.is_connect() => e.to_string(),
+                result => return result
+            };
+            log::warn!("{temporary}, retrying in {}ms.", backoff.as_millis());
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+        request.send().await
+    }
+
+    fn send_callback(&self, progress: FetchProgress) {
         log::info!("{progress}");
         if let Some(callback) = &self.callback {
             callback(progress);
@@ -222,24 +951,113 @@ impl ApiClient {
     }
 }
 
-async fn parse_youtube_response(response: Response) -> Result<yt_api::ApiResponse, ApiError> {
+// Converts an RFC 3339 UTC date, like `2024-03-01T18:00:00Z`, into a Unix timestamp.
+fn parse_timestamp(date: &str) -> Option<u64> {
 
-    serde_json::from_str::<yt_api::ApiResponse>(&response.text_with_charset("utf-8").await
-        .map_err(|_| ApiError::DecodingError)?)
-        .map_err(|_| ApiError::ParsingError)
+    let (day, time) = date.split_once('T')?;
+    let mut day = day.splitn(3, '-').map(|n| n.parse::<i64>().ok());
+    let (year, month, day) = (day.next()??, day.next()??, day.next()??);
+    let mut time = time.trim_end_matches('Z').splitn(3, ':').map(|n| n.split('.').next()?.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+
+    // Days since 1970-01-01, from Howard Hinnant's days_from_civil.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    u64::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+// Words that uploaders add to titles, and that don't tell one song from another.
+const TITLE_NOISE: [&str; 8] = ["official", "video", "audio", "lyrics", "lyric", "hd", "hq", "remastered"];
+
+fn title_words(title: &str) -> Vec<String> {
+
+    title.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !TITLE_NOISE.contains(word))
+        .map(String::from)
+        .collect()
+}
+
+/// The words of a title that tell which song it is, to find the same song uploaded with another title.
+pub fn normalize_title(title: &str) -> String {
+    title_words(title).join(" ")
+}
+
+/// Picks the candidate whose title shares the most words with `title`, if it shares at least 3 out of 4.
+pub fn best_alternate(title: &str, candidates: impl IntoIterator<Item = NewVideo>) -> Option<NewVideo> {
+
+    let words = title_words(title);
+    if words.is_empty() { return None; }
+
+    candidates.into_iter()
+        .map(|video| {
+            let candidate_words = title_words(&video.title);
+            let shared = words.iter().filter(|word| candidate_words.contains(word)).count();
+            (shared as f64 / words.len() as f64, video)
+        })
+        .filter(|(score, _)| *score >= 0.75)
+        // The first of the best ones, as search results come sorted by relevance.
+        .min_by(|(a, _), (b, _)| b.total_cmp(a))
+        .map(|(_, video)| video)
 }
 
-async fn parse_invidious_reponse(response: Response) -> Result<invidious_api::PlaylistResponse, ApiError> {
+async fn parse_youtube_response(response: Response) -> Result<yt_api::ApiResponse, ApiError> {
 
-    serde_json::from_str::<invidious_api::PlaylistResponse>(&response.text_with_charset("utf-8").await
+    serde_json::from_str::<yt_api::ApiResponse>(&response.text_with_charset("utf-8").await
         .map_err(|_| ApiError::DecodingError)?)
         .map_err(|_| ApiError::ParsingError)
 }
 
+
 fn convert_reqwest_err(err: reqwest::Error) -> ApiError {
 
+    if err.is_timeout() {
+        return ApiError::RequestError(String::from("The server took too long to answer."));
+    }
     match err.status() {
         Some(err) => { ApiError::RequestError(err.to_string())},
         None => ApiError::Unknown,
     }
-}
\ No newline at end of file
+}
+
+fn build_client(connect: Duration, request: Duration) -> reqwest::Client {
+
+    reqwest::Client::builder()
+        .connect_timeout(connect)
+        .timeout(request)
+        .build()
+        .unwrap_or_default()
+}
+
+// Streams the body of `request` into `file_path`.
+//
+// It's written into a temporary file first, so a half downloaded track is never played.
+async fn download_file(request: reqwest::RequestBuilder, file_path: &Path) -> Result<(), ApiError> {
+
+    let mut response = request
+        .send().await
+        .and_then(|r| r.error_for_status())
+        .map_err(convert_reqwest_err)?;
+
+    let part_path = file_path.with_extension("part");
+    let mut file = tokio::fs::File::create(&part_path).await
+        .map_err(|e| ApiError::RequestError(e.to_string()))?;
+
+    let result = async {
+        while let Some(chunk) = response.chunk().await.map_err(convert_reqwest_err)? {
+            file.write_all(&chunk).await.map_err(|e| ApiError::RequestError(e.to_string()))?;
+        }
+        file.flush().await.map_err(|e| ApiError::RequestError(e.to_string()))?;
+        tokio::fs::rename(&part_path, file_path).await.map_err(|e| ApiError::RequestError(e.to_string()))
+    }.await;
+
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&part_path).await;
+    }
+    result
+}
@@ -0,0 +1,203 @@
+//! MusicBrainz, the open music encyclopedia, to find the artist, album and year of a track.
+//!
+//! Recordings are searched by their title and artist. MusicBrainz allows one request per second
+//! from each client, and rejects the ones without a User-Agent that says who is asking.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use super::{convert_reqwest_err, ApiError};
+
+const MUSICBRAINZ_URL: &str = "https://musicbrainz.org/ws/2";
+
+// Time between requests, as MusicBrainz asks.
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+// How sure MusicBrainz has to be (out of 100) for a recording to be taken.
+const MIN_SCORE: u32 = 90;
+
+// Seconds a recording can be longer or shorter than the track, since videos often have an intro.
+const MAX_LENGTH_DIFFERENCE: i32 = 15;
+
+#[derive(Deserialize, Debug)]
+struct SearchResponse {
+    #[serde(default)]
+    recordings: Vec<MbRecording>
+}
+
+#[derive(Deserialize, Debug)]
+struct MbRecording {
+    #[serde(default)]
+    score: u32,
+    title: String,
+    /// In milliseconds.
+    length: Option<u64>,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    releases: Vec<MbRelease>
+}
+
+#[derive(Deserialize, Debug)]
+struct ArtistCredit {
+    name: String,
+    #[serde(default)]
+    joinphrase: String
+}
+
+#[derive(Deserialize, Debug)]
+struct MbRelease {
+    title: String,
+    status: Option<String>,
+    date: Option<String>,
+    #[serde(rename = "release-group")]
+    release_group: Option<ReleaseGroup>
+}
+
+#[derive(Deserialize, Debug)]
+struct ReleaseGroup {
+    #[serde(rename = "primary-type")]
+    primary_type: Option<String>
+}
+
+/// What MusicBrainz knows about a track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub title: String,
+    /// Everyone credited, like "Artist feat. Guest".
+    pub artist: String,
+    /// The first album it was released in, or the first single or EP if it isn't in any.
+    pub album: Option<String>,
+    pub year: Option<i32>
+}
+
+#[derive(Clone)]
+pub struct MusicBrainzClient {
+    client: reqwest::Client,
+    url: String,
+    // When the last request was sent, shared by the clones so they all keep to the rate limit.
+    last_request: Arc<Mutex<Option<Instant>>>
+}
+
+impl Default for MusicBrainzClient {
+
+    fn default() -> Self {
+        Self::new(MUSICBRAINZ_URL)
+    }
+}
+
+impl MusicBrainzClient {
+
+    /// A client of the MusicBrainz API at `url`, like `https://musicbrainz.org/ws/2`.
+    pub fn new(url: &str) -> Self {
+
+        Self {
+            client: reqwest::Client::new(),
+            url: String::from(url.trim_end_matches('/')),
+            last_request: Arc::new(Mutex::new(None))
+        }
+    }
+
+    /// Searches the recording of a track, by its title and its artist, if it's known. Titles like
+    /// "Artist - Song (Official Video)" are split, and the bracketed parts left out. `duration`, in
+    /// seconds, rules out the recordings that are much longer or shorter.
+    pub async fn lookup(&self, title: &str, artist: Option<&str>, duration: Option<i32>) -> Result<Option<Recording>, ApiError> {
+
+        let (title, artist) = search_terms(title, artist);
+        if title.is_empty() {
+            return Ok(None);
+        }
+        let mut query = format!("recording:\"{}\"", escape(&title));
+        if let Some(artist) = artist {
+            query.push_str(&format!(" AND artist:\"{}\"", escape(&artist)));
+        }
+
+        // One request at a time, a second after the last one.
+        let mut last_request = self.last_request.lock().await;
+        if let Some(wait) = last_request.map(|last| REQUEST_INTERVAL.saturating_sub(last.elapsed())) {
+            tokio::time::sleep(wait).await;
+        }
+        let response = self.client.get(format!("{}/recording", self.url))
+            .query(&[("query", query.as_str()), ("fmt", "json"), ("limit", "5")])
+            .header("User-Agent", concat!("lisTUI/", env!("CARGO_PKG_VERSION"), " ( https://github.com/gulis1/lisTUI )"))
+            .send().await;
+        *last_request = Some(Instant::now());
+        drop(last_request);
+
+        let content = response.and_then(|response| response.error_for_status())
+            .map_err(convert_reqwest_err)?
+            .text_with_charset("utf-8").await
+            .map_err(|_| ApiError::DecodingError)?;
+        parse_search(&content, duration)
+    }
+}
+
+// The best recording of a search response, if MusicBrainz is sure enough about it and it's about
+// as long as the track.
+fn parse_search(content: &str, duration: Option<i32>) -> Result<Option<Recording>, ApiError> {
+
+    let response = serde_json::from_str::<SearchResponse>(content).map_err(|_| ApiError::ParsingError)?;
+    Ok(response.recordings.into_iter()
+        .filter(|recording| recording.score >= MIN_SCORE)
+        .find(|recording| match (recording.length, duration) {
+            (Some(length), Some(duration)) => ((length / 1000) as i32 - duration).abs() <= MAX_LENGTH_DIFFERENCE,
+            _ => true
+        })
+        .map(|recording| {
+            let artist = recording.artist_credit.iter().map(|credit| format!("{}{}", credit.name, credit.joinphrase)).collect();
+            // Official albums first, then singles and the rest, each from the oldest.
+            let release = recording.releases.iter()
+                .filter(|release| release.status.as_deref().is_none_or(|status| status == "Official"))
+                .min_by_key(|release| {
+                    let is_album = release.release_group.as_ref().and_then(|group| group.primary_type.as_deref()) == Some("Album");
+                    (!is_album, release.date.as_deref().filter(|date| !date.is_empty()).unwrap_or("9999"))
+                });
+            Recording {
+                title: recording.title,
+                artist,
+                album: release.map(|release| release.title.clone()),
+                year: release.and_then(|release| release.date.as_ref()?.get(..4)?.parse().ok())
+            }
+        }))
+}
+
+// The title and artist to search, from the title of a video and the channel that uploaded it.
+fn search_terms(title: &str, artist: Option<&str>) -> (String, Option<String>) {
+
+    // Leaves out "(Official Video)", "[HD]" and the like.
+    let mut clean = String::new();
+    let mut depth = 0;
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = (depth - 1).max(0),
+            _ if depth == 0 => clean.push(c),
+            _ => {}
+        }
+    }
+
+    let artist = artist.map(str::trim).filter(|artist| !artist.is_empty());
+    match clean.split_once(" - ") {
+        // Videos are usually titled "Artist - Song", and uploaded by anyone, so that artist is the one searched.
+        Some((by, song)) if !by.trim().is_empty() && !song.trim().is_empty() => {
+            (String::from(song.trim()), Some(String::from(by.trim())))
+        }
+        _ => (String::from(clean.trim()), artist.map(String::from))
+    }
+}
+
+// Backslashes the characters that mean something in a Lucene query.
+fn escape(term: &str) -> String {
+
+    let mut escaped = String::new();
+    for c in term.chars() {
+        if "+-&|!(){}[]^\"~*?:\\/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
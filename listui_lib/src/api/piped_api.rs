@@ -0,0 +1,57 @@
+//! Module with structs for Piped's API.
+
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamItem {
+
+    /// Relative URL of the video, like `/watch?v=<id>`.
+    pub url: String,
+    pub title: String,
+    pub uploader_name: Option<String>,
+    /// In seconds, -1 for lives.
+    #[serde(default)]
+    pub duration: Option<i32>,
+    /// Unix timestamp of the upload in milliseconds, -1 if it's unknown.
+    #[serde(default)]
+    pub uploaded: Option<i64>,
+}
+
+impl StreamItem {
+
+    pub fn video_id(&self) -> Option<&str> {
+        self.url.split_once("v=").map(|(_, id)| id.split('&').next().unwrap_or(id))
+    }
+
+    /// In seconds.
+    pub fn upload_time(&self) -> Option<i64> {
+        self.uploaded.map(|uploaded| uploaded / 1000)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistResponse {
+    pub name: String,
+    pub uploader: Option<String>,
+    pub description: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub related_streams: Vec<StreamItem>,
+    pub nextpage: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NextPageResponse {
+    pub related_streams: Vec<StreamItem>,
+    pub nextpage: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamResponse {
+    pub title: String,
+    pub uploader: Option<String>,
+}
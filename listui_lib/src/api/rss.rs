@@ -0,0 +1,36 @@
+//! Podcasts, fetched from their RSS feed.
+
+use crate::models::{NewPlaylist, NewVideo};
+use super::{convert_reqwest_err, ApiError};
+
+/// Prefix of the ids of playlists that are podcast feeds.
+pub const RSS_PREFIX: &str = "rss:";
+
+/// Gets the title and episodes of the podcast at `url`.
+///
+/// The episodes are saved with the URL of their audio file as `yt_id`, and sorted from oldest
+/// to newest, so new episodes end up at the bottom after updating the podcast.
+pub async fn fetch_feed(client: &reqwest::Client, url: &str) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
+
+    let response = client.get(url)
+        .send().await
+        .and_then(|r| r.error_for_status())
+        .map_err(convert_reqwest_err)?;
+
+    let content = response.bytes().await.map_err(|_| ApiError::DecodingError)?;
+    let channel = rss::Channel::read_from(&content[..]).map_err(|_| ApiError::ParsingError)?;
+
+    let videos = channel.items().iter().rev()
+        .filter_map(|item| {
+            let url = item.enclosure()?.url().to_string();
+            Some(NewVideo::new(item.title().unwrap_or("Untitled episode"), url))
+        })
+        .collect();
+
+    let author = channel.itunes_ext().and_then(|itunes| itunes.author()).map(String::from);
+    let image = channel.image().map(|image| image.url().to_string())
+        .or_else(|| channel.itunes_ext().and_then(|itunes| itunes.image()).map(String::from));
+    let playlist = NewPlaylist::new(channel.title(), format!("{RSS_PREFIX}{url}"))
+        .with_details(author, Some(channel.description().to_string()), image);
+    Ok((playlist, videos))
+}
@@ -0,0 +1,155 @@
+//! Client for servers that implement the Subsonic API, like Navidrome or Gonic.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Serialize, Deserialize};
+
+use crate::models::{NewPlaylist, NewVideo};
+use super::{convert_reqwest_err, download_file, ApiError, RemotePlaylist};
+
+/// Prefix of the ids of playlists and tracks that come from a Subsonic server.
+pub const SUBSONIC_PREFIX: &str = "subsonic:";
+
+const API_VERSION: &str = "1.16.1";
+const CLIENT_NAME: &str = "listui";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ApiError_ {
+    message: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Song {
+    id: String,
+    title: String,
+    artist: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Playlist {
+    id: String,
+    name: String,
+    owner: Option<String>,
+    comment: Option<String>,
+    #[serde(default)]
+    entry: Vec<Song>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Playlists {
+    #[serde(default)]
+    playlist: Vec<Playlist>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    status: String,
+    error: Option<ApiError_>,
+    playlists: Option<Playlists>,
+    playlist: Option<Playlist>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ResponseWrapper {
+    #[serde(rename = "subsonic-response")]
+    response: Response
+}
+
+/// Client for a Subsonic-compatible server.
+///
+/// Every request is authenticated with a salted token, so the password itself is never sent.
+#[derive(Clone)]
+pub struct SubsonicClient {
+    client: reqwest::Client,
+    url: String,
+    user: String,
+    password: String
+}
+
+impl SubsonicClient {
+
+    pub fn new(url: &str, user: &str, password: &str) -> Self {
+
+        Self {
+            client: reqwest::Client::new(),
+            url: String::from(url.trim_end_matches('/')),
+            user: String::from(user),
+            password: String::from(password)
+        }
+    }
+
+    /// Gets all the playlists in the server.
+    pub async fn fetch_playlists(&self) -> Result<Vec<RemotePlaylist>, ApiError> {
+
+        let response = self.request("getPlaylists", &[]).await?;
+        Ok(response.playlists.map(|p| p.playlist).unwrap_or_default()
+            .into_iter()
+            .map(|playlist| RemotePlaylist {
+                yt_id: format!("{SUBSONIC_PREFIX}{}", playlist.id),
+                title: playlist.name
+            })
+            .collect())
+    }
+
+    /// Gets a playlist and all its tracks.
+    pub async fn fetch_playlist(&self, playlist_id: &str) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
+
+        let response = self.request("getPlaylist", &[("id", playlist_id)]).await?;
+        let playlist = response.playlist.ok_or(ApiError::NotFoundError(String::from(playlist_id)))?;
+        let videos = playlist.entry.into_iter()
+            .map(|song| {
+                let title = match &song.artist {
+                    Some(artist) => format!("{artist} - {}", song.title),
+                    None => song.title
+                };
+                NewVideo::new(title, format!("{SUBSONIC_PREFIX}{}", song.id)).with_artist(song.artist)
+            })
+            .collect();
+
+        let details = NewPlaylist::new(playlist.name, format!("{SUBSONIC_PREFIX}{}", playlist.id))
+            .with_details(playlist.owner, playlist.comment, None);
+        Ok((details, videos))
+    }
+
+    /// Downloads a track as mp3 (the server transcodes it if needed) into `file_path`.
+    pub async fn download(&self, song_id: &str, file_path: &Path) -> Result<(), ApiError> {
+
+        let request = self.client.get(self.method_url("stream", &[("id", song_id), ("format", "mp3")]));
+        download_file(request, file_path).await
+    }
+
+    async fn request(&self, method: &str, params: &[(&str, &str)]) -> Result<Response, ApiError> {
+
+        let response = self.client.get(self.method_url(method, params))
+            .send().await
+            .map_err(convert_reqwest_err)?;
+
+        let content = serde_json::from_str::<ResponseWrapper>(&response.text_with_charset("utf-8").await
+            .map_err(|_| ApiError::DecodingError)?)
+            .map_err(|_| ApiError::ParsingError)?
+            .response;
+
+        if content.status == "ok" { Ok(content) }
+        else {
+            let message = content.error.and_then(|e| e.message).unwrap_or_else(|| String::from("Unknown error."));
+            Err(ApiError::RequestError(format!("Subsonic server error: {message}")))
+        }
+    }
+
+    fn method_url(&self, method: &str, params: &[(&str, &str)]) -> String {
+
+        let salt = format!("{:x}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos());
+        let token = format!("{:x}", md5::compute(format!("{}{salt}", self.password)));
+
+        let mut url = format!("{}/rest/{method}?u={}&t={token}&s={salt}&v={API_VERSION}&c={CLIENT_NAME}&f=json", self.url, self.user);
+        for (key, value) in params {
+            url.push_str(&format!("&{key}={value}"));
+        }
+        url
+    }
+}
@@ -0,0 +1,53 @@
+//! Webhooks, that are sent a JSON object for every event, for home automation and the like.
+
+use serde::Serialize;
+
+use super::{convert_reqwest_err, ApiError};
+
+/// What is posted to the webhook, like
+/// `{"event": "track", "message": "Playing Song", "title": "Song", "artist": "Band", "yt_id": "...", "playlist": "Mix"}`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WebhookEvent {
+    pub event: String,
+    pub message: String,
+    /// The title of the track the event is about, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub yt_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub playlist: Option<String>
+}
+
+#[derive(Clone)]
+pub struct WebhookClient {
+    client: reqwest::Client,
+    url: String
+}
+
+impl WebhookClient {
+
+    pub fn new(url: &str) -> Self {
+
+        Self {
+            client: reqwest::Client::new(),
+            url: String::from(url)
+        }
+    }
+
+    /// POSTs `event` as JSON to the webhook's URL.
+    pub async fn post(&self, event: &WebhookEvent) -> Result<(), ApiError> {
+
+        let body = serde_json::to_string(event).map_err(|_| ApiError::ParsingError)?;
+        self.client.post(&self.url)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(convert_reqwest_err)?;
+
+        Ok(())
+    }
+}
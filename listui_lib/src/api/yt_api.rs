@@ -1,4 +1,4 @@
-/// Module with structs for Youtube's API.
+//! Module with structs for Youtube's API.
 
 use serde::{Serialize, Deserialize};
 
@@ -20,13 +20,60 @@ pub struct ResourceId {
 #[serde(rename_all = "camelCase")]
 pub struct Snippet {
     pub title: String,
-    pub resource_id: Option<ResourceId>
+    pub resource_id: Option<ResourceId>,
+    /// When the video was added to the playlist, like `2024-03-01T18:00:00Z`.
+    pub published_at: Option<String>,
+    /// The channel that uploaded a video of a playlist (`channel_title` is the owner of the playlist).
+    pub video_owner_channel_title: Option<String>,
+    pub channel_title: Option<String>,
+    pub description: Option<String>,
+    pub thumbnails: Option<Thumbnails>
 }
 
+/// The thumbnails of a playlist or video, from the smallest to the biggest. Not every size is
+/// always there.
 #[derive(Serialize, Deserialize, Debug)]
+pub struct Thumbnails {
+    pub default: Option<Thumbnail>,
+    pub medium: Option<Thumbnail>,
+    pub high: Option<Thumbnail>
+}
+
+impl Thumbnails {
+
+    pub fn biggest(&self) -> Option<String> {
+        self.high.as_ref().or(self.medium.as_ref()).or(self.default.as_ref()).map(|thumbnail| thumbnail.url.clone())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Thumbnail {
+    pub url: String
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemContentDetails {
+    /// When the video was uploaded, like `2024-03-01T18:00:00Z`.
+    pub video_published_at: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct Item {
     pub snippet: Snippet,
-    pub id: String
+    pub id: String,
+    /// Only sent when the `contentDetails` part is requested.
+    pub content_details: Option<ItemContentDetails>
+}
+
+impl Item {
+
+    /// Unix timestamp of the upload of a playlist's video.
+    pub fn upload_time(&self) -> Option<i64> {
+        let date = self.content_details.as_ref()?.video_published_at.as_deref()?;
+        i64::try_from(super::parse_timestamp(date)?).ok()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -35,4 +82,46 @@ pub struct ApiResponse {
     pub page_info: PageInfo,
     pub items: Vec<Item>,
     pub next_page_token: Option<String>
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Channel {
+    pub id: String
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChannelsResponse {
+    #[serde(default)]
+    pub items: Vec<Channel>
+}
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RegionRestriction {
+    pub allowed: Option<Vec<String>>,
+    pub blocked: Option<Vec<String>>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentRating {
+    pub yt_rating: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentDetails {
+    pub region_restriction: Option<RegionRestriction>,
+    pub content_rating: Option<ContentRating>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoDetails {
+    pub id: String,
+    pub content_details: ContentDetails
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VideoDetailsResponse {
+    #[serde(default)]
+    pub items: Vec<VideoDetails>
+}
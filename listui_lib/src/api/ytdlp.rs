@@ -0,0 +1,193 @@
+//! Playlists from sites other than YouTube (like Bandcamp albums), extracted with `yt-dlp`.
+//!
+//! It is also the last resort for YouTube playlists, when none of the HTTP APIs work.
+
+use serde::{Serialize, Deserialize};
+use crate::models::{NewPlaylist, NewVideo, Restriction};
+use crate::tools::Tools;
+use super::ApiError;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Entry {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub webpage_url: Option<String>,
+    pub channel: Option<String>,
+    pub uploader: Option<String>,
+    /// In seconds.
+    pub duration: Option<f64>,
+    /// Unix timestamp of the upload. Flat playlists only have it for some sites.
+    pub timestamp: Option<f64>,
+    /// Day of the upload, like `20240301`, when the exact time isn't known.
+    pub upload_date: Option<String>,
+}
+
+impl Entry {
+
+    // Bandcamp and some other sites only say who the uploader is.
+    fn channel(&self) -> Option<String> {
+        self.channel.clone().or_else(|| self.uploader.clone())
+    }
+
+    fn duration(&self) -> Option<i32> {
+        self.duration.map(|duration| duration.round() as i32)
+    }
+
+    fn upload_time(&self) -> Option<i64> {
+        self.timestamp.map(|timestamp| timestamp as i64).or_else(|| {
+            let date = self.upload_date.as_deref().filter(|date| date.len() == 8)?;
+            let timestamp = super::parse_timestamp(&format!("{}-{}-{}T00:00:00Z", &date[..4], &date[4..6], &date[6..]))?;
+            i64::try_from(timestamp).ok()
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlaylistInfo {
+    pub title: Option<String>,
+    pub webpage_url: Option<String>,
+    pub entries: Option<Vec<Entry>>,
+    pub channel: Option<String>,
+    pub uploader: Option<String>,
+    pub description: Option<String>,
+    /// From the worst to the best one.
+    pub thumbnails: Option<Vec<Thumbnail>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Thumbnail {
+    pub url: String,
+}
+
+impl PlaylistInfo {
+
+    fn playlist(&self, title: String, yt_id: &str) -> NewPlaylist {
+        let author = self.channel.clone().or_else(|| self.uploader.clone());
+        let thumbnail = self.thumbnails.as_ref().and_then(|thumbnails| thumbnails.last()).map(|thumbnail| thumbnail.url.clone());
+        NewPlaylist::new(title, yt_id).with_details(author, self.description.clone(), thumbnail)
+    }
+}
+
+/// Gets the title and tracks of the playlist at `url`, using `yt-dlp -J`.
+///
+/// The tracks are saved with their URL as `source_url`, so the downloader fetches them from there.
+pub async fn fetch_playlist(tools: &Tools, url: &str) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
+
+    let info = extract_flat(tools, url).await?
+        .ok_or(ApiError::NotFoundError(String::from(url)))?;
+
+    let title = info.title.clone().unwrap_or_else(|| String::from(url));
+    let playlist = info.playlist(title.clone(), url);
+    let videos = match info.entries {
+        Some(entries) => entries.into_iter()
+            .filter_map(|entry| {
+                let (channel, duration, uploaded_at) = (entry.channel(), entry.duration(), entry.upload_time());
+                let url = entry.webpage_url.or(entry.url)?;
+                Some(NewVideo::new(entry.title.unwrap_or_else(|| String::from("Unknown track")), url)
+                    .with_channel(channel).with_duration(duration).with_upload_time(uploaded_at))
+            })
+            .collect(),
+        // A single track.
+        None => vec![NewVideo::new(title, info.webpage_url.unwrap_or_else(|| String::from(url)))]
+    };
+
+    Ok((playlist, videos))
+}
+
+/// Gets the title and video ids of a YouTube playlist, for when none of the HTTP APIs work.
+pub async fn fetch_youtube_playlist(tools: &Tools, playlist_id: &str) -> Result<(NewPlaylist, Vec<NewVideo>), ApiError> {
+
+    let info = extract_flat(tools, &format!("https://www.youtube.com/playlist?list={playlist_id}")).await?
+        .ok_or(ApiError::NotFoundError(String::from(playlist_id)))?;
+
+    let title = info.title.clone().unwrap_or_else(|| String::from(playlist_id));
+    let playlist = info.playlist(title, playlist_id);
+    let videos = info.entries.unwrap_or_default().into_iter()
+        .filter_map(|entry| {
+            let (channel, duration, uploaded_at) = (entry.channel(), entry.duration(), entry.upload_time());
+            let id = entry.id?;
+            Some(NewVideo::new(entry.title.unwrap_or_else(|| String::from("Unknown track")), id)
+                .with_channel(channel).with_duration(duration).with_upload_time(uploaded_at))
+        })
+        .collect();
+
+    Ok((playlist, videos))
+}
+
+// Runs `yt-dlp -J --flat-playlist`, which lists the entries of a playlist without extracting each of them.
+// Returns `None` if yt-dlp couldn't extract anything from `url`.
+async fn extract_flat(tools: &Tools, url: &str) -> Result<Option<PlaylistInfo>, ApiError> {
+
+    let output = tools.ytdlp_command()
+        .arg("-J")
+        .arg("--flat-playlist")
+        .arg(url)
+        .stderr(std::process::Stdio::null())
+        .output().await
+        .map_err(|e| ApiError::RequestError(format!("Failed to run yt-dlp: {e}")))?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    serde_json::from_slice::<PlaylistInfo>(&output.stdout)
+        .map(Some)
+        .map_err(|_| ApiError::ParsingError)
+}
+
+/// Finds the videos that can't be downloaded because they are age restricted or blocked
+/// in the user's region, by asking `yt-dlp` to extract them without downloading anything.
+pub async fn probe_restrictions(tools: &Tools, yt_ids: &[String]) -> Result<Vec<(String, Restriction)>, ApiError> {
+
+    let mut restrictions = Vec::new();
+    for chunk in yt_ids.chunks(50) {
+
+        let output = tools.ytdlp_command()
+            .arg("--simulate")
+            .arg("--ignore-errors")
+            .arg("--no-warnings")
+            .arg("--print")
+            .arg("%(id)s %(age_limit)s")
+            .args(chunk.iter().map(|id| format!("https://www.youtube.com/watch?v={id}")))
+            .output().await
+            .map_err(|e| ApiError::RequestError(format!("Failed to run yt-dlp: {e}")))?;
+
+        // Videos that can be extracted print their age limit.
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some((id, age_limit)) = line.split_once(' ') {
+                if age_limit.parse::<u32>().is_ok_and(|age| age >= 18) {
+                    restrictions.push((String::from(id), Restriction::AgeRestricted));
+                }
+            }
+        }
+
+        // The rest fail with an error like "ERROR: [youtube] <id>: <reason>".
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            let Some((id, reason)) = line.strip_prefix("ERROR: [youtube] ").and_then(|l| l.split_once(": ")) else { continue };
+            if reason.contains("your country") {
+                restrictions.push((String::from(id), Restriction::RegionBlocked));
+            }
+            else if reason.contains("confirm your age") {
+                restrictions.push((String::from(id), Restriction::AgeRestricted));
+            }
+        }
+    }
+
+    Ok(restrictions)
+}
+
+/// Searches YouTube for `query`, returning up to `count` videos.
+pub async fn search(tools: &Tools, query: &str, count: usize) -> Result<Vec<NewVideo>, ApiError> {
+
+    let info = extract_flat(tools, &format!("ytsearch{count}:{query}")).await?
+        .ok_or(ApiError::RequestError(format!("Failed to search for {query}.")))?;
+
+    Ok(info.entries.unwrap_or_default().into_iter()
+        .filter_map(|entry| {
+            let (channel, duration, uploaded_at) = (entry.channel(), entry.duration(), entry.upload_time());
+            let id = entry.id?;
+            Some(NewVideo::new(entry.title.unwrap_or_default(), id).with_channel(channel).with_duration(duration).with_upload_time(uploaded_at))
+        })
+        .collect())
+}
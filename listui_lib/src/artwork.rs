@@ -0,0 +1,206 @@
+//! The thumbnails of the videos, shown as album art in the terminal.
+//!
+//! They are decoded into pixels with `ffmpeg`, and drawn with the graphics protocol of the
+//! terminal (kitty, sixel or iTerm's), or with colored half blocks in any other terminal.
+
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use tokio::process::Command;
+
+use crate::tools::Tools;
+
+/// How the album art is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    /// Colored "▀" characters, two pixels in each cell. Works in any terminal with true color.
+    Blocks,
+    Kitty,
+    Sixel,
+    /// iTerm2's inline images, also supported by WezTerm.
+    Iterm,
+}
+
+impl Protocol {
+
+    /// The protocol the terminal supports, guessed from its environment variables. Sixel
+    /// terminals can't be told apart this way, so they get blocks unless sixel is chosen.
+    pub fn detect() -> Self {
+
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        if var("TERM").contains("kitty") || !var("KITTY_WINDOW_ID").is_empty() {
+            Protocol::Kitty
+        }
+        else if ["iTerm.app", "WezTerm"].contains(&var("TERM_PROGRAM").as_str()) {
+            Protocol::Iterm
+        }
+        else { Protocol::Blocks }
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "blocks" => Ok(Protocol::Blocks),
+            "kitty" => Ok(Protocol::Kitty),
+            "sixel" => Ok(Protocol::Sixel),
+            "iterm" => Ok(Protocol::Iterm),
+            "auto" => Ok(Protocol::detect()),
+            other => Err(format!("unknown album art protocol \"{other}\""))
+        }
+    }
+}
+
+/// An image, as RGB pixels row by row.
+#[derive(Clone, PartialEq)]
+pub struct Artwork {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 3]>,
+    // The file it was decoded from, that iTerm reads itself.
+    file: Vec<u8>
+}
+
+impl std::fmt::Debug for Artwork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Artwork({}x{})", self.width, self.height)
+    }
+}
+
+/// The thumbnail of a YouTube video, 320x180.
+pub fn thumbnail_url(video_id: &str) -> String {
+    format!("https://i.ytimg.com/vi/{video_id}/mqdefault.jpg")
+}
+
+/// Downloads an image into `path`, unless it's already there.
+pub async fn fetch(url: &str, path: &Path) -> io::Result<()> {
+
+    if path.exists() {
+        return Ok(());
+    }
+    let response = reqwest::get(url).await
+        .and_then(|response| response.error_for_status())
+        .map_err(io::Error::other)?;
+    let bytes = response.bytes().await.map_err(io::Error::other)?;
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tokio::fs::write(path, bytes).await
+}
+
+impl Artwork {
+
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<[u8; 3]>) -> Self {
+        Self { width, height, pixels, file: Vec::new() }
+    }
+
+    /// Decodes an image file with the `ffmpeg` of `tools`, scaled to `width`x`height` pixels.
+    pub async fn decode(tools: &Tools, path: &Path, width: u32, height: u32) -> io::Result<Self> {
+
+        let output = Command::new(tools.ffmpeg_path())
+            .arg("-hide_banner")
+            .arg("-loglevel").arg("error")
+            .arg("-i").arg(path)
+            .arg("-vf").arg(format!("scale={width}:{height}"))
+            .arg("-frames:v").arg("1")
+            .arg("-f").arg("rawvideo")
+            .arg("-pix_fmt").arg("rgb24")
+            .arg("pipe:1")
+            .kill_on_drop(true)
+            .output()
+            .await?;
+
+        let expected = (width * height * 3) as usize;
+        if !output.status.success() || output.stdout.len() != expected {
+            return Err(io::Error::other(format!("ffmpeg failed to decode {}: {}", path.display(), String::from_utf8_lossy(&output.stderr).trim())));
+        }
+        let pixels = output.stdout.chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]).collect();
+        Ok(Self { width, height, pixels, file: tokio::fs::read(path).await? })
+    }
+
+    /// The pixel at (`x`, `y`) of the image, scaled to `width`x`height`.
+    pub fn sample(&self, x: u32, y: u32, width: u32, height: u32) -> [u8; 3] {
+        let x = (x * self.width / width.max(1)).min(self.width - 1);
+        let y = (y * self.height / height.max(1)).min(self.height - 1);
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Escape sequence that draws the image in kitty, filling `columns`x`rows` cells from the cursor.
+    pub fn kitty(&self, columns: u16, rows: u16) -> String {
+
+        let data: Vec<u8> = self.pixels.iter().flatten().copied().collect();
+        let encoded = base64(&data);
+        // The data is sent in chunks of 4096 bytes, with m=1 on every one but the last.
+        let chunks: Vec<&str> = encoded.as_bytes().chunks(4096).map(|chunk| std::str::from_utf8(chunk).unwrap_or_default()).collect();
+        let mut sequence = String::new();
+        for (ind, chunk) in chunks.iter().enumerate() {
+            let more = (ind + 1 < chunks.len()) as u8;
+            if ind == 0 {
+                sequence.push_str(&format!("\x1b_Ga=T,f=24,s={},v={},c={columns},r={rows},q=2,m={more};{chunk}\x1b\\", self.width, self.height));
+            }
+            else {
+                sequence.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\"));
+            }
+        }
+        sequence
+    }
+
+    /// Escape sequence that draws the image with iTerm's protocol, filling `columns`x`rows` cells
+    /// from the cursor.
+    pub fn iterm(&self, columns: u16, rows: u16) -> String {
+        format!("\x1b]1337;File=inline=1;size={};width={columns};height={rows};preserveAspectRatio=0:{}\x07", self.file.len(), base64(&self.file))
+    }
+
+    /// Sixel sequence of the image, with the colors reduced to a 6x6x6 cube.
+    pub fn sixel(&self) -> String {
+
+        let level = |value: u8| (value as u16 * 5 + 127) / 255;
+        let index = |[r, g, b]: [u8; 3]| (level(r) * 36 + level(g) * 6 + level(b)) as usize;
+
+        let mut sequence = format!("\x1bPq\"1;1;{};{}", self.width, self.height);
+        for color in 0..216 {
+            let (r, g, b) = (color / 36, color / 6 % 6, color % 6);
+            sequence.push_str(&format!("#{color};2;{};{};{}", r * 20, g * 20, b * 20));
+        }
+
+        // Every band of 6 rows is drawn once for each color in it.
+        for top in (0..self.height).step_by(6) {
+            let mut bands = vec![vec![0u8; self.width as usize]; 216];
+            for y in top..(top + 6).min(self.height) {
+                for x in 0..self.width {
+                    let color = index(self.pixels[(y * self.width + x) as usize]);
+                    bands[color][x as usize] |= 1 << (y - top);
+                }
+            }
+            for (color, band) in bands.iter().enumerate().filter(|(_, band)| band.iter().any(|bits| *bits != 0)) {
+                sequence.push_str(&format!("#{color}"));
+                sequence.extend(band.iter().map(|bits| (63 + bits) as char));
+                sequence.push('$');
+            }
+            sequence.push('-');
+        }
+        sequence.push_str("\x1b\\");
+        sequence
+    }
+}
+
+// Standard base64, with padding.
+fn base64(data: &[u8]) -> String {
+
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let triple = ((bytes[0] as u32) << 16) | ((bytes[1] as u32) << 8) | bytes[2] as u32;
+        for ind in 0..4 {
+            if ind <= chunk.len() {
+                encoded.push(ALPHABET[((triple >> (18 - 6 * ind)) & 0x3f) as usize] as char);
+            }
+            else { encoded.push('='); }
+        }
+    }
+    encoded
+}
@@ -1,27 +1,50 @@
 use diesel::SqliteConnection;
 use diesel::prelude::*;
+use diesel::connection::{AnsiTransactionManager, SimpleConnection, TransactionManager};
+use diesel::r2d2::{Builder, ConnectionManager, CustomizeConnection, Pool, PooledConnection};
 use diesel::result::DatabaseErrorKind;
 use diesel::result::Error as DieselError;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 
 
-use std::cell::RefCell;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::models::*;
 use crate::models::Playlist;
 use crate::schema::track as TrackTable;
 use crate::schema::playlist as PlaylistTable;
+use crate::schema::playlist_track as PlaylistTrackTable;
+use crate::schema::app_state as AppStateTable;
+use crate::schema::fingerprint as FingerprintTable;
+use crate::schema::play as PlayTable;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
 
+// Connections the background tasks and the interface can use at the same time.
+const POOL_SIZE: u32 = 4;
+
+// How long a connection waits for another one that is writing, before giving up with "database is locked".
+const BUSY_TIMEOUT_MS: u32 = 5000;
+
+/// Title of the playlist where single videos are saved.
+pub const SINGLES_PLAYLIST: &str = "Singles";
+
 #[derive(Debug)]
 pub enum DbError {
     UnknownError,
     UniqueViolation,
     NotFoundError,
     ConnectionError,
-    MigrationError
+    MigrationError,
+    /// The problems SQLite's integrity check found.
+    Corrupted(Vec<String>),
+    /// A backup couldn't be written or restored.
+    FileError(String)
 }
 
 impl std::error::Error for DbError {}
@@ -34,6 +57,8 @@ impl std::fmt::Display for DbError {
             DbError::NotFoundError => write!(f, "Item not found."),
             DbError::ConnectionError => write!(f, "Failed to connect to database."),
             DbError::MigrationError =>  write!(f, "Failed to run database migrations."),
+            DbError::Corrupted(problems) => write!(f, "The database is damaged: {}", problems.join("; ")),
+            DbError::FileError(e) => write!(f, "{e}"),
         }
     }
 }
@@ -45,29 +70,267 @@ fn run_migrations(connection: &mut SqliteConnection) -> Result<(), DbError> {
     Ok(())
 }
 
-// Struct used to communicate with the sqlite database.
+// A row of `PRAGMA integrity_check`: "ok", or one of the problems found.
+#[derive(QueryableByName)]
+struct IntegrityRow {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    integrity_check: String
+}
+
+// The problems SQLite finds in the database, none if it is fine.
+fn integrity_problems(connection: &mut SqliteConnection) -> Result<Vec<String>, DbError> {
+
+    let rows = diesel::sql_query("PRAGMA integrity_check")
+        .load::<IntegrityRow>(connection)
+        .map_err(convert_err)?;
+
+    Ok(rows.into_iter().map(|row| row.integrity_check).filter(|row| row != "ok").collect())
+}
+
+// A track found by `search_tracks`.
+#[derive(QueryableByName)]
+struct FoundTrack {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    id: i32
+}
+
+// The FTS5 query for what was typed in the search bar: every word is the start of one, and the
+// words between double quotes are a phrase. Each one is quoted, so punctuation like "AC/DC" or a
+// stray "-" can't be taken for the syntax of FTS5. None if there are no words.
+fn fts_query(query: &str) -> Option<String> {
+
+    let has_words = |text: &&str| text.chars().any(char::is_alphanumeric);
+    let terms: Vec<String> = query.split('"')
+        .enumerate()
+        .flat_map(|(ind, part)| {
+            if ind % 2 == 1 { has_words(&part).then(|| format!("\"{part}\"")).into_iter().collect::<Vec<_>>() }
+            else { part.split_whitespace().filter(has_words).map(|word| format!("\"{word}\"*")).collect() }
+        })
+        .collect();
+
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// What changed in a playlist after updating it.
+#[derive(Debug, Default, Clone)]
+pub struct TrackDiff {
+    /// The titles of the new tracks, and of the removed ones that are back.
+    pub added: Vec<String>,
+    /// The titles of the tracks that aren't in the playlist anymore.
+    pub removed: Vec<String>,
+    /// The tracks whose video has another title now, as they were before, and their new title.
+    pub renamed: Vec<(Track, String)>
+}
+
+impl TrackDiff {
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+}
+
+impl std::fmt::Display for TrackDiff {
+
+    // Like "+12 new, −3 removed, 2 renamed".
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+{} new, −{} removed, {} renamed", self.added.len(), self.removed.len(), self.renamed.len())
+    }
+}
+
+// Set up on every connection of the pool: in WAL mode, the interface can read while a background
+// task writes, and writers wait for each other instead of failing.
+#[derive(Debug)]
+struct ConnectionOptions;
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for ConnectionOptions {
+
+    fn on_acquire(&self, connection: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        connection.batch_execute(&format!("PRAGMA journal_mode = WAL; PRAGMA busy_timeout = {BUSY_TIMEOUT_MS};"))
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+// Struct used to communicate with the sqlite database. Clones share the same pool of connections,
+// so the slow queries can run on another thread.
+#[derive(Clone)]
 pub struct Database {
-    connection: RefCell<SqliteConnection>,
+    pool: Pool<ConnectionManager<SqliteConnection>>,
+    // The connection of the transaction this runs in, that every query goes through. See `transaction`.
+    held: Option<Arc<Mutex<PooledConnection<ConnectionManager<SqliteConnection>>>>>,
+}
+
+// A connection of the pool, or the one of a transaction.
+enum Conn<'a> {
+    Pooled(PooledConnection<ConnectionManager<SqliteConnection>>),
+    Held(MutexGuard<'a, PooledConnection<ConnectionManager<SqliteConnection>>>),
+}
+
+impl Deref for Conn<'_> {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &SqliteConnection {
+        match self {
+            Conn::Pooled(connection) => connection,
+            Conn::Held(connection) => connection,
+        }
+    }
+}
+
+impl DerefMut for Conn<'_> {
+
+    fn deref_mut(&mut self) -> &mut SqliteConnection {
+        match self {
+            Conn::Pooled(connection) => connection,
+            Conn::Held(connection) => connection,
+        }
+    }
 }
 
 impl Database {
 
-    /// Creates a new SQlite database connection.
+    /// Opens the SQlite database at `database_path`, creating it if needed.
     pub fn new(database_path: &Path) -> Result<Self, DbError> {
-        
-        let mut connection = SqliteConnection::establish(&database_path.as_os_str().to_string_lossy()).map_err(|_| DbError::ConnectionError)?;
-        run_migrations(&mut connection)?;
 
-        Ok(Database {
-            connection: RefCell::new(connection)
-        })
+        let manager = ConnectionManager::<SqliteConnection>::new(database_path.as_os_str().to_string_lossy());
+        Self::with_pool(manager, Pool::builder().max_size(POOL_SIZE))
     }
     
+    /// Creates a new database that only lives in memory.
+    pub fn in_memory() -> Result<Self, DbError> {
+
+        // Every connection to :memory: would get a database of its own, so the only one is never
+        // closed and replaced by an empty one.
+        let builder = Pool::builder().max_size(1).max_lifetime(None).idle_timeout(None);
+        Self::with_pool(ConnectionManager::<SqliteConnection>::new(":memory:"), builder)
+    }
+
+    fn with_pool(manager: ConnectionManager<SqliteConnection>, builder: Builder<ConnectionManager<SqliteConnection>>) -> Result<Self, DbError> {
+
+        let pool = builder
+            .connection_customizer(Box::new(ConnectionOptions))
+            .build(manager)
+            .map_err(|_| DbError::ConnectionError)?;
+
+        let database = Database { pool, held: None };
+        run_migrations(&mut *database.conn()?)?;
+        Ok(database)
+    }
+
+    /// Replaces the database at `database_path` with the backup at `backup_path`, once SQLite
+    /// has checked it. Nothing may be using the database meanwhile. The file that is replaced is
+    /// kept next to it, ending in `.old`.
+    pub fn restore(database_path: &Path, backup_path: &Path) -> Result<(), DbError> {
+
+        let mut backup = SqliteConnection::establish(&backup_path.as_os_str().to_string_lossy())
+            .map_err(|_| DbError::ConnectionError)?;
+        let problems = integrity_problems(&mut backup)?;
+        if !problems.is_empty() {
+            return Err(DbError::Corrupted(problems));
+        }
+        drop(backup);
+
+        let file_err = |e: std::io::Error| DbError::FileError(e.to_string());
+        let with_suffix = |suffix: &str| {
+            let mut path = database_path.as_os_str().to_owned();
+            path.push(suffix);
+            PathBuf::from(path)
+        };
+
+        // Copied first, so a failed copy leaves the database as it was.
+        let restoring = with_suffix(".restoring");
+        fs::copy(backup_path, &restoring).map_err(file_err)?;
+        if database_path.exists() {
+            fs::rename(database_path, with_suffix(".old")).map_err(file_err)?;
+        }
+        // The journal of the old database would be applied to the new one.
+        for journal in [with_suffix("-wal"), with_suffix("-shm")] {
+            if journal.exists() {
+                fs::remove_file(journal).map_err(file_err)?;
+            }
+        }
+        fs::rename(restoring, database_path).map_err(file_err)
+    }
+
+    /// Writes a copy of the database to `path`, that must not exist yet. The copy is taken at
+    /// once, so it's whole even if a background task is writing, and leaves out the free space.
+    pub fn backup(&self, path: &Path) -> Result<(), DbError> {
+
+        if path.exists() {
+            return Err(DbError::FileError(format!("{} already exists.", path.display())));
+        }
+
+        diesel::sql_query("VACUUM INTO ?")
+            .bind::<diesel::sql_types::Text, _>(path.to_string_lossy().into_owned())
+            .execute(&mut*self.conn()?)
+            .map(|_| ())
+            .map_err(|e| DbError::FileError(format!("Failed to back up the database: {e}")))
+    }
+
+    /// Runs SQLite's integrity check, returning the problems it finds. None means the database is fine.
+    pub fn check_integrity(&self) -> Result<Vec<String>, DbError> {
+        integrity_problems(&mut *self.conn()?)
+    }
+
+    /// Rebuilds the database file, giving back the space of the rows that were deleted.
+    pub fn vacuum(&self) -> Result<(), DbError> {
+
+        self.conn()?.batch_execute("VACUUM")
+            .map_err(convert_err)
+    }
+
+    // A connection of the pool, once one is free, or the one of the transaction.
+    fn conn(&self) -> Result<Conn<'_>, DbError> {
+
+        match &self.held {
+            Some(held) => Ok(Conn::Held(held.lock().unwrap_or_else(PoisonError::into_inner))),
+            None => self.pool.get().map(Conn::Pooled).map_err(|_| DbError::ConnectionError)
+        }
+    }
+
+    // Runs `work` in a transaction, with every query on the same connection: all it writes is
+    // saved, or nothing if it fails. Transactions inside `work` join this one.
+    fn transaction<T>(&self, work: impl FnOnce(&Database) -> Result<T, DbError>) -> Result<T, DbError> {
+
+        if self.held.is_some() {
+            return work(self);
+        }
+
+        // With the write lock taken from the start, the other writers wait for it instead of failing.
+        // Begun through Diesel, so the queries that open their own transaction use a savepoint.
+        let mut connection = self.pool.get().map_err(|_| DbError::ConnectionError)?;
+        AnsiTransactionManager::begin_transaction_sql(&mut *connection, "BEGIN IMMEDIATE").map_err(convert_err)?;
+        let held = Database { pool: self.pool.clone(), held: Some(Arc::new(Mutex::new(connection))) };
+
+        let result = work(&held);
+        let mut connection = held.conn()?;
+        let ended = match result {
+            Ok(_) => AnsiTransactionManager::commit_transaction(&mut *connection),
+            Err(_) => AnsiTransactionManager::rollback_transaction(&mut *connection),
+        };
+        if ended.is_err() {
+            // The connection goes back to the pool, it can't be left in the transaction.
+            let _ = AnsiTransactionManager::rollback_transaction(&mut *connection);
+        }
+        drop(connection);
+        let value = result?;
+        ended.map_err(convert_err)?;
+        Ok(value)
+    }
+
+    // The id of the row added by the last INSERT of the connection. Only inside `transaction`
+    // is it the connection of the INSERT.
+    fn last_insert_id(&self) -> Result<i32, DbError> {
+
+        diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>("last_insert_rowid()"))
+            .get_result::<i32>(&mut*self.conn()?)
+            .map_err(convert_err)
+    }
+
     /// Gets all the playlists from the database.
     pub fn get_playlists(&self) -> Result<Vec<Playlist>, DbError> {
 
         PlaylistTable::table
-            .load::<Playlist>(&mut*self.connection.borrow_mut())
+            .load::<Playlist>(&mut*self.conn()?)
         .map_err(convert_err)
     }
 
@@ -76,35 +339,126 @@ impl Database {
 
         PlaylistTable::table
             .filter(PlaylistTable::columns::id.is(playlist_id))
-            .first::<Playlist>(&mut*self.connection.borrow_mut())
+            .first::<Playlist>(&mut*self.conn()?)
         .map_err(convert_err)
     }
 
-    /// Saves a playlist into the database.
+    /// Saves a playlist into the database, as created and synced now.
     pub fn save_playlist(&self, plist: NewPlaylist) -> Result<Playlist, DbError> {
 
-        let result = diesel::insert_into(PlaylistTable::table)
-            .values(&plist)
-            .execute(&mut*self.connection.borrow_mut());
+        self.transaction(|dao| {
+            diesel::insert_into(PlaylistTable::table)
+                .values(&plist)
+                .execute(&mut*dao.conn()?)
+                .map_err(convert_err)?;
+
+            let id = dao.last_insert_id()?;
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+            diesel::update(PlaylistTable::table.filter(PlaylistTable::columns::id.is(id)))
+                .set((PlaylistTable::columns::created_at.eq(now), PlaylistTable::columns::last_synced_at.eq(now)))
+                .execute(&mut*dao.conn()?)
+                .map_err(convert_err)?;
+
+            dao.get_playlist(id)
+        })
+    }
+
+    /// Saves what was fetched about a playlist again, and that it was synced now. The details that
+    /// weren't sent this time are kept. The title isn't changed, since the user may have renamed it.
+    pub fn sync_playlist(&self, playlist_id: i32, details: &NewPlaylist) -> Result<Playlist, DbError> {
+
+        let saved = self.get_playlist(playlist_id)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        diesel::update(PlaylistTable::table.filter(PlaylistTable::columns::id.is(playlist_id)))
+            .set((
+                PlaylistTable::columns::author.eq(details.author.as_deref().or(saved.author.as_deref())),
+                PlaylistTable::columns::description.eq(details.description.as_deref().or(saved.description.as_deref())),
+                PlaylistTable::columns::thumbnail_url.eq(details.thumbnail_url.as_deref().or(saved.thumbnail_url.as_deref())),
+                PlaylistTable::columns::last_synced_at.eq(now)
+            ))
+            .execute(&mut*self.conn()?)
+            .map_err(convert_err)?;
+
+        self.get_playlist(playlist_id)
+    }
+
+    /// Creates an empty playlist, without a youtube id.
+    pub fn create_playlist(&self, title: &str) -> Result<Playlist, DbError> {
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        self.transaction(|dao| {
+            diesel::insert_into(PlaylistTable::table)
+                .values((PlaylistTable::columns::title.eq(title), PlaylistTable::columns::created_at.eq(now)))
+                .execute(&mut*dao.conn()?)
+                .map_err(convert_err)?;
+
+            dao.get_playlist(dao.last_insert_id()?)
+        })
+    }
 
+    /// Sets the color and emoji of a playlist. `None` removes them.
+    pub fn set_playlist_style(&self, playlist_id: i32, color: Option<&str>, emoji: Option<&str>) -> Result<Playlist, DbError> {
 
-        result.and_then(|_| {
+        diesel::update(PlaylistTable::table.filter(PlaylistTable::columns::id.is(playlist_id)))
+            .set((PlaylistTable::columns::color.eq(color), PlaylistTable::columns::emoji.eq(emoji)))
+            .execute(&mut*self.conn()?)
+            .map_err(convert_err)?;
 
-            PlaylistTable::table
-                .filter(PlaylistTable::columns::yt_id.is(plist.yt_id))
-                .first::<Playlist>(&mut*self.connection.borrow_mut())
-        }).map_err(convert_err)
+        self.get_playlist(playlist_id)
     }
 
-    /// Deletes a playlist from the database.
+    /// Changes the title of a playlist. Updating it from YouTube doesn't change it back.
+    pub fn rename_playlist(&self, playlist_id: i32, title: &str) -> Result<Playlist, DbError> {
+
+        diesel::update(PlaylistTable::table.filter(PlaylistTable::columns::id.is(playlist_id)))
+            .set(PlaylistTable::columns::title.eq(title))
+            .execute(&mut*self.conn()?)
+            .map_err(convert_err)?;
+
+        self.get_playlist(playlist_id)
+    }
+
+    /// Saves a single video into the special "Singles" playlist, creating it if needed.
+    /// 
+    /// If the video was already there, the existing track is returned.
+    pub fn add_single(&self, video: NewVideo) -> Result<(Playlist, Track), DbError> {
+
+        self.transaction(|dao| {
+            let singles = PlaylistTable::table
+                .filter(PlaylistTable::columns::title.eq(SINGLES_PLAYLIST))
+                .filter(PlaylistTable::columns::yt_id.is_null())
+                .first::<Playlist>(&mut*dao.conn()?)
+                .optional()
+                .map_err(convert_err)?;
+
+            let singles = match singles {
+                Some(playlist) => playlist,
+                None => dao.create_playlist(SINGLES_PLAYLIST)?
+            };
+
+            let existing = dao.get_tracks(singles.id)?
+                .into_iter()
+                .find(|t| t.source() == Some(video.source()));
+
+            if let Some(track) = existing {
+                return Ok((singles, track));
+            }
+
+            let track = dao.insert_track(singles.id, video)?;
+            Ok((singles, track))
+        })
+    }
+
+    /// Deletes a playlist from the database, along with the tracks that were imported with it.
     pub fn delete_playlist(&self, playlist_id: i32) -> Result<(), DbError> {
 
-        
-        let _: Result<usize, DieselError> = diesel::delete(TrackTable::table.filter(TrackTable::columns::playlist_id.is(playlist_id)))
-            .execute(&mut*self.connection.borrow_mut());
+        let _: Result<usize, DieselError> = diesel::delete(PlaylistTrackTable::table.filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id)))
+            .execute(&mut*self.conn()?);
+
+        self.delete_imported_tracks(playlist_id)?;
 
         let result: Result<usize, DieselError> = diesel::delete(PlaylistTable::table.filter(PlaylistTable::columns::id.is(playlist_id)))
-            .execute(&mut*self.connection.borrow_mut());
+            .execute(&mut*self.conn()?);
 
         match result {
 
@@ -117,12 +471,40 @@ impl Database {
         }
     }
 
+    /// Searches the tracks of a playlist by the start of the words of their title, artist or
+    /// album, like "beat rev" for "Revolution" by The Beatles. Words between double quotes have
+    /// to go together. Returns the ids of the tracks found, the best matches first.
+    pub fn search_tracks(&self, playlist_id: i32, query: &str) -> Result<Vec<i32>, DbError> {
+
+        // Nothing to search for, so every track is found.
+        let Some(query) = fts_query(query) else {
+            return PlaylistTrackTable::table
+                .filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id))
+                .select(PlaylistTrackTable::columns::track_id)
+                .load::<i32>(&mut*self.conn()?)
+                .map_err(convert_err);
+        };
+
+        let rows = diesel::sql_query("SELECT track_fts.rowid AS id FROM track_fts
+                JOIN playlist_track ON playlist_track.track_id = track_fts.rowid
+                WHERE track_fts MATCH ? AND playlist_track.playlist_id = ? ORDER BY rank")
+            .bind::<diesel::sql_types::Text, _>(query)
+            .bind::<diesel::sql_types::Integer, _>(playlist_id)
+            .load::<FoundTrack>(&mut*self.conn()?)
+            .map_err(convert_err)?;
+
+        Ok(rows.into_iter().map(|row| row.id).collect())
+    }
+
     /// Gets all tracks from a playlist.
     pub fn get_tracks(&self, playlist_id: i32) -> Result<Vec<Track>, DbError> {
 
         let result: Result<Vec<Track>, DieselError> = TrackTable::table
-            .filter(TrackTable::columns::playlist_id.is(playlist_id))
-            .load::<Track>(&mut*self.connection.borrow_mut());
+            .inner_join(PlaylistTrackTable::table)
+            .filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id))
+            .order((PlaylistTrackTable::columns::position, TrackTable::columns::id))
+            .select(TrackTable::all_columns)
+            .load::<Track>(&mut*self.conn()?);
 
         result.map_err(convert_err)
 
@@ -135,20 +517,408 @@ impl Database {
             vid.playlist_id = Some(playlist_id);
         }
 
-        diesel::insert_into(TrackTable::table)
-            .values(videos)
-            .execute(&mut*self.connection.borrow_mut())
+        self.transaction(|dao| {
+            diesel::insert_into(TrackTable::table)
+                .values(videos)
+                .execute(&mut*dao.conn()?)
+                .map_err(convert_err)?;
+
+            // Add the new tracks to the end of the playlist, in the order they were saved.
+            let last = dao.last_position(playlist_id)?;
+            diesel::sql_query("INSERT INTO playlist_track (playlist_id, track_id, position)
+                    SELECT playlist_id, id, ? + ROW_NUMBER() OVER (ORDER BY id) FROM track
+                    WHERE playlist_id = ? AND id NOT IN (SELECT track_id FROM playlist_track WHERE playlist_id = ?)")
+                .bind::<diesel::sql_types::Integer, _>(last)
+                .bind::<diesel::sql_types::Integer, _>(playlist_id)
+                .bind::<diesel::sql_types::Integer, _>(playlist_id)
+                .execute(&mut*dao.conn()?)
+                .map(|_| ()).map_err(convert_err)
+        })
+    }
+
+    /// Saves the episodes of a podcast that weren't saved before, as unplayed.
+    /// 
+    /// Unlike `update_tracks`, the episodes that aren't in the feed anymore are kept as they were.
+    pub fn save_episodes(&self, playlist_id: i32, videos: Vec<NewVideo>) -> Result<(), DbError> {
+
+        self.transaction(|dao| {
+            let saved: Vec<Track> = TrackTable::table
+                .filter(TrackTable::columns::playlist_id.is(playlist_id))
+                .load(&mut*dao.conn()?)
+                .map_err(convert_err)?;
+
+            let saved: HashSet<&str> = saved.iter().filter_map(Track::source).collect();
+            let new_videos = videos.into_iter().filter(|v| !saved.contains(v.source())).collect();
+            dao.save_tracks(new_videos, playlist_id)?;
+
+            diesel::update(TrackTable::table
+                    .filter(TrackTable::columns::playlist_id.is(playlist_id))
+                    .filter(TrackTable::columns::played.is_null()))
+                .set(TrackTable::columns::played.eq(false))
+                .execute(&mut*dao.conn()?)
+                .map(|_| ()).map_err(convert_err)
+        })
+    }
+
+    /// Counts another play of a track, that was played until the end, adds it to the history, and saves its
+    /// duration if it's known.
+    pub fn record_play(&self, track_id: i32, duration: Option<i32>) -> Result<(), DbError> {
+
+        diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track_id)))
+            .set(TrackTable::columns::play_count.eq(TrackTable::columns::play_count + 1))
+            .execute(&mut*self.conn()?)
+            .map_err(convert_err)?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        diesel::insert_into(PlayTable::table)
+            .values((PlayTable::columns::track_id.eq(track_id), PlayTable::columns::played_at.eq(now)))
+            .execute(&mut*self.conn()?)
+            .map_err(convert_err)?;
+
+        if let Some(duration) = duration {
+            diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track_id)))
+                .set(TrackTable::columns::duration.eq(duration))
+                .execute(&mut*self.conn()?)
+                .map_err(convert_err)?;
+        }
+        Ok(())
+    }
+
+    /// Every time a track was played until the end, from the oldest, with when it was (in seconds
+    /// since the epoch). Plays before the history was kept are only counted in `play_count`.
+    pub fn get_plays(&self) -> Result<Vec<(i64, Track)>, DbError> {
+
+        PlayTable::table
+            .inner_join(TrackTable::table)
+            .order(PlayTable::columns::id)
+            .select((PlayTable::columns::played_at, TrackTable::all_columns))
+            .load::<(i64, Track)>(&mut*self.conn()?)
+            .map_err(convert_err)
+    }
+
+    /// Saves where a track starts and stops playing, in seconds. `None` plays it from the
+    /// beginning or until the end.
+    pub fn set_trim(&self, track_id: i32, start: Option<i32>, end: Option<i32>) -> Result<(), DbError> {
+
+        diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track_id)))
+            .set((TrackTable::columns::trim_start.eq(start), TrackTable::columns::trim_end.eq(end)))
+            .execute(&mut*self.conn()?)
+            .map(|_| ()).map_err(convert_err)
+    }
+
+    /// Saves what MusicBrainz knows about a track, and that it has been looked up. The artist found
+    /// replaces the one it had (usually the channel that uploaded it), but the album is only set if
+    /// it didn't have one, so the tracks of an imported album keep it.
+    pub fn set_metadata(&self, track_id: i32, artist: Option<&str>, album: Option<&str>, year: Option<i32>) -> Result<(), DbError> {
+
+        diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track_id)))
+            .set((TrackTable::columns::metadata_checked.eq(true), TrackTable::columns::year.eq(year)))
+            .execute(&mut*self.conn()?)
+            .map_err(convert_err)?;
+
+        if let Some(artist) = artist {
+            diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track_id)))
+                .set(TrackTable::columns::artist.eq(artist))
+                .execute(&mut*self.conn()?)
+                .map_err(convert_err)?;
+        }
+        if let Some(album) = album {
+            diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track_id).and(TrackTable::columns::album.is_null())))
+                .set(TrackTable::columns::album.eq(album))
+                .execute(&mut*self.conn()?)
+                .map_err(convert_err)?;
+        }
+        Ok(())
+    }
+
+    /// Saves where the user stopped listening to an episode, and whether it has been played.
+    pub fn set_episode_progress(&self, track_id: i32, position: i32, played: bool) -> Result<(), DbError> {
+
+        diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track_id)))
+            .set((TrackTable::columns::position.eq(position), TrackTable::columns::played.eq(played)))
+            .execute(&mut*self.conn()?)
             .map(|_| ()).map_err(convert_err)
     }
 
-    /// Deletes all tracks from a playlist, and then saves the new ones.
-    pub fn replace_tracks(&self, playlist_id: i32,  videos: Vec<NewVideo>) -> Result<(), DbError> {
-        // Removes all tracks asociated with a playlists and inserts the new ones.
+    /// Points a track to another video, keeping it in the same playlists.
+    pub fn replace_source(&self, track_id: i32, video: &NewVideo) -> Result<(), DbError> {
+
+        diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track_id)))
+            .set((
+                TrackTable::columns::source_id.eq(&video.source_id),
+                TrackTable::columns::source_url.eq(&video.source_url),
+                TrackTable::columns::restriction.eq(None::<Restriction>)
+            ))
+            .execute(&mut*self.conn()?)
+            .map(|_| ()).map_err(convert_err)
+    }
+
+    /// Marks the tracks of the videos that will probably fail to download.
+    pub fn set_restrictions(&self, restrictions: &[(String, Restriction)]) -> Result<(), DbError> {
+
+        for (yt_id, restriction) in restrictions {
+            diesel::update(TrackTable::table.filter(TrackTable::columns::source_id.eq(yt_id)))
+                .set(TrackTable::columns::restriction.eq(restriction))
+                .execute(&mut*self.conn()?)
+                .map_err(convert_err)?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the tracks of `videos` that weren't in the playlist, marks the ones that aren't
+    /// in it anymore as removed, and renames the ones whose video has another title now.
+    ///
+    /// The tracks that are still there keep their id, their place in other playlists and
+    /// anything else saved about them.
+    pub fn update_tracks(&self, playlist_id: i32, videos: Vec<NewVideo>) -> Result<TrackDiff, DbError> {
+
+        self.transaction(|dao| {
+            let saved: Vec<Track> = TrackTable::table
+                .filter(TrackTable::columns::playlist_id.is(playlist_id))
+                .load(&mut*dao.conn()?)
+                .map_err(convert_err)?;
+
+            let mut fetched: HashMap<&str, &NewVideo> = HashMap::new();
+            for video in &videos {
+                fetched.entry(video.source()).or_insert(video);
+            }
+
+            let mut diff = TrackDiff::default();
+            let (mut removed, mut restored) = (Vec::new(), Vec::new());
+            for track in &saved {
+                match track.source().and_then(|source| fetched.get(source)) {
+                    Some(video) => {
+                        let title = &video.title;
+                        if track.removed {
+                            restored.push(track.id);
+                            diff.added.push(title.clone());
+                        }
+                        if track.title != *title {
+                            diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track.id)))
+                                .set(TrackTable::columns::title.eq(title))
+                                .execute(&mut*dao.conn()?)
+                                .map_err(convert_err)?;
+                            diff.renamed.push((track.clone(), title.clone()));
+                        }
+                        // Tracks saved before artists were, get the channel's name.
+                        if track.artist.is_none() && video.artist.is_some() {
+                            diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track.id)))
+                                .set(TrackTable::columns::artist.eq(&video.artist))
+                                .execute(&mut*dao.conn()?)
+                                .map_err(convert_err)?;
+                        }
+                        // Tracks saved before upload dates were, or fetched from an API that didn't say it.
+                        if track.uploaded_at.is_none() && video.uploaded_at.is_some() {
+                            diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track.id)))
+                                .set(TrackTable::columns::uploaded_at.eq(video.uploaded_at))
+                                .execute(&mut*dao.conn()?)
+                                .map_err(convert_err)?;
+                        }
+                        // The tracks of an album are numbered again, in case some were added or removed.
+                        if video.track_number.is_some() && (track.track_number != video.track_number || track.album != video.album) {
+                            diesel::update(TrackTable::table.filter(TrackTable::columns::id.is(track.id)))
+                                .set((TrackTable::columns::album.eq(&video.album), TrackTable::columns::track_number.eq(video.track_number)))
+                                .execute(&mut*dao.conn()?)
+                                .map_err(convert_err)?;
+                        }
+                    },
+                    // Local files are added by the user, they're kept until they remove them.
+                    None if !track.removed && !track.is_local() => {
+                        removed.push(track.id);
+                        diff.removed.push(track.title.clone());
+                    },
+                    None => {}
+                }
+            }
+
+            dao.set_removed(&removed, true)?;
+            dao.set_removed(&restored, false)?;
+
+            let saved: HashSet<&str> = saved.iter().filter_map(Track::source).collect();
+            let new_videos: Vec<NewVideo> = videos.into_iter().filter(|v| !saved.contains(v.source())).collect();
+            diff.added.extend(new_videos.iter().map(|v| v.title.clone()));
+            dao.save_tracks(new_videos, playlist_id)?;
+            Ok(diff)
+        })
+    }
+
+    /// Marks tracks as removed from the playlist they were imported from, or as back in it.
+    pub fn set_removed(&self, track_ids: &[i32], removed: bool) -> Result<(), DbError> {
+
+        diesel::update(TrackTable::table.filter(TrackTable::columns::id.eq_any(track_ids.to_vec())))
+            .set(TrackTable::columns::removed.eq(removed))
+            .execute(&mut*self.conn()?)
+            .map(|_| ()).map_err(convert_err)
+    }
+
+    /// Adds an existing track to the end of a playlist.
+    pub fn add_track(&self, playlist_id: i32, track_id: i32) -> Result<(), DbError> {
+
+        let position = self.last_position(playlist_id)? + 1;
+        diesel::insert_or_ignore_into(PlaylistTrackTable::table)
+            .values(PlaylistTrack { playlist_id, track_id, position })
+            .execute(&mut*self.conn()?)
+            .map(|_| ()).map_err(convert_err)
+    }
+
+    /// Saves a new track at the end of a playlist, and returns it.
+    pub fn insert_track(&self, playlist_id: i32, mut video: NewVideo) -> Result<Track, DbError> {
+
+        video.playlist_id = Some(playlist_id);
+        self.transaction(|dao| {
+            diesel::insert_into(TrackTable::table)
+                .values(video)
+                .execute(&mut*dao.conn()?)
+                .map_err(convert_err)?;
+
+            let track = TrackTable::table
+                .filter(TrackTable::columns::id.is(dao.last_insert_id()?))
+                .first::<Track>(&mut*dao.conn()?)
+                .map_err(convert_err)?;
+
+            dao.add_track(playlist_id, track.id)?;
+            Ok(track)
+        })
+    }
+
+    /// Moves a track of a playlist right after the track `after`, or to the top if it's `None`.
+    pub fn move_track(&self, playlist_id: i32, track_id: i32, after: Option<i32>) -> Result<(), DbError> {
+
+        let position = match after {
+            Some(after) => PlaylistTrackTable::table
+                .filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id))
+                .filter(PlaylistTrackTable::columns::track_id.is(after))
+                .select(PlaylistTrackTable::columns::position)
+                .first::<i32>(&mut*self.conn()?)
+                .map_err(convert_err)? + 1,
+            None => PlaylistTrackTable::table
+                .filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id))
+                .select(diesel::dsl::min(PlaylistTrackTable::columns::position))
+                .first::<Option<i32>>(&mut*self.conn()?)
+                .map_err(convert_err)?
+                .unwrap_or_default()
+        };
+
+        // Make room for it.
+        diesel::update(PlaylistTrackTable::table
+                .filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id))
+                .filter(PlaylistTrackTable::columns::position.ge(position)))
+            .set(PlaylistTrackTable::columns::position.eq(PlaylistTrackTable::columns::position + 1))
+            .execute(&mut*self.conn()?)
+            .map_err(convert_err)?;
+
+        diesel::update(PlaylistTrackTable::table
+                .filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id))
+                .filter(PlaylistTrackTable::columns::track_id.is(track_id)))
+            .set(PlaylistTrackTable::columns::position.eq(position))
+            .execute(&mut*self.conn()?)
+            .map(|_| ()).map_err(convert_err)
+    }
+
+    /// Swaps the positions of two tracks of a playlist, to move one of them up or down.
+    pub fn swap_tracks(&self, playlist_id: i32, first_id: i32, second_id: i32) -> Result<(), DbError> {
+
+        let position = |track_id: i32| -> Result<i32, DbError> {
+            PlaylistTrackTable::table
+                .filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id))
+                .filter(PlaylistTrackTable::columns::track_id.is(track_id))
+                .select(PlaylistTrackTable::columns::position)
+                .first::<i32>(&mut*self.conn()?)
+                .map_err(convert_err)
+        };
+
+        let (first, second) = (position(first_id)?, position(second_id)?);
+        for (track_id, position) in [(first_id, second), (second_id, first)] {
+            diesel::update(PlaylistTrackTable::table
+                    .filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id))
+                    .filter(PlaylistTrackTable::columns::track_id.is(track_id)))
+                .set(PlaylistTrackTable::columns::position.eq(position))
+                .execute(&mut*self.conn()?)
+                .map_err(convert_err)?;
+        }
+
+        Ok(())
+    }
+
+    // The position of the last track of a playlist, or 0 if it's empty.
+    fn last_position(&self, playlist_id: i32) -> Result<i32, DbError> {
+
+        PlaylistTrackTable::table
+            .filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id))
+            .select(diesel::dsl::max(PlaylistTrackTable::columns::position))
+            .first::<Option<i32>>(&mut*self.conn()?)
+            .map(Option::unwrap_or_default)
+            .map_err(convert_err)
+    }
+
+    /// Removes a track from a playlist. The track is kept in the playlist it was imported from.
+    pub fn remove_track(&self, playlist_id: i32, track_id: i32) -> Result<(), DbError> {
+
+        diesel::delete(PlaylistTrackTable::table
+                .filter(PlaylistTrackTable::columns::playlist_id.is(playlist_id))
+                .filter(PlaylistTrackTable::columns::track_id.is(track_id)))
+            .execute(&mut*self.conn()?)
+            .map(|_| ()).map_err(convert_err)
+    }
+
+    /// Gets a value saved with `set_state`, if there is one.
+    pub fn get_state(&self, key: &str) -> Result<Option<String>, DbError> {
+
+        AppStateTable::table
+            .filter(AppStateTable::columns::key.eq(key))
+            .select(AppStateTable::columns::value)
+            .first::<String>(&mut*self.conn()?)
+            .optional()
+            .map_err(convert_err)
+    }
+
+    /// Saves a value that has to be remembered between runs, replacing the previous one.
+    pub fn set_state(&self, key: &str, value: &str) -> Result<(), DbError> {
+
+        diesel::replace_into(AppStateTable::table)
+            .values((AppStateTable::columns::key.eq(key), AppStateTable::columns::value.eq(value)))
+            .execute(&mut*self.conn()?)
+            .map(|_| ()).map_err(convert_err)
+    }
+
+    /// The fingerprint saved for a file, if it had `size` bytes when it was fingerprinted.
+    pub fn get_fingerprint(&self, path: &str, size: i64) -> Result<Option<String>, DbError> {
+
+        FingerprintTable::table
+            .filter(FingerprintTable::columns::path.eq(path).and(FingerprintTable::columns::size.eq(size)))
+            .select(FingerprintTable::columns::data)
+            .first::<String>(&mut*self.conn()?)
+            .optional()
+            .map_err(convert_err)
+    }
+
+    /// Saves the fingerprint of a file of `size` bytes, replacing the one it had.
+    pub fn set_fingerprint(&self, path: &str, size: i64, data: &str) -> Result<(), DbError> {
+
+        diesel::replace_into(FingerprintTable::table)
+            .values((
+                FingerprintTable::columns::path.eq(path),
+                FingerprintTable::columns::size.eq(size),
+                FingerprintTable::columns::data.eq(data)
+            ))
+            .execute(&mut*self.conn()?)
+            .map(|_| ()).map_err(convert_err)
+    }
+
+    /// Deletes the tracks that were imported with a playlist, removing them from every other playlist.
+    fn delete_imported_tracks(&self, playlist_id: i32) -> Result<(), DbError> {
+
+        let imported = TrackTable::table
+            .filter(TrackTable::columns::playlist_id.is(playlist_id))
+            .select(TrackTable::columns::id);
+
+        diesel::delete(PlaylistTrackTable::table.filter(PlaylistTrackTable::columns::track_id.eq_any(imported)))
+            .execute(&mut*self.conn()?).map_err(convert_err)?;
 
         diesel::delete(TrackTable::table.filter(TrackTable::columns::playlist_id.is(playlist_id)))
-            .execute(&mut*self.connection.borrow_mut()).map_err(convert_err)?;
+            .execute(&mut*self.conn()?).map_err(convert_err)?;
 
-        self.save_tracks(videos, playlist_id)?;
         Ok(())
     }
 }
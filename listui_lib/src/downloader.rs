@@ -1,37 +1,227 @@
-
-use std::collections::HashSet;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::{ Mutex, Semaphore, SemaphorePermit};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::{ Mutex, Notify, Semaphore, SemaphorePermit};
+use crate::api::{ApiError, JellyfinClient, SubsonicClient, JELLYFIN_PREFIX, SUBSONIC_PREFIX};
+use crate::tools::Tools;
 
 
 pub enum DownloadResult {
     Completed(PathBuf),
-    Failed,
+    /// Why it failed, like "yt-dlp exited with an error".
+    Failed(String),
+    Cancelled,
+}
+
+/// When a `yt-dlp` download is given up. Without it, a hung download would keep its slot forever.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Watchdog {
+    /// For how long a download can run. `None` for no limit.
+    pub timeout: Option<Duration>,
+    /// For how long a download can go without receiving anything. `None` for no limit.
+    pub stall_timeout: Option<Duration>
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(30 * 60)),
+            stall_timeout: Some(Duration::from_secs(2 * 60))
+        }
+    }
+}
+
+/// How far a download has got, as reported by `yt-dlp`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DownloadProgress {
+    /// From 0 to 100. Unknown until the size of the file is known.
+    pub percent: Option<f64>,
+    /// In bytes per second.
+    pub speed: Option<f64>,
+    /// Seconds until the download ends.
+    pub eta: Option<u64>
+}
+
+impl std::fmt::Display for DownloadProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.percent {
+            Some(percent) => write!(f, "{percent:.0}%")?,
+            None => write!(f, "...")?
+        }
+        // Like "(1.2 MiB/s, 0:42 left)", with whatever is known.
+        let speed = self.speed.map(|speed| format!("{:.1} MiB/s", speed / (1024.0 * 1024.0)));
+        let eta = self.eta.map(|eta| match eta {
+            eta if eta >= 3600 => format!("{}:{:02}:{:02} left", eta / 3600, eta / 60 % 60, eta % 60),
+            eta => format!("{}:{:02} left", eta / 60, eta % 60)
+        });
+        let details: Vec<String> = speed.into_iter().chain(eta).collect();
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Audio formats `yt-dlp` can convert the downloads into.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum AudioFormat {
+    #[default]
+    Mp3,
+    Opus,
+    M4a
+}
+
+impl AudioFormat {
+
+    pub const ALL: [AudioFormat; 3] = [AudioFormat::Mp3, AudioFormat::Opus, AudioFormat::M4a];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Opus => "opus",
+            AudioFormat::M4a => "m4a"
+        }
+    }
+}
+
+impl std::str::FromStr for AudioFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mp3" => Ok(AudioFormat::Mp3),
+            "opus" => Ok(AudioFormat::Opus),
+            "m4a" => Ok(AudioFormat::M4a),
+            _ => Err(format!("Unknown audio format: {s}. Expected mp3, opus or m4a."))
+        }
+    }
+}
+
+/// How `yt-dlp` downloads the tracks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadOptions {
+    pub format: AudioFormat,
+    /// Passed to `--audio-quality`: from 0 (best) to 10, or a bitrate like `128K`. `yt-dlp`'s default if `None`.
+    pub quality: Option<String>,
+    pub embed_thumbnail: bool,
+    /// Passed to `--limit-rate`, like `500K`, while the limit is on. Not limited if `None`.
+    pub rate_limit: Option<String>,
+    /// Added to the command after every other argument, except the URL.
+    pub extra_args: Vec<String>
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self { format: AudioFormat::default(), quality: None, embed_thumbnail: true, rate_limit: None, extra_args: Vec::new() }
+    }
+}
+
+/// Where a track is in its way to the download directory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DownloadState {
+    /// Waiting for a free slot.
+    Queued,
+    Downloading,
+    Downloaded,
+    Failed
+}
+
+/// The `DownloadState` of every track a downloader knows about, by youtube id (or URL). Clones
+/// share the same states, so the list of tracks sees what the downloader does.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadStates(Arc<std::sync::Mutex<HashMap<String, DownloadState>>>);
+
+impl DownloadStates {
+
+    pub fn get(&self, yt_id: &str) -> Option<DownloadState> {
+        self.0.lock().unwrap().get(yt_id).copied()
+    }
+
+    pub fn set(&self, yt_id: &str, state: DownloadState) {
+        self.0.lock().unwrap().insert(String::from(yt_id), state);
+    }
+
+    /// Forgets the track, like when its download is cancelled or its file is removed.
+    pub fn remove(&self, yt_id: &str) {
+        self.0.lock().unwrap().remove(yt_id);
+    }
+
+    // The state a download ends in.
+    fn finish(&self, yt_id: &str, result: &DownloadResult) {
+        match result {
+            DownloadResult::Completed(_) => self.set(yt_id, DownloadState::Downloaded),
+            DownloadResult::Failed(_) => self.set(yt_id, DownloadState::Failed),
+            DownloadResult::Cancelled => self.remove(yt_id)
+        }
+    }
+}
+
+pub type DownloadProgressCallback = Box<dyn Fn(DownloadProgress) + Send + Sync>;
+
+// Printed by yt-dlp on its own line for every progress update. Missing values are printed as NA.
+const PROGRESS_PREFIX: &str = "[listui]";
+const PROGRESS_TEMPLATE: &str = "download:[listui] %(progress.downloaded_bytes)s %(progress.total_bytes)s %(progress.total_bytes_estimate)s %(progress.speed)s %(progress.eta)s";
+
+/// Something that can download tracks.
+#[async_trait]
+pub trait Downloader: Send + Sync {
+
+    /// Downloads a video with a given youtube ID (or URL) into `file_path`.
+    /// 
+    /// If a callback is provided, it will be called multiple times with the progress of the download.
+    /// Returns `None` if the video was already being downloaded.
+    async fn download_id(&self, yt_id: &str, file_path: &Path, progress: Option<DownloadProgressCallback>) -> Option<DownloadResult>;
+
+    /// Stops the download of a video and removes its partial files. Its `download_id` call
+    /// returns `DownloadResult::Cancelled`.
+    async fn cancel(&self, yt_id: &str);
+
+    /// The states of the downloads, updated as they go.
+    fn states(&self) -> DownloadStates {
+        DownloadStates::default()
+    }
+
+    /// Turns the speed limit of the downloads that start from now on off, or back on. Returns
+    /// whether it's on now, or `None` if there's no limit to turn on.
+    fn toggle_rate_limit(&self) -> Option<bool> {
+        None
+    }
 }
 
 /// Client to download videos from YouTube, using `yt-dlp`.
 /// 
 /// The client keeps track of previously enqueued videos, so
 /// it doesn't download the same video twice. 
-pub struct Downloader {
+pub struct YtDlpDownloader {
 
     sem: Arc<Semaphore>,
 
-    /*Hashet containing the youtube IDs of downloads that are either:
-
-      - Still in progress
-      - Already completed, but haven't been confirmed by the main thread
-        by calling check_for_completed_download.
-    */
-    downloads: Mutex<HashSet<String>>,
+    // The youtube IDs of the downloads that are enqueued or in progress, with
+    // the signal that cancels each of them.
+    downloads: Mutex<HashMap<String, Arc<Notify>>>,
 
     // The id of the last video the user asked to download. This video will
     // have the top priority in the queue.
-    last_enqueued: Mutex<Option<String>> 
+    last_enqueued: Mutex<Option<String>>,
+
+    options: DownloadOptions,
+    // Whether the rate limit of the options is used, until it's toggled.
+    limited: AtomicBool,
+    tools: Tools,
+    watchdog: Watchdog,
+
+    // Servers the tracks with a `subsonic:` or `jellyfin:` id are downloaded from.
+    subsonic: Option<SubsonicClient>,
+    jellyfin: Option<JellyfinClient>,
+
+    states: DownloadStates
 }
 
-impl Downloader {
+impl YtDlpDownloader {
 
     /// Creates a new client that can download up to `max_downloads` simultaneously.
     pub fn new(max_downloads: usize) -> Self {       
@@ -39,29 +229,70 @@ impl Downloader {
         Self {
             sem: Arc::new(Semaphore::new(max_downloads)),
             last_enqueued: Mutex::new(None),
-            downloads: Mutex::new(HashSet::new()),
+            downloads: Mutex::new(HashMap::new()),
+            options: DownloadOptions::default(),
+            limited: AtomicBool::new(true),
+            tools: Tools::default(),
+            watchdog: Watchdog::default(),
+            subsonic: None,
+            jellyfin: None,
+            states: DownloadStates::default()
         }
     }
 
-    /// Download a video with a given youtube ID.
-    /// 
-    /// If there are other enqueued videos, the last newly enqueued one will have priority.
-    pub async fn download_id(&self, yt_id: &str, file_path: &Path) -> Option<DownloadResult> {
+    /// Changes the format and the arguments `yt-dlp` is run with. The files passed to
+    /// `download_id` should have the extension of the format.
+    pub fn with_options(mut self, options: DownloadOptions) -> Self {
+        self.options = options;
+        self
+    }
 
-        let mut downloads = self.downloads.lock().await; 
-        let mut last_enqueued = self.last_enqueued.lock().await;
-        
-        last_enqueued.replace(String::from(yt_id));
-        if downloads.contains(yt_id) {
-            // Early return if the video is already enqueued.
-            log::info!("Video {yt_id} was already enqueued.");
-            return None;
+    /// Runs the `yt-dlp` and `ffmpeg` of `tools`, instead of the ones in the `PATH`.
+    pub fn with_tools(mut self, tools: Tools) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Kills the `yt-dlp` processes that take too long or stall, instead of the default limits.
+    pub fn with_watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.watchdog = watchdog;
+        self
+    }
+
+    /// Downloads the tracks that come from a Subsonic server with `client`, instead of `yt-dlp`.
+    pub fn with_subsonic(mut self, client: SubsonicClient) -> Self {
+        self.subsonic = Some(client);
+        self
+    }
+
+    /// Downloads the tracks that come from a Jellyfin server with `client`, instead of `yt-dlp`.
+    pub fn with_jellyfin(mut self, client: JellyfinClient) -> Self {
+        self.jellyfin = Some(client);
+        self
+    }
+
+    // Downloads a track from the media server it belongs to, or returns `None` if it
+    // doesn't come from one.
+    async fn download_from_server(&self, yt_id: &str, file_path: &Path) -> Option<Result<(), ApiError>> {
+
+        let not_configured = |server: &str| ApiError::RequestError(format!("No {server} server is configured."));
+        if let Some(song_id) = yt_id.strip_prefix(SUBSONIC_PREFIX) {
+            Some(match &self.subsonic {
+                Some(client) => client.download(song_id, file_path).await,
+                None => Err(not_configured("Subsonic"))
+            })
+        }
+        else if let Some(track_id) = yt_id.strip_prefix(JELLYFIN_PREFIX) {
+            Some(match &self.jellyfin {
+                Some(client) => client.download(track_id, file_path).await,
+                None => Err(not_configured("Jellyfin"))
+            })
         }
+        else { None }
+    }
 
-        log::info!("Enqueued video {yt_id}.");
-        downloads.insert(String::from(yt_id));
-        drop(downloads);
-        drop(last_enqueued);
+    // Waits for its turn in the queue and downloads the track, holding a permit until it's done.
+    async fn run_download(&self, yt_id: &str, file_path: &Path, progress: Option<DownloadProgressCallback>) -> DownloadResult {
 
         let mut permit: SemaphorePermit;
         loop {
@@ -82,40 +313,366 @@ impl Downloader {
         }
         
         log::info!("Starting download for video {yt_id}");
-        let child = tokio::process::Command::new("yt-dlp")
-            .arg("-x")
+        self.states.set(yt_id, DownloadState::Downloading);
+        if let Some(result) = self.download_from_server(yt_id, file_path).await {
+            drop(permit);
+            return match result {
+                Ok(_) => {
+                    log::info!("Download for video {yt_id} completed succesfully.");
+                    DownloadResult::Completed(file_path.to_path_buf())
+                },
+                Err(e) => {
+                    log::error!("Download for video {yt_id} failed: {e}");
+                    DownloadResult::Failed(e.to_string())
+                }
+            };
+        }
+
+        let mut command = self.tools.ytdlp_command();
+        command.arg("-x")
             .arg("--audio-format")
-            .arg("mp3")
+            .arg(self.options.format.extension())
             .arg("-f")
             .arg("bestaudio")
             .arg("--output")
-            .arg(file_path)
-            .arg("--embed-thumbnail")
-            .arg(format!("https://www.youtube.com/watch?v={yt_id}"))
-            .stdout(std::process::Stdio::null())
+            .arg(file_path);
+        if let Some(quality) = &self.options.quality {
+            command.arg("--audio-quality").arg(quality);
+        }
+        if self.options.embed_thumbnail {
+            command.arg("--embed-thumbnail");
+        }
+        if let Some(rate) = self.options.rate_limit.as_ref().filter(|_| self.limited.load(Ordering::Relaxed)) {
+            command.arg("--limit-rate").arg(rate);
+        }
+        let child = command.arg("--newline")
+            .arg("--progress-template")
+            .arg(PROGRESS_TEMPLATE)
+            .args(&self.options.extra_args)
+            .arg(video_url(yt_id))
+            .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
             .spawn();
         
-        drop(permit);
-        Some(match child {
+        let result = match child {
             // The download did not even start.
             Err(e) =>  {
                 log::error!("Download for video {yt_id} failed: {e}");
-                DownloadResult::Failed
+                DownloadResult::Failed(format!("yt-dlp could not be started: {e}"))
             },
             Ok(mut child) => {
-                
-                match child.wait().await.map(|exit| exit.success()) {
-                    Ok(true) => {
-                        log::info!("Download for video {yt_id} completed succesfully.");
-                        DownloadResult::Completed(file_path.to_path_buf())
+
+                let stuck = match child.stdout.take() {
+                    Some(stdout) => self.watch_progress(BufReader::new(stdout), progress.as_ref()).await,
+                    None => None
+                };
+
+                if let Some(reason) = stuck {
+                    log::error!("Download for video {yt_id} failed: {reason}");
+                    let _ = child.kill().await;
+                    remove_partial_files(file_path).await;
+                    DownloadResult::Failed(reason)
+                }
+                else {
+                    match child.wait().await.map(|exit| exit.success()) {
+                        Ok(true) => {
+                            log::info!("Download for video {yt_id} completed succesfully.");
+                            DownloadResult::Completed(file_path.to_path_buf())
+                        },
+                        Ok(false) | Err(_) => {
+                            log::error!("Download for video {yt_id} failed: yt-dlp exited with an error.");
+                            DownloadResult::Failed(String::from("yt-dlp exited with an error"))
+                        }
+                    }
+                }
+            }
+        };
+
+        drop(permit);
+        result
+    }
+
+    // Reports the progress printed by yt-dlp until it closes its output. Returns why the download
+    // is stuck instead, if the watchdog gives up on it first.
+    //
+    // Nothing is printed while the audio is being converted, so once every byte has arrived only
+    // the total time is limited.
+    async fn watch_progress<R>(&self, output: R, progress: Option<&DownloadProgressCallback>) -> Option<String>
+        where R: tokio::io::AsyncBufRead + Unpin {
+
+        let mut lines = output.lines();
+        let started = Instant::now();
+        let mut last_received = Instant::now();
+        let mut received_everything = false;
+        loop {
+            let timeout_left = self.watchdog.timeout.map(|timeout| timeout.saturating_sub(started.elapsed()));
+            let stall_left = self.watchdog.stall_timeout
+                .filter(|_| !received_everything)
+                .map(|stall_timeout| stall_timeout.saturating_sub(last_received.elapsed()));
+
+            let line = match timeout_left.into_iter().chain(stall_left).min() {
+                Some(wait) => match tokio::time::timeout(wait, lines.next_line()).await {
+                    Ok(line) => line,
+                    Err(_) if timeout_left == Some(wait) => {
+                        return Some(format!("it took longer than {}", describe(self.watchdog.timeout.unwrap_or_default())));
                     },
-                    Ok(false) | Err(_) => {
-                        DownloadResult::Failed
+                    Err(_) => {
+                        return Some(format!("it stalled for {}", describe(self.watchdog.stall_timeout.unwrap_or_default())));
                     }
+                },
+                None => lines.next_line().await
+            };
+
+            let Ok(Some(line)) = line else { return None };
+            if let Some(update) = parse_progress(&line) {
+                if update.speed.is_some_and(|speed| speed > 0.0) {
+                    last_received = Instant::now();
+                }
+                received_everything = update.percent.is_some_and(|percent| percent >= 100.0);
+                if let Some(callback) = progress {
+                    callback(update);
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Downloader for YtDlpDownloader {
+
+    /// Download a video with a given youtube ID, or from the URL of any other site supported by `yt-dlp`.
+    /// 
+    /// If there are other enqueued videos, the last newly enqueued one will have priority.
+    async fn download_id(&self, yt_id: &str, file_path: &Path, progress: Option<DownloadProgressCallback>) -> Option<DownloadResult> {
+
+        let mut downloads = self.downloads.lock().await; 
+        let mut last_enqueued = self.last_enqueued.lock().await;
+        
+        last_enqueued.replace(String::from(yt_id));
+        if downloads.contains_key(yt_id) {
+            // Early return if the video is already enqueued.
+            log::info!("Video {yt_id} was already enqueued.");
+            return None;
+        }
+
+        log::info!("Enqueued video {yt_id}.");
+        self.states.set(yt_id, DownloadState::Queued);
+        let cancelled = Arc::new(Notify::new());
+        downloads.insert(String::from(yt_id), Arc::clone(&cancelled));
+        drop(downloads);
+        drop(last_enqueued);
+
+        // Dropping the download releases its permit and kills yt-dlp.
+        let result = tokio::select! {
+            result = self.run_download(yt_id, file_path, progress) => result,
+            _ = cancelled.notified() => {
+                log::info!("Download for video {yt_id} was cancelled.");
+                remove_partial_files(file_path).await;
+                DownloadResult::Cancelled
+            }
+        };
+
+        // A cancelled download may have been enqueued again already.
+        let mut downloads = self.downloads.lock().await;
+        if downloads.get(yt_id).is_some_and(|signal| Arc::ptr_eq(signal, &cancelled)) {
+            downloads.remove(yt_id);
+            self.states.finish(yt_id, &result);
+        }
+        Some(result)
+    }
+
+    /// Stops the download of a video, whether it's still in the queue or already running.
+    async fn cancel(&self, yt_id: &str) {
+
+        if let Some(cancelled) = self.downloads.lock().await.remove(yt_id) {
+            self.states.remove(yt_id);
+            cancelled.notify_one();
+        }
+    }
+
+    fn states(&self) -> DownloadStates {
+        self.states.clone()
+    }
+
+    fn toggle_rate_limit(&self) -> Option<bool> {
+        self.options.rate_limit.as_ref()?;
+        Some(!self.limited.fetch_xor(true, Ordering::Relaxed))
+    }
+}
+
+// Removes what an interrupted download leaves behind: yt-dlp's partial and
+// temporary files, the converted file if it was being written, and the `.part`
+// file of media server downloads.
+async fn remove_partial_files(file_path: &Path) {
+
+    let mut partial = vec![file_path.to_path_buf(), file_path.with_extension("part")];
+    // Converting into the same extension goes through `name.temp.ext`.
+    if let Some(ext) = file_path.extension() {
+        partial.push(file_path.with_extension(format!("temp.{}", ext.to_string_lossy())));
+    }
+    for suffix in [".part", ".ytdl", ".webp", ".jpg"] {
+        let mut path = file_path.as_os_str().to_owned();
+        path.push(suffix);
+        partial.push(PathBuf::from(path));
+    }
+
+    for path in partial {
+        if tokio::fs::remove_file(&path).await.is_ok() {
+            log::info!("Removed partial download {}.", path.display());
+        }
+    }
+}
+
+/// `Downloader` that writes the same content for every track, to be used in tests.
+pub struct MockDownloader {
+
+    content: Vec<u8>,
+    failing: HashSet<String>,
+    delay: std::time::Duration,
+    downloaded: std::sync::Mutex<Vec<String>>,
+    // Signals that stop the delayed downloads.
+    in_progress: std::sync::Mutex<HashMap<String, Arc<Notify>>>,
+    cancelled: std::sync::Mutex<Vec<String>>,
+    states: DownloadStates
+}
+
+impl MockDownloader {
+
+    /// Creates a downloader that writes `content` into every downloaded file.
+    pub fn new(content: Vec<u8>) -> Self {
+
+        Self {
+            content,
+            failing: HashSet::new(),
+            delay: std::time::Duration::ZERO,
+            downloaded: std::sync::Mutex::new(Vec::new()),
+            in_progress: std::sync::Mutex::new(HashMap::new()),
+            cancelled: std::sync::Mutex::new(Vec::new()),
+            states: DownloadStates::default()
+        }
+    }
+
+    /// Makes the download of the given video fail.
+    pub fn failing(mut self, yt_id: &str) -> Self {
+        self.failing.insert(String::from(yt_id));
+        self
+    }
+
+    /// Makes every download take `delay`, reporting that it is halfway at 1 MiB/s meanwhile.
+    pub fn with_delay(mut self, delay: std::time::Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// IDs of the videos that have been downloaded, in order.
+    pub fn downloaded(&self) -> Vec<String> {
+        self.downloaded.lock().unwrap().clone()
+    }
+
+    /// IDs of the videos whose download has been cancelled, in order.
+    pub fn cancelled(&self) -> Vec<String> {
+        self.cancelled.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Downloader for MockDownloader {
+
+    async fn download_id(&self, yt_id: &str, file_path: &Path, progress: Option<DownloadProgressCallback>) -> Option<DownloadResult> {
+
+        let result = self.run_download(yt_id, file_path, progress).await;
+        self.states.finish(yt_id, &result);
+        Some(result)
+    }
+
+    async fn cancel(&self, yt_id: &str) {
+
+        if let Some(cancelled) = self.in_progress.lock().unwrap().remove(yt_id) {
+            self.cancelled.lock().unwrap().push(String::from(yt_id));
+            cancelled.notify_one();
+        }
+    }
+
+    fn states(&self) -> DownloadStates {
+        self.states.clone()
+    }
+}
+
+impl MockDownloader {
+
+    async fn run_download(&self, yt_id: &str, file_path: &Path, progress: Option<DownloadProgressCallback>) -> DownloadResult {
+
+        if self.failing.contains(yt_id) {
+            return DownloadResult::Failed(String::from("the mock download failed"));
+        }
+
+        self.states.set(yt_id, DownloadState::Downloading);
+
+        self.downloaded.lock().unwrap().push(String::from(yt_id));
+        if !self.delay.is_zero() {
+            // Cancellable before the progress is shown.
+            let cancelled = Arc::new(Notify::new());
+            self.in_progress.lock().unwrap().insert(String::from(yt_id), Arc::clone(&cancelled));
+
+            // Reported again and again like yt-dlp does, since the player skips the updates
+            // that arrive while it's being drawn.
+            let report = async {
+                let Some(callback) = progress else { return std::future::pending().await };
+                loop {
+                    callback(DownloadProgress { percent: Some(50.0), speed: Some(1024.0 * 1024.0), eta: Some(self.delay.as_secs()) });
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
                 }
+            };
+            let finished = tokio::select! {
+                _ = tokio::time::sleep(self.delay) => true,
+                _ = cancelled.notified() => false,
+                () = report => unreachable!("The progress is reported until the download ends.")
+            };
+            self.in_progress.lock().unwrap().remove(yt_id);
+            if !finished {
+                return DownloadResult::Cancelled;
             }
-        })
+        }
+        match tokio::fs::write(file_path, &self.content).await {
+            Ok(_) => DownloadResult::Completed(file_path.to_path_buf()),
+            Err(e) => DownloadResult::Failed(e.to_string())
+        }
+    }
+}
+
+// Parses a line printed with `PROGRESS_TEMPLATE`.
+fn parse_progress(line: &str) -> Option<DownloadProgress> {
+
+    let mut fields = line.strip_prefix(PROGRESS_PREFIX)?
+        .split_whitespace()
+        .map(|field| field.parse::<f64>().ok());
+
+    let downloaded = fields.next()?;
+    let (total, estimate) = (fields.next()?, fields.next()?);
+    let (speed, eta) = (fields.next()?, fields.next()?);
+
+    Some(DownloadProgress {
+        percent: downloaded.zip(total.or(estimate))
+            .filter(|(_, total)| *total > 0.0)
+            .map(|(downloaded, total)| (downloaded / total * 100.0).min(100.0)),
+        speed,
+        eta: eta.map(|eta| eta as u64)
+    })
+}
+
+// Like "2 minutes", for the limits of the watchdog.
+fn describe(limit: Duration) -> String {
+
+    match limit.as_secs() {
+        seconds if seconds >= 60 => format!("{} minutes", seconds / 60),
+        seconds => format!("{seconds} seconds")
     }
 }
 
+// Tracks that don't come from YouTube store their full URL instead of a video id.
+fn video_url(yt_id: &str) -> String {
+
+    if yt_id.contains("://") { String::from(yt_id) }
+    else { format!("https://www.youtube.com/watch?v={yt_id}") }
+}
@@ -0,0 +1,105 @@
+//! Export playlists to formats that other players understand.
+
+use std::path::PathBuf;
+use serde::Serialize;
+
+use crate::models::{Playlist, Track};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    M3u,
+    Json
+}
+
+impl ExportFormat {
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::M3u => "m3u",
+            ExportFormat::Json => "json"
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "m3u" | "m3u8" => Ok(ExportFormat::M3u),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!("Unknown export format: {s}. Expected m3u or json."))
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExportedTrack {
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<i32>,
+    pub year: Option<i32>,
+    pub yt_id: Option<String>,
+    /// Path of the downloaded file, if the track has been downloaded.
+    pub path: Option<PathBuf>
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExportedPlaylist {
+    pub title: String,
+    pub yt_id: Option<String>,
+    pub tracks: Vec<ExportedTrack>
+}
+
+impl ExportedPlaylist {
+
+    /// Creates the exported version of a playlist. `get_path` should return the
+    /// path of a track's file, or `None` if it hasn't been downloaded.
+    pub fn new(playlist: &Playlist, tracks: &[Track], get_path: impl Fn(&Track) -> Option<PathBuf>) -> Self {
+
+        Self {
+            title: playlist.title.clone(),
+            yt_id: playlist.yt_id.clone(),
+            tracks: tracks.iter()
+                .map(|track| ExportedTrack {
+                    title: track.title.clone(),
+                    artist: track.artist.clone(),
+                    album: track.album.clone(),
+                    track_number: track.track_number,
+                    year: track.year,
+                    yt_id: track.source().map(String::from),
+                    path: get_path(track)
+                })
+                .collect()
+        }
+    }
+
+    pub fn export(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::M3u => self.to_m3u(),
+            ExportFormat::Json => self.to_json()
+        }
+    }
+
+    /// Tracks that have not been downloaded are linked to their YouTube page.
+    pub fn to_m3u(&self) -> String {
+
+        let mut content = format!("#EXTM3U\n#PLAYLIST:{}\n", self.title);
+        for track in &self.tracks {
+            let location = match (&track.path, &track.yt_id) {
+                (Some(path), _) => path.to_string_lossy().to_string(),
+                (None, Some(yt_id)) if yt_id.contains("://") => yt_id.clone(),
+                (None, Some(yt_id)) => format!("https://www.youtube.com/watch?v={yt_id}"),
+                (None, None) => continue
+            };
+            content.push_str(&format!("#EXTINF:-1,{}\n{}\n", track.title, location));
+        }
+
+        content
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize playlist.")
+    }
+}
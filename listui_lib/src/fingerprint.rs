@@ -0,0 +1,134 @@
+//! Acoustic fingerprints of the downloaded files, computed with Chromaprint's `fpcalc`.
+//!
+//! Two uploads of the same song can have unrelated titles and ids, but their audio gives them
+//! away: their fingerprints have most of their bits in common once they are lined up.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use tokio::process::Command;
+
+use crate::tools::Tools;
+
+/// Seconds of the beginning of each file that are fingerprinted.
+const LENGTH: u32 = 120;
+
+/// How far apart the fingerprints are lined up, in items (about 8 per second), for uploads that
+/// start a few seconds earlier or later.
+const MAX_OFFSET: isize = 100;
+
+/// Items two fingerprints must overlap to be compared, some 20 seconds.
+const MIN_OVERLAP: usize = 160;
+
+/// Fraction of the bits that must be the same for two fingerprints to be the same recording.
+/// Unrelated songs get around half of them.
+const MATCH_THRESHOLD: f64 = 0.75;
+
+/// Only the fingerprints that share this many exact items are compared, so the whole library
+/// doesn't have to be compared pair by pair.
+const MIN_SHARED: usize = 5;
+
+/// Items in more fingerprints than this, like the ones of silence, don't tell them apart.
+const MAX_SHARING: usize = 20;
+
+/// The raw Chromaprint fingerprint of a file, one item for every eighth of a second or so.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint(Vec<u32>);
+
+impl Fingerprint {
+
+    pub fn new(items: Vec<u32>) -> Self {
+        Self(items)
+    }
+
+    /// Fingerprints a file with the `fpcalc` of `tools`.
+    pub async fn compute(tools: &Tools, file: &Path) -> io::Result<Self> {
+
+        let output = Command::new(tools.fpcalc_path())
+            .arg("-raw")
+            .arg("-length").arg(LENGTH.to_string())
+            .arg(file)
+            .kill_on_drop(true)
+            .output()
+            .await?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match Self::from_fpcalc(&stdout).filter(|_| output.status.success()) {
+            Some(fingerprint) => Ok(fingerprint),
+            None => Err(io::Error::other(format!("fpcalc failed to fingerprint {}: {}", file.display(), String::from_utf8_lossy(&output.stderr).trim())))
+        }
+    }
+
+    /// Reads the output of `fpcalc -raw`, a `DURATION=` line and a `FINGERPRINT=` line with the items.
+    pub fn from_fpcalc(output: &str) -> Option<Self> {
+        output.lines()
+            .find_map(|line| line.strip_prefix("FINGERPRINT="))
+            .and_then(|items| items.parse().ok())
+    }
+
+    /// Fraction of the bits the fingerprints have in common, where they line up best.
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+
+        let mut best: f64 = 0.0;
+        for offset in -MAX_OFFSET..=MAX_OFFSET {
+            let (overlap, differing) = self.0.iter()
+                .enumerate()
+                .filter_map(|(ind, item)| other.0.get(usize::try_from(ind as isize + offset).ok()?).map(|other| item ^ other))
+                .fold((0, 0), |(overlap, differing), bits| (overlap + 1, differing + bits.count_ones() as usize));
+            if overlap >= MIN_OVERLAP {
+                best = best.max(1.0 - differing as f64 / (overlap * 32) as f64);
+            }
+        }
+        best
+    }
+
+    pub fn matches(&self, other: &Fingerprint) -> bool {
+        self.similarity(other) >= MATCH_THRESHOLD
+    }
+}
+
+/// Comma separated items, like `fpcalc` prints them, to be saved.
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items: Vec<String> = self.0.iter().map(u32::to_string).collect();
+        write!(f, "{}", items.join(","))
+    }
+}
+
+impl std::str::FromStr for Fingerprint {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim().split(',').map(str::parse).collect::<Result<Vec<u32>, _>>().map(Self)
+    }
+}
+
+/// The pairs of `fingerprints` (by their position) that are the same recording, sorted.
+pub fn matching_pairs(fingerprints: &[&Fingerprint]) -> Vec<(usize, usize)> {
+
+    let mut sharing: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (ind, fingerprint) in fingerprints.iter().enumerate() {
+        for item in fingerprint.0.iter().collect::<HashSet<_>>() {
+            sharing.entry(*item).or_default().push(ind);
+        }
+    }
+
+    let mut shared: HashMap<(usize, usize), usize> = HashMap::new();
+    for inds in sharing.values().filter(|inds| inds.len() <= MAX_SHARING) {
+        for (n, first) in inds.iter().enumerate() {
+            for second in &inds[n + 1..] {
+                *shared.entry((*first, *second)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize)> = shared.into_iter()
+        .filter(|(_, count)| *count >= MIN_SHARED)
+        .map(|(pair, _)| pair)
+        .filter(|(first, second)| fingerprints[*first].matches(fingerprints[*second]))
+        .collect();
+    pairs.sort();
+    pairs
+}
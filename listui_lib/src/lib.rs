@@ -3,4 +3,13 @@ pub mod models;
 pub mod api;
 pub mod db;
 pub mod downloader;
-pub mod player;
\ No newline at end of file
+pub mod player;
+pub mod export;
+pub mod album;
+pub mod transcoder;
+pub mod tools;
+pub mod artwork;
+pub mod fingerprint;
+pub mod spectrum;
+pub mod stats;
+pub mod scrobble;
@@ -1,8 +1,72 @@
 use diesel::prelude::*;
-use crate::schema::{track, playlist};
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::Sqlite;
+use crate::schema::{track, playlist, playlist_track};
 
 pub trait Drawable {
     fn get_text(&self) -> &str;
+
+    /// Emoji drawn before the text.
+    fn get_icon(&self) -> Option<&str> { None }
+
+    /// Name or hex code of the color of the text.
+    fn get_color(&self) -> Option<&str> { None }
+
+    /// Number drawn before the text, like the track number of a song in an album.
+    fn get_number(&self) -> Option<i32> { None }
+
+    /// Length in seconds, to sort by it.
+    fn get_duration(&self) -> Option<i32> { None }
+
+    /// Times it has been played, to sort by it.
+    fn get_play_count(&self) -> Option<i32> { None }
+
+    /// Anything that grows with the time it was added, like its id, to sort by it.
+    fn get_added(&self) -> Option<i32> { None }
+
+    /// Unix timestamp of when it was uploaded or published, to sort by it.
+    fn get_uploaded(&self) -> Option<i64> { None }
+}
+
+/// Reason why a video will probably fail to download.
+#[derive(Debug, Clone, Copy, PartialEq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+pub enum Restriction {
+    AgeRestricted,
+    RegionBlocked
+}
+
+impl std::fmt::Display for Restriction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Restriction::AgeRestricted => write!(f, "This video is age restricted."),
+            Restriction::RegionBlocked => write!(f, "This video is not available in your region.")
+        }
+    }
+}
+
+impl ToSql<Text, Sqlite> for Restriction {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        let value = match self {
+            Restriction::AgeRestricted => "age",
+            Restriction::RegionBlocked => "region"
+        };
+        <str as ToSql<Text, Sqlite>>::to_sql(value, out)
+    }
+}
+
+impl FromSql<Text, Sqlite> for Restriction {
+    fn from_sql(bytes: <Sqlite as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Sqlite>>::from_sql(bytes)?.as_str() {
+            "age" => Ok(Restriction::AgeRestricted),
+            "region" => Ok(Restriction::RegionBlocked),
+            other => Err(format!("Unknown restriction {other}").into())
+        }
+    }
 }
 
 #[derive(Queryable, Identifiable, Debug, Clone)]
@@ -10,8 +74,52 @@ pub trait Drawable {
 pub struct Track {
     pub id: i32,
     pub title: String,
-    pub yt_id: Option<String>,
-    pub playlist_id: Option<i32>
+    /// The id of a YouTube video, or of a song in a media server (like `subsonic:42`).
+    pub source_id: Option<String>,
+    pub playlist_id: Option<i32>,
+    /// `None` unless the track is a podcast episode.
+    pub played: Option<bool>,
+    /// Where the user stopped listening to an episode, in seconds.
+    pub position: Option<i32>,
+    /// Set if the video can't be downloaded from the user's region or without signing in.
+    pub restriction: Option<Restriction>,
+    /// Set if the track was removed from its playlist on YouTube.
+    pub removed: bool,
+    /// Where the track is played from, if it isn't a video or a song of a media server: a local
+    /// file (`file://...`), a podcast episode, a Bandcamp track or any other URL `yt-dlp` supports.
+    pub source_url: Option<String>,
+    /// Who made the track. For videos, the channel that uploaded it.
+    pub artist: Option<String>,
+    /// The album the track is from, if it was imported from one.
+    pub album: Option<String>,
+    pub track_number: Option<i32>,
+    /// In seconds, once the track has been played.
+    pub duration: Option<i32>,
+    /// Times the track has been played until the end.
+    pub play_count: i32,
+    /// Where the track starts playing, in seconds, to skip its intro.
+    pub trim_start: Option<i32>,
+    /// Where the track stops playing, in seconds from its beginning.
+    pub trim_end: Option<i32>,
+    /// The year it was released, if MusicBrainz knows it.
+    pub year: Option<i32>,
+    /// Whether it has been looked up in MusicBrainz, even if it wasn't found.
+    pub metadata_checked: bool,
+    /// Unix timestamp of when the video was uploaded, if the API said it.
+    pub uploaded_at: Option<i64>
+}
+
+impl Track {
+
+    /// What the track is downloaded from: its URL, or its id if it doesn't have one.
+    pub fn source(&self) -> Option<&str> {
+        self.source_url.as_deref().or(self.source_id.as_deref())
+    }
+
+    /// Whether it's a file of the user's, instead of something downloaded.
+    pub fn is_local(&self) -> bool {
+        self.source_url.as_ref().is_some_and(|url| url.starts_with("file://"))
+    }
 }
 
 impl Drawable for Track {
@@ -19,6 +127,37 @@ impl Drawable for Track {
     fn get_text(&self) -> &str {
         &self.title
     }
+
+    // Marks the removed and restricted tracks, and the episodes that haven't been played yet.
+    fn get_icon(&self) -> Option<&str> {
+        match (&self.restriction, self.played) {
+            _ if self.removed => Some("✗"),
+            (Some(_), _) => Some("⚠"),
+            (None, Some(false)) => Some("●"),
+            _ => None
+        }
+    }
+
+    fn get_number(&self) -> Option<i32> {
+        self.track_number
+    }
+
+    fn get_duration(&self) -> Option<i32> {
+        self.duration
+    }
+
+    fn get_play_count(&self) -> Option<i32> {
+        Some(self.play_count)
+    }
+
+    // Tracks are saved as they are added, so their ids go in that order.
+    fn get_added(&self) -> Option<i32> {
+        Some(self.id)
+    }
+
+    fn get_uploaded(&self) -> Option<i64> {
+        self.uploaded_at
+    }
 }
 
 #[derive(Queryable, Identifiable, Debug, Clone)]
@@ -26,7 +165,18 @@ impl Drawable for Track {
 pub struct Playlist {
     pub id: i32,
     pub title: String,
-    pub yt_id: String
+    /// `None` for playlists created by the user.
+    pub yt_id: Option<String>,
+    pub color: Option<String>,
+    pub emoji: Option<String>,
+    /// The channel or user that made it, if the API says it.
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub thumbnail_url: Option<String>,
+    /// Unix timestamp of when it was saved. Unknown for playlists saved by older versions.
+    pub created_at: Option<i64>,
+    /// Unix timestamp of the last time it was fetched again.
+    pub last_synced_at: Option<i64>
 }
 
 impl Drawable for Playlist {
@@ -34,19 +184,113 @@ impl Drawable for Playlist {
     fn get_text(&self) -> &str {
         &self.title
     }
+
+    fn get_icon(&self) -> Option<&str> {
+        self.emoji.as_deref()
+    }
+
+    fn get_color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    fn get_added(&self) -> Option<i32> {
+        Some(self.id)
+    }
 }
 
-#[derive(Insertable, Debug)]
+#[derive(Insertable, Debug, Clone)]
 #[diesel(table_name = track)]
 pub struct NewVideo {
     pub title: String,
-    pub yt_id: String,
-    pub playlist_id: Option<i32>
+    pub source_id: Option<String>,
+    pub source_url: Option<String>,
+    pub playlist_id: Option<i32>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<i32>,
+    /// In seconds, if the API says it.
+    pub duration: Option<i32>,
+    /// Unix timestamp of the upload, if the API says it.
+    pub uploaded_at: Option<i64>
 }
 
-#[derive(Insertable, Debug)]
+impl NewVideo {
+
+    /// A track of a YouTube video or a media server song, or of a URL if `source` has a scheme.
+    pub fn new(title: impl Into<String>, source: impl Into<String>) -> Self {
+
+        let source = source.into();
+        let (source_id, source_url) = if source.contains("://") { (None, Some(source)) }
+            else { (Some(source), None) };
+        Self { title: title.into(), source_id, source_url, playlist_id: None, artist: None, album: None, track_number: None, duration: None, uploaded_at: None }
+    }
+
+    /// Lives and unknown lengths are sent as 0 or less by some APIs, so they are left unset.
+    pub fn with_duration(mut self, duration: Option<i32>) -> Self {
+        self.duration = duration.filter(|duration| *duration > 0);
+        self
+    }
+
+    /// Unknown upload times are sent as 0 or less by some APIs, so they are left unset too.
+    pub fn with_upload_time(mut self, uploaded_at: Option<i64>) -> Self {
+        self.uploaded_at = uploaded_at.filter(|uploaded_at| *uploaded_at > 0);
+        self
+    }
+
+    pub fn with_artist(mut self, artist: Option<String>) -> Self {
+        self.artist = artist.filter(|artist| !artist.trim().is_empty());
+        self
+    }
+
+    /// Uses the name of the channel that uploaded the video as its artist. It's usually right
+    /// for music, specially for the "Artist - Topic" channels that YouTube makes for every artist.
+    pub fn with_channel(self, channel: Option<String>) -> Self {
+        let artist = channel.map(|channel| {
+            let name = channel.trim();
+            let name = name.strip_suffix(" - Topic")
+                .or_else(|| name.strip_suffix("VEVO").filter(|name| !name.is_empty()))
+                .unwrap_or(name);
+            String::from(name.trim())
+        });
+        self.with_artist(artist)
+    }
+
+    /// Its URL, or its id if it doesn't have one.
+    pub fn source(&self) -> &str {
+        self.source_url.as_deref().or(self.source_id.as_deref()).unwrap_or_default()
+    }
+}
+
+#[derive(Insertable, Debug, Clone)]
 #[diesel(table_name = playlist)]
 pub struct NewPlaylist {
     pub title: String,
-    pub yt_id: String
+    pub yt_id: String,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub thumbnail_url: Option<String>
+}
+
+impl NewPlaylist {
+
+    pub fn new(title: impl Into<String>, yt_id: impl Into<String>) -> Self {
+        Self { title: title.into(), yt_id: yt_id.into(), author: None, description: None, thumbnail_url: None }
+    }
+
+    /// Some APIs send empty values instead of leaving them out, so they are left unset.
+    pub fn with_details(mut self, author: Option<String>, description: Option<String>, thumbnail_url: Option<String>) -> Self {
+        let given = |value: Option<String>| value.map(|value| String::from(value.trim())).filter(|value| !value.is_empty());
+        self.author = given(author);
+        self.description = given(description);
+        self.thumbnail_url = given(thumbnail_url);
+        self
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = playlist_track)]
+pub struct PlaylistTrack {
+    pub playlist_id: i32,
+    pub track_id: i32,
+    pub position: i32
 }
@@ -2,10 +2,26 @@ use std::fmt::Debug;
 use std::sync::atomic::{AtomicI64, Ordering};
 use std::{fs::File, time::Duration};
 use std::io::BufReader;
-use std::path::Path;
-use rodio::{Decoder, OutputStream, Source, Sink};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use rodio::{Decoder, Source, Sink};
+use rodio::buffer::SamplesBuffer;
 use thiserror::Error;
 
+use crate::spectrum::{SampleTap, Tapped};
+
+mod output;
+pub use output::{AudioDevice, list_output_devices};
+use output::{Output, SharedQueue};
+
+#[cfg(feature = "mpv")]
+mod mpv;
+#[cfg(feature = "mpv")]
+pub use mpv::MpvPlayer;
+#[cfg(feature = "gstreamer")]
+mod gstreamer;
+#[cfg(feature = "gstreamer")]
+pub use self::gstreamer::GstPlayer;
 
 #[derive(Error, Debug)]
 pub enum PlayerError {
@@ -15,59 +31,203 @@ pub enum PlayerError {
     IOError(#[from] std::io::Error),
     #[error("Decoding error: {0}")]
     DecodingError(#[from] rodio::decoder::DecoderError),
+    #[error("{0}")]
+    BackendError(String),
+}
+
+/// Information about a player's output, shown in the diagnostics screen.
+#[derive(Debug, Clone, Default)]
+pub struct PlayerInfo {
+    pub backend: &'static str,
+    pub device: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// Times the audio device ran out of samples, if the backend reports it.
+    pub underruns: Option<u64>,
+    /// Errors reported by the audio stream, if the backend reports them.
+    pub stream_errors: Option<u64>,
+}
+
+/// Something that can play audio files.
+pub trait Player: Send + Sync {
+
+    /// Starts playing a file, stopping the current track.
+    fn play_file(&self, path: &Path) -> Result<(), PlayerError>;
+    fn is_playing(&self) -> bool;
+    fn is_paused(&self) -> bool;
+    fn seek(&self, seconds: u64);
+    fn seek_percentage(&self, percentage: u64);
+    /// Jumps forward, stopping the track if it goes past its end.
+    fn forward(&self, seconds: u64);
+    fn rewind(&self, seconds: u64);
+    fn pause(&self);
+    fn resume(&self);
+    /// Current position in seconds, or `None` if nothing is playing.
+    fn get_progress(&self) -> Option<u64>;
+    /// Duration of the current track in seconds, or `None` if nothing is playing.
+    fn get_duration(&self) -> Option<u64>;
+    fn increase_volume(&self, volume_inc: i32);
+    fn decrease_volume(&self, volume_inc: i32);
+    /// Volume in percentage.
+    fn get_volume(&self) -> i32;
+    fn stop(&self);
+    fn info(&self) -> PlayerInfo;
+    /// Where the samples being played are copied for the visualizer, if the backend supports it.
+    fn sample_tap(&self) -> Option<SampleTap> {
+        None
+    }
+    /// Whether the output device disappeared, like headphones that were unplugged, if the
+    /// backend can tell.
+    fn output_lost(&self) -> bool {
+        false
+    }
+    /// Opens the output again on the default device (or any other that works) after it was
+    /// lost. The current track goes on from where it was when the device went away.
+    fn reopen_output(&self) -> Result<(), PlayerError> {
+        Ok(())
+    }
 }
 
-pub struct Player {
+/// `Player` that outputs to the default audio device using `rodio`.
+pub struct RodioPlayer {
 
     sink: Sink,
-    current_track_duration: AtomicI64
+    current_track_duration: AtomicI64,
+    // Replaced when the device goes away. The queue is what the sink plays, kept to open it again.
+    output: Mutex<Option<Output>>,
+    queue: Option<SharedQueue>,
+    // Tracks smaller than this (once decoded) are fully loaded into memory.
+    preload_limit: usize,
+    tap: Option<SampleTap>
 }
 
-impl Debug for Player {
+impl Debug for RodioPlayer {
     fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         Ok(())
     }
 }
 
-impl Player {
+impl RodioPlayer {
 
+    /// Creates a player that outputs to the default device, or to any other
+    /// device if the default one doesn't work.
     pub fn try_default() -> Result<Self, PlayerError> {
         
-        let (stream, stream_handle) = OutputStream::try_default()?;
-        let sink = Sink::try_new(&stream_handle).unwrap();
-        std::mem::forget(stream);
+        Self::with_device(None).or_else(|err| {
+            list_output_devices().iter()
+                .find_map(|device| Self::with_device(Some(device)).ok())
+                .ok_or(err)
+        })
+    }
+
+    /// Creates a player that outputs to `device`, or to the default device if `None`.
+    pub fn with_device(device: Option<&AudioDevice>) -> Result<Self, PlayerError> {
+
+        let (sink, queue) = Sink::new_idle();
+        let queue = SharedQueue::new(queue);
+        let output = Output::open(device, queue.clone())?;
         Ok(Self {
             sink,
-            current_track_duration: AtomicI64::new(0)
+            current_track_duration: AtomicI64::new(0),
+            output: Mutex::new(Some(output)),
+            queue: Some(queue),
+            preload_limit: 0,
+            tap: None
         })
     }
 
-    pub fn play_file(&self, path: &Path) -> Result<(), PlayerError> {
+    /// Creates a player that doesn't output any sound.
+    /// 
+    /// Tracks still play in real time, so it can be used where no audio device is available.
+    pub fn with_null_output() -> Self {
+
+        let (sink, mut output) = Sink::new_idle();
+        std::thread::spawn(move || {
+            // Consume 10ms worth of samples every 10ms. The iterator ends once the sink is dropped.
+            loop {
+                let samples = output.sample_rate() as usize * output.channels() as usize / 100;
+                if output.by_ref().take(samples).count() < samples {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        Self {
+            sink,
+            current_track_duration: AtomicI64::new(0),
+            output: Mutex::new(None),
+            queue: None,
+            preload_limit: 0,
+            tap: None
+        }
+    }
+
+    /// Decodes tracks fully into memory before playing them, as long as they take less
+    /// than `max_bytes`, so seeking is instant. Longer tracks are still streamed from disk.
+    pub fn with_preload(mut self, max_bytes: usize) -> Self {
+        self.preload_limit = max_bytes;
+        self
+    }
+
+    /// Copies the samples being played into a `SampleTap`, to draw their spectrum.
+    pub fn with_visualizer(mut self) -> Self {
+        self.tap = Some(SampleTap::default());
+        self
+    }
+
+    // Queues a track, through the tap if there is one.
+    fn append<S>(&self, source: S)
+    where S: Source<Item = i16> + Send + 'static {
+        match &self.tap {
+            Some(tap) => {
+                tap.clear();
+                self.sink.append(Tapped::new(source, tap.clone()));
+            },
+            None => self.sink.append(source)
+        }
+    }
+}
+
+impl Player for RodioPlayer {
+
+    fn play_file(&self, path: &Path) -> Result<(), PlayerError> {
         
         let file = BufReader::new(File::open(path)?);
         let source = Decoder::new(file)?;
         self.stop();
-        self.current_track_duration.store(source.total_duration().unwrap().as_secs() as i64, Ordering::SeqCst);
+        let duration = source.total_duration().unwrap();
+        self.current_track_duration.store(duration.as_secs() as i64, Ordering::SeqCst);
 
-        
-        self.sink.append(source);
+        let decoded_size = duration.as_secs_f64() * source.sample_rate() as f64 * source.channels() as f64 * std::mem::size_of::<i16>() as f64;
+        if (decoded_size as usize) < self.preload_limit {
+            let (channels, sample_rate) = (source.channels(), source.sample_rate());
+            let samples: Vec<i16> = source.collect();
+            self.append(SamplesBuffer::new(channels, sample_rate, samples));
+        }
+        else {
+            if self.preload_limit > 0 {
+                log::info!("{} is too long to be preloaded, streaming it.", path.display());
+            }
+            self.append(source);
+        }
         Ok(())
     }
 
-    pub fn is_playing(&self) -> bool {
+    fn is_playing(&self) -> bool {
         // WARNING
         self.current_track_duration.load(Ordering::Relaxed) >= 0
     }
 
-    pub fn is_paused(&self) -> bool {
+    fn is_paused(&self) -> bool {
         self.sink.is_paused()
     }
 
-    pub fn seek(&self, seconds: u64) {
+    fn seek(&self, seconds: u64) {
         self.sink.try_seek(Duration::from_secs(seconds)).expect("Failed to seek");
     }
 
-    pub fn seek_percentage(&self, percentage: u64) {
+    fn seek_percentage(&self, percentage: u64) {
         
         if let Some(duration) = self.get_duration() {
             let time = percentage * duration / 100;
@@ -76,7 +236,7 @@ impl Player {
         
     }
 
-    pub fn forward(&self, seconds: u64) {
+    fn forward(&self, seconds: u64) {
         
         if let Some(progress) = self.get_progress() {
             let newpos = progress + seconds;
@@ -88,7 +248,7 @@ impl Player {
         }
     }
 
-    pub fn rewind(&self, seconds: u64) {
+    fn rewind(&self, seconds: u64) {
     
         if let Some(progress) = self.get_progress() {
             if progress > seconds {
@@ -101,15 +261,15 @@ impl Player {
         }
     }
 
-    pub fn pause(&self) {
+    fn pause(&self) {
         self.sink.pause();
     }
 
-    pub fn resume(&self) {
+    fn resume(&self) {
         self.sink.play();    
     }
 
-    pub fn get_progress(&self) -> Option<u64> {
+    fn get_progress(&self) -> Option<u64> {
     
         if !self.is_playing() {
             None
@@ -119,7 +279,7 @@ impl Player {
         }
     }
 
-    pub fn get_duration(&self) -> Option<u64> {
+    fn get_duration(&self) -> Option<u64> {
 
         if !self.is_playing() {
             None
@@ -129,26 +289,219 @@ impl Player {
         }
     }
 
-    pub fn increase_volume(&self, volume_inc: i32) {
+    fn increase_volume(&self, volume_inc: i32) {
 
         let mut new_volume = self.sink.volume() + (volume_inc as f32 / 100.0);
         if new_volume > 2.0 { new_volume = 2.0; } 
         self.sink.set_volume(new_volume);
     }
 
-    pub fn decrease_volume(&self, volume_inc: i32) {
+    fn decrease_volume(&self, volume_inc: i32) {
 
         let mut new_volume = self.sink.volume() - (volume_inc as f32 / 100.0);
         if new_volume < 0.0 { new_volume = 0.0; }
         self.sink.set_volume(new_volume);
     }
 
-    pub fn get_volume(&self) -> i32 {
+    fn get_volume(&self) -> i32 {
         (100.0 * self.sink.volume()).round() as i32
     }
 
-    pub fn stop(&self) {
+    fn stop(&self) {
         self.current_track_duration.store(-1, Ordering::SeqCst);
         self.sink.stop();
     }
+
+    fn info(&self) -> PlayerInfo {
+
+        match self.output.lock().unwrap().as_ref() {
+            Some(output) => PlayerInfo {
+                backend: "rodio",
+                device: Some(format!("{} / {}", output.device.host, output.device.name)),
+                sample_rate: Some(output.sample_rate),
+                channels: Some(output.channels),
+                underruns: Some(output.underruns()),
+                stream_errors: Some(output.errors()),
+            },
+            None => PlayerInfo {
+                backend: "rodio",
+                device: Some(String::from("none")),
+                ..Default::default()
+            }
+        }
+    }
+
+    fn sample_tap(&self) -> Option<SampleTap> {
+        self.tap.clone()
+    }
+
+    fn output_lost(&self) -> bool {
+        self.output.lock().unwrap().as_ref().is_some_and(Output::is_lost)
+    }
+
+    fn reopen_output(&self) -> Result<(), PlayerError> {
+
+        let Some(queue) = &self.queue else { return Ok(()) };
+        let output = Output::open(None, queue.clone()).or_else(|err| {
+            list_output_devices().iter()
+                .find_map(|device| Output::open(Some(device), queue.clone()).ok())
+                .ok_or(err)
+        })?;
+        log::info!("Audio output opened again on {} / {}.", output.device.host, output.device.name);
+        // The stream of the old device is closed once it's dropped.
+        *self.output.lock().unwrap() = Some(output);
+        Ok(())
+    }
+}
+
+/// `Player` that doesn't play anything, to be used in tests.
+/// 
+/// The position of the current track only changes when seeking. Files that start with `DAMAGED`
+/// can't be played, like a download that was cut short.
+pub struct MockPlayer {
+
+    track_duration: u64,
+    state: Mutex<MockPlayerState>
+}
+
+/// What the files that `MockPlayer` can't play start with.
+pub const DAMAGED: &[u8] = b"DAMAGED";
+
+#[derive(Default)]
+struct MockPlayerState {
+    played: Vec<PathBuf>,
+    playing: bool,
+    paused: bool,
+    position: u64,
+    volume: i32,
+    output_lost: bool,
+    no_devices: bool
+}
+
+impl MockPlayer {
+
+    /// Creates a player where every track lasts `track_duration` seconds.
+    pub fn new(track_duration: u64) -> Self {
+        Self {
+            track_duration,
+            state: Mutex::new(MockPlayerState { volume: 100, ..Default::default() })
+        }
+    }
+
+    /// Files that have been played, in order.
+    pub fn played_files(&self) -> Vec<PathBuf> {
+        self.state.lock().unwrap().played.clone()
+    }
+
+    /// Makes the output device disappear, like unplugged headphones.
+    pub fn lose_output(&self) {
+        self.state.lock().unwrap().output_lost = true;
+    }
+
+    /// Leaves no device to open the output on, or brings them back.
+    pub fn set_devices_available(&self, available: bool) {
+        self.state.lock().unwrap().no_devices = !available;
+    }
+}
+
+impl Player for MockPlayer {
+
+    fn play_file(&self, path: &Path) -> Result<(), PlayerError> {
+
+        if !path.exists() {
+            return Err(PlayerError::IOError(std::io::Error::from(std::io::ErrorKind::NotFound)));
+        }
+        if std::fs::read(path)?.starts_with(DAMAGED) {
+            return Err(PlayerError::BackendError(String::from("The file is damaged.")));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.played.push(path.to_path_buf());
+        state.playing = true;
+        state.paused = false;
+        state.position = 0;
+        Ok(())
+    }
+
+    fn is_playing(&self) -> bool {
+        self.state.lock().unwrap().playing
+    }
+
+    fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    fn seek(&self, seconds: u64) {
+        self.state.lock().unwrap().position = seconds.min(self.track_duration);
+    }
+
+    fn seek_percentage(&self, percentage: u64) {
+        self.seek(percentage * self.track_duration / 100);
+    }
+
+    fn forward(&self, seconds: u64) {
+        let position = self.state.lock().unwrap().position + seconds;
+        if position >= self.track_duration { self.stop(); }
+        else { self.seek(position); }
+    }
+
+    fn rewind(&self, seconds: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.position = state.position.saturating_sub(seconds);
+    }
+
+    fn pause(&self) {
+        self.state.lock().unwrap().paused = true;
+    }
+
+    fn resume(&self) {
+        self.state.lock().unwrap().paused = false;
+    }
+
+    fn get_progress(&self) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        state.playing.then_some(state.position)
+    }
+
+    fn get_duration(&self) -> Option<u64> {
+        self.is_playing().then_some(self.track_duration)
+    }
+
+    fn increase_volume(&self, volume_inc: i32) {
+        let mut state = self.state.lock().unwrap();
+        state.volume = (state.volume + volume_inc).min(200);
+    }
+
+    fn decrease_volume(&self, volume_inc: i32) {
+        let mut state = self.state.lock().unwrap();
+        state.volume = (state.volume - volume_inc).max(0);
+    }
+
+    fn get_volume(&self) -> i32 {
+        self.state.lock().unwrap().volume
+    }
+
+    fn stop(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.playing = false;
+        state.position = 0;
+    }
+
+    fn info(&self) -> PlayerInfo {
+        PlayerInfo { backend: "mock", ..Default::default() }
+    }
+
+    fn output_lost(&self) -> bool {
+        self.state.lock().unwrap().output_lost
+    }
+
+    fn reopen_output(&self) -> Result<(), PlayerError> {
+
+        let mut state = self.state.lock().unwrap();
+        if state.no_devices {
+            return Err(PlayerError::BackendError(String::from("Failed to open audio device: no output device")));
+        }
+        state.output_lost = false;
+        Ok(())
+    }
 }
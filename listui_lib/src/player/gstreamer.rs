@@ -0,0 +1,163 @@
+//! `Player` backed by GStreamer.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use gstreamer as gst;
+use gst::prelude::*;
+use gst::{ClockTime, SeekFlags, State};
+
+use super::{Player, PlayerError, PlayerInfo};
+
+/// `Player` that uses a GStreamer `playbin`, for systems where rodio can't
+/// open the audio device.
+pub struct GstPlayer {
+
+    playbin: gst::Element,
+    playing: AtomicBool
+}
+
+impl GstPlayer {
+
+    pub fn try_default() -> Result<Self, PlayerError> {
+
+        gst::init().map_err(|e| convert_err(e.to_string()))?;
+        let playbin = gst::ElementFactory::make("playbin").build()
+            .map_err(|e| convert_err(e.to_string()))?;
+
+        let video_sink = gst::ElementFactory::make("fakesink").build()
+            .map_err(|e| convert_err(e.to_string()))?;
+        playbin.set_property("video-sink", &video_sink);
+
+        Ok(Self {
+            playbin,
+            playing: AtomicBool::new(false)
+        })
+    }
+
+    // Checks whether the current track has ended.
+    fn poll_bus(&self) {
+
+        if let Some(bus) = self.playbin.bus() {
+            while let Some(msg) = bus.pop() {
+                match msg.view() {
+                    gst::MessageView::Eos(_) => self.playing.store(false, Ordering::SeqCst),
+                    gst::MessageView::Error(err) => {
+                        log::error!("GStreamer error: {}", err.error());
+                        self.playing.store(false, Ordering::SeqCst);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn seek_to(&self, seconds: u64) {
+        let _ = self.playbin.seek_simple(SeekFlags::FLUSH | SeekFlags::KEY_UNIT, ClockTime::from_seconds(seconds));
+    }
+}
+
+impl Player for GstPlayer {
+
+    fn play_file(&self, path: &Path) -> Result<(), PlayerError> {
+
+        let path = path.canonicalize()?;
+        let uri = gst::glib::filename_to_uri(&path, None).map_err(|e| convert_err(e.to_string()))?;
+
+        self.stop();
+        self.playbin.set_property("uri", uri.as_str());
+        self.playbin.set_state(State::Playing).map_err(|e| convert_err(e.to_string()))?;
+
+        // Wait until the file has been opened, so the duration is known.
+        let (result, _, _) = self.playbin.state(ClockTime::from_seconds(5));
+        result.map_err(|e| convert_err(e.to_string()))?;
+
+        self.playing.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn is_playing(&self) -> bool {
+        self.poll_bus();
+        self.playing.load(Ordering::SeqCst)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.playbin.current_state() == State::Paused
+    }
+
+    fn seek(&self, seconds: u64) {
+        self.seek_to(seconds);
+    }
+
+    fn seek_percentage(&self, percentage: u64) {
+
+        if let Some(duration) = self.get_duration() {
+            self.seek_to(percentage * duration / 100);
+        }
+    }
+
+    fn forward(&self, seconds: u64) {
+
+        if let (Some(progress), Some(duration)) = (self.get_progress(), self.get_duration()) {
+            if progress + seconds >= duration { self.stop(); }
+            else { self.seek_to(progress + seconds); }
+        }
+    }
+
+    fn rewind(&self, seconds: u64) {
+
+        if let Some(progress) = self.get_progress() {
+            self.seek_to(progress.saturating_sub(seconds));
+        }
+    }
+
+    fn pause(&self) {
+        let _ = self.playbin.set_state(State::Paused);
+    }
+
+    fn resume(&self) {
+        let _ = self.playbin.set_state(State::Playing);
+    }
+
+    fn get_progress(&self) -> Option<u64> {
+        if !self.is_playing() { None }
+        else { self.playbin.query_position::<ClockTime>().map(|pos| pos.seconds()) }
+    }
+
+    fn get_duration(&self) -> Option<u64> {
+        if !self.is_playing() { None }
+        else { self.playbin.query_duration::<ClockTime>().map(|duration| duration.seconds()) }
+    }
+
+    fn increase_volume(&self, volume_inc: i32) {
+        let volume = (self.get_volume() + volume_inc).min(200);
+        self.playbin.set_property("volume", volume as f64 / 100.0);
+    }
+
+    fn decrease_volume(&self, volume_inc: i32) {
+        let volume = (self.get_volume() - volume_inc).max(0);
+        self.playbin.set_property("volume", volume as f64 / 100.0);
+    }
+
+    fn get_volume(&self) -> i32 {
+        (100.0 * self.playbin.property::<f64>("volume")).round() as i32
+    }
+
+    fn stop(&self) {
+        self.playing.store(false, Ordering::SeqCst);
+        let _ = self.playbin.set_state(State::Null);
+    }
+
+    fn info(&self) -> PlayerInfo {
+        PlayerInfo { backend: "gstreamer", ..Default::default() }
+    }
+}
+
+impl Drop for GstPlayer {
+    fn drop(&mut self) {
+        let _ = self.playbin.set_state(State::Null);
+    }
+}
+
+fn convert_err(err: String) -> PlayerError {
+    PlayerError::BackendError(format!("GStreamer error: {err}"))
+}
@@ -0,0 +1,159 @@
+//! `Player` backed by libmpv.
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use libmpv2::Mpv;
+use libmpv2::events::Event;
+
+use super::{Player, PlayerError, PlayerInfo};
+
+// How long to wait for mpv to open a file.
+const LOAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `Player` that uses libmpv, so anything that mpv can play is supported.
+pub struct MpvPlayer {
+
+    mpv: Mpv,
+    playing: AtomicBool,
+    // Only one thread can wait for mpv's events at a time.
+    events_lock: Mutex<()>
+}
+
+impl MpvPlayer {
+
+    pub fn try_default() -> Result<Self, PlayerError> {
+
+        let mpv = Mpv::with_initializer(|init| {
+            init.set_option("vid", "no")?;
+            init.set_option("idle", "yes")?;
+            init.set_option("gapless-audio", "yes")?;
+            init.set_option("volume-max", 200.0)?;
+            Ok(())
+        }).map_err(convert_err)?;
+
+        Ok(Self {
+            mpv,
+            playing: AtomicBool::new(false),
+            events_lock: Mutex::new(())
+        })
+    }
+
+    fn get_f64(&self, property: &str) -> Option<f64> {
+        self.mpv.get_property::<f64>(property).ok()
+    }
+}
+
+impl Player for MpvPlayer {
+
+    fn play_file(&self, path: &Path) -> Result<(), PlayerError> {
+
+        if !path.exists() {
+            return Err(PlayerError::IOError(std::io::Error::from(std::io::ErrorKind::NotFound)));
+        }
+
+        let _guard = self.events_lock.lock().unwrap();
+        self.mpv.command("loadfile", &[&path.to_string_lossy(), "replace"]).map_err(convert_err)?;
+        self.mpv.set_property("pause", false).map_err(convert_err)?;
+
+        // loadfile returns before the file is opened, wait until the duration is known.
+        let start = Instant::now();
+        loop {
+            match self.mpv.wait_event(0.1) {
+                Some(Ok(Event::FileLoaded)) => break,
+                Some(Err(e)) => return Err(convert_err(e)),
+                _ if start.elapsed() > LOAD_TIMEOUT => {
+                    return Err(PlayerError::BackendError(String::from("mpv took too long to open the file.")));
+                }
+                _ => {}
+            }
+        }
+
+        self.playing.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn is_playing(&self) -> bool {
+        self.playing.load(Ordering::SeqCst) && !self.mpv.get_property::<bool>("idle-active").unwrap_or(true)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.mpv.get_property::<bool>("pause").unwrap_or(false)
+    }
+
+    fn seek(&self, seconds: u64) {
+        let _ = self.mpv.command("seek", &[&seconds.to_string(), "absolute"]);
+    }
+
+    fn seek_percentage(&self, percentage: u64) {
+        let _ = self.mpv.command("seek", &[&percentage.to_string(), "absolute-percent"]);
+    }
+
+    fn forward(&self, seconds: u64) {
+
+        if let (Some(progress), Some(duration)) = (self.get_progress(), self.get_duration()) {
+            if progress + seconds >= duration { self.stop(); }
+            else { self.seek(progress + seconds); }
+        }
+    }
+
+    fn rewind(&self, seconds: u64) {
+
+        if let Some(progress) = self.get_progress() {
+            self.seek(progress.saturating_sub(seconds));
+        }
+    }
+
+    fn pause(&self) {
+        let _ = self.mpv.set_property("pause", true);
+    }
+
+    fn resume(&self) {
+        let _ = self.mpv.set_property("pause", false);
+    }
+
+    fn get_progress(&self) -> Option<u64> {
+        if !self.is_playing() { None }
+        else { self.get_f64("time-pos").map(|pos| pos as u64) }
+    }
+
+    fn get_duration(&self) -> Option<u64> {
+        if !self.is_playing() { None }
+        else { self.get_f64("duration").map(|duration| duration as u64) }
+    }
+
+    fn increase_volume(&self, volume_inc: i32) {
+        let volume = (self.get_volume() + volume_inc).min(200);
+        let _ = self.mpv.set_property("volume", volume as f64);
+    }
+
+    fn decrease_volume(&self, volume_inc: i32) {
+        let volume = (self.get_volume() - volume_inc).max(0);
+        let _ = self.mpv.set_property("volume", volume as f64);
+    }
+
+    fn get_volume(&self) -> i32 {
+        self.get_f64("volume").unwrap_or(100.0).round() as i32
+    }
+
+    fn stop(&self) {
+        self.playing.store(false, Ordering::SeqCst);
+        let _ = self.mpv.command("stop", &[]);
+    }
+
+    fn info(&self) -> PlayerInfo {
+
+        PlayerInfo {
+            backend: "mpv",
+            device: self.mpv.get_property::<String>("audio-device").ok(),
+            sample_rate: self.mpv.get_property::<i64>("audio-params/samplerate").ok().map(|rate| rate as u32),
+            channels: self.mpv.get_property::<i64>("audio-params/channel-count").ok().map(|channels| channels as u16),
+            ..Default::default()
+        }
+    }
+}
+
+fn convert_err(err: libmpv2::Error) -> PlayerError {
+    PlayerError::BackendError(format!("mpv error: {err}"))
+}
@@ -0,0 +1,225 @@
+//! Audio output used by `RodioPlayer`.
+//!
+//! The cpal stream is built here instead of using `rodio::OutputStream`, so the
+//! device can be chosen, the problems of the stream can be counted, and the stream can be
+//! opened again when the device goes away.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use rodio::Source;
+use rodio::cpal::{self, FromSample, SizedSample};
+use rodio::cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use rodio::queue::SourcesQueueOutput;
+use rodio::source::UniformSourceIterator;
+
+use super::PlayerError;
+
+/// An audio output device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioDevice {
+    pub host: String,
+    pub name: String,
+    pub is_default: bool
+}
+
+/// Lists the output devices of every available audio host.
+pub fn list_output_devices() -> Vec<AudioDevice> {
+
+    let default_host = cpal::default_host().id();
+    let default_device = cpal::default_host().default_output_device().and_then(|d| d.name().ok());
+
+    let mut devices = Vec::new();
+    for host_id in cpal::available_hosts() {
+        let Ok(host) = cpal::host_from_id(host_id) else { continue };
+        let Ok(outputs) = host.output_devices() else { continue };
+        for name in outputs.filter_map(|d| d.name().ok()) {
+            devices.push(AudioDevice {
+                host: String::from(host_id.name()),
+                is_default: host_id == default_host && Some(&name) == default_device.as_ref(),
+                name
+            });
+        }
+    }
+
+    devices
+}
+
+fn find_device(device: &AudioDevice) -> Option<cpal::Device> {
+
+    let host_id = cpal::available_hosts().into_iter().find(|id| id.name() == device.host)?;
+    cpal::host_from_id(host_id).ok()?
+        .output_devices().ok()?
+        .find(|d| d.name().ok().as_ref() == Some(&device.name))
+}
+
+/// The samples of the sink. They outlive the stream playing them, so the output can be opened
+/// again on another device and the track goes on from where it was.
+#[derive(Clone)]
+pub(super) struct SharedQueue(Arc<Mutex<SourcesQueueOutput<f32>>>);
+
+impl SharedQueue {
+    pub fn new(queue: SourcesQueueOutput<f32>) -> Self {
+        Self(Arc::new(Mutex::new(queue)))
+    }
+}
+
+impl Iterator for SharedQueue {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.0.lock().unwrap().next()
+    }
+}
+
+impl Source for SharedQueue {
+
+    fn current_frame_len(&self) -> Option<usize> {
+        self.0.lock().unwrap().current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.0.lock().unwrap().channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.0.lock().unwrap().sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.0.lock().unwrap().total_duration()
+    }
+}
+
+#[derive(Default)]
+struct OutputStats {
+    underruns: AtomicU64,
+    errors: AtomicU64,
+    // Set once the device is gone, like unplugged headphones.
+    lost: AtomicBool
+}
+
+/// An open output stream. The stream is closed when this is dropped.
+pub(super) struct Output {
+    pub device: AudioDevice,
+    pub sample_rate: u32,
+    pub channels: u16,
+    stats: Arc<OutputStats>,
+    // The stream lives in its own thread (it isn't Send), which stops when this is dropped.
+    _stop: mpsc::Sender<()>
+}
+
+impl Output {
+
+    /// Starts playing `queue` on `device`, or on the default device if `None`.
+    pub fn open(device: Option<&AudioDevice>, queue: SharedQueue) -> Result<Self, PlayerError> {
+
+        let device = device.cloned();
+        let (result_tx, result_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let stats = Arc::new(OutputStats::default());
+
+        let thread_stats = Arc::clone(&stats);
+        std::thread::spawn(move || {
+            match build_stream(device.as_ref(), queue, thread_stats) {
+                Ok((stream, info)) => {
+                    let _ = result_tx.send(Ok(info));
+                    // Blocks until the sender is dropped.
+                    let _ = stop_rx.recv();
+                    drop(stream);
+                },
+                Err(e) => { let _ = result_tx.send(Err(e)); }
+            }
+        });
+
+        let (device, sample_rate, channels) = result_rx.recv()
+            .map_err(|_| PlayerError::BackendError(String::from("Audio output thread died.")))??;
+
+        Ok(Self { device, sample_rate, channels, stats, _stop: stop_tx })
+    }
+
+    pub fn underruns(&self) -> u64 {
+        self.stats.underruns.load(Ordering::Relaxed)
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.stats.errors.load(Ordering::Relaxed)
+    }
+
+    /// Whether the device was disconnected. The stream doesn't play anything after that.
+    pub fn is_lost(&self) -> bool {
+        self.stats.lost.load(Ordering::Relaxed)
+    }
+}
+
+fn build_stream(device: Option<&AudioDevice>, queue: SharedQueue, stats: Arc<OutputStats>) -> Result<(cpal::Stream, (AudioDevice, u32, u16)), PlayerError> {
+
+    let host = cpal::default_host();
+    let (cpal_device, device) = match device {
+        Some(device) => (find_device(device).ok_or(device_err(format!("device {} not found", device.name)))?, device.clone()),
+        None => {
+            let cpal_device = host.default_output_device().ok_or(device_err(String::from("no output device")))?;
+            let device = AudioDevice {
+                host: String::from(host.id().name()),
+                name: cpal_device.name().unwrap_or_default(),
+                is_default: true
+            };
+            (cpal_device, device)
+        }
+    };
+
+    let supported = cpal_device.default_output_config().map_err(|e| device_err(e.to_string()))?;
+    let config = supported.config();
+    let source = UniformSourceIterator::new(queue, config.channels, config.sample_rate.0);
+
+    let stream = match supported.sample_format() {
+        cpal::SampleFormat::F32 => build_typed_stream::<f32>(&cpal_device, &config, source, stats),
+        cpal::SampleFormat::F64 => build_typed_stream::<f64>(&cpal_device, &config, source, stats),
+        cpal::SampleFormat::I16 => build_typed_stream::<i16>(&cpal_device, &config, source, stats),
+        cpal::SampleFormat::I32 => build_typed_stream::<i32>(&cpal_device, &config, source, stats),
+        cpal::SampleFormat::U16 => build_typed_stream::<u16>(&cpal_device, &config, source, stats),
+        format => return Err(device_err(format!("unsupported sample format {format}")))
+    }.map_err(|e| device_err(e.to_string()))?;
+
+    stream.play().map_err(|e| device_err(e.to_string()))?;
+    Ok((stream, (device, config.sample_rate.0, config.channels)))
+}
+
+fn build_typed_stream<T>(device: &cpal::Device, config: &cpal::StreamConfig, mut source: UniformSourceIterator<SharedQueue, f32>, stats: Arc<OutputStats>) -> Result<cpal::Stream, cpal::BuildStreamError>
+where T: SizedSample + FromSample<f32> {
+
+    let frames_per_sec = config.sample_rate.0 as f64 * config.channels as f64;
+    let mut last_callback: Option<(Instant, Duration)> = None;
+    let error_stats = Arc::clone(&stats);
+
+    device.build_output_stream::<T, _, _>(
+        config,
+        move |data: &mut [T], _| {
+            // If this callback came long after the previous buffer should have run out,
+            // the device was left without samples.
+            let now = Instant::now();
+            if let Some((last, buffer)) = last_callback {
+                if now.duration_since(last) > buffer * 2 + Duration::from_millis(10) {
+                    stats.underruns.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            last_callback = Some((now, Duration::from_secs_f64(data.len() as f64 / frames_per_sec)));
+
+            data.iter_mut().for_each(|d| *d = T::from_sample(source.next().unwrap_or(0.0)));
+        },
+        move |err| {
+            log::error!("Audio output error: {err}");
+            error_stats.errors.fetch_add(1, Ordering::Relaxed);
+            // Every host reports a device that was unplugged (or disabled) this way.
+            if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                error_stats.lost.store(true, Ordering::Relaxed);
+            }
+        },
+        None
+    )
+}
+
+fn device_err(msg: String) -> PlayerError {
+    PlayerError::BackendError(format!("Failed to open audio device: {msg}"))
+}
@@ -1,10 +1,48 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    app_state (key) {
+        key -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    fingerprint (path) {
+        path -> Text,
+        size -> BigInt,
+        data -> Text,
+    }
+}
+
+diesel::table! {
+    play (id) {
+        id -> Integer,
+        track_id -> Integer,
+        played_at -> BigInt,
+    }
+}
+
 diesel::table! {
     playlist (id) {
         id -> Integer,
         title -> Text,
-        yt_id -> Text,
+        yt_id -> Nullable<Text>,
+        color -> Nullable<Text>,
+        emoji -> Nullable<Text>,
+        author -> Nullable<Text>,
+        description -> Nullable<Text>,
+        thumbnail_url -> Nullable<Text>,
+        created_at -> Nullable<BigInt>,
+        last_synced_at -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    playlist_track (playlist_id, track_id) {
+        playlist_id -> Integer,
+        track_id -> Integer,
+        position -> Integer,
     }
 }
 
@@ -12,14 +50,36 @@ diesel::table! {
     track (id) {
         id -> Integer,
         title -> Text,
-        yt_id -> Nullable<Text>,
+        source_id -> Nullable<Text>,
         playlist_id -> Nullable<Integer>,
+        played -> Nullable<Bool>,
+        position -> Nullable<Integer>,
+        restriction -> Nullable<Text>,
+        removed -> Bool,
+        source_url -> Nullable<Text>,
+        artist -> Nullable<Text>,
+        album -> Nullable<Text>,
+        track_number -> Nullable<Integer>,
+        duration -> Nullable<Integer>,
+        play_count -> Integer,
+        trim_start -> Nullable<Integer>,
+        trim_end -> Nullable<Integer>,
+        year -> Nullable<Integer>,
+        metadata_checked -> Bool,
+        uploaded_at -> Nullable<BigInt>,
     }
 }
 
 diesel::joinable!(track -> playlist (playlist_id));
+diesel::joinable!(play -> track (track_id));
+diesel::joinable!(playlist_track -> playlist (playlist_id));
+diesel::joinable!(playlist_track -> track (track_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    app_state,
+    fingerprint,
+    play,
     playlist,
+    playlist_track,
     track,
 );
@@ -0,0 +1,62 @@
+//! Scrobbling: telling a service like ListenBrainz what is being listened to.
+//!
+//! Every service gets the same `Listen`s, one when a track starts playing and another once it
+//! has been listened to, so supporting another service only takes implementing `Scrobbler`.
+
+use async_trait::async_trait;
+
+use crate::api::ApiError;
+use crate::models::Track;
+
+/// A track that is being, or has been, listened to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listen {
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    /// In seconds, once the track has been played.
+    pub duration: Option<i32>,
+    /// Where the track can be found, like the URL of its video.
+    pub origin_url: Option<String>,
+    /// Unix timestamp of when it started playing.
+    pub listened_at: i64
+}
+
+impl Listen {
+
+    /// The listen of a track that started playing at `listened_at`. Tracks without an artist
+    /// don't have one, since no service takes them.
+    pub fn from_track(track: &Track, listened_at: i64) -> Option<Self> {
+
+        let artist = track.artist.clone().filter(|artist| !artist.trim().is_empty())?;
+        let origin_url = match (&track.source_url, &track.source_id) {
+            (Some(url), _) if !track.is_local() => Some(url.clone()),
+            // Songs of media servers have ids like `subsonic:42`, that only mean something to the server.
+            (None, Some(id)) if !id.contains(':') => Some(format!("https://www.youtube.com/watch?v={id}")),
+            _ => None
+        };
+
+        Some(Self {
+            title: track.title.clone(),
+            artist,
+            album: track.album.clone(),
+            duration: track.duration,
+            origin_url,
+            listened_at
+        })
+    }
+}
+
+/// A service listens are sent to.
+#[async_trait]
+pub trait Scrobbler: Send + Sync {
+
+    /// The name of the service, for the logs.
+    fn name(&self) -> &'static str;
+
+    /// Tells the service that a track has started playing. It isn't saved as listened.
+    async fn now_playing(&self, listen: &Listen) -> Result<(), ApiError>;
+
+    /// Saves a track as listened.
+    async fn submit(&self, listen: &Listen) -> Result<(), ApiError>;
+}
@@ -0,0 +1,204 @@
+//! The spectrum of the audio being played, for the visualizer.
+//!
+//! A `Tapped` source copies the samples the player is outputting into a `SampleTap`, and the
+//! spectrum of the latest ones is computed with an FFT whenever it's drawn.
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rodio::{Sample, Source};
+
+/// Samples the spectrum is computed from, some 46ms at 44.1kHz.
+pub const WINDOW: usize = 2048;
+
+/// Samples a `Tapped` source collects before handing them to the tap, so it isn't locked for each one.
+const BATCH: usize = 256;
+
+/// Frequencies shown, in Hz. Higher ones are left out, since most music has little there.
+const LOWEST: f32 = 50.0;
+const HIGHEST: f32 = 16_000.0;
+
+/// Loudness of the quietest band that is drawn, in decibels below full scale.
+const FLOOR_DB: f32 = 60.0;
+
+#[derive(Default)]
+struct TapBuffer {
+    samples: VecDeque<f32>,
+    sample_rate: u32,
+}
+
+/// The last samples that were played, mixed into one channel.
+#[derive(Clone, Default)]
+pub struct SampleTap(Arc<Mutex<TapBuffer>>);
+
+impl SampleTap {
+
+    fn push(&self, samples: &mut Vec<f32>, sample_rate: u32) -> bool {
+
+        let Ok(mut buffer) = self.0.try_lock() else { return false };
+        buffer.sample_rate = sample_rate;
+        buffer.samples.extend(samples.drain(..));
+        let excess = buffer.samples.len().saturating_sub(WINDOW);
+        buffer.samples.drain(..excess);
+        true
+    }
+
+    /// Forgets the samples of the previous track.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().samples.clear();
+    }
+
+    /// The spectrum of the last samples in `count` bands, from 0 (silent) to 1 (full scale).
+    /// Until there are enough samples, every band is 0.
+    pub fn bands(&self, count: usize) -> Vec<f32> {
+
+        let buffer = self.0.lock().unwrap();
+        if buffer.samples.len() < WINDOW {
+            return vec![0.0; count];
+        }
+        let samples: Vec<f32> = buffer.samples.iter().copied().collect();
+        let sample_rate = buffer.sample_rate;
+        drop(buffer);
+        bands(&samples, sample_rate, count)
+    }
+}
+
+/// A source that copies its samples into a `SampleTap` as they are played.
+pub struct Tapped<S> {
+    source: S,
+    tap: SampleTap,
+    pending: Vec<f32>,
+    // The channels of the current frame, added up.
+    frame: f32,
+    channel: u16,
+}
+
+impl<S> Tapped<S> {
+
+    pub fn new(source: S, tap: SampleTap) -> Self {
+        Self { source, tap, pending: Vec::with_capacity(BATCH), frame: 0.0, channel: 0 }
+    }
+}
+
+impl<S> Iterator for Tapped<S>
+where S: Source, S::Item: Sample {
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        let sample = self.source.next()?;
+        let channels = self.source.channels().max(1);
+        self.frame += sample.to_f32();
+        self.channel += 1;
+        if self.channel >= channels {
+            self.pending.push(self.frame / channels as f32);
+            self.frame = 0.0;
+            self.channel = 0;
+            // If the tap is being read, they are handed over with the next batch.
+            if self.pending.len() >= BATCH && !self.tap.push(&mut self.pending, self.source.sample_rate()) && self.pending.len() > WINDOW {
+                self.pending.drain(..BATCH);
+            }
+        }
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.source.size_hint()
+    }
+}
+
+impl<S> Source for Tapped<S>
+where S: Source, S::Item: Sample {
+
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), rodio::source::SeekError> {
+        self.source.try_seek(pos)
+    }
+}
+
+/// The spectrum of `samples` (of which only the first `WINDOW` are used) in `count` bands spaced
+/// logarithmically, like pitch is heard. Each band is the loudest frequency in it, from 0 for
+/// `FLOOR_DB` or quieter to 1 for a full scale sine.
+pub fn bands(samples: &[f32], sample_rate: u32, count: usize) -> Vec<f32> {
+
+    if samples.len() < WINDOW || count == 0 || sample_rate == 0 {
+        return vec![0.0; count];
+    }
+
+    // A Hann window, so the edges of the window don't add frequencies of their own.
+    let mut bins: Vec<(f32, f32)> = samples[..WINDOW].iter()
+        .enumerate()
+        .map(|(ind, sample)| (sample * 0.5 * (1.0 - (2.0 * PI * ind as f32 / WINDOW as f32).cos()), 0.0))
+        .collect();
+    fft(&mut bins);
+
+    // The window halves the amplitude, and each half of the spectrum has half of it.
+    let magnitude = |bin: usize| {
+        let (re, im) = bins[bin];
+        (re * re + im * im).sqrt() * 4.0 / WINDOW as f32
+    };
+    let bin_width = sample_rate as f32 / WINDOW as f32;
+    let highest = HIGHEST.min(sample_rate as f32 / 2.0);
+
+    (0..count).map(|band| {
+        let edge = |band: usize| LOWEST * (highest / LOWEST).powf(band as f32 / count as f32);
+        let (low, high) = (edge(band), edge(band + 1));
+        let first = (low / bin_width).ceil() as usize;
+        let last = ((high / bin_width).ceil() as usize).max(first + 1).min(WINDOW / 2);
+        // The lowest bands can be narrower than a bin, and get the closest one.
+        let loudest = (first.min(last - 1)..last).map(magnitude).fold(0.0, f32::max);
+        let db = 20.0 * loudest.max(f32::MIN_POSITIVE).log10();
+        ((db + FLOOR_DB) / FLOOR_DB).clamp(0.0, 1.0)
+    }).collect()
+}
+
+// Iterative radix-2 FFT, in place. The length must be a power of two.
+fn fft(data: &mut [(f32, f32)]) {
+
+    let n = data.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (re, im) = data[start + k + len / 2];
+                let odd = (re * cos - im * sin, re * sin + im * cos);
+                let even = data[start + k];
+                data[start + k] = (even.0 + odd.0, even.1 + odd.1);
+                data[start + k + len / 2] = (even.0 - odd.0, even.1 - odd.1);
+            }
+        }
+        len <<= 1;
+    }
+}
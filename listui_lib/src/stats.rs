@@ -0,0 +1,186 @@
+//! Listening stats, exported to CSV or JSON to be analyzed with other tools.
+//!
+//! There are two exports: every track with how much it has been played, and the history of
+//! every play, from the oldest. Times are in UTC, like `2026-10-15T14:03:00Z`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::models::Track;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatsFormat {
+    Csv,
+    Json
+}
+
+impl FromStr for StatsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(StatsFormat::Csv),
+            "json" => Ok(StatsFormat::Json),
+            _ => Err(format!("Unknown stats format: {s}. Expected csv or json."))
+        }
+    }
+}
+
+/// How much a track has been played.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TrackStats {
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub yt_id: Option<String>,
+    /// Times it was played until the end, including the ones before the history was kept.
+    pub plays: i32,
+    /// In seconds, once it has been played.
+    pub duration: Option<i32>,
+    /// Seconds it has been listened to, the plays times its duration.
+    pub listened: Option<i64>,
+    pub last_played: Option<String>
+}
+
+/// A track that was played until the end.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PlayRecord {
+    pub played_at: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub yt_id: Option<String>,
+    pub duration: Option<i32>
+}
+
+/// Something that can be a line of a CSV file.
+pub trait CsvRecord {
+    const HEADER: &'static [&'static str];
+
+    fn fields(&self) -> Vec<String>;
+}
+
+impl CsvRecord for TrackStats {
+    const HEADER: &'static [&'static str] = &["title", "artist", "album", "yt_id", "plays", "duration", "listened", "last_played"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.title.clone(),
+            self.artist.clone().unwrap_or_default(),
+            self.album.clone().unwrap_or_default(),
+            self.yt_id.clone().unwrap_or_default(),
+            self.plays.to_string(),
+            optional(self.duration),
+            optional(self.listened),
+            self.last_played.clone().unwrap_or_default()
+        ]
+    }
+}
+
+impl CsvRecord for PlayRecord {
+    const HEADER: &'static [&'static str] = &["played_at", "title", "artist", "album", "yt_id", "duration"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.played_at.clone(),
+            self.title.clone(),
+            self.artist.clone().unwrap_or_default(),
+            self.album.clone().unwrap_or_default(),
+            self.yt_id.clone().unwrap_or_default(),
+            optional(self.duration)
+        ]
+    }
+}
+
+/// The stats of every track, from the most played one. `plays` is the history, as returned by
+/// `Database::get_plays`.
+pub fn track_stats(tracks: &[Track], plays: &[(i64, Track)]) -> Vec<TrackStats> {
+
+    let mut last_played: HashMap<i32, i64> = HashMap::new();
+    for (played_at, track) in plays {
+        let last = last_played.entry(track.id).or_insert(*played_at);
+        *last = (*last).max(*played_at);
+    }
+
+    let mut stats: Vec<TrackStats> = tracks.iter()
+        .map(|track| TrackStats {
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            album: track.album.clone(),
+            yt_id: track.source().map(String::from),
+            plays: track.play_count,
+            duration: track.duration,
+            listened: track.duration.map(|duration| duration as i64 * track.play_count as i64),
+            last_played: last_played.get(&track.id).map(|time| utc_time(*time))
+        })
+        .collect();
+    // Sorting is stable, so the tracks played as many times keep their order.
+    stats.sort_by_key(|stats| std::cmp::Reverse(stats.plays));
+    stats
+}
+
+/// Every play of the history, in the same order.
+pub fn history(plays: &[(i64, Track)]) -> Vec<PlayRecord> {
+
+    plays.iter()
+        .map(|(played_at, track)| PlayRecord {
+            played_at: utc_time(*played_at),
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            album: track.album.clone(),
+            yt_id: track.source().map(String::from),
+            duration: track.duration
+        })
+        .collect()
+}
+
+pub fn export<T: CsvRecord + Serialize>(records: &[T], format: StatsFormat) -> String {
+    match format {
+        StatsFormat::Csv => to_csv(records),
+        StatsFormat::Json => serde_json::to_string_pretty(records).expect("Failed to serialize stats.")
+    }
+}
+
+/// A header line and a line for each record. Fields with commas, quotes or line breaks are quoted.
+pub fn to_csv<T: CsvRecord>(records: &[T]) -> String {
+
+    let line = |fields: Vec<String>| {
+        let escaped: Vec<String> = fields.into_iter()
+            .map(|field| {
+                if field.contains([',', '"', '\n', '\r']) { format!("\"{}\"", field.replace('"', "\"\"")) }
+                else { field }
+            })
+            .collect();
+        escaped.join(",") + "\n"
+    };
+
+    let mut content = line(T::HEADER.iter().map(|name| String::from(*name)).collect());
+    for record in records {
+        content.push_str(&line(record.fields()));
+    }
+    content
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Seconds since the epoch as an ISO 8601 UTC time, like `2026-10-15T14:03:00Z`.
+pub fn utc_time(seconds: i64) -> String {
+
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    // Howard Hinnant's days_from_civil, backwards: the date of a number of days since 1970-01-01.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z", time / 3600, time % 3600 / 60, time % 60)
+}
@@ -0,0 +1,89 @@
+//! The external programs lisTUI runs: `yt-dlp`, `ffmpeg` and, to find duplicates, Chromaprint's `fpcalc`.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+/// Where `yt-dlp`, `ffmpeg` and `fpcalc` are. The ones that aren't set are searched in the `PATH`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tools {
+    pub ytdlp: Option<PathBuf>,
+    pub ffmpeg: Option<PathBuf>,
+    pub fpcalc: Option<PathBuf>,
+    /// Where lisTUI installs its own `yt-dlp`. Once it's there, it's used instead of the one in the
+    /// `PATH`, but not instead of `ytdlp`.
+    pub installed_ytdlp: Option<PathBuf>
+}
+
+/// Whether the `yt-dlp` that is used can download anything.
+#[derive(Debug, Clone, PartialEq)]
+pub enum YtdlpStatus {
+    /// It can't be run.
+    Missing,
+    /// A newer version is out, which YouTube may need.
+    Outdated { version: String, latest: String },
+    UpToDate
+}
+
+impl Tools {
+
+    pub fn ytdlp_path(&self) -> &Path {
+        match (&self.ytdlp, &self.installed_ytdlp) {
+            (Some(path), _) => path,
+            (None, Some(path)) if path.is_file() => path,
+            _ => Path::new("yt-dlp")
+        }
+    }
+
+    pub fn ffmpeg_path(&self) -> &Path {
+        self.ffmpeg.as_deref().unwrap_or(Path::new("ffmpeg"))
+    }
+
+    pub fn fpcalc_path(&self) -> &Path {
+        self.fpcalc.as_deref().unwrap_or(Path::new("fpcalc"))
+    }
+
+    /// A `yt-dlp` command, that converts the downloads with our `ffmpeg`.
+    pub fn ytdlp_command(&self) -> tokio::process::Command {
+
+        let mut command = tokio::process::Command::new(self.ytdlp_path());
+        if let Some(ffmpeg) = &self.ffmpeg {
+            command.arg("--ffmpeg-location").arg(ffmpeg);
+        }
+        command
+    }
+
+    /// Decodes the whole of an audio file with `ffmpeg`, without playing it, to find out whether
+    /// it's damaged, like a download that was cut short. Returns the first problem `ffmpeg` found,
+    /// or an error if it can't be run.
+    pub async fn check_audio(&self, path: &Path) -> std::io::Result<Option<String>> {
+
+        let output = tokio::process::Command::new(self.ffmpeg_path())
+            .args(["-v", "error", "-nostdin", "-i"])
+            .arg(path)
+            .args(["-f", "null", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .output().await?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let problem = stderr.lines().map(str::trim).find(|line| !line.is_empty()).map(String::from);
+        Ok(match problem {
+            Some(problem) => Some(problem),
+            None if !output.status.success() => Some(format!("ffmpeg failed with {}", output.status)),
+            None => None
+        })
+    }
+
+    /// The version of `yt-dlp`, like `2024.08.06`, or `None` if it can't be run.
+    pub async fn ytdlp_version(&self) -> Option<String> {
+
+        let output = tokio::process::Command::new(self.ytdlp_path())
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output().await
+            .ok()?;
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !version.is_empty()).then_some(version)
+    }
+}
@@ -0,0 +1,121 @@
+//! Converts audio files with `ffmpeg`, for tracks that the player can't decode, or to
+//! shrink the downloads into another format.
+
+use std::io;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+use crate::downloader::AudioFormat;
+use crate::tools::Tools;
+
+/// Transcodes `input` into a FLAC file at `output`, which every backend can play, with the `ffmpeg` of `tools`.
+///
+/// `on_progress` is called with the percentage of the file that has been converted so far.
+pub async fn transcode(tools: &Tools, input: &Path, output: &Path, mut on_progress: impl FnMut(u8)) -> io::Result<()> {
+
+    log::info!("Transcoding {} into {}", input.display(), output.display());
+    let mut child = Command::new(tools.ffmpeg_path())
+        .arg("-hide_banner")
+        .arg("-nostats")
+        .arg("-y")
+        .arg("-i")
+        .arg(input)
+        .arg("-vn")
+        .arg("-c:a")
+        .arg("flac")
+        .arg("-progress")
+        .arg("pipe:1")
+        .arg(output)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("ffmpeg stdout is piped")).lines();
+    let mut stderr = BufReader::new(child.stderr.take().expect("ffmpeg stderr is piped")).lines();
+
+    // The duration is printed to stderr, and the progress to stdout.
+    let mut duration_us: Option<u64> = None;
+    let mut stderr_open = true;
+    loop {
+        tokio::select! {
+            line = stderr.next_line(), if stderr_open => match line? {
+                Some(line) => {
+                    if duration_us.is_none() { duration_us = parse_duration(&line); }
+                },
+                None => stderr_open = false
+            },
+            line = stdout.next_line() => match line? {
+                Some(line) => {
+                    let position = line.strip_prefix("out_time_us=").and_then(|us| us.trim().parse::<u64>().ok());
+                    if let Some(pcent) = position.zip(duration_us).and_then(|(pos, duration)| (100 * pos).checked_div(duration)) {
+                        on_progress(pcent.min(100) as u8);
+                    }
+                },
+                None => break
+            }
+        }
+    }
+
+    if child.wait().await?.success() {
+        on_progress(100);
+        Ok(())
+    }
+    else {
+        let _ = tokio::fs::remove_file(output).await;
+        Err(io::Error::other(format!("ffmpeg failed to transcode {}", input.display())))
+    }
+}
+
+/// Converts a downloaded track into `format`, at `bitrate` (like `96k`) or ffmpeg's default one.
+///
+/// The tags are copied, and so is the cover if `format` can hold one (opus files can't).
+pub async fn reencode(tools: &Tools, input: &Path, output: &Path, format: AudioFormat, bitrate: Option<&str>) -> io::Result<()> {
+
+    log::info!("Re-encoding {} into {}", input.display(), output.display());
+    let mut command = Command::new(tools.ffmpeg_path());
+    command.arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-y")
+        .arg("-i")
+        .arg(input)
+        .args(["-map", "0:a", "-map_metadata", "0"]);
+
+    match format {
+        AudioFormat::Mp3 => command.args(["-map", "0:v?", "-c:v", "copy", "-c:a", "libmp3lame"]),
+        AudioFormat::M4a => command.args(["-map", "0:v?", "-c:v", "copy", "-disposition:v", "attached_pic", "-c:a", "aac"]),
+        AudioFormat::Opus => command.args(["-c:a", "libopus"]),
+    };
+    if let Some(bitrate) = bitrate {
+        command.arg("-b:a").arg(bitrate);
+    }
+
+    let result = command.arg(output)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output().await?;
+
+    if result.status.success() {
+        Ok(())
+    }
+    else {
+        let _ = tokio::fs::remove_file(output).await;
+        let error = String::from_utf8_lossy(&result.stderr);
+        Err(io::Error::other(format!("ffmpeg failed to re-encode {}: {}", input.display(), error.trim())))
+    }
+}
+
+// Parses ffmpeg's "  Duration: 00:03:25.47, start: ..." line, in microseconds.
+fn parse_duration(line: &str) -> Option<u64> {
+
+    let time = line.trim().strip_prefix("Duration: ")?.split(',').next()?;
+    let mut parts = time.split(':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(((hours * 3600.0 + minutes * 60.0 + seconds) * 1_000_000.0) as u64)
+}